@@ -1,3 +1,89 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 fn main() {
     embuild::espidf::sysenv::output();
+
+    if std::env::var_os("CARGO_FEATURE_INJECTION").is_some() {
+        keep_injection_table();
+    }
+    if std::env::var_os("CARGO_FEATURE_HARDENED").is_some() {
+        check_hardened_sdkconfig();
+    }
+}
+
+/// Hand the linker `pulse_injection.x` so `--gc-sections` leaves the
+/// unreferenced `.pulse_injection_table` entries in the ELF.
+fn keep_injection_table() {
+    let script =
+        PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("pulse_injection.x");
+    println!("cargo:rerun-if-changed={}", script.display());
+    println!("cargo:rustc-link-arg-bins=-Wl,-T{}", script.display());
+}
+
+// Settings a hardened build must not ship with, and the value that gives it away.
+const DEBUG_ONLY: &[(&str, &str, &str)] = &[
+    (
+        "CONFIG_ESP32_DEBUG_OCDAWARE",
+        "y",
+        "OpenOCD awareness leaves the JTAG debug hooks in",
+    ),
+    ("CONFIG_ESP_TASK_WDT_EN", "n", "task watchdog is disabled"),
+    (
+        "CONFIG_ESP_INT_WDT_EN",
+        "n",
+        "interrupt watchdog is disabled",
+    ),
+    (
+        "CONFIG_ESP_SYSTEM_PANIC_GDBSTUB",
+        "y",
+        "panics drop into the GDB stub instead of rebooting",
+    ),
+];
+
+/// Refuse to build `hardened` against the debug sdkconfig.
+///
+/// Looks at the same files esp-idf-sys will use: `ESP_IDF_SDKCONFIG_DEFAULTS`
+/// (`;`-separated, later files win) or `sdkconfig.defaults`.
+fn check_hardened_sdkconfig() {
+    println!("cargo:rerun-if-env-changed=ESP_IDF_SDKCONFIG_DEFAULTS");
+
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let files =
+        std::env::var("ESP_IDF_SDKCONFIG_DEFAULTS").unwrap_or_else(|_| "sdkconfig.defaults".into());
+
+    let mut config = HashMap::new();
+    for file in files.split(';').filter(|f| !f.is_empty()) {
+        let path = manifest_dir.join(file);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("hardened build: cannot read {}: {}", path.display(), e));
+        for line in text.lines().map(str::trim) {
+            if let Some(key) = line
+                .strip_prefix("# ")
+                .and_then(|l| l.strip_suffix(" is not set"))
+            {
+                config.insert(key.to_string(), "n".to_string());
+            } else if let Some((key, value)) = line.split_once('=') {
+                if !line.starts_with('#') {
+                    config.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    let problems: Vec<String> = DEBUG_ONLY
+        .iter()
+        .filter(|(key, bad, _)| config.get(*key).map(String::as_str) == Some(*bad))
+        .map(|(key, bad, why)| format!("  {}={} ({})", key, bad, why))
+        .collect();
+
+    if !problems.is_empty() {
+        panic!(
+            "`hardened` feature refuses to build with debug settings from {}:\n{}\n\
+             Build with ESP_IDF_SDKCONFIG_DEFAULTS=sdkconfig.hardened instead.",
+            files,
+            problems.join("\n")
+        );
+    }
 }
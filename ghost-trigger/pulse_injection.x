@@ -0,0 +1,12 @@
+/* Keep the injection_point! table through --gc-sections.
+ *
+ * Nothing in the firmware references the entries, and the section name is
+ * not a C identifier so the linker emits no __start_/__stop_ symbols that
+ * would hold on to it. Host tools read it from the ELF, it is never loaded. */
+SECTIONS
+{
+  .pulse_injection_table (INFO) :
+  {
+    KEEP(*(.pulse_injection_table))
+  }
+}
@@ -0,0 +1,22 @@
+//! Debugger-facing injection points with stable, exported symbols.
+
+use pulse_core::{AuditLog, Mailbox};
+
+/// Polled once per cycle, see `pulse_core::mailbox` for the protocol.
+///
+/// `#[no_mangle]` keeps the symbol name and address stable across dev and
+/// release builds so `print &PULSE_MAILBOX` always works.
+#[no_mangle]
+#[used]
+pub static PULSE_MAILBOX: Mailbox = Mailbox::new();
+
+/// Ring buffer of every injection the firmware consumed, see `pulse_core::audit`.
+/// Read it with `x/228xw &PULSE_AUDIT` or post a `DumpAudit` (2) to the mailbox.
+#[no_mangle]
+#[used]
+pub static PULSE_AUDIT: AuditLog = AuditLog::new();
+
+pulse_core::injection_point! {
+    /// Sleep between detection cycles, re-read every cycle.
+    pub static CYCLE_MS: u32 = 1000, range(10, 60_000);
+}
@@ -1,35 +1,67 @@
-use esp_idf_svc::hal::delay::FreeRtos;
+#[cfg(feature = "injection")]
+mod injection;
+mod platform;
+
+#[cfg(feature = "injection")]
+use core::sync::atomic::Ordering;
+
+use platform::EspClock;
+use pulse_core::{Detector, Dwell, LogSink, Timing};
+
+#[cfg(all(feature = "source-gpio", feature = "source-adc"))]
+compile_error!("pick at most one threat source: `source-gpio` or `source-adc`");
+
+#[cfg(all(feature = "injection", feature = "hardened"))]
+compile_error!("`hardened` strips the injection paths, build it with `--no-default-features`");
+
+#[cfg(not(any(feature = "injection", feature = "hardened")))]
+compile_error!("pick a build mode: `injection` (default) or `hardened`");
+
+#[cfg(all(feature = "hardened", not(any(feature = "source-gpio", feature = "source-adc"))))]
+compile_error!("a `hardened` build has no debugger input, enable `source-gpio` or `source-adc`");
 
 fn main(){
     // link patches to the esp-idf logging system
     esp_idf_svc::sys::link_patches();
     esp_idf_svc::log::EspLogger::initialize_default();
 
+    // every consumed injection lands in PULSE_AUDIT
+    #[cfg(feature = "injection")]
+    let auditor = pulse_core::Auditor::new(&injection::PULSE_AUDIT, platform::now_us);
+
+    #[cfg(any(feature = "source-gpio", feature = "source-adc"))]
+    let peripherals = esp_idf_svc::hal::peripherals::Peripherals::take().unwrap();
+
+    #[cfg(feature = "source-gpio")]
+    let source = platform::check_motion_detector(peripherals.pins.gpio4).unwrap();
+
+    #[cfg(feature = "source-adc")]
+    let source = platform::read_radar_sensor(peripherals.adc1, peripherals.pins.gpio34).unwrap();
+
     // this variable represents a sensor state
     // in code - it is permanently false and be force to true via JTAG
+    #[cfg(not(any(feature = "source-gpio", feature = "source-adc")))]
     let mut threat_detected = false;
-    let mut counter = 0;
+    #[cfg(not(any(feature = "source-gpio", feature = "source-adc")))]
+    let source = pulse_core::DebuggerFlag::new(&mut threat_detected).with_audit(auditor);
+
+    // mailbox injections work the same in dev and release builds, whatever the source
+    #[cfg(feature = "injection")]
+    let source = pulse_core::MailboxSource::new(&injection::PULSE_MAILBOX, source).with_audit(auditor);
+
+    let mut source = source;
+
+    // counter, threat state machine and cycle delay live in pulse-core so they can be tested on host
+    let mut detector = Detector::new(Timing::default(), Dwell::default());
+    let mut clock = EspClock;
+    let mut sink = LogSink;
 
     log::info!("System altered!");
 
     loop{
-        // simulate sensor check
-        counter += 1;
-
-        // allow the variable to 'live' so optimizer doesn't delete it
-        // and returns a place to breakpoint
-        core::hint::black_box(&threat_detected);
-
-        if threat_detected{
-            log::error!(" !! THREAT DETECTED !! [Cycle: {}]", counter);
-            log::warn!("Engaging backup protocols...");
-
-            // reset for next state
-            threat_detected = false;
-            FreeRtos::delay_ms(2000);
-        } else{
-            log::info!("System secure. [Cycle: {}]", counter);
-        }
-        FreeRtos::delay_ms(1000);
+        #[cfg(feature = "injection")]
+        detector.set_timing(Timing { cycle_ms: injection::CYCLE_MS.load(Ordering::Relaxed) });
+
+        detector.cycle(&mut source, &mut clock, &mut sink);
     }
 }
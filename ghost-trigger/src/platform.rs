@@ -0,0 +1,78 @@
+//! ESP32 implementations of the pulse-core traits.
+
+use esp_idf_svc::hal::delay::FreeRtos;
+use pulse_core::Clock;
+
+#[cfg(feature = "source-gpio")]
+use esp_idf_svc::hal::gpio::{Gpio4, PinDriver};
+#[cfg(feature = "source-adc")]
+use esp_idf_svc::hal::{
+    adc::{
+        attenuation::DB_11,
+        oneshot::{config::AdcChannelConfig, AdcChannelDriver, AdcDriver},
+        ADC1,
+    },
+    gpio::Gpio34,
+};
+#[cfg(any(feature = "source-gpio", feature = "source-adc"))]
+use esp_idf_svc::sys::EspError;
+#[cfg(feature = "source-adc")]
+use pulse_core::AdcThreshold;
+#[cfg(feature = "source-gpio")]
+use pulse_core::GpioSource;
+
+/// Microseconds since boot from esp_timer.
+pub fn now_us() -> u64 {
+    // esp_timer counts up from boot and never goes negative
+    unsafe { esp_idf_svc::sys::esp_timer_get_time() as u64 }
+}
+
+/// esp_timer for timestamps, FreeRTOS for sleeping.
+pub struct EspClock;
+
+impl Clock for EspClock {
+    fn now_us(&self) -> u64 {
+        now_us()
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        FreeRtos::delay_ms(ms);
+    }
+}
+
+/// Raw 12-bit ADC counts at which the radar return counts as a threat (about half scale).
+#[cfg(feature = "source-adc")]
+pub const RADAR_THRESHOLD: u16 = 2048;
+
+/// PIR motion detector on GPIO4, output goes high while motion is seen.
+///
+/// GPIO12-15 are taken by JTAG so keep sensors off those pins.
+#[cfg(feature = "source-gpio")]
+pub fn check_motion_detector(pin: Gpio4) -> Result<GpioSource<impl FnMut() -> bool>, EspError> {
+    let pin = PinDriver::input(pin)?;
+    Ok(GpioSource::active_high(move || pin.is_high()))
+}
+
+/// Analog radar front end on GPIO34 (ADC1 channel 6, input only).
+#[cfg(feature = "source-adc")]
+pub fn read_radar_sensor(
+    adc: ADC1,
+    pin: Gpio34,
+) -> Result<AdcThreshold<impl FnMut() -> u16>, EspError> {
+    let adc = AdcDriver::new(adc)?;
+    let config = AdcChannelConfig {
+        attenuation: DB_11,
+        ..Default::default()
+    };
+    let mut channel = AdcChannelDriver::new(adc, pin, &config)?;
+
+    Ok(AdcThreshold::new(
+        move || {
+            channel.read().unwrap_or_else(|e| {
+                log::warn!("Radar read failed: {}", e);
+                0
+            })
+        },
+        RADAR_THRESHOLD,
+    ))
+}
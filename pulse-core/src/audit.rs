@@ -0,0 +1,242 @@
+//! RAM ring buffer of consumed injections.
+//!
+//! Every injection the firmware actually acts on (a mailbox request or a
+//! debugger write to `threat_detected`) is recorded here with its cycle,
+//! signal, old/new value and esp_timer timestamp. The firmware exports the
+//! log as `PULSE_AUDIT` so test reports can read it back through the debug
+//! link, and dumps it to the serial log on a [`Signal::DumpAudit`] request.
+//!
+//! Layout (all little-endian `u32`): a 16 byte header `magic, version,
+//! capacity, head` followed by `capacity` records of 7 words each. `head`
+//! counts every record ever written, the newest is at `(head - 1) % capacity`.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::mailbox::Signal;
+
+/// "PAUD" in memory.
+pub const AUDIT_MAGIC: u32 = u32::from_le_bytes(*b"PAUD");
+pub const AUDIT_VERSION: u32 = 1;
+/// Symbol the firmware exports its audit log under.
+pub const AUDIT_SYMBOL: &str = "PULSE_AUDIT";
+pub const AUDIT_CAPACITY: usize = 32;
+
+pub const AUDIT_HEADER_SIZE: usize = 16;
+pub const AUDIT_RECORD_SIZE: usize = 28;
+pub const AUDIT_SIZE: usize = AUDIT_HEADER_SIZE + AUDIT_CAPACITY * AUDIT_RECORD_SIZE;
+
+/// Signal id recorded for a debugger write to the local `threat_detected`
+/// flag, outside the mailbox id space.
+pub const SIGNAL_DEBUGGER_FLAG: u32 = 0x8000_0001;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct AuditRecord {
+    sequence: AtomicU32,
+    cycle: AtomicU32,
+    signal: AtomicU32,
+    old: AtomicU32,
+    new: AtomicU32,
+    timestamp_lo: AtomicU32,
+    timestamp_hi: AtomicU32,
+}
+
+impl AuditRecord {
+    // only used as the array repeat operand in `AuditLog::new`
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: AuditRecord = AuditRecord {
+        sequence: AtomicU32::new(0),
+        cycle: AtomicU32::new(0),
+        signal: AtomicU32::new(0),
+        old: AtomicU32::new(0),
+        new: AtomicU32::new(0),
+        timestamp_lo: AtomicU32::new(0),
+        timestamp_hi: AtomicU32::new(0),
+    };
+
+    fn load(&self) -> AuditEntry {
+        AuditEntry {
+            sequence: self.sequence.load(Ordering::Relaxed),
+            cycle: self.cycle.load(Ordering::Relaxed),
+            signal: self.signal.load(Ordering::Relaxed),
+            old: self.old.load(Ordering::Relaxed),
+            new: self.new.load(Ordering::Relaxed),
+            timestamp_us: u64::from(self.timestamp_lo.load(Ordering::Relaxed))
+                | u64::from(self.timestamp_hi.load(Ordering::Relaxed)) << 32,
+        }
+    }
+}
+
+/// Plain copy of one record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Mailbox sequence number, 0 for debugger flag writes.
+    pub sequence: u32,
+    pub cycle: u32,
+    pub signal: u32,
+    pub old: u32,
+    pub new: u32,
+    pub timestamp_us: u64,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let signal = match self.signal {
+            SIGNAL_DEBUGGER_FLAG => "DEBUGGER_FLAG",
+            id => match Signal::from_id(id) {
+                Signal::None => "NONE",
+                Signal::Threat => "THREAT",
+                Signal::DumpAudit => "DUMP_AUDIT",
+                Signal::Unknown(_) => "UNKNOWN",
+            },
+        };
+        write!(
+            f,
+            "Injection {} (id {}) {} -> {} [Cycle: {}] [Seq: {}] @ {} us",
+            signal, self.signal, self.old, self.new, self.cycle, self.sequence, self.timestamp_us
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct AuditLog {
+    magic: AtomicU32,
+    version: AtomicU32,
+    capacity: AtomicU32,
+    head: AtomicU32,
+    records: [AuditRecord; AUDIT_CAPACITY],
+}
+
+impl AuditLog {
+    pub const fn new() -> Self {
+        Self {
+            magic: AtomicU32::new(AUDIT_MAGIC),
+            version: AtomicU32::new(AUDIT_VERSION),
+            capacity: AtomicU32::new(AUDIT_CAPACITY as u32),
+            head: AtomicU32::new(0),
+            records: [AuditRecord::EMPTY; AUDIT_CAPACITY],
+        }
+    }
+
+    /// Append a record, overwriting the oldest once full. Single writer only.
+    pub fn record(&self, entry: AuditEntry) {
+        let head = self.head.load(Ordering::Relaxed);
+        let slot = &self.records[head as usize % AUDIT_CAPACITY];
+        slot.sequence.store(entry.sequence, Ordering::Relaxed);
+        slot.cycle.store(entry.cycle, Ordering::Relaxed);
+        slot.signal.store(entry.signal, Ordering::Relaxed);
+        slot.old.store(entry.old, Ordering::Relaxed);
+        slot.new.store(entry.new, Ordering::Relaxed);
+        slot.timestamp_lo
+            .store(entry.timestamp_us as u32, Ordering::Relaxed);
+        slot.timestamp_hi
+            .store((entry.timestamp_us >> 32) as u32, Ordering::Relaxed);
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Drop all records, as after a reboot.
+    pub fn clear(&self) {
+        self.head.store(0, Ordering::Release);
+    }
+
+    /// Records ever written, including overwritten ones.
+    pub fn total(&self) -> u32 {
+        self.head.load(Ordering::Acquire)
+    }
+
+    /// Retained records, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        let head = self.total() as usize;
+        let len = head.min(AUDIT_CAPACITY);
+        (head - len..head)
+            .map(|i| self.records[i % AUDIT_CAPACITY].load())
+            .collect()
+    }
+
+    /// Print the retained records to the log.
+    pub fn dump(&self) {
+        let entries = self.entries();
+        log::info!(
+            "Injection audit: {} retained, {} total",
+            entries.len(),
+            self.total()
+        );
+        for entry in entries {
+            log::info!("{}", entry);
+        }
+    }
+
+    /// Decode a raw memory image of the log, e.g. read through the debugger.
+    /// Returns `None` if the magic or version do not match.
+    pub fn decode(bytes: &[u8]) -> Option<Vec<AuditEntry>> {
+        let word = |at: usize| -> Option<u32> {
+            let raw = bytes.get(at..at + 4)?;
+            Some(u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+        };
+        if word(0)? != AUDIT_MAGIC || word(4)? != AUDIT_VERSION {
+            return None;
+        }
+        let capacity = word(8)? as usize;
+        let head = word(12)? as usize;
+        let len = head.min(capacity);
+
+        (head - len..head)
+            .map(|i| {
+                let at = AUDIT_HEADER_SIZE + (i % capacity) * AUDIT_RECORD_SIZE;
+                Some(AuditEntry {
+                    sequence: word(at)?,
+                    cycle: word(at + 4)?,
+                    signal: word(at + 8)?,
+                    old: word(at + 12)?,
+                    new: word(at + 16)?,
+                    timestamp_us: u64::from(word(at + 20)?) | u64::from(word(at + 24)?) << 32,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handle sources use to record what they consumed.
+#[derive(Clone, Copy)]
+pub struct Auditor<'a> {
+    log: &'a AuditLog,
+    now_us: fn() -> u64,
+}
+
+impl<'a> Auditor<'a> {
+    /// `now_us` is the esp_timer on target.
+    pub fn new(log: &'a AuditLog, now_us: fn() -> u64) -> Self {
+        Self { log, now_us }
+    }
+
+    pub fn log(&self) -> &'a AuditLog {
+        self.log
+    }
+
+    pub fn record(&self, cycle: u32, sequence: u32, signal: u32, old: u32, new: u32) {
+        self.log.record(AuditEntry {
+            sequence,
+            cycle,
+            signal,
+            old,
+            new,
+            timestamp_us: (self.now_us)(),
+        });
+    }
+}
+
+impl fmt::Debug for Auditor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auditor")
+            .field("total", &self.log.total())
+            .finish()
+    }
+}
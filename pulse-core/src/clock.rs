@@ -0,0 +1,43 @@
+/// Time source and blocking delay for the detection loop.
+///
+/// On target this is backed by `esp_timer_get_time` and `FreeRtos::delay_ms`,
+/// on host by [`VirtualClock`].
+pub trait Clock {
+    /// Microseconds since boot.
+    fn now_us(&self) -> u64;
+
+    /// Block the current task for `ms` milliseconds.
+    fn delay_ms(&mut self, ms: u32);
+}
+
+/// Clock that only moves when the loop sleeps - no real waiting on host.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VirtualClock {
+    now_us: u64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whole milliseconds elapsed since the clock was created.
+    pub fn elapsed_ms(&self) -> u64 {
+        self.now_us / 1000
+    }
+
+    /// Move time forward without going through a delay.
+    pub fn advance_us(&mut self, us: u64) {
+        self.now_us += us;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_us(&self) -> u64 {
+        self.now_us
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.now_us += u64::from(ms) * 1000;
+    }
+}
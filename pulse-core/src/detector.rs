@@ -0,0 +1,92 @@
+use crate::clock::Clock;
+use crate::sink::{Event, Sink};
+use crate::source::ThreatSource;
+use crate::state::{Dwell, ThreatMachine, ThreatState};
+
+/// Loop delays in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// Sleep at the end of every cycle.
+    pub cycle_ms: u32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self { cycle_ms: 1000 }
+    }
+}
+
+/// The sense/detect/respond cycle that used to live inline in `main()`.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    timing: Timing,
+    counter: u32,
+    machine: ThreatMachine,
+}
+
+impl Detector {
+    pub fn new(timing: Timing, dwell: Dwell) -> Self {
+        Self {
+            timing,
+            counter: 0,
+            machine: ThreatMachine::new(dwell),
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Takes effect from the next cycle.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Number of cycles run so far (the `[Cycle: N]` in the log).
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    pub fn state(&self) -> ThreatState {
+        self.machine.state()
+    }
+
+    pub fn machine(&self) -> &ThreatMachine {
+        &self.machine
+    }
+
+    /// Run one cycle.
+    ///
+    /// Polls `source` once and feeds the reading to the state machine.
+    /// Every transition is reported, entering Detected and BackupEngaged
+    /// also produce the classic THREAT DETECTED / backup protocol lines.
+    ///
+    /// Never inlined: host tools and GDB scripts count cycles with a
+    /// breakpoint on this symbol, and at opt-level `s`/`z` its only caller
+    /// would otherwise absorb it.
+    #[inline(never)]
+    pub fn cycle<T: ThreatSource, C: Clock, S: Sink>(
+        &mut self,
+        source: &mut T,
+        clock: &mut C,
+        sink: &mut S,
+    ) {
+        self.counter = self.counter.wrapping_add(1);
+        let cycle = self.counter;
+
+        let reading = source.poll();
+        self.machine.step(cycle, reading, |transition| {
+            sink.emit(Event::Transition(transition));
+            match transition.to {
+                ThreatState::Detected => sink.emit(Event::ThreatDetected { cycle }),
+                ThreatState::BackupEngaged => sink.emit(Event::EngagingBackup { cycle }),
+                _ => {}
+            }
+        });
+
+        if self.machine.state() == ThreatState::Secure {
+            sink.emit(Event::Secure { cycle });
+        }
+        clock.delay_ms(self.timing.cycle_ms);
+    }
+}
@@ -0,0 +1,298 @@
+//! Debugger-writable variables registered in a linker section.
+//!
+//! [`injection_point!`](crate::injection_point) declares an atomic static
+//! and drops an [`InjectionPoint`] describing it into
+//! `.pulse_injection_table`. Host tools read that section straight from the
+//! ELF to enumerate every injectable value with its address, type and
+//! allowed range, no guessing at local-variable locations.
+//!
+//! The entries are `#[used]` but never referenced, so a firmware linked with
+//! `--gc-sections` has to `KEEP` the section (ghost-trigger's
+//! `pulse_injection.x`) or the table is dropped.
+//!
+//! ```
+//! pulse_core::injection_point! {
+//!     /// Sleep between cycles.
+//!     pub static CYCLE_MS: u32 = 1000, range(10, 60_000);
+//!     pub static FORCE_THREAT: bool = false;
+//! }
+//!
+//! assert_eq!(CYCLE_MS.load(core::sync::atomic::Ordering::Relaxed), 1000);
+//! ```
+
+/// Name of the section holding the [`InjectionPoint`] table.
+pub const TABLE_SECTION: &str = ".pulse_injection_table";
+
+/// Value type of an injection point.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bool = 1,
+    U8 = 2,
+    I8 = 3,
+    U16 = 4,
+    I16 = 5,
+    U32 = 6,
+    I32 = 7,
+}
+
+impl Kind {
+    pub const fn from_id(id: u32) -> Option<Self> {
+        Some(match id {
+            1 => Kind::Bool,
+            2 => Kind::U8,
+            3 => Kind::I8,
+            4 => Kind::U16,
+            5 => Kind::I16,
+            6 => Kind::U32,
+            7 => Kind::I32,
+            _ => return None,
+        })
+    }
+
+    pub const fn size(self) -> u32 {
+        match self {
+            Kind::Bool | Kind::U8 | Kind::I8 => 1,
+            Kind::U16 | Kind::I16 => 2,
+            Kind::U32 | Kind::I32 => 4,
+        }
+    }
+
+    /// Full range of the type, used when no explicit range is given.
+    pub const fn bounds(self) -> (i64, i64) {
+        match self {
+            Kind::Bool => (0, 1),
+            Kind::U8 => (0, u8::MAX as i64),
+            Kind::I8 => (i8::MIN as i64, i8::MAX as i64),
+            Kind::U16 => (0, u16::MAX as i64),
+            Kind::I16 => (i16::MIN as i64, i16::MAX as i64),
+            Kind::U32 => (0, u32::MAX as i64),
+            Kind::I32 => (i32::MIN as i64, i32::MAX as i64),
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Kind::Bool => "bool",
+            Kind::U8 => "u8",
+            Kind::I8 => "i8",
+            Kind::U16 => "u16",
+            Kind::I16 => "i16",
+            Kind::U32 => "u32",
+            Kind::I32 => "i32",
+        }
+    }
+}
+
+/// One entry in `.pulse_injection_table`, layout is ABI.
+///
+/// The 64-bit range limits come first so the only padding is at the end:
+/// 40 bytes per entry on the 32-bit ESP32, 48 on a 64-bit host.
+#[repr(C)]
+#[derive(Debug)]
+pub struct InjectionPoint {
+    pub min: i64,
+    pub max: i64,
+    pub name: *const u8,
+    pub name_len: usize,
+    pub addr: *const (),
+    pub kind: u32,
+    pub size: u32,
+}
+
+// only ever read, the pointers are to 'static data
+unsafe impl Sync for InjectionPoint {}
+
+impl InjectionPoint {
+    pub const fn new(name: &'static str, addr: *const (), kind: Kind, min: i64, max: i64) -> Self {
+        Self {
+            min,
+            max,
+            name: name.as_ptr(),
+            name_len: name.len(),
+            addr,
+            kind: kind as u32,
+            size: kind.size(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        // built from a &'static str in `new`
+        unsafe {
+            core::str::from_utf8_unchecked(core::slice::from_raw_parts(self.name, self.name_len))
+        }
+    }
+
+    pub fn kind(&self) -> Option<Kind> {
+        Kind::from_id(self.kind)
+    }
+}
+
+/// Table entry as seen by a host tool reading the target's ELF.
+///
+/// `name_addr` still has to be looked up in the ELF's loadable sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawEntry {
+    pub min: i64,
+    pub max: i64,
+    pub name_addr: u64,
+    pub name_len: u64,
+    pub addr: u64,
+    pub kind: Option<Kind>,
+    pub size: u32,
+}
+
+impl RawEntry {
+    /// Size of one entry for a target with `ptr_width`-byte pointers.
+    pub const fn stride(ptr_width: usize) -> usize {
+        let raw = 16 + 3 * ptr_width + 8;
+        (raw + 7) & !7
+    }
+
+    /// Decode a whole little-endian table section. Trailing bytes that do
+    /// not make up a full entry are ignored.
+    pub fn parse_table(section: &[u8], ptr_width: usize) -> Vec<RawEntry> {
+        let stride = Self::stride(ptr_width);
+        section
+            .chunks_exact(stride)
+            .map(|entry| Self::parse(entry, ptr_width))
+            .collect()
+    }
+
+    fn parse(entry: &[u8], ptr_width: usize) -> RawEntry {
+        let mut at = 0;
+        let mut take = |len: usize| {
+            let mut buf = [0u8; 8];
+            buf[..len].copy_from_slice(&entry[at..at + len]);
+            at += len;
+            u64::from_le_bytes(buf)
+        };
+        let min = take(8) as i64;
+        let max = take(8) as i64;
+        let name_addr = take(ptr_width);
+        let name_len = take(ptr_width);
+        let addr = take(ptr_width);
+        let kind = Kind::from_id(take(4) as u32);
+        let size = take(4) as u32;
+        RawEntry {
+            min,
+            max,
+            name_addr,
+            name_len,
+            addr,
+            kind,
+            size,
+        }
+    }
+
+    pub fn in_range(&self, value: i64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+/// Declare debugger-writable statics and register them in the injection
+/// table. Supported types are `bool`, `u8`, `i8`, `u16`, `i16`, `u32` and
+/// `i32`; each becomes the matching `core::sync::atomic` type so reads are
+/// never cached. `range(min, max)` is optional and defaults to the full
+/// range of the type.
+///
+/// Without the `injection` feature the statics are still declared but
+/// nothing is registered, so hardened builds carry no table.
+#[macro_export]
+macro_rules! injection_point {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis static $name:ident : $ty:tt = $init:expr $(, range($min:expr, $max:expr))?;
+    )*) => {$(
+        $(#[$meta])*
+        $vis static $name: $crate::__injection_atomic!($ty) =
+            <$crate::__injection_atomic!($ty)>::new($init);
+
+        $crate::__injection_register!($name, $ty $(, $min, $max)?);
+    )*};
+}
+
+#[cfg(feature = "injection")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __injection_register {
+    ($name:ident, $ty:tt $(, $min:expr, $max:expr)?) => {
+        const _: () = {
+            #[used]
+            #[cfg_attr(target_os = "macos", link_section = "__DATA,__pulse_inject")]
+            #[cfg_attr(not(target_os = "macos"), link_section = ".pulse_injection_table")]
+            static ENTRY: $crate::injection::InjectionPoint = {
+                let kind = $crate::__injection_kind!($ty);
+                #[allow(unused_mut, unused_assignments)]
+                let mut bounds = kind.bounds();
+                $( bounds = ($min as i64, $max as i64); )?
+                $crate::injection::InjectionPoint::new(
+                    stringify!($name),
+                    &$name as *const _ as *const (),
+                    kind,
+                    bounds.0,
+                    bounds.1,
+                )
+            };
+        };
+    };
+}
+
+#[cfg(not(feature = "injection"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __injection_register {
+    ($($tt:tt)*) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __injection_atomic {
+    (bool) => {
+        ::core::sync::atomic::AtomicBool
+    };
+    (u8) => {
+        ::core::sync::atomic::AtomicU8
+    };
+    (i8) => {
+        ::core::sync::atomic::AtomicI8
+    };
+    (u16) => {
+        ::core::sync::atomic::AtomicU16
+    };
+    (i16) => {
+        ::core::sync::atomic::AtomicI16
+    };
+    (u32) => {
+        ::core::sync::atomic::AtomicU32
+    };
+    (i32) => {
+        ::core::sync::atomic::AtomicI32
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __injection_kind {
+    (bool) => {
+        $crate::injection::Kind::Bool
+    };
+    (u8) => {
+        $crate::injection::Kind::U8
+    };
+    (i8) => {
+        $crate::injection::Kind::I8
+    };
+    (u16) => {
+        $crate::injection::Kind::U16
+    };
+    (i16) => {
+        $crate::injection::Kind::I16
+    };
+    (u32) => {
+        $crate::injection::Kind::U32
+    };
+    (i32) => {
+        $crate::injection::Kind::I32
+    };
+}
@@ -0,0 +1,30 @@
+//! Host-testable core of the ghost-trigger firmware.
+//!
+//! The sense/detect/respond loop lives here instead of in `main()` so the
+//! exact same code runs on the ESP32 (with FreeRTOS delays and the esp-idf
+//! logger) and under `cargo test` on Linux (with a [`VirtualClock`]).
+
+#[cfg(feature = "injection")]
+pub mod audit;
+pub mod clock;
+pub mod detector;
+pub mod injection;
+#[cfg(feature = "injection")]
+pub mod mailbox;
+pub mod sink;
+pub mod source;
+pub mod state;
+
+#[cfg(feature = "injection")]
+pub use audit::{AuditLog, Auditor};
+pub use clock::{Clock, VirtualClock};
+pub use detector::{Detector, Timing};
+#[cfg(feature = "injection")]
+pub use mailbox::{Mailbox, MailboxSource};
+pub use sink::{Event, LogSink, Sink};
+#[cfg(feature = "injection")]
+pub use source::DebuggerFlag;
+pub use source::{
+    AdcThreshold, AnalogInput, DigitalInput, GpioSource, Reading, ScriptedSource, ThreatSource,
+};
+pub use state::{Dwell, ThreatMachine, ThreatState, Transition, TransitionLog};
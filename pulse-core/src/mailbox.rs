@@ -0,0 +1,229 @@
+//! Fixed-address injection mailbox.
+//!
+//! Poking `threat_detected` directly only works while the local survives
+//! optimisation and the DWARF location info is right, which it often is
+//! not with `opt-level = "s"`. The firmware instead exports one
+//! `#[no_mangle]` [`Mailbox`] static and polls it every cycle, so a
+//! debugger or script can always inject at the address of `PULSE_MAILBOX`.
+//!
+//! Protocol (host side, target halted or running):
+//!
+//! 1. check `magic` and `version`
+//! 2. write `signal` and `payload`
+//! 3. bump `sequence` (wrapping)
+//! 4. wait for `ack == sequence`
+//!
+//! All fields are little-endian `u32`, see the `OFFSET_*` constants.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::audit::Auditor;
+use crate::source::{Reading, ThreatSource};
+
+/// "PLSE" in memory.
+pub const MAILBOX_MAGIC: u32 = u32::from_le_bytes(*b"PLSE");
+/// Bumped on any layout or signal change.
+pub const MAILBOX_VERSION: u32 = 2;
+/// Symbol the firmware exports its mailbox under.
+pub const MAILBOX_SYMBOL: &str = "PULSE_MAILBOX";
+
+pub const OFFSET_MAGIC: u32 = 0;
+pub const OFFSET_VERSION: u32 = 4;
+pub const OFFSET_SEQUENCE: u32 = 8;
+pub const OFFSET_SIGNAL: u32 = 12;
+pub const OFFSET_PAYLOAD: u32 = 16;
+pub const OFFSET_ACK: u32 = 20;
+pub const MAILBOX_SIZE: u32 = 24;
+
+/// Step 1 of the protocol, for hosts: what is wrong with the `magic` and
+/// `version` words read at the mailbox's address, if anything. Writing
+/// anyway would scribble over whatever really lives there.
+pub fn check_header(magic: u32, version: u32) -> Result<(), String> {
+    if magic != MAILBOX_MAGIC {
+        return Err(format!(
+            "no mailbox, magic is {:#010x} instead of {:#010x}",
+            magic, MAILBOX_MAGIC
+        ));
+    }
+    if version != MAILBOX_VERSION {
+        return Err(format!(
+            "mailbox version {}, expected {}",
+            version, MAILBOX_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// What an injection asks the firmware to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    None,
+    /// Report a threat for `payload` cycles (0 counts as 1).
+    Threat,
+    /// Print the injection audit log to serial (since version 2).
+    DumpAudit,
+    Unknown(u32),
+}
+
+impl Signal {
+    pub const fn id(self) -> u32 {
+        match self {
+            Signal::None => 0,
+            Signal::Threat => 1,
+            Signal::DumpAudit => 2,
+            Signal::Unknown(id) => id,
+        }
+    }
+
+    pub const fn from_id(id: u32) -> Self {
+        match id {
+            0 => Signal::None,
+            1 => Signal::Threat,
+            2 => Signal::DumpAudit,
+            id => Signal::Unknown(id),
+        }
+    }
+}
+
+/// One consumed mailbox request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Injection {
+    pub sequence: u32,
+    pub signal: Signal,
+    pub payload: u32,
+}
+
+/// The shared memory block, layout is ABI.
+///
+/// Atomics have the same in-memory representation as `u32` and keep the
+/// compiler from caching fields that change behind its back.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Mailbox {
+    pub magic: AtomicU32,
+    pub version: AtomicU32,
+    pub sequence: AtomicU32,
+    pub signal: AtomicU32,
+    pub payload: AtomicU32,
+    pub ack: AtomicU32,
+}
+
+impl Mailbox {
+    pub const fn new() -> Self {
+        Self {
+            magic: AtomicU32::new(MAILBOX_MAGIC),
+            version: AtomicU32::new(MAILBOX_VERSION),
+            sequence: AtomicU32::new(0),
+            signal: AtomicU32::new(0),
+            payload: AtomicU32::new(0),
+            ack: AtomicU32::new(0),
+        }
+    }
+
+    /// Consume the pending request, if any, and acknowledge it.
+    pub fn take(&self) -> Option<Injection> {
+        let sequence = self.sequence.load(Ordering::Acquire);
+        if sequence == self.ack.load(Ordering::Relaxed) {
+            return None;
+        }
+        let injection = Injection {
+            sequence,
+            signal: Signal::from_id(self.signal.load(Ordering::Relaxed)),
+            payload: self.payload.load(Ordering::Relaxed),
+        };
+        self.ack.store(sequence, Ordering::Release);
+        Some(injection)
+    }
+
+    /// Queue a request the way a debugger would. Used by host tests and
+    /// anything running on target that wants to inject into itself.
+    pub fn post(&self, signal: Signal, payload: u32) -> u32 {
+        self.signal.store(signal.id(), Ordering::Relaxed);
+        self.payload.store(payload, Ordering::Relaxed);
+        let sequence = self.sequence.load(Ordering::Relaxed).wrapping_add(1);
+        self.sequence.store(sequence, Ordering::Release);
+        sequence
+    }
+
+    pub fn is_acked(&self, sequence: u32) -> bool {
+        self.ack.load(Ordering::Acquire) == sequence
+    }
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps another source and ORs in threats injected through the mailbox.
+pub struct MailboxSource<'a, S> {
+    mailbox: &'a Mailbox,
+    inner: S,
+    pending: u32,
+    // polled exactly once per detection cycle, so this is the cycle number
+    polls: u32,
+    auditor: Option<Auditor<'a>>,
+}
+
+impl<'a, S: ThreatSource> MailboxSource<'a, S> {
+    pub fn new(mailbox: &'a Mailbox, inner: S) -> Self {
+        Self {
+            mailbox,
+            inner,
+            pending: 0,
+            polls: 0,
+            auditor: None,
+        }
+    }
+
+    /// Record every consumed request, and answer `DumpAudit`.
+    pub fn with_audit(mut self, auditor: Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: ThreatSource> ThreatSource for MailboxSource<'_, S> {
+    fn poll(&mut self) -> Reading {
+        self.polls = self.polls.wrapping_add(1);
+
+        if let Some(injection) = self.mailbox.take() {
+            let old = self.pending;
+            match injection.signal {
+                Signal::None => {}
+                Signal::Threat => self.pending = injection.payload.max(1),
+                Signal::DumpAudit => match self.auditor {
+                    Some(auditor) => auditor.log().dump(),
+                    None => log::warn!("No injection audit log in this build"),
+                },
+                Signal::Unknown(id) => log::warn!(
+                    "Ignoring unknown injection signal {} [Seq: {}]",
+                    id,
+                    injection.sequence
+                ),
+            }
+            if let Some(auditor) = self.auditor {
+                auditor.record(
+                    self.polls,
+                    injection.sequence,
+                    injection.signal.id(),
+                    old,
+                    self.pending,
+                );
+            }
+        }
+
+        // always poll the real source so it keeps its own timing
+        let reading = self.inner.poll();
+        if self.pending > 0 {
+            self.pending -= 1;
+            return Reading::Threat;
+        }
+        reading
+    }
+}
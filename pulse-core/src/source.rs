@@ -0,0 +1,207 @@
+//! Threat inputs for the detection loop.
+//!
+//! The firmware picks one of these at build time (see the `source-*`
+//! features in ghost-trigger), host tests mostly use [`ScriptedSource`].
+
+use std::collections::VecDeque;
+
+#[cfg(feature = "injection")]
+use crate::audit::{Auditor, SIGNAL_DEBUGGER_FLAG};
+
+/// Result of a single sensor poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reading {
+    #[default]
+    Clear,
+    Threat,
+}
+
+impl Reading {
+    pub fn is_threat(self) -> bool {
+        self == Reading::Threat
+    }
+}
+
+impl From<bool> for Reading {
+    fn from(threat: bool) -> Self {
+        if threat {
+            Reading::Threat
+        } else {
+            Reading::Clear
+        }
+    }
+}
+
+/// Anything the detection loop can poll once per cycle.
+pub trait ThreatSource {
+    fn poll(&mut self) -> Reading;
+}
+
+impl<T: ThreatSource + ?Sized> ThreatSource for &mut T {
+    fn poll(&mut self) -> Reading {
+        (**self).poll()
+    }
+}
+
+/// The original PoC input: a bool that is only ever set from a debugger.
+///
+/// The flag is borrowed rather than owned so it stays a plain local in
+/// `main()` and `set var threat_detected = true` keeps working in GDB.
+/// It is consumed on poll, i.e. reset to `false` for the next cycle.
+#[cfg(feature = "injection")]
+pub struct DebuggerFlag<'a> {
+    threat_detected: &'a mut bool,
+    polls: u32,
+    auditor: Option<Auditor<'a>>,
+}
+
+#[cfg(feature = "injection")]
+impl<'a> DebuggerFlag<'a> {
+    pub fn new(threat_detected: &'a mut bool) -> Self {
+        Self {
+            threat_detected,
+            polls: 0,
+            auditor: None,
+        }
+    }
+
+    /// Record every debugger write that gets consumed (old 0, new 1).
+    pub fn with_audit(mut self, auditor: Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+}
+
+#[cfg(feature = "injection")]
+impl ThreatSource for DebuggerFlag<'_> {
+    fn poll(&mut self) -> Reading {
+        // leak the flag's address so the local in `main()` keeps a stack
+        // slot, then read it volatile so a debugger write is always seen
+        let flag = core::hint::black_box(&mut *self.threat_detected as *mut bool);
+        // points at the borrowed `&mut bool`, valid for `'a`
+        let threat = unsafe { flag.read_volatile() };
+        self.polls = self.polls.wrapping_add(1);
+
+        if threat {
+            unsafe { flag.write_volatile(false) };
+            if let Some(auditor) = self.auditor {
+                auditor.record(self.polls, 0, SIGNAL_DEBUGGER_FLAG, 0, 1);
+            }
+        }
+        Reading::from(threat)
+    }
+}
+
+/// Digital line, e.g. a PIR motion detector output.
+pub trait DigitalInput {
+    fn is_high(&mut self) -> bool;
+}
+
+impl<F: FnMut() -> bool> DigitalInput for F {
+    fn is_high(&mut self) -> bool {
+        self()
+    }
+}
+
+/// Threat while a digital input sits at its active level.
+pub struct GpioSource<P> {
+    pin: P,
+    active_high: bool,
+}
+
+impl<P: DigitalInput> GpioSource<P> {
+    pub fn active_high(pin: P) -> Self {
+        Self {
+            pin,
+            active_high: true,
+        }
+    }
+
+    pub fn active_low(pin: P) -> Self {
+        Self {
+            pin,
+            active_high: false,
+        }
+    }
+}
+
+impl<P: DigitalInput> ThreatSource for GpioSource<P> {
+    fn poll(&mut self) -> Reading {
+        Reading::from(self.pin.is_high() == self.active_high)
+    }
+}
+
+/// One-shot analog conversion, in whatever unit the driver reports.
+pub trait AnalogInput {
+    fn read(&mut self) -> u16;
+}
+
+impl<F: FnMut() -> u16> AnalogInput for F {
+    fn read(&mut self) -> u16 {
+        self()
+    }
+}
+
+/// Threat once an analog reading reaches `threshold`.
+pub struct AdcThreshold<A> {
+    input: A,
+    threshold: u16,
+    last: u16,
+}
+
+impl<A: AnalogInput> AdcThreshold<A> {
+    pub fn new(input: A, threshold: u16) -> Self {
+        Self {
+            input,
+            threshold,
+            last: 0,
+        }
+    }
+
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    /// Raw value from the most recent poll.
+    pub fn last(&self) -> u16 {
+        self.last
+    }
+}
+
+impl<A: AnalogInput> ThreatSource for AdcThreshold<A> {
+    fn poll(&mut self) -> Reading {
+        self.last = self.input.read();
+        Reading::from(self.last >= self.threshold)
+    }
+}
+
+/// Plays back a fixed list of readings, then reports [`Reading::Clear`].
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedSource {
+    script: VecDeque<Reading>,
+}
+
+impl ScriptedSource {
+    pub fn new<I: IntoIterator<Item = Reading>>(script: I) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+        }
+    }
+
+    /// Threat on the given 1-based cycles, clear on every other one.
+    pub fn threat_on(cycles: &[u32]) -> Self {
+        let last = cycles.iter().copied().max().unwrap_or(0);
+        Self::new((1..=last).map(|c| Reading::from(cycles.contains(&c))))
+    }
+
+    /// Readings not yet played back.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+}
+
+impl ThreatSource for ScriptedSource {
+    fn poll(&mut self) -> Reading {
+        self.script.pop_front().unwrap_or_default()
+    }
+}
@@ -0,0 +1,239 @@
+//! Threat escalation state machine.
+//!
+//! ```text
+//! Secure -> Suspected -> Detected -> BackupEngaged -> Cooldown -> Secure
+//!              |                                         |
+//!              +-> Secure (false alarm)                  +-> Detected (threat again)
+//! ```
+//!
+//! Time is counted in detection cycles, not milliseconds, so the machine
+//! never blocks and behaves identically on target and on host.
+
+use core::fmt;
+use std::collections::VecDeque;
+
+use crate::source::Reading;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ThreatState {
+    #[default]
+    Secure,
+    Suspected,
+    Detected,
+    BackupEngaged,
+    Cooldown,
+}
+
+impl ThreatState {
+    pub const ALL: [ThreatState; 5] = [
+        ThreatState::Secure,
+        ThreatState::Suspected,
+        ThreatState::Detected,
+        ThreatState::BackupEngaged,
+        ThreatState::Cooldown,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThreatState::Secure => "Secure",
+            ThreatState::Suspected => "Suspected",
+            ThreatState::Detected => "Detected",
+            ThreatState::BackupEngaged => "BackupEngaged",
+            ThreatState::Cooldown => "Cooldown",
+        }
+    }
+}
+
+impl fmt::Display for ThreatState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How many cycles to stay in a state after the cycle it was entered on.
+///
+/// A dwell of 0 means the state is passed straight through in the same cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dwell {
+    /// Extra consecutive threat readings needed to confirm a suspicion.
+    pub suspected: u32,
+    pub detected: u32,
+    pub backup: u32,
+    /// Clear readings needed before dropping back to Secure.
+    pub cooldown: u32,
+}
+
+impl Default for Dwell {
+    // one reading confirms and backup holds ~2 s at the default 1000 ms cycle,
+    // which is what the firmware did before with its blocking 2000 ms sleep
+    fn default() -> Self {
+        Self {
+            suspected: 0,
+            detected: 0,
+            backup: 2,
+            cooldown: 1,
+        }
+    }
+}
+
+/// A single state change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub cycle: u32,
+    pub from: ThreatState,
+    pub to: ThreatState,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "State {} -> {} [Cycle: {}]",
+            self.from, self.to, self.cycle
+        )
+    }
+}
+
+/// Bounded history of the most recent transitions, oldest first.
+#[derive(Debug, Clone)]
+pub struct TransitionLog {
+    entries: VecDeque<Transition>,
+    capacity: usize,
+}
+
+impl TransitionLog {
+    pub const DEFAULT_CAPACITY: usize = 32;
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, transition: Transition) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(transition);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Transition> {
+        self.entries.iter()
+    }
+
+    pub fn last(&self) -> Option<&Transition> {
+        self.entries.back()
+    }
+}
+
+impl Default for TransitionLog {
+    fn default() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThreatMachine {
+    dwell: Dwell,
+    state: ThreatState,
+    // completed cycles in `state` after the one it was entered on
+    in_state: u32,
+    log: TransitionLog,
+}
+
+impl ThreatMachine {
+    pub fn new(dwell: Dwell) -> Self {
+        Self {
+            dwell,
+            state: ThreatState::Secure,
+            in_state: 0,
+            log: TransitionLog::default(),
+        }
+    }
+
+    pub fn state(&self) -> ThreatState {
+        self.state
+    }
+
+    pub fn dwell(&self) -> Dwell {
+        self.dwell
+    }
+
+    pub fn log(&self) -> &TransitionLog {
+        &self.log
+    }
+
+    /// Feed one cycle's reading, calling `on_transition` for every state
+    /// change in order. Several changes can happen in one cycle when dwell
+    /// times are zero, but each state is entered at most once per cycle.
+    pub fn step<F: FnMut(Transition)>(
+        &mut self,
+        cycle: u32,
+        reading: Reading,
+        mut on_transition: F,
+    ) {
+        let threat = reading.is_threat();
+        // the reading that got us into a state has already been acted on
+        let mut fresh = false;
+
+        loop {
+            let next = match self.state {
+                ThreatState::Secure if threat && !fresh => ThreatState::Suspected,
+                ThreatState::Secure => break,
+                ThreatState::Suspected if !threat => ThreatState::Secure,
+                ThreatState::Cooldown if threat && !fresh => ThreatState::Detected,
+                state => {
+                    if self.in_state < self.held(state) {
+                        self.in_state += 1;
+                        break;
+                    }
+                    match state {
+                        ThreatState::Suspected => ThreatState::Detected,
+                        ThreatState::Detected => ThreatState::BackupEngaged,
+                        ThreatState::BackupEngaged => ThreatState::Cooldown,
+                        _ => ThreatState::Secure,
+                    }
+                }
+            };
+
+            let transition = Transition {
+                cycle,
+                from: self.state,
+                to: next,
+            };
+            self.state = next;
+            self.in_state = 0;
+            self.log.push(transition);
+            on_transition(transition);
+            fresh = true;
+        }
+    }
+
+    fn held(&self, state: ThreatState) -> u32 {
+        match state {
+            ThreatState::Secure => 0,
+            ThreatState::Suspected => self.dwell.suspected,
+            ThreatState::Detected => self.dwell.detected,
+            ThreatState::BackupEngaged => self.dwell.backup,
+            ThreatState::Cooldown => self.dwell.cooldown,
+        }
+    }
+}
+
+impl Default for ThreatMachine {
+    fn default() -> Self {
+        Self::new(Dwell::default())
+    }
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "injection")]
+
+use std::mem::size_of;
+
+use pulse_core::audit::{self, AuditEntry, AuditLog, Auditor};
+use pulse_core::mailbox::{Mailbox, MailboxSource, Signal};
+use pulse_core::{DebuggerFlag, ThreatSource};
+
+fn fake_timer() -> u64 {
+    0x1_0000_0010
+}
+
+#[test]
+fn ring_keeps_newest_and_decodes_from_raw_memory() {
+    assert_eq!(size_of::<AuditLog>(), audit::AUDIT_SIZE);
+
+    let log = AuditLog::new();
+    let auditor = Auditor::new(&log, fake_timer);
+    for cycle in 1..=40 {
+        auditor.record(cycle, cycle, 1, 0, 1);
+    }
+
+    let entries = log.entries();
+    assert_eq!(log.total(), 40);
+    assert_eq!(entries.len(), audit::AUDIT_CAPACITY);
+    assert_eq!(entries.first().unwrap().cycle, 9);
+    assert_eq!(entries.last().unwrap().timestamp_us, 0x1_0000_0010);
+
+    let raw = unsafe {
+        std::slice::from_raw_parts(&log as *const AuditLog as *const u8, audit::AUDIT_SIZE)
+    };
+    assert_eq!(AuditLog::decode(raw), Some(entries));
+    assert_eq!(AuditLog::decode(&raw[4..]), None);
+}
+
+#[test]
+fn sources_record_what_they_consume() {
+    let log = AuditLog::new();
+    let auditor = Auditor::new(&log, fake_timer);
+    let mailbox = Mailbox::new();
+    let mut threat_detected = false;
+
+    {
+        let flag = DebuggerFlag::new(&mut threat_detected).with_audit(auditor);
+        let mut source = MailboxSource::new(&mailbox, flag).with_audit(auditor);
+
+        source.poll();
+        let seq = mailbox.post(Signal::Threat, 3);
+        source.poll();
+        source.poll();
+        assert!(mailbox.is_acked(seq));
+        mailbox.post(Signal::DumpAudit, 0);
+        source.poll();
+    }
+    threat_detected = true;
+    let mut flag = DebuggerFlag::new(&mut threat_detected).with_audit(auditor);
+    flag.poll();
+    flag.poll();
+
+    assert_eq!(
+        log.entries(),
+        vec![
+            AuditEntry {
+                sequence: 1,
+                cycle: 2,
+                signal: 1,
+                old: 0,
+                new: 3,
+                timestamp_us: fake_timer(),
+            },
+            AuditEntry {
+                sequence: 2,
+                cycle: 4,
+                signal: 2,
+                old: 1,
+                new: 1,
+                timestamp_us: fake_timer(),
+            },
+            AuditEntry {
+                sequence: 0,
+                cycle: 1,
+                signal: audit::SIGNAL_DEBUGGER_FLAG,
+                old: 0,
+                new: 1,
+                timestamp_us: fake_timer(),
+            },
+        ]
+    );
+}
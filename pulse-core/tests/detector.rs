@@ -0,0 +1,91 @@
+#[cfg(feature = "injection")]
+use pulse_core::DebuggerFlag;
+use pulse_core::{
+    AdcThreshold, Detector, Dwell, Event, GpioSource, Reading, ScriptedSource, Timing, VirtualClock,
+};
+
+#[test]
+fn secure_cycle_sleeps_once() {
+    let mut detector = Detector::new(Timing::default(), Dwell::default());
+    let mut clock = VirtualClock::new();
+    let mut events = Vec::new();
+    let mut source = ScriptedSource::default();
+
+    detector.cycle(&mut source, &mut clock, &mut events);
+
+    assert_eq!(events, vec![Event::Secure { cycle: 1 }]);
+    assert_eq!(clock.elapsed_ms(), 1000);
+}
+
+#[test]
+fn threat_is_reported_without_blocking() {
+    let mut detector = Detector::new(Timing::default(), Dwell::default());
+    let mut clock = VirtualClock::new();
+    let mut events = Vec::new();
+    let mut source = ScriptedSource::threat_on(&[2]);
+
+    for _ in 0..3 {
+        detector.cycle(&mut source, &mut clock, &mut events);
+    }
+
+    assert!(events.contains(&Event::ThreatDetected { cycle: 2 }));
+    assert!(events.contains(&Event::EngagingBackup { cycle: 2 }));
+    assert!(!events.contains(&Event::Secure { cycle: 3 }));
+    assert_eq!(clock.elapsed_ms(), 3000);
+    assert_eq!(detector.counter(), 3);
+}
+
+#[cfg(feature = "injection")]
+#[test]
+fn debugger_flag_is_consumed() {
+    let mut detector = Detector::new(Timing::default(), Dwell::default());
+    let mut clock = VirtualClock::new();
+    let mut events = Vec::new();
+    let mut threat_detected = true;
+
+    let mut source = DebuggerFlag::new(&mut threat_detected);
+    detector.cycle(&mut source, &mut clock, &mut events);
+    detector.cycle(&mut source, &mut clock, &mut events);
+
+    assert!(!threat_detected);
+    assert!(events.contains(&Event::ThreatDetected { cycle: 1 }));
+    assert!(!events
+        .iter()
+        .any(|e| e.cycle() == 2 && matches!(e, Event::ThreatDetected { .. })));
+}
+
+#[test]
+fn gpio_and_adc_sources() {
+    use pulse_core::ThreatSource;
+
+    let mut level = [true, false].into_iter();
+    let mut pir = GpioSource::active_high(move || level.next().unwrap());
+    assert_eq!(pir.poll(), Reading::Threat);
+    assert_eq!(pir.poll(), Reading::Clear);
+
+    let mut inverted = GpioSource::active_low(|| false);
+    assert_eq!(inverted.poll(), Reading::Threat);
+
+    let mut samples = [1199u16, 1200, 4095].into_iter();
+    let mut radar = AdcThreshold::new(move || samples.next().unwrap(), 1200);
+    assert_eq!(radar.poll(), Reading::Clear);
+    assert_eq!(radar.poll(), Reading::Threat);
+    assert_eq!(radar.poll(), Reading::Threat);
+    assert_eq!(radar.last(), 4095);
+}
+
+#[test]
+fn log_lines_match_firmware_output() {
+    assert_eq!(
+        Event::ThreatDetected { cycle: 7 }.to_string(),
+        " !! THREAT DETECTED !! [Cycle: 7]"
+    );
+    assert_eq!(
+        Event::EngagingBackup { cycle: 7 }.to_string(),
+        "Engaging backup protocols..."
+    );
+    assert_eq!(
+        Event::Secure { cycle: 8 }.to_string(),
+        "System secure. [Cycle: 8]"
+    );
+}
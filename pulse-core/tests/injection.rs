@@ -0,0 +1,65 @@
+#![cfg(feature = "injection")]
+
+use std::sync::atomic::Ordering;
+
+use pulse_core::injection::{InjectionPoint, Kind, RawEntry};
+
+pulse_core::injection_point! {
+    /// Sleep between cycles.
+    pub static CYCLE_MS: u32 = 1000, range(10, 60_000);
+    static FORCE_THREAT: bool = false;
+    static OFFSET: i16 = -3, range(-100, 100);
+}
+
+#[test]
+fn declared_points_are_plain_atomics() {
+    assert_eq!(CYCLE_MS.load(Ordering::Relaxed), 1000);
+    assert!(!FORCE_THREAT.load(Ordering::Relaxed));
+    OFFSET.store(42, Ordering::Relaxed);
+    assert_eq!(OFFSET.load(Ordering::Relaxed), 42);
+}
+
+#[test]
+fn entries_round_trip_through_raw_bytes() {
+    static NAME: &str = "CYCLE_MS";
+    let entries = [
+        InjectionPoint::new(
+            NAME,
+            &CYCLE_MS as *const _ as *const (),
+            Kind::U32,
+            10,
+            60_000,
+        ),
+        InjectionPoint::new(
+            "FORCE_THREAT",
+            &FORCE_THREAT as *const _ as *const (),
+            Kind::Bool,
+            0,
+            1,
+        ),
+    ];
+    let ptr_width = std::mem::size_of::<usize>();
+    assert_eq!(
+        std::mem::size_of::<InjectionPoint>(),
+        RawEntry::stride(ptr_width)
+    );
+    assert_eq!(RawEntry::stride(4), 40);
+
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            entries.as_ptr() as *const u8,
+            std::mem::size_of_val(&entries),
+        )
+    };
+    let raw = RawEntry::parse_table(bytes, ptr_width);
+
+    assert_eq!(raw.len(), 2);
+    assert_eq!(raw[0].addr, &CYCLE_MS as *const _ as u64);
+    assert_eq!(raw[0].name_addr, NAME.as_ptr() as u64);
+    assert_eq!(raw[0].name_len, 8);
+    assert_eq!(raw[0].kind, Some(Kind::U32));
+    assert_eq!(raw[0].size, 4);
+    assert!(raw[0].in_range(10) && !raw[0].in_range(9));
+    assert_eq!((raw[1].min, raw[1].max), Kind::Bool.bounds());
+    assert_eq!(entries[1].name(), "FORCE_THREAT");
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "injection")]
+
+use std::mem::{offset_of, size_of};
+
+use pulse_core::mailbox::{self, Mailbox, MailboxSource, Signal};
+use pulse_core::{Reading, ScriptedSource, ThreatSource};
+
+#[test]
+fn layout_matches_published_offsets() {
+    assert_eq!(size_of::<Mailbox>() as u32, mailbox::MAILBOX_SIZE);
+    assert_eq!(offset_of!(Mailbox, magic) as u32, mailbox::OFFSET_MAGIC);
+    assert_eq!(offset_of!(Mailbox, version) as u32, mailbox::OFFSET_VERSION);
+    assert_eq!(
+        offset_of!(Mailbox, sequence) as u32,
+        mailbox::OFFSET_SEQUENCE
+    );
+    assert_eq!(offset_of!(Mailbox, signal) as u32, mailbox::OFFSET_SIGNAL);
+    assert_eq!(offset_of!(Mailbox, payload) as u32, mailbox::OFFSET_PAYLOAD);
+    assert_eq!(offset_of!(Mailbox, ack) as u32, mailbox::OFFSET_ACK);
+
+    let fresh = Mailbox::new();
+    let (magic, version) = (fresh.magic.into_inner(), fresh.version.into_inner());
+    assert_eq!(mailbox::check_header(magic, version), Ok(()));
+    assert!(mailbox::check_header(0, version).is_err());
+    assert!(mailbox::check_header(magic, version - 1).is_err());
+}
+
+#[test]
+fn requests_are_consumed_once_and_acked() {
+    let mailbox = Mailbox::new();
+    assert_eq!(mailbox.take(), None);
+
+    let seq = mailbox.post(Signal::Threat, 3);
+    assert!(!mailbox.is_acked(seq));
+    let injection = mailbox.take().unwrap();
+    assert_eq!(injection.signal, Signal::Threat);
+    assert_eq!(injection.payload, 3);
+    assert!(mailbox.is_acked(seq));
+    assert_eq!(mailbox.take(), None);
+}
+
+#[test]
+fn source_holds_threat_for_payload_cycles() {
+    let mailbox = Mailbox::new();
+    let mut source = MailboxSource::new(&mailbox, ScriptedSource::default());
+
+    assert_eq!(source.poll(), Reading::Clear);
+    mailbox.post(Signal::Threat, 2);
+    assert_eq!(source.poll(), Reading::Threat);
+    assert_eq!(source.poll(), Reading::Threat);
+    assert_eq!(source.poll(), Reading::Clear);
+
+    mailbox.post(Signal::Unknown(99), 5);
+    assert_eq!(source.poll(), Reading::Clear);
+}
@@ -0,0 +1,204 @@
+use pulse_core::{
+    Detector, Dwell, Event, Reading, ScriptedSource, ThreatMachine, ThreatState, Timing,
+    Transition, VirtualClock,
+};
+
+use ThreatState::*;
+
+const T: Reading = Reading::Threat;
+const C: Reading = Reading::Clear;
+
+fn dwell(suspected: u32, detected: u32, backup: u32, cooldown: u32) -> Dwell {
+    Dwell {
+        suspected,
+        detected,
+        backup,
+        cooldown,
+    }
+}
+
+/// Feed readings starting at cycle 1, returning (cycle, from, to) per transition
+/// and the state at the end of every cycle.
+fn run(
+    machine: &mut ThreatMachine,
+    readings: &[Reading],
+) -> (Vec<(u32, ThreatState, ThreatState)>, Vec<ThreatState>) {
+    let mut transitions = Vec::new();
+    let mut states = Vec::new();
+    for (i, &reading) in readings.iter().enumerate() {
+        machine.step(i as u32 + 1, reading, |t: Transition| {
+            transitions.push((t.cycle, t.from, t.to))
+        });
+        states.push(machine.state());
+    }
+    (transitions, states)
+}
+
+#[test]
+fn secure_stays_secure_on_clear() {
+    let mut machine = ThreatMachine::new(dwell(1, 1, 1, 1));
+    let (transitions, states) = run(&mut machine, &[C, C, C]);
+    assert!(transitions.is_empty());
+    assert_eq!(states, vec![Secure; 3]);
+}
+
+#[test]
+fn secure_to_suspected_on_threat() {
+    let mut machine = ThreatMachine::new(dwell(1, 1, 1, 1));
+    let (transitions, _) = run(&mut machine, &[C, T]);
+    assert_eq!(transitions, vec![(2, Secure, Suspected)]);
+}
+
+#[test]
+fn suspected_to_secure_on_false_alarm() {
+    let mut machine = ThreatMachine::new(dwell(2, 1, 1, 1));
+    let (transitions, states) = run(&mut machine, &[T, T, C, C]);
+    assert_eq!(
+        transitions,
+        vec![(1, Secure, Suspected), (3, Suspected, Secure)]
+    );
+    assert_eq!(states, vec![Suspected, Suspected, Secure, Secure]);
+}
+
+#[test]
+fn suspected_to_detected_after_dwell() {
+    let mut machine = ThreatMachine::new(dwell(2, 5, 1, 1));
+    let (transitions, states) = run(&mut machine, &[T, T, T]);
+    assert_eq!(
+        transitions,
+        vec![(1, Secure, Suspected), (3, Suspected, Detected)]
+    );
+    assert_eq!(states, vec![Suspected, Suspected, Detected]);
+}
+
+#[test]
+fn detected_to_backup_after_dwell_ignoring_readings() {
+    let mut machine = ThreatMachine::new(dwell(0, 2, 5, 1));
+    let (transitions, states) = run(&mut machine, &[T, C, T, C]);
+    assert_eq!(
+        transitions,
+        vec![
+            (1, Secure, Suspected),
+            (1, Suspected, Detected),
+            (3, Detected, BackupEngaged),
+        ]
+    );
+    assert_eq!(
+        states,
+        vec![Detected, Detected, BackupEngaged, BackupEngaged]
+    );
+}
+
+#[test]
+fn backup_to_cooldown_after_dwell_ignoring_readings() {
+    let mut machine = ThreatMachine::new(dwell(0, 0, 2, 5));
+    let (transitions, states) = run(&mut machine, &[T, T, C, C]);
+    assert_eq!(transitions.last(), Some(&(3, BackupEngaged, Cooldown)));
+    assert_eq!(
+        states,
+        vec![BackupEngaged, BackupEngaged, Cooldown, Cooldown]
+    );
+}
+
+#[test]
+fn cooldown_to_secure_after_clear_dwell() {
+    let mut machine = ThreatMachine::new(dwell(0, 0, 0, 2));
+    let (transitions, states) = run(&mut machine, &[T, C, C, C]);
+    assert_eq!(transitions.last(), Some(&(3, Cooldown, Secure)));
+    assert_eq!(states, vec![Cooldown, Cooldown, Secure, Secure]);
+}
+
+#[test]
+fn cooldown_to_detected_on_threat() {
+    let mut machine = ThreatMachine::new(dwell(0, 1, 0, 3));
+    let (transitions, states) = run(&mut machine, &[T, C, C, T]);
+    assert_eq!(
+        transitions,
+        vec![
+            (1, Secure, Suspected),
+            (1, Suspected, Detected),
+            (2, Detected, BackupEngaged),
+            (2, BackupEngaged, Cooldown),
+            (4, Cooldown, Detected),
+        ]
+    );
+    assert_eq!(states, vec![Detected, Cooldown, Cooldown, Detected]);
+}
+
+#[test]
+fn zero_dwell_runs_the_full_cycle_at_once() {
+    let mut machine = ThreatMachine::new(dwell(0, 0, 0, 0));
+    let (transitions, states) = run(&mut machine, &[T, T]);
+    let chain = [
+        (Secure, Suspected),
+        (Suspected, Detected),
+        (Detected, BackupEngaged),
+        (BackupEngaged, Cooldown),
+        (Cooldown, Secure),
+    ];
+    let expected: Vec<_> = [1, 2]
+        .iter()
+        .flat_map(|&cycle| chain.iter().map(move |&(from, to)| (cycle, from, to)))
+        .collect();
+    assert_eq!(transitions, expected);
+    assert_eq!(states, vec![Secure, Secure]);
+}
+
+#[test]
+fn every_state_is_reachable() {
+    let mut machine = ThreatMachine::new(dwell(1, 1, 1, 1));
+    let (_, states) = run(&mut machine, &[T, T, C, C, C, C, C]);
+    for state in ThreatState::ALL {
+        assert!(states.contains(&state), "{} never reached", state);
+    }
+}
+
+#[test]
+fn transition_log_keeps_most_recent() {
+    let mut machine = ThreatMachine::new(dwell(0, 0, 0, 0));
+    let readings = vec![T; 10];
+    run(&mut machine, &readings);
+
+    let log = machine.log();
+    assert_eq!(log.len(), 32);
+    assert_eq!(
+        log.last(),
+        Some(&Transition {
+            cycle: 10,
+            from: Cooldown,
+            to: Secure,
+        })
+    );
+    assert_eq!(log.iter().next().unwrap().cycle, 4);
+}
+
+#[test]
+fn detector_default_matches_legacy_output() {
+    let mut detector = Detector::new(Timing::default(), Dwell::default());
+    let mut clock = VirtualClock::new();
+    let mut events = Vec::new();
+    let mut source = ScriptedSource::threat_on(&[2]);
+
+    for _ in 0..6 {
+        detector.cycle(&mut source, &mut clock, &mut events);
+    }
+
+    let lines: Vec<_> = events
+        .iter()
+        .filter(|e| !matches!(e, Event::Transition(_)))
+        .copied()
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            Event::Secure { cycle: 1 },
+            Event::ThreatDetected { cycle: 2 },
+            Event::EngagingBackup { cycle: 2 },
+            Event::Secure { cycle: 5 },
+            Event::Secure { cycle: 6 },
+        ]
+    );
+    // nothing blocks any more, every cycle is one cycle_ms
+    assert_eq!(clock.elapsed_ms(), 6000);
+    assert_eq!(detector.state(), Secure);
+}
@@ -0,0 +1,381 @@
+//! The ELF core file ESP-IDF writes on a crash, and the two ways it
+//! reaches the host.
+//!
+//! With `CONFIG_ESP_COREDUMP_DATA_FORMAT_ELF` the panic handler writes an
+//! `ET_CORE` ELF: a `PT_NOTE` segment with one `NT_PRSTATUS` per task
+//! (the TCB address in `pr_pid`, the spilled registers in `pr_reg`), the
+//! dump info and the exception registers, then a `PT_LOAD` segment per
+//! TCB, stack and captured DRAM region. In the `coredump` partition it is
+//! preceded by a small header and followed by a checksum; on the UART the
+//! same bytes come base64-encoded between two marker lines.
+
+use base64::Engine;
+use object::elf::{FileHeader32, ET_CORE, NT_PRSTATUS, PT_LOAD};
+use object::read::elf::{FileHeader, ProgramHeader};
+use object::Endianness;
+use pulse_trace::{Registers, Windows};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// What ESP-IDF prints on the UART before and after the base64 lines.
+pub const UART_START: &str = "CORE DUMP START";
+pub const UART_END: &str = "CORE DUMP END";
+
+/// Xtensa special register numbers, as `EXTRA_INFO` tags them.
+pub const EPC1: u32 = 177;
+pub const EXCCAUSE: u32 = 232;
+pub const EXCVADDR: u32 = 238;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// `pr_pid` in a 32-bit `elf_prstatus`, and where `pr_reg` starts.
+const PRSTATUS_PID: usize = 24;
+const PRSTATUS_REG: usize = 72;
+/// `pr_reg`: `pc`, `ps`, `lbeg`, `lend`, `lcount`, `sar`, `windowstart`,
+/// `windowbase`, 56 reserved words, then the 64 physical `ar` registers.
+const REG_WINDOWSTART: usize = 6;
+const REG_WINDOWBASE: usize = 7;
+const REG_AR0: usize = 64;
+const AR_COUNT: usize = 64;
+/// Where the partition header may end and the ELF begin: ESP-IDF's
+/// header grew over releases, the ELF stays word aligned.
+const MAX_HEADER: usize = 64;
+
+/// One task as its `NT_PRSTATUS` note left it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskState {
+    pub tcb: u64,
+    pub pc: u32,
+    pub ps: u32,
+    pub windowbase: u32,
+    pub windowstart: u32,
+    /// The physical register file, `ar0`-`ar63`.
+    pub ar: [u32; AR_COUNT],
+}
+
+impl TaskState {
+    /// `a<n>` of the window `windowbase` selects.
+    pub fn a(&self, n: u32) -> u32 {
+        self.ar[((self.windowbase * 4 + n) as usize) % AR_COUNT]
+    }
+
+    /// What the unwinder starts from.
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.pc,
+            a0: self.a(0),
+            a1: self.a(1),
+            windows: Some(Windows {
+                windowbase: self.windowbase,
+                windowstart: self.windowstart,
+                ar: self.ar,
+            }),
+        }
+    }
+
+    fn from_prstatus(desc: &[u8]) -> Option<Self> {
+        let word = |n: usize| {
+            let b = desc.get(n..n + 4)?;
+            Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let reg = |n: usize| word(PRSTATUS_REG + n * 4);
+        let mut ar = [0; AR_COUNT];
+        for (n, value) in ar.iter_mut().enumerate() {
+            *value = reg(REG_AR0 + n)?;
+        }
+        Some(Self {
+            tcb: u64::from(word(PRSTATUS_PID)?),
+            pc: reg(0)?,
+            ps: reg(1)?,
+            windowbase: reg(REG_WINDOWBASE)? % (AR_COUNT as u32 / 4),
+            windowstart: reg(REG_WINDOWSTART)?,
+            ar,
+        })
+    }
+}
+
+/// Memory the dump captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoreDump {
+    /// The dump format version, chip ID in the upper half.
+    pub version: Option<u32>,
+    /// Hex SHA-256 of the app ELF, as much of it as the firmware keeps.
+    pub app_sha256: Option<String>,
+    /// TCB of the task that crashed, from `EXTRA_INFO`.
+    pub crashed_tcb: Option<u64>,
+    /// Special registers at the exception, `(register number, value)`.
+    pub exception: Vec<(u32, u32)>,
+    /// `ESP_PANIC_DETAILS`, the panic handler's text, when written.
+    pub panic_details: Option<String>,
+    /// In the order the dump lists them.
+    pub tasks: Vec<TaskState>,
+    pub segments: Vec<Segment>,
+}
+
+impl CoreDump {
+    /// Any of the three forms: a bare ELF core, a `coredump` partition
+    /// image, or a serial log with a UART dump in it.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.starts_with(ELF_MAGIC) {
+            return Self::from_elf(data);
+        }
+        if data
+            .windows(UART_START.len())
+            .any(|w| w == UART_START.as_bytes())
+        {
+            return Self::from_uart(&String::from_utf8_lossy(data));
+        }
+        Self::from_partition(data)
+    }
+
+    /// The `coredump` partition as `parttool.py read_partition` saves it:
+    /// header, ELF, checksum, then erased flash.
+    pub fn from_partition(data: &[u8]) -> Result<Self> {
+        let word = |at: usize| {
+            data.get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let (Some(len), Some(version)) = (word(0), word(4)) else {
+            return Err(Error::Format(format!("{} bytes is no header", data.len())));
+        };
+        if len == 0xffff_ffff || len == 0 {
+            return Err(Error::Empty);
+        }
+        if (version >> 8) & 0xff != 1 {
+            return Err(Error::Format(format!(
+                "version {:#x} is the binary format, only ELF dumps are read",
+                version
+            )));
+        }
+        let len = len as usize;
+        if len > data.len() {
+            return Err(Error::Format(format!(
+                "{} bytes stored, {} available",
+                len,
+                data.len()
+            )));
+        }
+        // even minor versions end in a CRC32, odd ones in a SHA-256
+        let sha256 = version & 1 == 1;
+        let body_len = len
+            .checked_sub(if sha256 { 32 } else { 4 })
+            .ok_or_else(|| Error::Format(format!("{} bytes is too short", len)))?;
+        let (body, stored) = (&data[..body_len], &data[body_len..len]);
+        let computed = match sha256 {
+            true => Sha256::digest(body).to_vec(),
+            false => crc32fast::hash(body).to_le_bytes().to_vec(),
+        };
+        if stored != computed {
+            return Err(Error::Checksum {
+                stored: hex(stored),
+                computed: hex(&computed),
+            });
+        }
+
+        let start = (8..=MAX_HEADER.min(body_len))
+            .step_by(4)
+            .find(|&at| body[at..].starts_with(ELF_MAGIC))
+            .ok_or_else(|| Error::Format("no ELF after the partition header".into()))?;
+        let mut dump = Self::from_elf(&body[start..])?;
+        dump.version = Some(version);
+        Ok(dump)
+    }
+
+    /// The first dump printed between the UART markers in `log`.
+    pub fn from_uart(log: &str) -> Result<Self> {
+        let mut uart = Uart::new();
+        for line in log.lines() {
+            if let Some(dump) = uart.line(line) {
+                return dump;
+            }
+        }
+        match uart.active() {
+            true => Err(Error::Format(format!("no {} line", UART_END))),
+            false => Err(Error::Empty),
+        }
+    }
+
+    /// A bare ELF core file.
+    pub fn from_elf(elf: &[u8]) -> Result<Self> {
+        let bad = |e: object::Error| Error::Elf(e.to_string());
+        let header = FileHeader32::<Endianness>::parse(elf).map_err(bad)?;
+        let endian = header.endian().map_err(bad)?;
+        if header.e_type(endian) != ET_CORE {
+            return Err(Error::Format("an ELF, but not a core file".into()));
+        }
+        let mut dump = Self {
+            version: None,
+            app_sha256: None,
+            crashed_tcb: None,
+            exception: Vec::new(),
+            panic_details: None,
+            tasks: Vec::new(),
+            segments: Vec::new(),
+        };
+        for phdr in header.program_headers(endian, elf).map_err(bad)? {
+            if phdr.p_type(endian) == PT_LOAD {
+                let data = phdr
+                    .data(endian, elf)
+                    .map_err(|()| Error::Elf("segment past the end of the file".into()))?;
+                dump.segments.push(Segment {
+                    addr: u64::from(phdr.p_vaddr(endian)),
+                    data: data.to_vec(),
+                });
+                continue;
+            }
+            let Some(mut notes) = phdr.notes(endian, elf).map_err(bad)? else {
+                continue;
+            };
+            while let Some(note) = notes.next().map_err(bad)? {
+                dump.note(note.name(), note.n_type(endian), note.desc())?;
+            }
+        }
+        if dump.tasks.is_empty() {
+            return Err(Error::Format("no task registers in the dump".into()));
+        }
+        Ok(dump)
+    }
+
+    fn note(&mut self, name: &[u8], kind: u32, desc: &[u8]) -> Result<()> {
+        let words = |desc: &[u8]| -> Vec<u32> {
+            desc.chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect()
+        };
+        let text = |bytes: &[u8]| {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+        match name {
+            b"CORE" if kind == NT_PRSTATUS => {
+                let task = TaskState::from_prstatus(desc)
+                    .ok_or_else(|| Error::Format("short NT_PRSTATUS note".into()))?;
+                self.tasks.push(task);
+            }
+            b"ESP_CORE_DUMP_INFO" => {
+                self.version = words(desc).first().copied();
+                let sha = text(desc.get(4..).unwrap_or_default());
+                self.app_sha256 = (!sha.is_empty()).then_some(sha);
+            }
+            b"EXTRA_INFO" => {
+                let words = words(desc);
+                self.crashed_tcb = words.first().map(|&tcb| u64::from(tcb));
+                self.exception = words
+                    .get(1..)
+                    .unwrap_or_default()
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect();
+            }
+            b"ESP_PANIC_DETAILS" => self.panic_details = Some(text(desc)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// A special register at the exception, by Xtensa register number.
+    pub fn special(&self, register: u32) -> Option<u32> {
+        self.exception
+            .iter()
+            .find(|&&(r, _)| r == register)
+            .map(|&(_, value)| value)
+    }
+
+    pub fn exccause(&self) -> Option<u32> {
+        self.special(EXCCAUSE)
+    }
+
+    pub fn excvaddr(&self) -> Option<u32> {
+        self.special(EXCVADDR)
+    }
+
+    /// The task that crashed; the first one listed if the dump does not say.
+    pub fn crashed(&self) -> Option<&TaskState> {
+        match self.crashed_tcb {
+            Some(tcb) => self.tasks.iter().find(|t| t.tcb == tcb),
+            None => self.tasks.first(),
+        }
+    }
+
+    /// `len` bytes at `addr`, if one segment captured them all.
+    pub fn read(&self, addr: u64, len: usize) -> Option<&[u8]> {
+        self.segments.iter().find_map(|s| {
+            let at = usize::try_from(addr.checked_sub(s.addr)?).ok()?;
+            s.data.get(at..at.checked_add(len)?)
+        })
+    }
+
+    pub fn read_u32(&self, addr: u64) -> Option<u32> {
+        let b = self.read(addr, 4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Whether `elf` is the build that crashed, `None` when the dump
+    /// does not say. The firmware may keep only a prefix of the hash.
+    pub fn elf_matches(&self, elf: &[u8]) -> Option<bool> {
+        let stored = self.app_sha256.as_deref()?.to_ascii_lowercase();
+        Some(hex(&Sha256::digest(elf)).starts_with(&stored))
+    }
+}
+
+/// Lets [`pulse_freertos`] walk the kernel's lists when the dump
+/// captured DRAM.
+impl pulse_freertos::Memory for CoreDump {
+    fn read(&mut self, addr: u64, len: usize) -> pulse_freertos::Result<Vec<u8>> {
+        CoreDump::read(self, addr, len)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| {
+                pulse_freertos::Error::Corrupt(format!("{:#010x} not in the core dump", addr))
+            })
+    }
+}
+
+/// Picks a UART core dump out of a serial log as it streams by.
+#[derive(Debug, Default)]
+pub struct Uart {
+    base64: Option<String>,
+}
+
+impl Uart {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Between the start and end markers: the line is part of a dump.
+    pub fn active(&self) -> bool {
+        self.base64.is_some()
+    }
+
+    /// Feed one line; the decoded dump once the end marker goes by.
+    pub fn line(&mut self, line: &str) -> Option<Result<CoreDump>> {
+        if line.contains(UART_START) {
+            self.base64 = Some(String::new());
+            return None;
+        }
+        let base64 = self.base64.as_mut()?;
+        if !line.contains(UART_END) {
+            base64.push_str(line.trim());
+            return None;
+        }
+        let base64 = self.base64.take()?;
+        if base64.is_empty() {
+            return Some(Err(Error::Empty));
+        }
+        Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(base64)
+                .map_err(|e| Error::Format(format!("base64: {}", e)))
+                .and_then(|data| CoreDump::from_partition(&data)),
+        )
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The core file itself does not parse.
+    Elf(String),
+    /// An erased partition, or markers with nothing between them.
+    Empty,
+    /// The checksum stored after the dump does not match it.
+    Checksum {
+        stored: String,
+        computed: String,
+    },
+    /// Not a core dump, a cut-off one, or a format we do not read.
+    Format(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Elf(e) => write!(f, "bad core ELF: {}", e),
+            Error::Empty => f.write_str("no core dump stored"),
+            Error::Checksum { stored, computed } => write!(
+                f,
+                "core dump checksum mismatch: stored {}, computed {}",
+                stored, computed
+            ),
+            Error::Format(what) => write!(f, "bad core dump: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,33 @@
+//! ESP-IDF core dumps of ghost-trigger, decoded on the host.
+//!
+//! A panic log has one backtrace and whatever the panic handler printed.
+//! A core dump has every task's registers and stack, and with
+//! `CONFIG_ESP_COREDUMP_CAPTURE_DRAM` the statics too, so the state the
+//! firmware died in can be read after the fact: [`CoreDump`] decodes the
+//! ELF core from the `coredump` partition, from the base64 the UART
+//! variant prints, or as a bare file, and [`CoreReport`] unwinds each task
+//! against the ELF and recovers the last known values of variables like
+//! `threat_detected`.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use pulse_coredump::{CoreDump, CoreReport, DEFAULT_VALUES};
+//!
+//! let elf = std::fs::read("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let debug = pulse_dwarf::DebugInfo::parse(&elf)?;
+//! // parttool.py read_partition --partition-type data --partition-subtype coredump --output core.bin
+//! let dump = CoreDump::parse(&std::fs::read("core.bin")?)?;
+//! let mut report = CoreReport::new(dump, Some(&debug), &Default::default(), &DEFAULT_VALUES);
+//! report.check_elf(&elf);
+//! print!("{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod dump;
+pub mod error;
+pub mod report;
+
+pub use dump::{CoreDump, Segment, TaskState, Uart};
+pub use error::{Error, Result};
+pub use report::{CoreReport, LastValue, TaskReport, DEFAULT_VALUES};
@@ -0,0 +1,291 @@
+//! A core dump turned into what an engineer reads: why it crashed, every
+//! task's backtrace, and what the variables of interest held.
+
+use std::fmt;
+
+use pulse_dwarf::{format_scalar, DebugInfo, Location, Scope};
+use pulse_freertos::Layout;
+use pulse_panic::{symbolize, write_backtrace, Frame};
+use pulse_trace::{unwind, MAX_DEPTH};
+use pulse_xtensa::exccause_name;
+
+use crate::dump::{CoreDump, TaskState};
+
+/// What ghost-trigger's post-mortems ask first: had the detector fired,
+/// and how far had it counted.
+pub const DEFAULT_VALUES: [&str; 2] = ["threat_detected", "detector.counter"];
+
+/// The windowed ABI's stack pointer, DWARF register 1.
+const SP: u16 = 1;
+
+/// One task's backtrace, unwound through the dumped stacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskReport {
+    pub tcb: u64,
+    /// `pcTaskName`, when the TCB is in the dump.
+    pub name: Option<String>,
+    pub crashed: bool,
+    pub pc: u64,
+    pub sp: u64,
+    /// Symbolized like a panic backtrace: inlined calls share an index.
+    pub frames: Vec<Frame>,
+}
+
+impl TaskReport {
+    fn name_or_tcb(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{:#010x}", self.tcb))
+    }
+}
+
+/// A variable's last value in the dump, or why there is none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastValue {
+    pub path: String,
+    /// Formatted after its DWARF type.
+    pub value: Result<String, String>,
+    /// Task and backtrace index it was read in, for locals.
+    pub frame: Option<(String, usize)>,
+    /// Where it was read from.
+    pub location: Option<String>,
+}
+
+impl fmt::Display for LastValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Ok(value) => write!(f, "{} = {}", self.path, value)?,
+            Err(why) => write!(f, "{}: {}", self.path, why)?,
+        }
+        match (&self.location, &self.frame) {
+            (Some(location), Some((task, index))) => {
+                write!(f, " ({} in {} #{})", location, task, index)
+            }
+            (Some(location), None) => write!(f, " ({})", location),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoreReport {
+    pub dump: CoreDump,
+    /// The crashed task first, then the rest in dump order.
+    pub tasks: Vec<TaskReport>,
+    pub values: Vec<LastValue>,
+    /// Whether the ELF symbolized against is the one that crashed, see
+    /// [`CoreReport::check_elf`].
+    pub elf_matches: Option<bool>,
+}
+
+impl CoreReport {
+    /// Unwind every task and look up `values`. Locals are searched in the
+    /// crashed task first, innermost frame first; only frame 0 has its
+    /// registers, outer frames have what their callees spilled to the
+    /// stack. Without debug info the frames carry addresses only and no
+    /// value can be found.
+    pub fn new(
+        dump: CoreDump,
+        debug: Option<&DebugInfo>,
+        layout: &Layout,
+        values: &[&str],
+    ) -> Self {
+        let crashed = dump.crashed().map(|t| t.tcb);
+        let mut order: Vec<&TaskState> = dump.tasks.iter().collect();
+        order.sort_by_key(|t| Some(t.tcb) != crashed);
+
+        let mut tasks = Vec::new();
+        let mut walks = Vec::new();
+        for task in order {
+            let walk = unwind(task.registers(), MAX_DEPTH, |addr| dump.read_u32(addr));
+            let name = dump
+                .read(task.tcb + layout.name, layout.name_len as usize)
+                .map(|raw| {
+                    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+                    String::from_utf8_lossy(&raw[..end]).into_owned()
+                });
+            tasks.push(TaskReport {
+                tcb: task.tcb,
+                name,
+                crashed: Some(task.tcb) == crashed,
+                pc: u64::from(task.pc),
+                sp: u64::from(task.a(1)),
+                frames: walk
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, f)| symbolize(index, f.pc, f.sp, debug))
+                    .collect(),
+            });
+            walks.push((task, walk));
+        }
+
+        let values = values
+            .iter()
+            .map(|path| {
+                let Some(debug) = debug else {
+                    return LastValue {
+                        path: path.to_string(),
+                        value: Err("no debug info".into()),
+                        frame: None,
+                        location: None,
+                    };
+                };
+                let frames = walks.iter().zip(&tasks).flat_map(|((task, walk), report)| {
+                    walk.iter()
+                        .enumerate()
+                        .map(move |(index, f)| (*task, report.name_or_tcb(), index, f.pc, f.sp))
+                });
+                last_value(&dump, debug, path, frames)
+            })
+            .collect();
+
+        Self {
+            dump,
+            tasks,
+            values,
+            elf_matches: None,
+        }
+    }
+
+    /// Compare `elf` with the SHA-256 the dump recorded.
+    pub fn check_elf(&mut self, elf: &[u8]) {
+        self.elf_matches = self.dump.elf_matches(elf);
+    }
+
+    /// `EXCCAUSE` with its name.
+    pub fn exccause(&self) -> Option<(u32, Option<&'static str>)> {
+        let cause = self.dump.exccause()?;
+        Some((cause, exccause_name(cause)))
+    }
+
+    /// One line: what happened, in which task and function.
+    pub fn summary(&self) -> String {
+        let mut out = match self.exccause() {
+            Some((_, Some(name))) => format!("core dump: {}", name),
+            Some((cause, None)) => format!("core dump: EXCCAUSE {}", cause),
+            None => "core dump".to_string(),
+        };
+        if let Some(task) = self.tasks.iter().find(|t| t.crashed) {
+            out.push_str(&format!(" in task {}", task.name_or_tcb()));
+            if let Some(function) = task.frames.iter().find_map(|f| f.function.as_deref()) {
+                out.push_str(", ");
+                out.push_str(function);
+            }
+        }
+        out
+    }
+}
+
+/// Search `frames` for `path`: the first frame it is in scope in decides.
+fn last_value<'a>(
+    dump: &CoreDump,
+    debug: &DebugInfo,
+    path: &str,
+    frames: impl Iterator<Item = (&'a TaskState, String, usize, u64, u64)>,
+) -> LastValue {
+    let mut out = LastValue {
+        path: path.to_string(),
+        value: Err("not in scope in any frame".into()),
+        frame: None,
+        location: None,
+    };
+    for (task, name, index, pc, sp) in frames {
+        let variable = match debug.resolve(path, Some(pc)) {
+            Ok(variable) => variable,
+            Err(pulse_dwarf::Error::NotFound(_)) => continue,
+            Err(pulse_dwarf::Error::OptimizedOut(_)) => {
+                out.value = Err("optimized out".into());
+                out.frame = Some((name, index));
+                return out;
+            }
+            Err(e) => {
+                out.value = Err(e.to_string());
+                return out;
+            }
+        };
+        out.location = Some(variable.location.to_string());
+        if let Scope::Local { .. } = variable.scope {
+            out.frame = Some((name, index));
+        }
+        let size = variable.ty.size as usize;
+        let bytes = match &variable.location {
+            Location::Value(bytes) => Some(bytes.clone()),
+            Location::Register(reg) if index == 0 && *reg < 16 => {
+                let value = task.a(u32::from(*reg)).to_le_bytes();
+                Some(value[..size.min(4)].to_vec())
+            }
+            Location::Register(reg) => {
+                out.value = Err(format!(
+                    "held in {} in frame #{}, not saved",
+                    pulse_dwarf::register_name(*reg),
+                    index
+                ));
+                return out;
+            }
+            location => {
+                let register = |reg: u16| match reg {
+                    SP => Some(sp),
+                    reg if index == 0 && reg < 16 => Some(u64::from(task.a(u32::from(reg)))),
+                    _ => None,
+                };
+                match location.address(register) {
+                    Some(addr) => {
+                        out.location = Some(format!("{:#010x}", addr));
+                        dump.read(addr, size).map(<[u8]>::to_vec)
+                    }
+                    None => {
+                        out.value = Err(format!("{} not saved in frame #{}", location, index));
+                        return out;
+                    }
+                }
+            }
+        };
+        out.value = match bytes {
+            Some(bytes) => Ok(format_scalar(&bytes, variable.ty.scalar())),
+            None => Err("not captured in the dump".into()),
+        };
+        return out;
+    }
+    out
+}
+
+impl fmt::Display for CoreReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        if let Some((cause, name)) = self.exccause() {
+            write!(f, "  EXCCAUSE {} {}", cause, name.unwrap_or("(unknown)"))?;
+            match self.dump.excvaddr() {
+                Some(vaddr) => writeln!(f, ", EXCVADDR {:#010x}", vaddr)?,
+                None => writeln!(f)?,
+            }
+        }
+        if let Some(details) = &self.dump.panic_details {
+            writeln!(f, "  {}", details.trim_end())?;
+        }
+        if self.elf_matches == Some(false) {
+            writeln!(
+                f,
+                "  warning: the ELF is not the one that crashed (SHA256 {}), symbols may be wrong",
+                self.dump.app_sha256.as_deref().unwrap_or_default()
+            )?;
+        }
+        if !self.values.is_empty() {
+            writeln!(f, "last known values:")?;
+            for value in &self.values {
+                writeln!(f, "  {}", value)?;
+            }
+        }
+        for task in &self.tasks {
+            let crashed = if task.crashed { " (crashed)" } else { "" };
+            writeln!(
+                f,
+                "task {}{}, TCB {:#010x}:",
+                task.name_or_tcb(),
+                crashed,
+                task.tcb
+            )?;
+            write_backtrace(f, &task.frames)?;
+        }
+        Ok(())
+    }
+}
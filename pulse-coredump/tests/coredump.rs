@@ -0,0 +1,155 @@
+use pulse_coredump::{CoreDump, CoreReport, Error, Uart, DEFAULT_VALUES};
+use pulse_dwarf::DebugInfo;
+use pulse_mock::{coredump, fixture};
+
+fn report(dump: CoreDump) -> CoreReport {
+    let debug = DebugInfo::parse(&fixture::ghost_trigger()).unwrap();
+    CoreReport::new(dump, Some(&debug), &Default::default(), &DEFAULT_VALUES)
+}
+
+#[test]
+fn elf_core_tasks_and_backtraces() {
+    let dump = CoreDump::parse(&coredump::ghost_trigger()).unwrap();
+    assert_eq!(dump.tasks.len(), 6);
+    assert_eq!(dump.exccause(), Some(28));
+    assert_eq!(dump.excvaddr(), Some(0));
+    let crashed = dump.crashed().unwrap();
+    assert_eq!(u64::from(crashed.pc), coredump::CRASH_PC);
+    assert_eq!(u64::from(crashed.a(1)), coredump::CRASH_SP);
+    assert_eq!(dump.elf_matches(&fixture::ghost_trigger()), Some(true));
+    assert_eq!(dump.elf_matches(b"another build"), Some(false));
+
+    let mut report = report(dump);
+    report.check_elf(&fixture::ghost_trigger());
+    assert_eq!(
+        report.summary(),
+        "core dump: LoadProhibited in task main, ghost_trigger::main"
+    );
+    let names: Vec<&str> = report
+        .tasks
+        .iter()
+        .map(|t| t.name.as_deref().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["main", "ipc0", "ipc1", "esp_timer", "IDLE0", "IDLE1"]
+    );
+    let main = &report.tasks[0];
+    assert!(main.crashed);
+    let frames: Vec<(usize, u64, Option<&str>)> = main
+        .frames
+        .iter()
+        .map(|f| (f.index, f.pc, f.function.as_deref()))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            (0, coredump::CRASH_PC, Some("ghost_trigger::main")),
+            (1, fixture::APP_MAIN + 0x20, Some("app_main")),
+        ]
+    );
+    assert!(report.tasks[1..].iter().all(|t| !t.crashed));
+
+    let text = report.to_string();
+    assert!(text.starts_with("core dump: LoadProhibited in task main, ghost_trigger::main\n"));
+    assert!(text.contains("  EXCCAUSE 28 LoadProhibited, EXCVADDR 0x00000000\n"));
+    assert!(text.contains(
+        "task main (crashed), TCB 0x3ffb8180:\n  #0  0x400d02c0 ghost_trigger::main+0x140"
+    ));
+    assert!(!text.contains("warning"));
+}
+
+#[test]
+fn last_known_values() {
+    let report = report(CoreDump::parse(&coredump::ghost_trigger()).unwrap());
+    let values: Vec<String> = report.values.iter().map(|v| v.to_string()).collect();
+    assert_eq!(
+        values,
+        [
+            "threat_detected = true (a8 in main #0)",
+            "detector.counter = 7 (0x3ffe1ee4 in main #0)",
+        ]
+    );
+    assert_eq!(
+        report.values[1].value,
+        Ok(coredump::CRASH_COUNTER.to_string())
+    );
+
+    // statics come from the captured DRAM, not from a frame
+    let debug = DebugInfo::parse(&fixture::ghost_trigger()).unwrap();
+    let dump = CoreDump::parse(&coredump::ghost_trigger()).unwrap();
+    let report = CoreReport::new(
+        dump,
+        Some(&debug),
+        &Default::default(),
+        &["PULSE_MAILBOX.magic", "no_such_thing"],
+    );
+    assert!(report.values[0].value.is_ok());
+    assert_eq!(report.values[0].frame, None);
+    assert_eq!(
+        report.values[1].value,
+        Err("not in scope in any frame".into())
+    );
+
+    // without the ELF: addresses only, no values
+    let report = CoreReport::new(
+        CoreDump::parse(&coredump::ghost_trigger()).unwrap(),
+        None,
+        &Default::default(),
+        &DEFAULT_VALUES,
+    );
+    assert_eq!(report.tasks[0].frames[0].function, None);
+    assert!(report.values.iter().all(|v| v.value.is_err()));
+}
+
+#[test]
+fn partition_image_and_checksum() {
+    let elf = coredump::ghost_trigger();
+    let mut partition = coredump::partition(&elf);
+    let stored = partition.len();
+    // the rest of the 64K partition is erased flash
+    partition.resize(0x10000, 0xff);
+    let dump = CoreDump::parse(&partition).unwrap();
+    assert_eq!(dump.version, Some(coredump::VERSION_ELF_CRC32));
+    assert_eq!(dump, CoreDump::from_elf(&elf).unwrap());
+
+    partition[stored / 2] ^= 1;
+    assert!(matches!(
+        CoreDump::parse(&partition),
+        Err(Error::Checksum { .. })
+    ));
+    assert!(matches!(
+        CoreDump::parse(&vec![0xff; 0x10000]),
+        Err(Error::Empty)
+    ));
+    assert!(matches!(
+        CoreDump::parse(&fixture::ghost_trigger()),
+        Err(Error::Format(_))
+    ));
+}
+
+#[test]
+fn uart_dump_in_a_serial_log() {
+    let partition = coredump::partition(&coredump::ghost_trigger());
+    let log = format!(
+        "Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled.\r\n{}Rebooting...\r\n",
+        coredump::uart(&partition)
+    );
+    let dump = CoreDump::parse(log.as_bytes()).unwrap();
+    assert_eq!(dump.tasks.len(), 6);
+
+    let mut uart = Uart::new();
+    let mut dumps = Vec::new();
+    for line in log.lines() {
+        dumps.extend(uart.line(line));
+        if line.starts_with("Guru") {
+            assert!(!uart.active());
+        }
+    }
+    assert!(!uart.active());
+    assert_eq!(dumps.len(), 1);
+    assert_eq!(dumps.pop().unwrap().unwrap(), dump);
+
+    let cut = log.replace(coredump::UART_END, "");
+    assert!(matches!(CoreDump::from_uart(&cut), Err(Error::Format(_))));
+}
@@ -0,0 +1,926 @@
+//! The adapter: DAP requests from the editor in, GDB RSP to OpenOCD or
+//! `pulse-mock` out.
+//!
+//! Requests are read on a thread of their own, so a running target can be
+//! watched for its stop and console output in between. `launch` resets
+//! the board and halts it, `attach` takes it where OpenOCD halted it; both
+//! let it run once the editor is done setting breakpoints, unless
+//! `stopOnEntry` is set. Breakpoints, `inject` and `monitor` commands sent
+//! while it runs halt it for as long as they take.
+
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulse_core::injection::RawEntry;
+use pulse_core::mailbox::Signal;
+use pulse_dwarf::{DebugInfo, SourceFrame, Variable};
+use pulse_rsp::stop::signal::SIGINT;
+use pulse_rsp::{BreakpointKind, Client, StopReason, StopReply};
+use pulse_scenario::{injection_points, stop_pc, Action};
+use pulse_trace::backtrace::REG_PC;
+use pulse_trace::MAX_DEPTH;
+use pulse_xtensa::TargetDescription;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+use crate::protocol::{read_message, Request, Writer};
+use crate::values::{self, Frame};
+
+/// Replies to everything but a resume, as `pulse` waits for them.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long either side is waited on while the target runs.
+const POLL: Duration = Duration::from_millis(10);
+/// `Z1` length, as pulse-scenario sets them.
+const BREAKPOINT_LEN: usize = 2;
+/// The one thread the editor sees: the CPU GDB stops.
+const THREAD: u64 = 1;
+
+/// What `launch` and `attach` load without an `elf` argument.
+pub const DEFAULT_ELF: &str = "target/xtensa-esp32-espidf/debug/ghost-trigger";
+
+/// Serve one editor on `input` and `output` until it disconnects or
+/// closes the stream. A target still attached then is detached and left
+/// running without the breakpoints.
+pub fn serve(mut input: impl BufRead + Send + 'static, output: impl Write) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || loop {
+        let message = read_message(&mut input);
+        let end = !matches!(message, Ok(Some(_)));
+        if tx.send(message).is_err() || end {
+            break;
+        }
+    });
+
+    let mut adapter = Adapter {
+        out: Writer::new(output),
+        target: None,
+        stop_on_entry: None,
+        events: Vec::new(),
+        done: false,
+    };
+    let served = loop {
+        if adapter.done {
+            break Ok(());
+        }
+        let message = match adapter.target.as_ref().is_some_and(|t| t.running) {
+            true => match rx.recv_timeout(POLL) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break Ok(()),
+            },
+            false => match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break Ok(()),
+            },
+        };
+        let handled = match message {
+            Some(Ok(Some(message))) => adapter.message(&message),
+            Some(Ok(None)) => break Ok(()),
+            Some(Err(e)) => break Err(e),
+            None => Ok(()),
+        };
+        if let Err(e) = handled.and_then(|()| adapter.poll()) {
+            break Err(e);
+        }
+    };
+    adapter.release();
+    served
+}
+
+struct Adapter<W: Write> {
+    out: Writer<W>,
+    target: Option<Target>,
+    /// The stop reason to report at `configurationDone` instead of
+    /// resuming, from `stopOnEntry`.
+    stop_on_entry: Option<&'static str>,
+    /// Sent after the response to the request that raised them.
+    events: Vec<(&'static str, Value)>,
+    done: bool,
+}
+
+struct Target {
+    gdb: Client,
+    debug: DebugInfo,
+    /// The ELF's `injection_point!` table, for `inject`.
+    points: Vec<(String, RawEntry)>,
+    desc: TargetDescription,
+    /// `(prefix in the DWARF, prefix on this machine)` pairs.
+    source_map: Vec<(String, String)>,
+    breakpoints: Vec<Breakpoint>,
+    next_id: u64,
+    running: bool,
+    /// Since the last stop, unwound when first asked for.
+    frames: Option<Vec<StackFrame>>,
+    /// `variablesReference` n is `handles[n - 1]`, until the next resume.
+    handles: Vec<Handle>,
+    /// `gotoTargets` ids, the same way.
+    goto_targets: Vec<u64>,
+}
+
+/// One address a source breakpoint is inserted at. A line with code in
+/// several places has several, under the same id.
+struct Breakpoint {
+    id: u64,
+    source: String,
+    address: u64,
+}
+
+/// One frame as the editor sees it: inlined calls get a frame each, on the
+/// stack frame they were inlined into.
+struct StackFrame {
+    pc: u64,
+    frame: Frame,
+    source: SourceFrame,
+}
+
+#[derive(Clone)]
+enum Handle {
+    /// Index into the frames.
+    Locals(usize),
+    Statics,
+    Members {
+        frame: Option<usize>,
+        variable: Variable,
+    },
+}
+
+/// A variable listed under a handle: its name there, the frame it is read
+/// in, and where it is.
+type Listed = (String, Option<usize>, Result<Variable>);
+
+impl<W: Write> Adapter<W> {
+    fn message(&mut self, message: &Value) -> Result<()> {
+        // responses to requests we never send, nothing to do
+        let Some(request) = Request::from_message(message)? else {
+            return Ok(());
+        };
+        match self.request(&request) {
+            Ok(body) => self.out.response(&request, body)?,
+            Err(e) => self.out.error(&request, &e.to_string())?,
+        }
+        self.flush()
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Value> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsSetVariable": true,
+                "supportsEvaluateForHovers": true,
+                "supportsGotoTargetsRequest": true,
+            })),
+            "launch" => self.start(args, true),
+            "attach" => self.start(args, false),
+            "configurationDone" => {
+                self.target()?;
+                match self.stop_on_entry.take() {
+                    Some(reason) => self.event("stopped", stopped(reason)),
+                    None => self.resume()?,
+                }
+                Ok(json!({}))
+            }
+            "disconnect" => {
+                self.release();
+                self.done = true;
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD, "name": "main" }] })),
+            "continue" => {
+                self.resume()?;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "pause" => self.pause(),
+            "setBreakpoints" => {
+                let path = args["source"]["path"]
+                    .as_str()
+                    .ok_or_else(|| Error::request("setBreakpoints needs a source path"))?
+                    .to_string();
+                let lines: Vec<u64> = args["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|b| b["line"].as_u64())
+                    .collect();
+                self.halted(|target| target.set_breakpoints(&path, &lines))
+            }
+            "stackTrace" => self.stack_trace(args),
+            "scopes" => {
+                let target = self.stopped()?;
+                let index = target.frame_index(args["frameId"].as_u64())?;
+                Ok(json!({ "scopes": [
+                    {
+                        "name": "Locals",
+                        "presentationHint": "locals",
+                        "variablesReference": target.handle(Handle::Locals(index)),
+                        "expensive": false,
+                    },
+                    {
+                        "name": "Statics",
+                        "variablesReference": target.handle(Handle::Statics),
+                        "expensive": true,
+                    },
+                ] }))
+            }
+            "variables" => {
+                let target = self.stopped()?;
+                let listed = target.listing(args["variablesReference"].as_u64())?;
+                let variables: Vec<Value> = listed
+                    .into_iter()
+                    .map(|(name, frame, variable)| target.variable(name, frame, variable))
+                    .collect();
+                Ok(json!({ "variables": variables }))
+            }
+            "setVariable" => self.set_variable(args),
+            "evaluate" => self.evaluate(args),
+            "gotoTargets" => self.goto_targets(args),
+            "goto" => {
+                let target = self.stopped()?;
+                let address = args["targetId"]
+                    .as_u64()
+                    .and_then(|id| target.goto_targets.get((id as usize).checked_sub(1)?))
+                    .copied()
+                    .ok_or_else(|| Error::request("no such goto target"))?;
+                target
+                    .gdb
+                    .write_register(REG_PC as u32, &(address as u32).to_le_bytes())?;
+                target.forget();
+                self.event("stopped", stopped("goto"));
+                Ok(json!({}))
+            }
+            "inject" => self.inject(args),
+            other => Err(Error::request(format!("`{}` is not supported", other))),
+        }
+    }
+
+    fn target(&mut self) -> Result<&mut Target> {
+        self.target
+            .as_mut()
+            .ok_or_else(|| Error::request("not attached to a target, launch or attach first"))
+    }
+
+    fn stopped(&mut self) -> Result<&mut Target> {
+        let target = self.target()?;
+        if target.running {
+            return Err(Error::request("the target is running, pause it first"));
+        }
+        Ok(target)
+    }
+
+    fn event(&mut self, event: &'static str, body: Value) {
+        self.events.push((event, body));
+    }
+
+    fn output(&mut self, category: &str, text: String) {
+        self.event("output", json!({ "category": category, "output": text }));
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        for (event, body) in std::mem::take(&mut self.events) {
+            self.out.event(event, body)?;
+        }
+        Ok(())
+    }
+
+    /// `launch` or `attach`: connect, load the ELF, and halt.
+    fn start(&mut self, args: &Value, launch: bool) -> Result<Value> {
+        if self.target.is_some() {
+            return Err(Error::request("already attached"));
+        }
+        let address = args["target"].as_str().map_or_else(
+            || format!("127.0.0.1:{}", pulse_rsp::DEFAULT_PORT),
+            str::to_string,
+        );
+        let elf = args["elf"].as_str().unwrap_or(DEFAULT_ELF);
+        let image = std::fs::read(elf).map_err(|e| Error::request(format!("{}: {}", elf, e)))?;
+        let debug =
+            DebugInfo::parse(&image).map_err(|e| Error::request(format!("{}: {}", elf, e)))?;
+        let points = injection_points(&image)?;
+        let source_map = match &args["sourceMap"] {
+            Value::Object(map) => map
+                .iter()
+                .filter_map(|(build, local)| Some((build.clone(), local.as_str()?.to_string())))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut gdb = Client::connect(address.as_str()).map_err(|e| {
+            Error::request(format!(
+                "cannot reach the debug server at {}: {}",
+                address, e
+            ))
+        })?;
+        gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+        gdb.handshake()?;
+        if launch {
+            gdb.monitor("reset halt")?;
+        }
+        let stop = gdb.halt_reason()?;
+        if !matches!(stop, StopReply::Signal { .. }) {
+            return Err(Error::request(format!("target is not running: {:?}", stop)));
+        }
+        let pc = stop_pc(&mut gdb, &stop)?;
+        let desc = TargetDescription::fetch_or_esp32(&mut gdb)?;
+        let function = debug
+            .function_at(pc)
+            .map_or(String::new(), |(f, _)| format!(" in {}", f));
+        self.output(
+            "console",
+            format!(
+                "{} {}, halted at {:#010x}{}\n",
+                if launch { "reset" } else { "attached to" },
+                address,
+                pc,
+                function
+            ),
+        );
+
+        self.target = Some(Target {
+            gdb,
+            debug,
+            points,
+            desc,
+            source_map,
+            breakpoints: Vec::new(),
+            next_id: 1,
+            running: false,
+            frames: None,
+            handles: Vec::new(),
+            goto_targets: Vec::new(),
+        });
+        self.stop_on_entry = args["stopOnEntry"]
+            .as_bool()
+            .unwrap_or(false)
+            .then_some(if launch { "entry" } else { "pause" });
+        self.event("initialized", json!({}));
+        Ok(json!({}))
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        let target = self.target()?;
+        if target.running {
+            return Ok(());
+        }
+        target.forget();
+        target.gdb.resume()?;
+        target.gdb.set_timeout(Some(POLL))?;
+        target.running = true;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<Value> {
+        let target = self.target()?;
+        if target.running {
+            target.gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+            let stop = target.gdb.interrupt()?;
+            self.console();
+            self.halt(stop)?;
+        }
+        Ok(json!({}))
+    }
+
+    /// Run `f` on a halted target. A running one is halted for it and let
+    /// go again after, unless it stopped on its own meanwhile.
+    fn halted<T>(&mut self, f: impl FnOnce(&mut Target) -> Result<T>) -> Result<T> {
+        let target = self.target()?;
+        let mut resume = false;
+        if target.running {
+            target.gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+            let stop = target.gdb.interrupt()?;
+            target.running = false;
+            if stop.signal() == Some(SIGINT) && stop.reason().is_none() {
+                resume = true;
+            } else {
+                self.console();
+                self.halt(stop)?;
+            }
+        }
+        let done = f(self.target()?);
+        if resume {
+            self.resume()?;
+        }
+        done
+    }
+
+    /// Between requests: the stop of a running target, and what it printed.
+    fn poll(&mut self) -> Result<()> {
+        let Some(target) = self.target.as_mut().filter(|t| t.running) else {
+            return self.flush();
+        };
+        let started = Instant::now();
+        let polled = loop {
+            match target.gdb.poll_stop() {
+                Ok(Some(stop)) => break Ok(Some(stop)),
+                // console output, more may follow
+                Ok(None) if started.elapsed() < POLL => {}
+                Ok(None) => break Ok(None),
+                Err(e) if e.is_timeout() => break Ok(None),
+                Err(e) => break Err(e),
+            }
+        };
+        self.console();
+        match polled {
+            Ok(Some(stop)) => self.halt(stop)?,
+            Ok(None) => {}
+            Err(e) => {
+                self.output("console", format!("lost the debug server: {}\n", e));
+                self.event("terminated", json!({}));
+                self.target = None;
+            }
+        }
+        self.flush()
+    }
+
+    /// Console output the target sent over GDB, as program output.
+    fn console(&mut self) {
+        let Some(target) = &mut self.target else {
+            return;
+        };
+        let console = target.gdb.take_console();
+        if !console.is_empty() {
+            self.output("stdout", String::from_utf8_lossy(&console).into_owned());
+        }
+    }
+
+    /// The target stopped, tell the editor why.
+    fn halt(&mut self, stop: StopReply) -> Result<()> {
+        let target = self.target()?;
+        target.running = false;
+        target.gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+        let signal = match stop {
+            StopReply::Signal { signal, .. } => signal,
+            StopReply::Exited(code) | StopReply::Terminated(code) => {
+                self.event("exited", json!({ "exitCode": code }));
+                self.event("terminated", json!({}));
+                self.target = None;
+                return Ok(());
+            }
+        };
+        let pc = stop_pc(&mut target.gdb, &stop)?;
+        let hit: Vec<u64> = target
+            .breakpoints
+            .iter()
+            .filter(|b| b.address == pc)
+            .map(|b| b.id)
+            .collect();
+        let reason = match stop.reason() {
+            _ if !hit.is_empty() => "breakpoint",
+            Some(StopReason::Watch { .. }) => "data breakpoint",
+            Some(_) => "breakpoint",
+            None if signal == SIGINT => "pause",
+            None => "exception",
+        };
+        let mut body = stopped(reason);
+        if !hit.is_empty() {
+            body["hitBreakpointIds"] = json!(hit);
+        }
+        if reason == "exception" {
+            body["description"] = json!(format!("signal {} at {:#010x}", signal, pc));
+        }
+        self.event("stopped", body);
+        Ok(())
+    }
+
+    /// Leave the board running as it was before, without our breakpoints.
+    fn release(&mut self) {
+        let Some(mut target) = self.target.take() else {
+            return;
+        };
+        let _ = target.gdb.set_timeout(Some(REPLY_TIMEOUT));
+        if target.running {
+            let _ = target.gdb.interrupt();
+        }
+        for breakpoint in &target.breakpoints {
+            let _ = target.gdb.remove_point(
+                BreakpointKind::Hardware,
+                breakpoint.address,
+                BREAKPOINT_LEN,
+            );
+        }
+        let _ = target.gdb.detach();
+    }
+
+    fn stack_trace(&mut self, args: &Value) -> Result<Value> {
+        let target = self.stopped()?;
+        let total = target.frames()?.len();
+        let start = args["startFrame"].as_u64().unwrap_or(0) as usize;
+        let levels = match args["levels"].as_u64() {
+            Some(n) if n > 0 => n as usize,
+            _ => total,
+        };
+        let frames = target.frames.as_deref().unwrap_or_default();
+        let stack: Vec<Value> = frames
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(levels)
+            .map(|(i, f)| {
+                let name = f
+                    .source
+                    .function
+                    .clone()
+                    .unwrap_or_else(|| format!("{:#010x}", f.pc));
+                let mut frame = json!({
+                    "id": i + 1,
+                    "name": if f.source.inlined { format!("{} (inlined)", name) } else { name },
+                    "line": f.source.line.unwrap_or(0),
+                    "column": f.source.column.unwrap_or(0),
+                    "instructionPointerReference": format!("{:#010x}", f.pc),
+                });
+                match &f.source.file {
+                    Some(file) => {
+                        let path = target.to_local(file);
+                        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+                        frame["source"] = json!({ "name": name, "path": path });
+                    }
+                    None => frame["presentationHint"] = json!("subtle"),
+                }
+                frame
+            })
+            .collect();
+        Ok(json!({ "stackFrames": stack, "totalFrames": total }))
+    }
+
+    fn set_variable(&mut self, args: &Value) -> Result<Value> {
+        let target = self.stopped()?;
+        let name = args["name"].as_str().unwrap_or_default();
+        let text = args["value"]
+            .as_str()
+            .ok_or_else(|| Error::request("setVariable needs a value"))?;
+        let (_, frame, variable) = target
+            .listing(args["variablesReference"].as_u64())?
+            .into_iter()
+            .find(|(n, ..)| n == name)
+            .ok_or_else(|| Error::request(format!("no variable `{}` here", name)))?;
+        let variable = variable?;
+        let bytes = values::parse(&variable.ty, text)?;
+        let at = target.frame_at(frame);
+        values::write(&mut target.gdb, &target.desc, at, &variable, &bytes)?;
+        Ok(json!({
+            "value": target.value(frame, &variable),
+            "type": variable.ty.name,
+            "variablesReference": 0,
+        }))
+    }
+
+    /// A variable path like `detector.counter` or `PULSE_AUDIT.records[3]`
+    /// in a frame, or `monitor CMD` for OpenOCD.
+    fn evaluate(&mut self, args: &Value) -> Result<Value> {
+        let expression = args["expression"].as_str().unwrap_or_default().trim();
+        if let Some(cmd) = expression.strip_prefix("monitor ") {
+            let output = self.halted(|target| Ok(target.gdb.monitor(cmd)?))?;
+            return Ok(json!({ "result": output.trim_end(), "variablesReference": 0 }));
+        }
+        let target = self.stopped()?;
+        let index = target.frame_index(args["frameId"].as_u64())?;
+        let pc = target.frames()?[index].pc;
+        let variable = target.debug.resolve(expression, Some(pc))?;
+        let shown = target.variable(expression.to_string(), Some(index), Ok(variable));
+        Ok(json!({
+            "result": shown["value"],
+            "type": shown["type"],
+            "variablesReference": shown["variablesReference"],
+        }))
+    }
+
+    fn goto_targets(&mut self, args: &Value) -> Result<Value> {
+        let target = self.target()?;
+        let path = args["source"]["path"].as_str().unwrap_or_default();
+        let line = args["line"].as_u64().unwrap_or(0);
+        let found = target.debug.line_addresses(&target.to_build(path), line)?;
+        let Some((line, addresses)) = found else {
+            return Ok(json!({ "targets": [] }));
+        };
+        let file = path.rsplit('/').next().unwrap_or(path);
+        let targets: Vec<Value> = addresses
+            .into_iter()
+            .map(|address| {
+                target.goto_targets.push(address);
+                json!({
+                    "id": target.goto_targets.len(),
+                    "label": format!("{}:{} ({:#010x})", file, line, address),
+                    "line": line,
+                    "instructionPointerReference": format!("{:#010x}", address),
+                })
+            })
+            .collect();
+        Ok(json!({ "targets": targets }))
+    }
+
+    /// The custom `inject` request: what `pulse inject` does, in the
+    /// session. Arguments are those of a scenario's `[[inject]]`.
+    fn inject(&mut self, args: &Value) -> Result<Value> {
+        let actions = actions(args)?;
+        let injected = self.halted(|target| {
+            let pc = target.pc()?;
+            let done = actions
+                .iter()
+                .map(|action| {
+                    Ok(pulse_scenario::apply(
+                        &mut target.gdb,
+                        &target.debug,
+                        &target.points,
+                        action,
+                        pc,
+                    )?)
+                })
+                .collect::<Result<Vec<String>>>();
+            target.forget();
+            done
+        })?;
+        for done in &injected {
+            self.output("console", format!("injected {}\n", done));
+        }
+        if self.target.as_ref().is_some_and(|t| !t.running) {
+            self.event("invalidated", json!({ "areas": ["variables"] }));
+        }
+        Ok(json!({ "injected": injected }))
+    }
+}
+
+fn stopped(reason: &str) -> Value {
+    json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true })
+}
+
+/// `mailbox`, `payload` and `write = [{ var, value }]`, as in a scenario.
+fn actions(args: &Value) -> Result<Vec<Action>> {
+    let mut actions = Vec::new();
+    if !args["mailbox"].is_null() {
+        let signal = match &args["mailbox"] {
+            Value::String(s) if s == "threat" => Signal::Threat,
+            Value::String(s) if s == "dump_audit" => Signal::DumpAudit,
+            id => id
+                .as_u64()
+                .and_then(|id| u32::try_from(id).ok())
+                .map(Signal::from_id)
+                .ok_or_else(|| {
+                    Error::request("`mailbox` is \"threat\", \"dump_audit\" or a signal id")
+                })?,
+        };
+        let payload = match &args["payload"] {
+            Value::Null => 0,
+            payload => payload
+                .as_u64()
+                .and_then(|p| u32::try_from(p).ok())
+                .ok_or_else(|| Error::request("`payload` out of range"))?,
+        };
+        actions.push(Action::Mailbox { signal, payload });
+    } else if !args["payload"].is_null() {
+        return Err(Error::request("`payload` goes with `mailbox`"));
+    }
+    for write in args["write"].as_array().into_iter().flatten() {
+        let var = write["var"]
+            .as_str()
+            .ok_or_else(|| Error::request("each `write` needs a `var`"))?;
+        let value = match &write["value"] {
+            Value::Bool(b) => u64::from(*b),
+            value => value
+                .as_u64()
+                .or_else(|| value.as_i64().map(|v| v as u64))
+                .ok_or_else(|| Error::request(format!("bad value for `{}`", var)))?,
+        };
+        actions.push(Action::Write {
+            var: var.to_string(),
+            value,
+        });
+    }
+    if actions.is_empty() {
+        return Err(Error::request(
+            "nothing to inject, give `mailbox` or `write`",
+        ));
+    }
+    Ok(actions)
+}
+
+impl Target {
+    /// Drop what only held until the next resume.
+    fn forget(&mut self) {
+        self.frames = None;
+        self.handles.clear();
+        self.goto_targets.clear();
+    }
+
+    fn pc(&mut self) -> Result<u64> {
+        Ok(values::word(&self.gdb.read_register(REG_PC as u32)?))
+    }
+
+    /// `path` from the line table as it is on this machine.
+    fn to_local(&self, path: &str) -> String {
+        self.source_map
+            .iter()
+            .find_map(|(build, local)| remap(path, build, local))
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    /// The other way round, a path the editor sent.
+    fn to_build(&self, path: &str) -> String {
+        self.source_map
+            .iter()
+            .find_map(|(build, local)| remap(path, local, build))
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    fn set_breakpoints(&mut self, path: &str, lines: &[u64]) -> Result<Value> {
+        let file = self.to_build(path);
+        let mut wanted = Vec::new();
+        for &line in lines {
+            wanted.push((line, self.debug.line_addresses(&file, line)?));
+        }
+        let keep: Vec<u64> = wanted
+            .iter()
+            .filter_map(|(_, found)| found.as_ref())
+            .flat_map(|(_, addresses)| addresses.iter().copied())
+            .collect();
+
+        let (old, others): (Vec<Breakpoint>, Vec<Breakpoint>) =
+            std::mem::take(&mut self.breakpoints)
+                .into_iter()
+                .partition(|b| b.source == path);
+        self.breakpoints = others;
+        let mut inserted: Vec<u64> = Vec::new();
+        for b in old {
+            let shared = self.breakpoints.iter().any(|o| o.address == b.address);
+            if keep.contains(&b.address) || shared {
+                inserted.push(b.address);
+            } else {
+                self.gdb
+                    .remove_point(BreakpointKind::Hardware, b.address, BREAKPOINT_LEN)?;
+            }
+        }
+        inserted.extend(self.breakpoints.iter().map(|b| b.address));
+
+        let mut reply = Vec::new();
+        for (line, found) in wanted {
+            let Some((at, addresses)) = found else {
+                reply.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": format!("no code at or after line {}", line),
+                }));
+                continue;
+            };
+            let id = self.next_id;
+            self.next_id += 1;
+            let mut failed = None;
+            for address in addresses {
+                if !inserted.contains(&address) {
+                    if let Err(e) =
+                        self.gdb
+                            .insert_point(BreakpointKind::Hardware, address, BREAKPOINT_LEN)
+                    {
+                        failed = Some(Error::from(e));
+                        continue;
+                    }
+                    inserted.push(address);
+                }
+                self.breakpoints.push(Breakpoint {
+                    id,
+                    source: path.to_string(),
+                    address,
+                });
+            }
+            reply.push(match failed {
+                Some(e) if !self.breakpoints.iter().any(|b| b.id == id) => json!({
+                    "id": id,
+                    "verified": false,
+                    "line": at,
+                    "message": e.to_string(),
+                }),
+                _ => json!({ "id": id, "verified": true, "line": at }),
+            });
+        }
+        Ok(json!({ "breakpoints": reply }))
+    }
+
+    fn frames(&mut self) -> Result<&[StackFrame]> {
+        if self.frames.is_none() {
+            let mut frames = Vec::new();
+            let stack = pulse_trace::capture(&mut self.gdb, &self.desc, MAX_DEPTH, None)?;
+            for (depth, f) in stack.into_iter().enumerate() {
+                let frame = Frame { depth, sp: f.sp };
+                let mut sources = self.debug.source_frames(f.pc)?;
+                if sources.is_empty() {
+                    sources.push(SourceFrame {
+                        function: None,
+                        file: None,
+                        line: None,
+                        column: None,
+                        inlined: false,
+                    });
+                }
+                frames.extend(sources.into_iter().map(|source| StackFrame {
+                    pc: f.pc,
+                    frame,
+                    source,
+                }));
+            }
+            self.frames = Some(frames);
+        }
+        Ok(self.frames.as_deref().unwrap_or_default())
+    }
+
+    /// A `frameId` as an index into the frames, the innermost without one.
+    fn frame_index(&mut self, id: Option<u64>) -> Result<usize> {
+        let count = self.frames()?.len();
+        let index = id.map_or(Some(0), |id| (id as usize).checked_sub(1));
+        index
+            .filter(|&i| i < count)
+            .ok_or_else(|| Error::request("no such stack frame"))
+    }
+
+    fn frame_at(&self, index: Option<usize>) -> Option<Frame> {
+        let frames = self.frames.as_deref().unwrap_or_default();
+        index.and_then(|i| frames.get(i)).map(|f| f.frame)
+    }
+
+    fn handle(&mut self, handle: Handle) -> usize {
+        self.handles.push(handle);
+        self.handles.len()
+    }
+
+    /// What a `variablesReference` lists.
+    fn listing(&mut self, reference: Option<u64>) -> Result<Vec<Listed>> {
+        let handle = reference
+            .and_then(|r| self.handles.get((r as usize).checked_sub(1)?))
+            .cloned()
+            .ok_or_else(|| Error::request("no such variables reference"))?;
+        Ok(match handle {
+            Handle::Locals(index) => {
+                let pc = self.frames()?[index].pc;
+                self.debug
+                    .locals(pc)?
+                    .into_iter()
+                    .map(|v| {
+                        let name = v.path.rsplit("::").next().unwrap_or(&v.path).to_string();
+                        (name, Some(index), Ok(v))
+                    })
+                    .collect()
+            }
+            Handle::Statics => self
+                .debug
+                .statics()
+                .into_iter()
+                .map(|v| (v.path.clone(), None, Ok(v)))
+                .collect(),
+            Handle::Members { frame, variable } => values::children(&variable)
+                .into_iter()
+                .map(|(name, v)| (name, frame, v))
+                .collect(),
+        })
+    }
+
+    /// A DAP `Variable`: structs and arrays get a handle to expand them,
+    /// everything else is read and shown.
+    fn variable(
+        &mut self,
+        name: String,
+        frame: Option<usize>,
+        variable: Result<Variable>,
+    ) -> Value {
+        let variable = match variable {
+            Ok(variable) => variable,
+            Err(e) => {
+                return json!({
+                    "name": name,
+                    "value": format!("<{}>", e),
+                    "variablesReference": 0,
+                })
+            }
+        };
+        let (value, reference) = match values::expandable(&variable.ty) {
+            true => (
+                variable.ty.name.clone(),
+                self.handle(Handle::Members {
+                    frame,
+                    variable: variable.clone(),
+                }),
+            ),
+            false => (self.value(frame, &variable), 0),
+        };
+        json!({
+            "name": name,
+            "value": value,
+            "type": variable.ty.name,
+            "evaluateName": variable.path,
+            "variablesReference": reference,
+        })
+    }
+
+    fn value(&mut self, frame: Option<usize>, variable: &Variable) -> String {
+        let at = self.frame_at(frame);
+        match values::read(&mut self.gdb, &self.desc, at, variable) {
+            Ok(bytes) => values::display(&variable.ty, &bytes),
+            Err(e) => format!("<{}>", e),
+        }
+    }
+}
+
+/// `path` with the directory `from` swapped for `to`.
+fn remap(path: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    let rest = path.strip_prefix(from)?;
+    (rest.is_empty() || rest.starts_with('/'))
+        .then(|| format!("{}{}", to.trim_end_matches('/'), rest))
+}
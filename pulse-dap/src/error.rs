@@ -0,0 +1,89 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Rsp(pulse_rsp::Error),
+    Dwarf(pulse_dwarf::Error),
+    Trace(pulse_trace::Error),
+    Xtensa(pulse_xtensa::Error),
+    Scenario(pulse_scenario::Error),
+    /// A message that is not DAP.
+    Protocol(String),
+    /// A request that cannot be done, said back in its error response.
+    Request(String),
+}
+
+impl Error {
+    pub(crate) fn request(what: impl Into<String>) -> Self {
+        Error::Request(what.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Dwarf(e) => write!(f, "debug info: {}", e),
+            Error::Trace(e) => write!(f, "backtrace: {}", e),
+            Error::Xtensa(e) => write!(f, "registers: {}", e),
+            Error::Scenario(e) => write!(f, "inject: {}", e),
+            Error::Protocol(what) => write!(f, "bad DAP message: {}", what),
+            Error::Request(what) => f.write_str(what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            Error::Trace(e) => Some(e),
+            Error::Xtensa(e) => Some(e),
+            Error::Scenario(e) => Some(e),
+            Error::Protocol(_) | Error::Request(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_dwarf::Error> for Error {
+    fn from(e: pulse_dwarf::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
+
+impl From<pulse_trace::Error> for Error {
+    fn from(e: pulse_trace::Error) -> Self {
+        Error::Trace(e)
+    }
+}
+
+impl From<pulse_xtensa::Error> for Error {
+    fn from(e: pulse_xtensa::Error) -> Self {
+        Error::Xtensa(e)
+    }
+}
+
+impl From<pulse_scenario::Error> for Error {
+    fn from(e: pulse_scenario::Error) -> Self {
+        Error::Scenario(e)
+    }
+}
@@ -0,0 +1,37 @@
+//! Debug Adapter Protocol server for ghost-trigger.
+//!
+//! VS Code (or any DAP client) talks to `pulse-dap` instead of
+//! Cortex-Debug and a GDB. It speaks GDB RSP to OpenOCD or `pulse-mock`
+//! itself and reads the ghost-trigger ELF's DWARF with `pulse-dwarf`, so
+//! breakpoints by source line, the call stack with inlined frames, Rust
+//! locals like `threat_detected` and `detector.counter`, setting them, and
+//! the custom `inject` request all work without an xtensa GDB installed.
+//!
+//! ```json
+//! {
+//!     "type": "pulse-dap",
+//!     "request": "launch",
+//!     "name": "ghost-trigger",
+//!     "debugServer": 4711,
+//!     "target": "127.0.0.1:3333",
+//!     "elf": "${workspaceFolder}/target/xtensa-esp32-espidf/debug/ghost-trigger",
+//!     "sourceMap": { "/build/ghost-trigger": "${workspaceFolder}" },
+//!     "stopOnEntry": true
+//! }
+//! ```
+//!
+//! ```no_run
+//! # fn main() -> pulse_dap::Result<()> {
+//! let stdin = std::io::BufReader::new(std::io::stdin());
+//! pulse_dap::serve(stdin, std::io::stdout())
+//! # }
+//! ```
+
+pub mod adapter;
+pub mod error;
+pub mod protocol;
+pub mod values;
+
+pub use adapter::{serve, DEFAULT_ELF};
+pub use error::{Error, Result};
+pub use protocol::{read_message, write_message, Request, Writer};
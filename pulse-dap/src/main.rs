@@ -0,0 +1,55 @@
+use std::io::BufReader;
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: pulse-dap [--port N]";
+
+fn main() -> ExitCode {
+    let mut port = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => match args.next().and_then(|v| v.parse::<u16>().ok()) {
+                Some(v) => port = Some(v),
+                None => return usage(),
+            },
+            _ => return usage(),
+        }
+    }
+
+    // stdout is the protocol, everything else goes to stderr
+    let Some(port) = port else {
+        let stdin = BufReader::new(std::io::stdin());
+        return match pulse_dap::serve(stdin, std::io::stdout()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    };
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("cannot listen on port {}: {}", port, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("debug adapter listening on 127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let served = stream.map_err(pulse_dap::Error::from).and_then(|stream| {
+            let input = BufReader::new(stream.try_clone()?);
+            pulse_dap::serve(input, stream)
+        });
+        if let Err(e) = served {
+            eprintln!("{}", e);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
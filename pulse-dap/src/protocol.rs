@@ -0,0 +1,133 @@
+//! The DAP wire format: a JSON body behind a `Content-Length` header, the
+//! same over stdin and stdout as over a TCP connection.
+//!
+//! ```text
+//! Content-Length: 61\r\n
+//! \r\n
+//! {"seq":1,"type":"request","command":"threads","arguments":{}}
+//! ```
+
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+/// The next message, `None` once the client closed the stream between
+/// messages.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    let mut started = false;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return match started {
+                false => Ok(None),
+                true => Err(Error::Protocol("stream ended in a header".into())),
+            };
+        }
+        started = true;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("Content-Length") {
+                let n = value.trim().parse::<usize>().map_err(|_| {
+                    Error::Protocol(format!("bad Content-Length {:?}", value.trim()))
+                })?;
+                length = Some(n);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| Error::Protocol("header without Content-Length".into()))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| Error::Protocol(e.to_string()))
+}
+
+pub fn write_message(out: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// A request from the client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub seq: u64,
+    pub command: String,
+    /// `{}` when the client sent none.
+    pub arguments: Value,
+}
+
+impl Request {
+    /// `None` for responses and events, which a client may also send.
+    pub fn from_message(message: &Value) -> Result<Option<Self>> {
+        if message["type"] != "request" {
+            return Ok(None);
+        }
+        let (Some(seq), Some(command)) = (message["seq"].as_u64(), message["command"].as_str())
+        else {
+            return Err(Error::Protocol(format!(
+                "request without seq or command: {}",
+                message
+            )));
+        };
+        Ok(Some(Self {
+            seq,
+            command: command.to_string(),
+            arguments: match &message["arguments"] {
+                Value::Null => json!({}),
+                arguments => arguments.clone(),
+            },
+        }))
+    }
+}
+
+/// Numbers and frames what the adapter sends.
+pub struct Writer<W: Write> {
+    out: W,
+    seq: u64,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, seq: 0 }
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.out, &message)
+    }
+
+    pub fn response(&mut self, request: &Request, body: Value) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+            "body": body,
+        }))
+    }
+
+    /// A failed request, `message` is what the user gets to see.
+    pub fn error(&mut self, request: &Request, message: &str) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message,
+            "body": { "error": { "id": 1, "format": message, "showUser": true } },
+        }))
+    }
+
+    pub fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+}
@@ -0,0 +1,252 @@
+//! Reading, showing and writing the variables of a stopped frame.
+//!
+//! The innermost frame has every register live. The unwinder only
+//! recovers the stack pointer of the frames above it, so their
+//! frame-relative locals can be read and written but locals they keep in
+//! a register cannot.
+
+use pulse_dwarf::{format_scalar, register_name, Encoding, Location, Type, TypeKind, Variable};
+use pulse_rsp::Client;
+use pulse_xtensa::{TargetDescription, Window};
+
+use crate::error::{Error, Result};
+
+/// Array elements listed at most, the rest are left out.
+pub const MAX_ELEMENTS: u64 = 256;
+
+/// The stack frame locals are read in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// 0 for the innermost frame.
+    pub depth: usize,
+    /// Its `a1`.
+    pub sp: u64,
+}
+
+/// The bytes of `variable`, `frame` being `None` for statics.
+pub fn read(
+    gdb: &mut Client,
+    desc: &TargetDescription,
+    frame: Option<Frame>,
+    variable: &Variable,
+) -> Result<Vec<u8>> {
+    let size = variable.ty.size as usize;
+    match &variable.location {
+        Location::Value(bytes) => Ok(bytes.iter().copied().take(size).collect()),
+        Location::Register(reg) => {
+            let regnum = live_register(gdb, desc, frame, *reg, &variable.path)?;
+            let mut bytes = gdb.read_register(regnum)?;
+            bytes.truncate(size);
+            Ok(bytes)
+        }
+        Location::Address(_) | Location::Memory { .. } => {
+            let addr = address(gdb, desc, frame, variable)?;
+            Ok(gdb.read_memory(addr, size)?)
+        }
+    }
+}
+
+/// Store `bytes`, as [`parse`] made them, into `variable`.
+pub fn write(
+    gdb: &mut Client,
+    desc: &TargetDescription,
+    frame: Option<Frame>,
+    variable: &Variable,
+    bytes: &[u8],
+) -> Result<()> {
+    match &variable.location {
+        Location::Value(_) => Err(Error::request(format!(
+            "`{}` is a constant here, nothing to write",
+            variable.path
+        ))),
+        Location::Register(reg) => {
+            let regnum = live_register(gdb, desc, frame, *reg, &variable.path)?;
+            let mut word = gdb.read_register(regnum)?;
+            let n = bytes.len().min(word.len());
+            word[..n].copy_from_slice(&bytes[..n]);
+            Ok(gdb.write_register(regnum, &word)?)
+        }
+        Location::Address(_) | Location::Memory { .. } => {
+            let addr = address(gdb, desc, frame, variable)?;
+            Ok(gdb.write_memory(addr, bytes)?)
+        }
+    }
+}
+
+fn address(
+    gdb: &mut Client,
+    desc: &TargetDescription,
+    frame: Option<Frame>,
+    variable: &Variable,
+) -> Result<u64> {
+    let mut base = None;
+    if let Location::Memory { register, .. } = variable.location {
+        base = Some(match frame {
+            Some(frame) if register == 1 => frame.sp,
+            _ => {
+                let regnum = live_register(gdb, desc, frame, register, &variable.path)?;
+                word(&gdb.read_register(regnum)?)
+            }
+        });
+    }
+    variable.location.address(|_| base).ok_or_else(|| {
+        Error::request(format!(
+            "`{}` at {} is not in memory",
+            variable.path, variable.location
+        ))
+    })
+}
+
+/// GDB's number for DWARF register `reg`, which only the innermost frame
+/// has for certain.
+fn live_register(
+    gdb: &mut Client,
+    desc: &TargetDescription,
+    frame: Option<Frame>,
+    reg: u16,
+    path: &str,
+) -> Result<u32> {
+    match frame {
+        Some(frame) if frame.depth > 0 => Err(Error::request(format!(
+            "`{}` is in {} of a caller, only its stack pointer is known",
+            path,
+            register_name(reg)
+        ))),
+        _ => Ok(Window::read(gdb, desc)?.regnum(u32::from(reg))),
+    }
+}
+
+pub(crate) fn word(bytes: &[u8]) -> u64 {
+    let mut word = [0u8; 8];
+    let n = bytes.len().min(8);
+    word[..n].copy_from_slice(&bytes[..n]);
+    u64::from_le_bytes(word)
+}
+
+/// Structs and arrays are listed member by member instead of shown.
+pub fn expandable(ty: &Type) -> bool {
+    if ty.scalar().is_some() {
+        return false;
+    }
+    match &ty.kind {
+        TypeKind::Struct(members) => !members.is_empty(),
+        TypeKind::Array { count, .. } => *count > 0,
+        _ => false,
+    }
+}
+
+/// A leaf value the way Rust would print it: numbers, `true`, the enum
+/// variant, pointers in hex, anything else as raw hex.
+pub fn display(ty: &Type, bytes: &[u8]) -> String {
+    match &ty.kind {
+        TypeKind::Enum(variants) => {
+            let value = signed(bytes);
+            variants
+                .iter()
+                .find(|&&(_, v)| v == value)
+                .map_or_else(|| value.to_string(), |(name, _)| name.clone())
+        }
+        TypeKind::Pointer { .. } => format!("{:#010x}", word(bytes)),
+        _ => format_scalar(bytes, ty.scalar()),
+    }
+}
+
+fn signed(bytes: &[u8]) -> i64 {
+    let shift = 64 - 8 * bytes.len().clamp(1, 8) as u32;
+    ((word(bytes) << shift) as i64) >> shift
+}
+
+/// Members or elements of an expandable variable, each with its name in
+/// the parent or why it has no location.
+pub fn children(variable: &Variable) -> Vec<(String, Result<Variable>)> {
+    let child = |name: String, access: String, offset: u64, ty: &Type| {
+        let located = variable
+            .location
+            .offset(offset)
+            .map(|location| Variable {
+                path: format!("{}{}", variable.path, access),
+                location,
+                ty: ty.clone(),
+                ..variable.clone()
+            })
+            .map_err(Error::from);
+        (name, located)
+    };
+    match &variable.ty.kind {
+        TypeKind::Struct(members) => members
+            .iter()
+            .map(|m| {
+                // tuple fields are `__0`, `__1`, ... in rustc's DWARF
+                let name = m
+                    .name
+                    .strip_prefix("__")
+                    .filter(|n| n.parse::<u32>().is_ok())
+                    .unwrap_or(&m.name)
+                    .to_string();
+                let access = format!(".{}", name);
+                child(name, access, m.offset, &m.ty)
+            })
+            .collect(),
+        TypeKind::Array { element, count } => (0..(*count).min(MAX_ELEMENTS))
+            .map(|i| {
+                let access = format!("[{}]", i);
+                child(access.clone(), access, i * element.size, element)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// What the user typed for a new value, as the bytes of `ty`: `true` and
+/// `false`, integers in decimal or `0x` hex, floats, enum variant names.
+pub fn parse(ty: &Type, text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    let size = ty.size as usize;
+    let bad = || Error::request(format!("cannot store `{}` in a {}", text, ty.name));
+    if !(1..=8).contains(&size) {
+        return Err(bad());
+    }
+    let value: i128 = match (&ty.kind, ty.scalar()) {
+        (_, Some(Encoding::Bool)) => match text {
+            "true" => 1,
+            "false" => 0,
+            _ => return Err(bad()),
+        },
+        (_, Some(Encoding::Float)) => {
+            return match size {
+                4 => text.parse::<f32>().map(|v| v.to_le_bytes().to_vec()),
+                8 => text.parse::<f64>().map(|v| v.to_le_bytes().to_vec()),
+                _ => return Err(bad()),
+            }
+            .map_err(|_| bad())
+        }
+        (TypeKind::Enum(variants), _) => variants
+            .iter()
+            .find(|(name, _)| name == text)
+            .map(|&(_, v)| i128::from(v))
+            .or_else(|| number(text))
+            .ok_or_else(bad)?,
+        (TypeKind::Pointer { .. }, _) | (_, Some(_)) => number(text).ok_or_else(bad)?,
+        _ => return Err(bad()),
+    };
+    let bits = size as u32 * 8;
+    if value < -(1i128 << (bits - 1)) || value >= 1i128 << bits {
+        return Err(Error::request(format!(
+            "{} does not fit in a {}",
+            text, ty.name
+        )));
+    }
+    Ok(value.to_le_bytes()[..size].to_vec())
+}
+
+fn number(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
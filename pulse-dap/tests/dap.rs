@@ -0,0 +1,343 @@
+use std::collections::VecDeque;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use pulse_dap::{read_message, write_message};
+use pulse_mock::{fixture, Image, Options, Server};
+use serde_json::{json, Value};
+
+/// Where the fixture's build directories are mapped to: this workspace.
+fn checkout() -> String {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    manifest.parent().unwrap().to_string_lossy().into_owned()
+}
+
+/// The fixture ELF on disk, for `elf`.
+///
+/// Written once per test binary and renamed into place, so a test running
+/// in parallel never sees it half written. It lives in cargo's per-target
+/// scratch directory under a fixed name: the next run replaces it and
+/// `cargo clean` removes it.
+fn elf() -> &'static Path {
+    static ELF: OnceLock<PathBuf> = OnceLock::new();
+    ELF.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+        let path = dir.join("pulse-dap.elf");
+        let partial = dir.join(format!("pulse-dap.elf.{}", std::process::id()));
+        std::fs::write(&partial, fixture::ghost_trigger()).unwrap();
+        std::fs::rename(&partial, &path).unwrap();
+        path
+    })
+}
+
+/// VS Code's side of a session, scripted.
+struct Editor {
+    input: BufReader<TcpStream>,
+    output: TcpStream,
+    seq: u64,
+    events: VecDeque<Value>,
+    adapter: Option<JoinHandle<pulse_dap::Result<()>>>,
+}
+
+impl Editor {
+    /// A fresh adapter, `initialize`d and launched on `target`.
+    fn launch(target: &str, stop_on_entry: bool) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let adapter = thread::spawn(move || {
+            let input = BufReader::new(stream.try_clone()?);
+            pulse_dap::serve(input, stream)
+        });
+        output
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut editor = Self {
+            input: BufReader::new(output.try_clone().unwrap()),
+            output,
+            seq: 0,
+            events: VecDeque::new(),
+            adapter: Some(adapter),
+        };
+
+        let caps = editor.ok("initialize", json!({ "adapterID": "pulse-dap" }));
+        assert_eq!(caps["supportsSetVariable"], true);
+        if !target.is_empty() {
+            editor.ok("launch", launch(target, stop_on_entry));
+            editor.event("initialized");
+        }
+        editor
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let message = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        write_message(&mut self.output, &message).unwrap();
+        loop {
+            let message = self.read();
+            if message["type"] == "event" {
+                self.events.push_back(message);
+                continue;
+            }
+            assert_eq!(message["request_seq"], self.seq, "{}", message);
+            return message;
+        }
+    }
+
+    /// The body of a response that must have succeeded.
+    fn ok(&mut self, command: &str, arguments: Value) -> Value {
+        let response = self.request(command, arguments);
+        assert_eq!(response["success"], true, "{}: {}", command, response);
+        response["body"].clone()
+    }
+
+    /// The message of one that must have failed.
+    fn fails(&mut self, command: &str, arguments: Value) -> String {
+        let response = self.request(command, arguments);
+        assert_eq!(response["success"], false, "{}: {}", command, response);
+        response["message"].as_str().unwrap().to_string()
+    }
+
+    /// The body of the next `name` event, skipping others.
+    fn event(&mut self, name: &str) -> Value {
+        loop {
+            let event = match self.events.pop_front() {
+                Some(event) => event,
+                None => self.read(),
+            };
+            if event["event"] == name {
+                return event["body"].clone();
+            }
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        read_message(&mut self.input)
+            .unwrap()
+            .expect("adapter hung up")
+    }
+
+    fn variables(&mut self, reference: &Value) -> Vec<Value> {
+        let body = self.ok("variables", json!({ "variablesReference": reference }));
+        body["variables"].as_array().unwrap().clone()
+    }
+
+    fn disconnect(mut self) {
+        self.ok("disconnect", json!({}));
+        self.adapter.take().unwrap().join().unwrap().unwrap();
+    }
+}
+
+fn launch(target: &str, stop_on_entry: bool) -> Value {
+    json!({
+        "target": target,
+        "elf": elf(),
+        "stopOnEntry": stop_on_entry,
+        "sourceMap": {
+            fixture::COMP_DIR: format!("{}/ghost-trigger", checkout()),
+            fixture::PULSE_CORE_DIR: format!("{}/pulse-core", checkout()),
+        },
+    })
+}
+
+fn named<'a>(variables: &'a [Value], name: &str) -> &'a Value {
+    variables
+        .iter()
+        .find(|v| v["name"] == name)
+        .unwrap_or_else(|| panic!("no {} in {:?}", name, variables))
+}
+
+fn mock(time_scale: f64) -> String {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        Options { time_scale },
+    )
+    .unwrap();
+    server.spawn().unwrap().0.to_string()
+}
+
+#[test]
+fn breaks_on_a_line_and_sets_a_callers_local() {
+    let mut editor = Editor::launch(&mock(0.0), false);
+    let detector_rs = format!("{}/pulse-core/src/detector.rs", checkout());
+    let body = editor.ok(
+        "setBreakpoints",
+        json!({ "source": { "path": detector_rs }, "breakpoints": [{ "line": 120 }] }),
+    );
+    let breakpoint = &body["breakpoints"][0];
+    assert_eq!(breakpoint["verified"], true);
+    assert_eq!(breakpoint["line"], 120);
+    editor.ok("configurationDone", json!({}));
+
+    let stopped = editor.event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    assert_eq!(stopped["hitBreakpointIds"], json!([breakpoint["id"]]));
+
+    let trace = editor.ok("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["stackFrames"].as_array().unwrap();
+    let names: Vec<&str> = frames.iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        [
+            "pulse_core::detector::Detector::cycle",
+            "ghost_trigger::main",
+            "app_main"
+        ]
+    );
+    assert_eq!(frames[0]["source"]["path"], detector_rs);
+    assert_eq!(
+        frames[1]["source"]["path"],
+        format!("{}/ghost-trigger/src/main.rs", checkout())
+    );
+    assert_eq!(frames[1]["line"], 47);
+
+    // main's flag lives in its stack frame, reachable from the caller's sp
+    let main = frames[1]["id"].clone();
+    let scopes = editor.ok("scopes", json!({ "frameId": main }));
+    let reference = scopes["scopes"][0]["variablesReference"].clone();
+    let locals = editor.variables(&reference);
+    let flag = named(&locals, "threat_detected");
+    assert_eq!(flag["value"], "false");
+    assert_eq!(flag["type"], "bool");
+
+    let set = editor.ok(
+        "setVariable",
+        json!({ "variablesReference": reference, "name": "threat_detected", "value": "true" }),
+    );
+    assert_eq!(set["value"], "true");
+    let shown = editor.ok(
+        "evaluate",
+        json!({ "expression": "threat_detected", "frameId": main }),
+    );
+    assert_eq!(shown["result"], "true");
+
+    // and the next cycle stops there again
+    editor.ok("continue", json!({ "threadId": 1 }));
+    assert_eq!(editor.event("stopped")["reason"], "breakpoint");
+    editor.disconnect();
+}
+
+#[test]
+fn jumps_to_a_line_and_edits_the_detector() {
+    let mut editor = Editor::launch(&mock(0.0), true);
+    editor.ok("configurationDone", json!({}));
+    assert_eq!(editor.event("stopped")["reason"], "entry");
+
+    let main_rs = format!("{}/ghost-trigger/src/main.rs", checkout());
+    let targets = editor.ok(
+        "gotoTargets",
+        json!({ "source": { "path": main_rs }, "line": 54 }),
+    );
+    let goto = &targets["targets"][0];
+    assert_eq!(goto["line"], 55);
+    editor.ok("goto", json!({ "threadId": 1, "targetId": goto["id"] }));
+    assert_eq!(editor.event("stopped")["reason"], "goto");
+
+    let trace = editor.ok("stackTrace", json!({ "threadId": 1 }));
+    let top = trace["stackFrames"][0].clone();
+    assert_eq!(top["line"], 55);
+    let scopes = editor.ok("scopes", json!({ "frameId": top["id"] }));
+    let locals = editor.variables(&scopes["scopes"][0]["variablesReference"]);
+    let names: Vec<&str> = locals.iter().map(|v| v["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["threat_detected", "detector"]);
+
+    let detector = named(&locals, "detector");
+    assert_eq!(detector["type"], "pulse_core::detector::Detector");
+    let reference = detector["variablesReference"].clone();
+    let members = editor.variables(&reference);
+    assert_eq!(
+        named(&members, "counter")["evaluateName"],
+        "ghost_trigger::main::detector.counter"
+    );
+    let set = editor.ok(
+        "setVariable",
+        json!({ "variablesReference": reference, "name": "counter", "value": "7" }),
+    );
+    assert_eq!(set["value"], "7");
+    let shown = editor.ok("evaluate", json!({ "expression": "detector.counter" }));
+    assert_eq!(shown["result"], "7");
+    assert!(editor
+        .fails(
+            "setVariable",
+            json!({ "variablesReference": reference, "name": "counter", "value": "0x100000000" }),
+        )
+        .contains("does not fit"));
+
+    // here the flag is in a8, live in the innermost frame
+    let reference = scopes["scopes"][0]["variablesReference"].clone();
+    let set = editor.ok(
+        "setVariable",
+        json!({ "variablesReference": reference, "name": "threat_detected", "value": "true" }),
+    );
+    assert_eq!(set["value"], "true");
+    editor.disconnect();
+}
+
+#[test]
+fn injects_into_a_running_target() {
+    let mut editor = Editor::launch(&mock(0.01), false);
+    editor.ok("configurationDone", json!({}));
+
+    let body = editor.ok("inject", json!({ "mailbox": "threat" }));
+    assert_eq!(body["injected"].as_array().unwrap().len(), 1);
+    loop {
+        let output = editor.event("output");
+        if output["category"] == "stdout"
+            && output["output"]
+                .as_str()
+                .unwrap()
+                .contains("THREAT DETECTED")
+        {
+            break;
+        }
+    }
+
+    editor.ok("pause", json!({ "threadId": 1 }));
+    assert_eq!(editor.event("stopped")["reason"], "pause");
+    let statics = editor.ok("scopes", json!({ "frameId": 1 }))["scopes"][1].clone();
+    assert_eq!(statics["expensive"], true);
+    editor.disconnect();
+}
+
+#[test]
+fn answers_what_it_cannot_do_with_errors() {
+    let target = mock(0.0);
+    let mut editor = Editor::launch("", false);
+    assert!(editor
+        .fails("stackTrace", json!({ "threadId": 1 }))
+        .contains("launch or attach first"));
+    editor.ok("launch", launch(&target, true));
+    editor.ok("configurationDone", json!({}));
+    editor.event("stopped");
+    assert!(editor
+        .fails("attach", launch(&target, false))
+        .contains("already attached"));
+
+    let main_rs = format!("{}/ghost-trigger/src/main.rs", checkout());
+    let body = editor.ok(
+        "setBreakpoints",
+        json!({ "source": { "path": main_rs }, "breakpoints": [{ "line": 71 }] }),
+    );
+    assert_eq!(body["breakpoints"][0]["verified"], false);
+
+    assert!(editor
+        .fails("next", json!({ "threadId": 1 }))
+        .contains("not supported"));
+    assert!(editor
+        .fails("evaluate", json!({ "expression": "no_such_thing" }))
+        .contains("no_such_thing"));
+    assert!(editor
+        .fails("inject", json!({ "payload": 3 }))
+        .contains("goes with `mailbox`"));
+    editor.disconnect();
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Os {
+    Linux,
+    MacOs,
+    Other,
+}
+
+/// Everything the checks look at, so tests can point them at a fake
+/// filesystem and `PATH`.
+#[derive(Debug, Clone)]
+pub struct Env {
+    pub os: Os,
+    /// `std::env::consts::ARCH` naming, `x86_64` or `aarch64`.
+    pub arch: String,
+    pub path: Vec<PathBuf>,
+    pub home: Option<PathBuf>,
+    pub vars: HashMap<String, String>,
+    /// The firmware crate, for its `.embuild` directory.
+    pub project: PathBuf,
+    /// Prefix for `/sys`, `/dev` and the udev rule directories.
+    pub root: PathBuf,
+    /// Whether the current user can open a device node read-write.
+    pub can_open: fn(&Path) -> bool,
+}
+
+impl Env {
+    pub fn current(project: impl Into<PathBuf>) -> Self {
+        let os = match std::env::consts::OS {
+            "linux" => Os::Linux,
+            "macos" => Os::MacOs,
+            _ => Os::Other,
+        };
+        Self {
+            os,
+            arch: std::env::consts::ARCH.to_string(),
+            path: std::env::var_os("PATH")
+                .map(|p| std::env::split_paths(&p).collect())
+                .unwrap_or_default(),
+            home: std::env::var_os("HOME").map(PathBuf::from),
+            vars: std::env::vars().collect(),
+            project: project.into(),
+            root: PathBuf::from("/"),
+            can_open: |node| OpenOptions::new().read(true).write(true).open(node).is_ok(),
+        }
+    }
+
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.vars
+            .get(name)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// `path` under [`root`](Self::root), `path` being absolute.
+    pub fn system(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn home_join(&self, path: &str) -> Option<PathBuf> {
+        self.home.as_ref().map(|home| home.join(path))
+    }
+
+    /// First executable called `name` on `PATH`.
+    pub fn which(&self, name: &str) -> Option<PathBuf> {
+        self.path
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|candidate| is_executable(candidate))
+    }
+}
+
+pub(crate) fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// First non-empty line a program prints for `args`, stdout or stderr
+/// (OpenOCD uses the latter).
+pub(crate) fn first_line(program: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Subdirectories of `dir`, sorted by name.
+pub(crate) fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
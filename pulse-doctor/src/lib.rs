@@ -0,0 +1,39 @@
+//! Environment checks for debugging ghost-trigger.
+//!
+//! Finds `xtensa-esp32-elf-gdb`, `openocd`, `espflash`, `ldproxy`, the
+//! espup `esp` toolchain and ESP-IDF, compares versions with what the docs
+//! were written against, and on Linux checks that the JTAG adapter and
+//! serial port can be opened without root. Every problem comes with the
+//! commands that fix it:
+//!
+//! ```no_run
+//! let env = pulse_doctor::Env::current("ghost-trigger");
+//! for check in pulse_doctor::run(&env) {
+//!     println!("{}", check);
+//! }
+//! ```
+
+pub mod check;
+pub mod env;
+pub mod tools;
+pub mod usb;
+pub mod version;
+
+pub use check::{Check, Status};
+pub use env::{Env, Os};
+pub use version::Version;
+
+/// Every check, in the order a new setup would fix them.
+pub fn run(env: &Env) -> Vec<Check> {
+    let mut checks = vec![
+        tools::gdb(env),
+        tools::openocd(env),
+        tools::espflash(env),
+        tools::ldproxy(env),
+        tools::toolchain(env),
+        tools::esp_idf(env),
+    ];
+    checks.extend(usb::adapter_access(env));
+    checks.extend(usb::serial_access(env));
+    checks
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use pulse_doctor::{Env, Status};
+
+const USAGE: &str = "usage: pulse-doctor [--project DIR]";
+
+fn main() -> ExitCode {
+    let mut project = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--project" => match args.next() {
+                Some(dir) => project = Some(PathBuf::from(dir)),
+                None => return usage(),
+            },
+            _ => return usage(),
+        }
+    }
+    // run from the workspace root or from ghost-trigger itself
+    let project = project.unwrap_or_else(|| {
+        let nested = PathBuf::from("ghost-trigger");
+        if nested.is_dir() {
+            nested
+        } else {
+            PathBuf::from(".")
+        }
+    });
+
+    let checks = pulse_doctor::run(&Env::current(project));
+    for check in &checks {
+        println!("{}", check);
+    }
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!();
+    println!("{} failed, {} warnings", failed, warned);
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
@@ -0,0 +1,258 @@
+//! Host tools and the firmware toolchain.
+
+use std::path::{Path, PathBuf};
+
+use crate::check::Check;
+use crate::env::{first_line, is_executable, subdirs, Env, Os};
+use crate::version::Version;
+
+/// What JTAG_SETUP.md and the README were written against.
+pub const GDB_VERSION: Version = Version::new(13, 2, 0);
+pub const OPENOCD_VERSION: Version = Version::new(0, 12, 0);
+/// `ESP_IDF_VERSION` in ghost-trigger/.cargo/config.toml.
+pub const IDF_VERSION: &str = "v5.3.3";
+
+const GDB: &str = "xtensa-esp32-elf-gdb";
+const GDB_RELEASE: &str =
+    "https://github.com/espressif/binutils-gdb/releases/download/esp-gdb-v13.2_20240530";
+const OPENOCD_RELEASES: &str = "https://github.com/espressif/openocd-esp32/releases";
+
+pub fn gdb(env: &Env) -> Check {
+    const NAME: &str = "esp-gdb";
+    let found = env.which(GDB).map(|p| (p, true)).or_else(|| {
+        let tools = env.home_join(".espressif/tools/xtensa-esp-elf-gdb")?;
+        // the manual install from the docs, then ESP-IDF's install.sh layout
+        std::iter::once(tools.join("bin"))
+            .chain(
+                subdirs(&tools)
+                    .into_iter()
+                    .map(|v| v.join("xtensa-esp-elf-gdb/bin")),
+            )
+            .map(|bin| bin.join(GDB))
+            .find(|p| is_executable(p))
+            .map(|p| (p, false))
+    });
+    let Some((path, on_path)) = found else {
+        return Check::fail(NAME, format!("{} not found", GDB), gdb_install(env));
+    };
+    if !on_path {
+        let bin = path.parent().unwrap_or(&path).display();
+        return Check::warn(
+            NAME,
+            format!("{} is installed but not on PATH", path.display()),
+            format!(
+                "export PATH=\"{}:$PATH\"   # add to your shell profile",
+                bin
+            ),
+        );
+    }
+    versioned(NAME, &path, GDB_VERSION, gdb_install(env))
+}
+
+fn gdb_install(env: &Env) -> String {
+    let arch = if env.arch == "aarch64" {
+        "aarch64"
+    } else {
+        "x86_64"
+    };
+    let archive = match env.os {
+        Os::MacOs => {
+            return format!(
+                "brew tap espressif/homebrew-esp && brew install esp32-elf-gdb\n\
+                 or: curl -LO {}/xtensa-esp-elf-gdb-13.2_20240530-{}-apple-darwin.tar.xz",
+                GDB_RELEASE, arch
+            )
+        }
+        _ => format!("xtensa-esp-elf-gdb-13.2_20240530-{}-linux-gnu.tar.gz", arch),
+    };
+    format!(
+        "curl -LO {}/{}\n\
+         mkdir -p ~/.espressif/tools && tar -xf {} -C ~/.espressif/tools/\n\
+         export PATH=\"$HOME/.espressif/tools/xtensa-esp-elf-gdb/bin:$PATH\"",
+        GDB_RELEASE, archive, archive
+    )
+}
+
+pub fn openocd(env: &Env) -> Check {
+    const NAME: &str = "openocd";
+    let install = match env.os {
+        Os::MacOs => format!(
+            "brew install openocd, or the esp32 build from {}",
+            OPENOCD_RELEASES
+        ),
+        _ => format!(
+            "download openocd-esp32 for your platform from {}\n\
+             and put its openocd-esp32/bin on PATH",
+            OPENOCD_RELEASES
+        ),
+    };
+    let Some(path) = env.which("openocd") else {
+        return Check::fail(NAME, "openocd not found", install);
+    };
+    let check = versioned(NAME, &path, OPENOCD_VERSION, install);
+    if check.status == crate::Status::Ok && !check.detail.contains("esp32") {
+        return Check::warn(
+            NAME,
+            check.detail,
+            format!(
+                "upstream build: `program_esp` and FreeRTOS awareness need openocd-esp32 from {}",
+                OPENOCD_RELEASES
+            ),
+        );
+    }
+    check
+}
+
+pub fn espflash(env: &Env) -> Check {
+    const NAME: &str = "espflash";
+    let install = match env.os {
+        Os::Linux => "sudo apt install libudev-dev pkg-config && cargo install espflash",
+        _ => "cargo install espflash",
+    };
+    match env.which("espflash") {
+        Some(path) => found(NAME, &path),
+        None => Check::fail(NAME, "espflash not found (the `cargo run` runner)", install),
+    }
+}
+
+pub fn ldproxy(env: &Env) -> Check {
+    const NAME: &str = "ldproxy";
+    match env.which("ldproxy") {
+        // no --version
+        Some(path) => Check::ok(NAME, path.display().to_string()),
+        None => Check::fail(
+            NAME,
+            "ldproxy not found (the firmware's linker)",
+            "cargo install ldproxy",
+        ),
+    }
+}
+
+/// The `esp` Rust toolchain espup installs.
+pub fn toolchain(env: &Env) -> Check {
+    const NAME: &str = "esp toolchain";
+    let install = "cargo install espup && espup install\n. ~/export-esp.sh   # in every new shell";
+    let Some(rustup) = env.which("rustup") else {
+        return Check::fail(
+            NAME,
+            "rustup not found",
+            format!("install rustup from https://rustup.rs, then\n{}", install),
+        );
+    };
+    let listed = std::process::Command::new(&rustup)
+        .args(["toolchain", "list"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default();
+    let Some(line) = listed.lines().find(|l| l.starts_with("esp")) else {
+        return Check::fail(
+            NAME,
+            "no `esp` toolchain in `rustup toolchain list`",
+            install,
+        );
+    };
+    let export = env.home_join("export-esp.sh").filter(|p| p.is_file());
+    match export {
+        Some(export) if env.os == Os::Linux && env.var("LIBCLANG_PATH").is_none() => Check::warn(
+            NAME,
+            format!("{}, but LIBCLANG_PATH is unset", line.trim()),
+            format!(". {}", export.display()),
+        ),
+        _ => Check::ok(NAME, line.trim().to_string()),
+    }
+}
+
+pub fn esp_idf(env: &Env) -> Check {
+    const NAME: &str = "ESP-IDF";
+    if let Some(idf_path) = env.var("IDF_PATH") {
+        // esp-idf-sys builds against IDF_PATH over ESP_IDF_VERSION
+        let idf_path = Path::new(idf_path);
+        return match idf_version(idf_path) {
+            Some(version) if version == IDF_VERSION => {
+                Check::ok(NAME, format!("{} ({})", version, idf_path.display()))
+            }
+            found => Check::fail(
+                NAME,
+                format!(
+                    "IDF_PATH={} is {}, the firmware needs {}",
+                    idf_path.display(),
+                    found.as_deref().unwrap_or("an unknown version"),
+                    IDF_VERSION
+                ),
+                format!(
+                    "unset IDF_PATH to let the build fetch {} itself, or\n\
+                     git -C \"$IDF_PATH\" checkout {} && git -C \"$IDF_PATH\" submodule update --init --recursive",
+                    IDF_VERSION, IDF_VERSION
+                ),
+            ),
+        };
+    }
+
+    let candidates: Vec<PathBuf> = [
+        Some(env.project.join(".embuild/espressif/esp-idf")),
+        env.home_join(".espressif/esp-idf"),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|dir| dir.join(IDF_VERSION))
+    .collect();
+    match candidates.iter().find(|dir| dir.is_dir()) {
+        Some(dir) => Check::ok(NAME, format!("{} ({})", IDF_VERSION, dir.display())),
+        None => Check::warn(
+            NAME,
+            format!("{} not downloaded yet", IDF_VERSION),
+            "the first `cargo build` in ghost-trigger fetches it into .embuild/\n\
+             (needs git, python3 with venv, cmake and ninja)",
+        ),
+    }
+}
+
+/// `vMAJOR.MINOR.PATCH` from `tools/cmake/version.cmake`.
+fn idf_version(idf_path: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(idf_path.join("tools/cmake/version.cmake")).ok()?;
+    let part = |name: &str| {
+        let key = format!("set(IDF_VERSION_{} ", name);
+        text.lines()
+            .find_map(|l| l.trim().strip_prefix(key.as_str()))
+            .and_then(|rest| rest.trim_end_matches(')').trim().parse::<u32>().ok())
+    };
+    Some(format!(
+        "v{}.{}.{}",
+        part("MAJOR")?,
+        part("MINOR")?,
+        part("PATCH")?
+    ))
+}
+
+fn found(name: &'static str, path: &Path) -> Check {
+    let line = first_line(path, &["--version"]).unwrap_or_default();
+    Check::ok(name, format!("{} ({})", line, path.display()))
+}
+
+fn versioned(name: &'static str, path: &Path, required: Version, install: String) -> Check {
+    let line = first_line(path, &["--version"]).unwrap_or_default();
+    match Version::find(&line) {
+        Some(version) if version >= required => {
+            Check::ok(name, format!("{} ({})", line, path.display()))
+        }
+        Some(version) => Check::fail(
+            name,
+            format!(
+                "{} is {}, need {} or later",
+                path.display(),
+                version,
+                required
+            ),
+            install,
+        ),
+        None => Check::warn(
+            name,
+            format!(
+                "{}: could not read a version from {:?}",
+                path.display(),
+                line
+            ),
+            format!("expected {} or later", required),
+        ),
+    }
+}
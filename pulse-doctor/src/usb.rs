@@ -0,0 +1,192 @@
+//! Adapter and serial port access.
+
+use std::path::{Path, PathBuf};
+
+use crate::check::Check;
+use crate::env::{Env, Os};
+
+const FTDI: &str = "0403";
+const ESPRESSIF: &str = "303a";
+
+const RULES_URL: &str =
+    "https://raw.githubusercontent.com/espressif/openocd-esp32/master/contrib/60-openocd.rules";
+const RULE_DIRS: [&str; 3] = [
+    "/etc/udev/rules.d",
+    "/lib/udev/rules.d",
+    "/usr/lib/udev/rules.d",
+];
+
+/// A JTAG-capable USB device in sysfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adapter {
+    pub vendor: String,
+    pub product: String,
+    pub name: String,
+    /// `/dev/bus/usb/BBB/DDD`, what OpenOCD opens through libusb.
+    pub node: PathBuf,
+}
+
+/// FTDI and Espressif USB devices under `/sys/bus/usb/devices`.
+pub fn adapters(env: &Env) -> Vec<Adapter> {
+    let mut found = Vec::new();
+    let devices = env.system("/sys/bus/usb/devices");
+    for dir in crate::env::subdirs(&devices) {
+        let read = |file: &str| {
+            std::fs::read_to_string(dir.join(file))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        let (Some(vendor), Some(product)) = (read("idVendor"), read("idProduct")) else {
+            continue;
+        };
+        if vendor != FTDI && vendor != ESPRESSIF {
+            continue;
+        }
+        let (Some(bus), Some(dev)) = (
+            read("busnum").and_then(|s| s.parse::<u32>().ok()),
+            read("devnum").and_then(|s| s.parse::<u32>().ok()),
+        ) else {
+            continue;
+        };
+        let name = read("product").unwrap_or_else(|| known_name(&vendor, &product).into());
+        found.push(Adapter {
+            node: env.system(&format!("/dev/bus/usb/{:03}/{:03}", bus, dev)),
+            vendor,
+            product,
+            name,
+        });
+    }
+    found
+}
+
+fn known_name(vendor: &str, product: &str) -> &'static str {
+    match (vendor, product) {
+        (FTDI, "6010") => "FT2232H (ESP-Prog)",
+        (FTDI, "6011") => "FT4232H",
+        (FTDI, "6014") => "FT232H",
+        (ESPRESSIF, "1001") => "ESP USB-JTAG",
+        _ => "USB device",
+    }
+}
+
+/// Whether OpenOCD can open each adapter without root.
+pub fn adapter_access(env: &Env) -> Vec<Check> {
+    const NAME: &str = "JTAG adapter";
+    if env.os != Os::Linux {
+        return vec![Check::skip(NAME, "udev permissions only apply on Linux")];
+    }
+    let adapters = adapters(env);
+    if adapters.is_empty() {
+        return vec![Check::warn(
+            NAME,
+            "no FTDI or Espressif USB device found",
+            "plug in the ESP-Prog; `lsusb -d 0403:` should list it",
+        )];
+    }
+    adapters
+        .into_iter()
+        .map(|adapter| {
+            let detail = format!(
+                "{}:{} {} at {}",
+                adapter.vendor,
+                adapter.product,
+                adapter.name,
+                adapter.node.display()
+            );
+            if (env.can_open)(&adapter.node) {
+                Check::ok(NAME, detail)
+            } else {
+                Check::fail(
+                    NAME,
+                    format!("{}, no permission", detail),
+                    udev_fix(env, &adapter),
+                )
+            }
+        })
+        .collect()
+}
+
+fn udev_fix(env: &Env, adapter: &Adapter) -> String {
+    match rule_for(env, &adapter.vendor) {
+        Some(rule) => format!(
+            "{} matches it; add yourself to its group and log in again:\n\
+             sudo usermod -aG plugdev $USER",
+            rule.display()
+        ),
+        None => format!(
+            "sudo curl -fLo /etc/udev/rules.d/60-openocd.rules {}\n\
+             sudo udevadm control --reload-rules && sudo udevadm trigger\n\
+             sudo usermod -aG plugdev $USER   # then log in again and replug",
+            RULES_URL
+        ),
+    }
+}
+
+/// A udev rule file mentioning the vendor id.
+fn rule_for(env: &Env, vendor: &str) -> Option<PathBuf> {
+    let needle = format!("\"{}\"", vendor);
+    RULE_DIRS
+        .iter()
+        .flat_map(|dir| {
+            std::fs::read_dir(env.system(dir))
+                .into_iter()
+                .flatten()
+                .flatten()
+        })
+        .map(|entry| entry.path())
+        .find(|path| std::fs::read_to_string(path).is_ok_and(|text| text.contains(&needle)))
+        .map(|path| Path::new("/").join(path.strip_prefix(&env.root).unwrap_or(&path)))
+}
+
+/// USB serial ports, where the firmware log shows up.
+pub fn serial_ports(env: &Env) -> Vec<PathBuf> {
+    let prefixes: &[&str] = match env.os {
+        Os::MacOs => &["cu.usbserial", "cu.SLAB_USBtoUART", "cu.wchusbserial"],
+        _ => &["ttyUSB", "ttyACM"],
+    };
+    let mut ports: Vec<PathBuf> = std::fs::read_dir(env.system("/dev"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            prefixes.iter().any(|p| name.starts_with(p))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    ports.sort();
+    ports
+}
+
+pub fn serial_access(env: &Env) -> Vec<Check> {
+    const NAME: &str = "serial port";
+    let ports = serial_ports(env);
+    if ports.is_empty() {
+        return vec![Check::warn(
+            NAME,
+            "no USB serial port found",
+            "connect the board's USB port; the ESP-Prog's second channel also shows up as one",
+        )];
+    }
+    let group = if env.system("/etc/arch-release").exists() {
+        "uucp"
+    } else {
+        "dialout"
+    };
+    ports
+        .into_iter()
+        .map(|port| {
+            let shown = Path::new("/").join(port.strip_prefix(&env.root).unwrap_or(&port));
+            if (env.can_open)(&port) {
+                Check::ok(NAME, shown.display().to_string())
+            } else {
+                Check::fail(
+                    NAME,
+                    format!("{}, no permission", shown.display()),
+                    format!("sudo usermod -aG {} $USER   # then log in again", group),
+                )
+            }
+        })
+        .collect()
+}
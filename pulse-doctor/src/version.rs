@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// `major.minor[.patch]`, as tools print it in their `--version` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// The first dotted number in `text`, so `GNU gdb (esp-gdb)
+    /// 13.2_20240530` gives 13.2.0 and `Open On-Chip Debugger
+    /// v0.12.0-esp32-20240318` gives 0.12.0.
+    pub fn find(text: &str) -> Option<Self> {
+        let bytes = text.as_bytes();
+        (0..bytes.len())
+            .filter(|&i| bytes[i].is_ascii_digit() && (i == 0 || !bytes[i - 1].is_ascii_digit()))
+            .find_map(|i| Self::parse_at(&text[i..]))
+    }
+
+    fn parse_at(text: &str) -> Option<Self> {
+        let end = text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len());
+        let mut parts = text[..end].split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.ok()?,
+            None => 0,
+        };
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use pulse_doctor::{tools, usb, Env, Os, Status, Version};
+
+/// A scratch root with an empty `bin` on PATH and a home directory.
+fn fake_env(name: &str) -> Env {
+    let root = std::env::temp_dir().join(format!("pulse-doctor-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::create_dir_all(root.join("project")).unwrap();
+    Env {
+        os: Os::Linux,
+        arch: "x86_64".into(),
+        path: vec![root.join("bin")],
+        home: Some(root.join("home")),
+        vars: HashMap::new(),
+        project: root.join("project"),
+        root,
+        can_open: |_| true,
+    }
+}
+
+fn script(path: &Path, body: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn write(path: PathBuf, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn versions_from_tool_banners() {
+    let find = |s| Version::find(s).unwrap();
+    assert_eq!(
+        find("GNU gdb (esp-gdb) 13.2_20240530"),
+        Version::new(13, 2, 0)
+    );
+    assert_eq!(
+        find("Open On-Chip Debugger v0.12.0-esp32-20240318 (2024-03-18-18:25)"),
+        Version::new(0, 12, 0)
+    );
+    assert_eq!(find("espflash 3.3.0"), Version::new(3, 3, 0));
+    assert_eq!(Version::find("no numbers 7 here"), None);
+    assert!(Version::new(0, 11, 0) < tools::OPENOCD_VERSION);
+}
+
+#[test]
+fn tool_versions_against_the_docs() {
+    let env = fake_env("versions");
+    let bin = env.root.join("bin");
+    script(
+        &bin.join("xtensa-esp32-elf-gdb"),
+        "echo 'GNU gdb (esp-gdb) 13.2_20240530'",
+    );
+    // OpenOCD prints its banner on stderr
+    script(
+        &bin.join("openocd"),
+        "echo 'Open On-Chip Debugger 0.11.0' >&2",
+    );
+    assert_eq!(tools::gdb(&env).status, Status::Ok);
+    let openocd = tools::openocd(&env);
+    assert_eq!(openocd.status, Status::Fail);
+    assert!(openocd.detail.contains("need 0.12.0"), "{}", openocd);
+
+    script(
+        &bin.join("openocd"),
+        "echo 'Open On-Chip Debugger 0.12.0' >&2",
+    );
+    assert_eq!(tools::openocd(&env).status, Status::Warn);
+    script(
+        &bin.join("openocd"),
+        "echo 'Open On-Chip Debugger v0.12.0-esp32-20240318' >&2",
+    );
+    assert_eq!(tools::openocd(&env).status, Status::Ok);
+
+    assert_eq!(tools::ldproxy(&env).status, Status::Fail);
+    script(&bin.join("ldproxy"), "exit 1");
+    assert_eq!(tools::ldproxy(&env).status, Status::Ok);
+}
+
+#[test]
+fn gdb_installed_off_path() {
+    let env = fake_env("gdb-off-path");
+    let missing = tools::gdb(&env);
+    assert_eq!(missing.status, Status::Fail);
+    assert!(missing.fix.unwrap().contains("x86_64-linux-gnu.tar.gz"));
+
+    let home = env.home.clone().unwrap();
+    script(
+        &home.join(".espressif/tools/xtensa-esp-elf-gdb/bin/xtensa-esp32-elf-gdb"),
+        "echo 'GNU gdb (esp-gdb) 13.2_20240530'",
+    );
+    let check = tools::gdb(&env);
+    assert_eq!(check.status, Status::Warn);
+    assert!(check.fix.unwrap().starts_with("export PATH="));
+}
+
+#[test]
+fn esp_idf_version() {
+    let mut env = fake_env("idf");
+    assert_eq!(tools::esp_idf(&env).status, Status::Warn);
+    fs::create_dir_all(env.project.join(".embuild/espressif/esp-idf/v5.3.3")).unwrap();
+    assert_eq!(tools::esp_idf(&env).status, Status::Ok);
+
+    // IDF_PATH wins over the downloaded copy
+    let idf = env.root.join("esp-idf");
+    write(
+        idf.join("tools/cmake/version.cmake"),
+        "set(IDF_VERSION_MAJOR 5)\nset(IDF_VERSION_MINOR 1)\nset(IDF_VERSION_PATCH 2)\n",
+    );
+    env.vars
+        .insert("IDF_PATH".into(), idf.display().to_string());
+    let check = tools::esp_idf(&env);
+    assert_eq!(check.status, Status::Fail);
+    assert!(
+        check
+            .detail
+            .contains("is v5.1.2, the firmware needs v5.3.3"),
+        "{}",
+        check
+    );
+}
+
+#[test]
+fn adapter_permissions() {
+    let mut env = fake_env("udev");
+    let device = env.root.join("sys/bus/usb/devices/1-2");
+    for (file, value) in [
+        ("idVendor", "0403"),
+        ("idProduct", "6010"),
+        ("busnum", "1"),
+        ("devnum", "5"),
+    ] {
+        write(device.join(file), &format!("{}\n", value));
+    }
+    write(env.root.join("sys/bus/usb/devices/1-3/idVendor"), "046d\n");
+    write(env.root.join("dev/ttyUSB1"), "");
+
+    let adapters = usb::adapters(&env);
+    assert_eq!(adapters.len(), 1);
+    assert_eq!(adapters[0].name, "FT2232H (ESP-Prog)");
+    assert!(adapters[0].node.ends_with("dev/bus/usb/001/005"));
+    assert_eq!(usb::adapter_access(&env)[0].status, Status::Ok);
+
+    env.can_open = |_| false;
+    let denied = &usb::adapter_access(&env)[0];
+    assert_eq!(denied.status, Status::Fail);
+    assert!(denied.fix.as_ref().unwrap().contains("60-openocd.rules"));
+
+    // with a rule installed only the group is missing
+    write(
+        env.root.join("etc/udev/rules.d/60-openocd.rules"),
+        "ATTRS{idVendor}==\"0403\", ATTRS{idProduct}==\"6010\", MODE=\"660\", GROUP=\"plugdev\"\n",
+    );
+    let fix = usb::adapter_access(&env)[0].fix.clone().unwrap();
+    assert!(
+        fix.starts_with("/etc/udev/rules.d/60-openocd.rules matches it"),
+        "{}",
+        fix
+    );
+
+    let serial = &usb::serial_access(&env)[0];
+    assert_eq!(serial.detail, "/dev/ttyUSB1, no permission");
+    assert!(serial.fix.as_ref().unwrap().contains("dialout"));
+
+    env.os = Os::MacOs;
+    assert_eq!(usb::adapter_access(&env)[0].status, Status::Skip);
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Elf(String),
+    Dwarf(gimli::Error),
+    /// No variable by that name in scope.
+    NotFound(String),
+    /// More than one global matches, with the full paths of each.
+    Ambiguous(String, Vec<String>),
+    /// A local was named without a PC to pick its scope.
+    NeedsPc(String),
+    /// The variable is in scope but has no location at this PC.
+    OptimizedOut(String),
+    /// Location expression or type we do not handle.
+    Unsupported(String),
+    /// Bad `.field` or `[index]` access for the type.
+    Path(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Elf(what) => write!(f, "bad ELF: {}", what),
+            Error::Dwarf(e) => write!(f, "bad DWARF: {}", e),
+            Error::NotFound(name) => write!(f, "no variable `{}` in scope", name),
+            Error::Ambiguous(name, paths) => {
+                write!(f, "`{}` is ambiguous: {}", name, paths.join(", "))
+            }
+            Error::NeedsPc(name) => write!(f, "`{}` is a local, give a PC", name),
+            Error::OptimizedOut(name) => write!(f, "`{}` is optimized out here", name),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+            Error::Path(what) => f.write_str(what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<gimli::Error> for Error {
+    fn from(e: gimli::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
@@ -0,0 +1,34 @@
+//! DWARF variable resolver for the ghost-trigger ELF.
+//!
+//! Turns a Rust variable path such as `threat_detected`,
+//! `ghost_trigger::injection::CYCLE_MS` or `PULSE_AUDIT.records[3].signal`
+//! into where it lives on the target at a given PC: a fixed address for
+//! statics, a register or a frame-relative slot for locals of `main`, read
+//! from location lists and `DW_AT_frame_base` the way GDB does. The type
+//! layout comes along so a script can inject by name instead of copying
+//! addresses out of `print &threat_detected`. The other way round,
+//! [`DebugInfo::source_frames`] maps a code address to function, file and
+//! line, inlined calls included, and [`DebugInfo::line_addresses`] a source
+//! line to the addresses to break at.
+//!
+//! ```no_run
+//! # fn main() -> pulse_dwarf::Result<()> {
+//! let info = pulse_dwarf::DebugInfo::from_file("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let var = info.resolve("threat_detected", Some(0x400d_0190))?;
+//! println!("{}", var);
+//! print!("{}", var.ty.layout());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod location;
+pub mod resolve;
+pub mod source;
+pub mod types;
+
+pub use error::{Error, Result};
+pub use location::{register_name, Location};
+pub use resolve::{DebugInfo, Scope, Variable};
+pub use source::SourceFrame;
+pub use types::{format_scalar, Encoding, Member, Type, TypeKind};
@@ -0,0 +1,166 @@
+//! DWARF location expressions, reduced to the forms a debugger can poke.
+
+use std::fmt;
+
+use gimli::{Encoding, Expression, Operation, Reader};
+
+use crate::error::{Error, Result};
+
+/// Where a variable lives at one PC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// Fixed address, statics.
+    Address(u64),
+    /// Held in a register (DWARF number).
+    Register(u16),
+    /// In memory at `register + offset`, frame-relative locals.
+    Memory { register: u16, offset: i64 },
+    /// Optimized into a constant, no storage to write to.
+    Value(Vec<u8>),
+}
+
+impl Location {
+    /// The same location `offset` bytes further in, for fields and elements.
+    pub fn offset(&self, offset: u64) -> Result<Location> {
+        Ok(match self {
+            Location::Address(addr) => Location::Address(addr + offset),
+            Location::Memory {
+                register,
+                offset: base,
+            } => Location::Memory {
+                register: *register,
+                offset: base + offset as i64,
+            },
+            Location::Register(_) if offset == 0 => self.clone(),
+            Location::Value(bytes) => {
+                Location::Value(bytes.get(offset as usize..).unwrap_or_default().to_vec())
+            }
+            Location::Register(reg) => {
+                return Err(Error::Unsupported(format!(
+                    "sub-field at offset {} of a value held in {}",
+                    offset,
+                    register_name(*reg)
+                )))
+            }
+        })
+    }
+
+    /// Final address, given how to read a register for `Memory` locations.
+    pub fn address(&self, read_register: impl FnOnce(u16) -> Option<u64>) -> Option<u64> {
+        match self {
+            Location::Address(addr) => Some(*addr),
+            Location::Memory { register, offset } => {
+                Some(read_register(*register)?.wrapping_add(*offset as u64))
+            }
+            Location::Register(_) | Location::Value(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Address(addr) => write!(f, "{:#010x}", addr),
+            Location::Register(reg) => f.write_str(&register_name(*reg)),
+            Location::Memory { register, offset } if *offset < 0 => {
+                write!(f, "[{} - {}]", register_name(*register), -offset)
+            }
+            Location::Memory { register, offset } => {
+                write!(f, "[{} + {}]", register_name(*register), offset)
+            }
+            Location::Value(bytes) => write!(f, "constant {:02x?}", bytes),
+        }
+    }
+}
+
+/// Xtensa DWARF registers 0-15 are the windowed `a0`-`a15`.
+pub fn register_name(reg: u16) -> String {
+    if reg < 16 {
+        format!("a{}", reg)
+    } else {
+        format!("r{}", reg)
+    }
+}
+
+/// Evaluate the location expressions rustc emits for statics and locals.
+///
+/// `frame_base` is the enclosing function's `DW_AT_frame_base`, itself
+/// evaluated with this function. A register frame base means "the value of
+/// that register", as in the DWARF spec.
+pub fn evaluate<R: Reader>(
+    expr: Expression<R>,
+    encoding: Encoding,
+    frame_base: Option<&Location>,
+) -> Result<Location> {
+    let mut ops = expr.0;
+    let mut location: Option<Location> = None;
+    let mut value: Option<u64> = None;
+
+    while !ops.is_empty() {
+        let op = Operation::parse(&mut ops, encoding)?;
+        location = Some(match op {
+            Operation::Address { address } => Location::Address(address),
+            Operation::Register { register } => Location::Register(register.0),
+            Operation::RegisterOffset {
+                register, offset, ..
+            } => Location::Memory {
+                register: register.0,
+                offset,
+            },
+            Operation::FrameOffset { offset } => match frame_base {
+                Some(Location::Register(register)) => Location::Memory {
+                    register: *register,
+                    offset,
+                },
+                Some(Location::Memory {
+                    register,
+                    offset: base,
+                }) => Location::Memory {
+                    register: *register,
+                    offset: base + offset,
+                },
+                Some(other) => {
+                    return Err(Error::Unsupported(format!("frame base {}", other)));
+                }
+                None => {
+                    return Err(Error::Unsupported(
+                        "DW_OP_fbreg without a frame base".into(),
+                    ))
+                }
+            },
+            Operation::PlusConstant { value: add } => match location {
+                Some(loc) => loc.offset(add)?,
+                None => return Err(Error::Unsupported("DW_OP_plus_uconst on nothing".into())),
+            },
+            Operation::UnsignedConstant { value: v } => {
+                value = Some(v);
+                continue;
+            }
+            Operation::SignedConstant { value: v } => {
+                value = Some(v as u64);
+                continue;
+            }
+            Operation::StackValue => match value {
+                Some(v) => {
+                    Location::Value(v.to_le_bytes()[..usize::from(encoding.address_size)].to_vec())
+                }
+                None => {
+                    return Err(Error::Unsupported(
+                        "DW_OP_stack_value on a computed value".into(),
+                    ))
+                }
+            },
+            Operation::ImplicitValue { data } => Location::Value(data.to_slice()?.into_owned()),
+            Operation::Piece { .. } => {
+                return Err(Error::Unsupported("variable split across pieces".into()));
+            }
+            Operation::CallFrameCFA => {
+                return Err(Error::Unsupported(
+                    "CFA-relative frame base, unwind info needed".into(),
+                ));
+            }
+            other => return Err(Error::Unsupported(format!("{:?}", other))),
+        });
+    }
+    location.ok_or_else(|| Error::OptimizedOut("empty location".into()))
+}
@@ -0,0 +1,64 @@
+use std::process::ExitCode;
+
+use pulse_dwarf::{DebugInfo, Scope};
+
+const USAGE: &str = "usage: pulse-dwarf <ghost-trigger ELF> <variable path> [--pc ADDR]";
+
+fn main() -> ExitCode {
+    let mut positional = Vec::new();
+    let mut pc = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pc" => match args.next().as_deref().and_then(parse_addr) {
+                Some(v) => pc = Some(v),
+                None => return usage(),
+            },
+            "-h" | "--help" => return usage(),
+            _ => positional.push(arg),
+        }
+    }
+    let [elf, path] = positional.as_slice() else {
+        return usage();
+    };
+
+    let info = match DebugInfo::from_file(elf) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("{}: {}", elf, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let var = match info.resolve(path, pc) {
+        Ok(var) => var,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("{}", var.path);
+    println!("  location: {}", var.location);
+    println!("  type:     {}", var.ty);
+    if let Scope::Local { function } = &var.scope {
+        println!("  scope:    local of {}", function);
+    }
+    if let Some(line) = var.line {
+        println!("  line:     {}", line);
+    }
+    print!("{}", var.ty.layout());
+    ExitCode::SUCCESS
+}
+
+fn parse_addr(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
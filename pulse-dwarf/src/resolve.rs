@@ -0,0 +1,825 @@
+//! Name lookup over the DIE tree.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use gimli::{AttributeValue, DwAt, DwTag, EndianArcSlice, Reader, RunTimeEndian, UnitOffset};
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+use crate::error::{Error, Result};
+use crate::location::{evaluate, Location};
+use crate::types::{Encoding, Member, Type, TypeKind};
+
+pub(crate) type R = EndianArcSlice<RunTimeEndian>;
+
+/// Deepest type nesting we expand, guards against malformed cycles.
+const MAX_TYPE_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Static,
+    Local { function: String },
+}
+
+/// A resolved variable path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// Qualified name plus any field/index accesses, e.g.
+    /// `ghost_trigger::injection::PULSE_AUDIT.records[3]`.
+    pub path: String,
+    pub location: Location,
+    pub ty: Type,
+    pub scope: Scope,
+    pub line: Option<u64>,
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} at {}", self.path, self.ty, self.location)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Access {
+    Field(String),
+    Index(u64),
+}
+
+fn parse_path(path: &str) -> Result<(&str, Vec<Access>)> {
+    let bad = || Error::Path(format!("bad variable path `{}`", path));
+    let split = path.find(['.', '[']).unwrap_or(path.len());
+    let (base, mut rest) = path.split_at(split);
+    if base.is_empty() {
+        return Err(bad());
+    }
+    let mut accesses = Vec::new();
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            if end == 0 {
+                return Err(bad());
+            }
+            accesses.push(Access::Field(tail[..end].to_string()));
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or_else(bad)?;
+            let index = tail[..end].trim();
+            let index = match index.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => index.parse(),
+            }
+            .map_err(|_| bad())?;
+            accesses.push(Access::Index(index));
+            rest = &tail[end + 1..];
+        } else {
+            return Err(bad());
+        }
+    }
+    Ok((base, accesses))
+}
+
+pub(crate) struct Node {
+    pub(crate) offset: UnitOffset,
+    pub(crate) tag: DwTag,
+    parent: Option<usize>,
+    /// One past the last DIE of its subtree, where its next sibling starts.
+    pub(crate) end: usize,
+    /// `DW_AT_name`, or the abstract origin's for inlined copies.
+    pub(crate) name: Option<String>,
+    /// The abstract origin of an unnamed inlined or out-of-line copy.
+    origin: Option<usize>,
+}
+
+pub(crate) struct IndexedUnit {
+    pub(crate) unit: gimli::Unit<R>,
+    /// Every DIE in DFS order, which is also offset order.
+    pub(crate) nodes: Vec<Node>,
+}
+
+impl IndexedUnit {
+    pub(crate) fn node(&self, offset: UnitOffset) -> Option<usize> {
+        self.nodes
+            .binary_search_by_key(&offset.0, |n| n.offset.0)
+            .ok()
+    }
+
+    pub(crate) fn ancestors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.nodes[index].parent, move |&i| self.nodes[i].parent)
+    }
+
+    fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        // the first child follows its parent, each next one its sibling's subtree
+        let end = self.nodes[index].end;
+        std::iter::successors(Some(index + 1), move |&c| Some(self.nodes[c].end))
+            .take_while(move |&c| c < end)
+    }
+
+    /// `ghost_trigger::main::threat_detected` style path of a DIE. Inlined
+    /// copies are named where their abstract origin is declared.
+    pub(crate) fn qualified(&self, index: usize) -> String {
+        let index = self.nodes[index].origin.unwrap_or(index);
+        let mut parts: Vec<&str> = self
+            .ancestors(index)
+            .filter(|&i| is_path_tag(self.nodes[i].tag))
+            .filter_map(|i| self.nodes[i].name.as_deref())
+            .collect();
+        parts.reverse();
+        parts.extend(self.nodes[index].name.as_deref());
+        parts.join("::")
+    }
+
+    fn function(&self, index: usize) -> Option<usize> {
+        self.ancestors(index)
+            .find(|&i| self.nodes[i].tag == gimli::DW_TAG_subprogram)
+    }
+}
+
+fn is_path_tag(tag: DwTag) -> bool {
+    matches!(
+        tag,
+        gimli::DW_TAG_namespace
+            | gimli::DW_TAG_subprogram
+            | gimli::DW_TAG_structure_type
+            | gimli::DW_TAG_enumeration_type
+            | gimli::DW_TAG_union_type
+    )
+}
+
+fn is_scope_tag(tag: DwTag) -> bool {
+    matches!(
+        tag,
+        gimli::DW_TAG_subprogram | gimli::DW_TAG_lexical_block | gimli::DW_TAG_inlined_subroutine
+    )
+}
+
+fn is_variable_tag(tag: DwTag) -> bool {
+    matches!(tag, gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter)
+}
+
+/// `name` is `path` or a `::`-separated tail of it.
+fn path_matches(path: &str, name: &str) -> bool {
+    path == name
+        || path
+            .strip_suffix(name)
+            .is_some_and(|head| head.ends_with("::"))
+}
+
+/// `path` and every `::`-separated tail of it, what [`path_matches`] accepts.
+fn tails(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(path).chain(path.match_indices("::").map(|(at, _)| &path[at + 2..]))
+}
+
+/// DWARF of one firmware ELF, indexed for variable lookups.
+pub struct DebugInfo {
+    pub(crate) dwarf: gimli::Dwarf<R>,
+    pub(crate) units: Vec<IndexedUnit>,
+    /// Every named variable and parameter DIE as (unit, index, path).
+    variables: Vec<(usize, usize, String)>,
+    /// Every tail of those paths, to the entries in `variables` it names.
+    by_name: HashMap<String, Vec<usize>>,
+    address_size: u8,
+    /// ELF symbols as (raw name, demangled path without hash, address).
+    symbols: Vec<(String, String, u64)>,
+    /// Sized function symbols as (demangled path, address, size), sorted.
+    functions: Vec<(String, u64, u64)>,
+}
+
+impl DebugInfo {
+    pub fn parse(elf: &[u8]) -> Result<Self> {
+        let file = object::File::parse(elf).map_err(|e| Error::Elf(e.to_string()))?;
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let load = |id: gimli::SectionId| -> Result<R> {
+            let data = match file.section_by_name(id.name()) {
+                Some(section) => section
+                    .uncompressed_data()
+                    .map_err(|e| Error::Elf(e.to_string()))?,
+                None => Cow::Borrowed(&[][..]),
+            };
+            Ok(EndianArcSlice::new(Arc::from(&*data), endian))
+        };
+        let dwarf = gimli::Dwarf::load(load)?;
+
+        let mut units = Vec::new();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut nodes: Vec<Node> = Vec::new();
+            // where unnamed entries point with DW_AT_abstract_origin
+            let mut origins: Vec<Option<UnitOffset>> = Vec::new();
+            let mut stack: Vec<usize> = Vec::new();
+            let mut cursor = unit.entries();
+            while let Some(entry) = cursor.next_dfs()? {
+                let depth = (entry.depth().max(0) as usize).min(stack.len());
+                for closed in stack.drain(depth..) {
+                    nodes[closed].end = nodes.len();
+                }
+                let name = match entry.attr_value(gimli::DW_AT_name) {
+                    Some(value) => Some(
+                        dwarf
+                            .attr_string(&unit, value)?
+                            .to_string_lossy()?
+                            .into_owned(),
+                    ),
+                    None => None,
+                };
+                origins.push(match entry.attr_value(gimli::DW_AT_abstract_origin) {
+                    Some(AttributeValue::UnitRef(origin)) if name.is_none() => Some(origin),
+                    _ => None,
+                });
+                stack.push(nodes.len());
+                nodes.push(Node {
+                    offset: entry.offset(),
+                    tag: entry.tag(),
+                    parent: stack.len().checked_sub(2).map(|i| stack[i]),
+                    end: 0,
+                    name,
+                    origin: None,
+                });
+            }
+            for closed in stack {
+                nodes[closed].end = nodes.len();
+            }
+            let mut unit = IndexedUnit { unit, nodes };
+            // origins are named, so they never have an origin themselves
+            for (i, origin) in origins.into_iter().enumerate() {
+                let Some(origin) = origin.and_then(|o| unit.node(o)) else {
+                    continue;
+                };
+                if let Some(name) = unit.nodes[origin].name.clone() {
+                    unit.nodes[i].name = Some(name);
+                    unit.nodes[i].origin = Some(origin);
+                }
+            }
+            units.push(unit);
+        }
+
+        let mut variables = Vec::new();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (u, unit) in units.iter().enumerate() {
+            for (i, node) in unit.nodes.iter().enumerate() {
+                if node.name.is_none() || !is_variable_tag(node.tag) {
+                    continue;
+                }
+                let path = unit.qualified(i);
+                for tail in tails(&path).filter(|tail| !tail.is_empty()) {
+                    by_name
+                        .entry(tail.to_string())
+                        .or_default()
+                        .push(variables.len());
+                }
+                variables.push((u, i, path));
+            }
+        }
+
+        let symbols = file
+            .symbols()
+            .filter(|s| s.is_definition())
+            .filter_map(|s| {
+                let name = s.name().ok()?.to_string();
+                let path = format!("{:#}", rustc_demangle::demangle(&name));
+                Some((name, path, s.address()))
+            })
+            .collect();
+        let mut functions: Vec<(String, u64, u64)> = file
+            .symbols()
+            .filter(|s| s.is_definition() && s.kind() == SymbolKind::Text && s.size() > 0)
+            .filter_map(|s| {
+                let path = format!("{:#}", rustc_demangle::demangle(s.name().ok()?));
+                Some((path, s.address(), s.size()))
+            })
+            .collect();
+        functions.sort_by_key(|&(_, addr, _)| addr);
+
+        Ok(Self {
+            dwarf,
+            units,
+            variables,
+            by_name,
+            address_size: file.architecture().address_size().map_or(4, |s| s.bytes()),
+            symbols,
+            functions,
+        })
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Address of an ELF symbol by raw name, demangled path or a `::` tail
+    /// of it, e.g. `Detector::cycle`. Functions are found here rather than
+    /// in the DIE tree, where rustc nests methods under `{impl#N}`.
+    pub fn symbol(&self, name: &str) -> Option<u64> {
+        self.symbols
+            .iter()
+            .find(|(raw, path, _)| raw == name || path == name)
+            .or_else(|| {
+                self.symbols
+                    .iter()
+                    .find(|(_, path, _)| path_matches(path, name))
+            })
+            .map(|&(_, _, addr)| addr)
+    }
+
+    /// The function containing `pc` and the offset into it, from the ELF
+    /// symbol table, for symbolizing backtraces.
+    pub fn function_at(&self, pc: u64) -> Option<(&str, u64)> {
+        let i = self.functions.partition_point(|&(_, addr, _)| addr <= pc);
+        let (path, addr, size) = self.functions[..i].last()?;
+        (pc < addr + size).then(|| (path.as_str(), pc - addr))
+    }
+
+    /// Resolve `path` (`name`, `module::name`, then any `.field` and
+    /// `[index]` accesses) at `pc`. Locals of the function containing `pc`
+    /// shadow statics; without a PC only statics are found.
+    pub fn resolve(&self, path: &str, pc: Option<u64>) -> Result<Variable> {
+        let (base, accesses) = parse_path(path)?;
+        let found = match pc {
+            Some(pc) => match self.find_local(base, pc)? {
+                Some(local) => Some(local),
+                None => self.find_static(base)?,
+            },
+            None => self.find_static(base)?,
+        };
+        let (u, index, location) = match found {
+            Some(found) => found,
+            None if pc.is_none() && self.is_local_anywhere(base) => {
+                return Err(Error::NeedsPc(base.into()))
+            }
+            None => return Err(Error::NotFound(base.into())),
+        };
+        let mut variable = self.variable(u, index, location)?;
+        for access in accesses {
+            variable = apply(variable, access)?;
+        }
+        Ok(variable)
+    }
+
+    /// Every local in scope at `pc` that has a location there, in
+    /// declaration order, the innermost of any that share a name. What a
+    /// debugger lists for the frame.
+    pub fn locals(&self, pc: u64) -> Result<Vec<Variable>> {
+        // (unit, index, scope depth) of the visible candidate per name
+        let mut visible: Vec<(usize, usize, usize)> = Vec::new();
+        for &(u, i, _) in &self.variables {
+            let unit = &self.units[u];
+            let name = unit.nodes[i].name.as_deref();
+            let scopes: Vec<usize> = unit
+                .ancestors(i)
+                .filter(|&a| is_scope_tag(unit.nodes[a].tag))
+                .collect();
+            if scopes.is_empty() {
+                continue;
+            }
+            let mut in_scope = true;
+            for &scope in &scopes {
+                if !self.contains_pc(u, scope, pc)? {
+                    in_scope = false;
+                    break;
+                }
+            }
+            if !in_scope {
+                continue;
+            }
+            let same = visible
+                .iter()
+                .position(|&(vu, vi, _)| self.units[vu].nodes[vi].name.as_deref() == name);
+            match same {
+                Some(at) if visible[at].2 < scopes.len() => visible[at] = (u, i, scopes.len()),
+                Some(_) => {}
+                None => visible.push((u, i, scopes.len())),
+            }
+        }
+
+        let mut locals = Vec::new();
+        for (u, i, _) in visible {
+            let unit = &self.units[u];
+            let frame_base = match unit.function(i) {
+                Some(f) => self.location(u, f, Some(pc), None).ok().flatten(),
+                None => None,
+            };
+            // optimized out here, nothing to show
+            if let Some(location) = self.location(u, i, Some(pc), frame_base.as_ref())? {
+                locals.push(self.variable(u, i, location)?);
+            }
+        }
+        Ok(locals)
+    }
+
+    /// The variable DIE `index` of unit `u`, found at `location`.
+    fn variable(&self, u: usize, index: usize, location: Location) -> Result<Variable> {
+        let unit = &self.units[u];
+        let entry = unit.unit.entry(unit.nodes[index].offset)?;
+        let ty = match self.attr_or_origin(u, &entry, gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(offset)) => self.ty(u, offset, 0)?,
+            _ => Type {
+                name: "()".into(),
+                size: 0,
+                kind: TypeKind::Opaque,
+            },
+        };
+        let scope = match unit.function(index) {
+            Some(f) => Scope::Local {
+                function: unit.qualified(f),
+            },
+            None => Scope::Static,
+        };
+        let line = self
+            .attr_or_origin(u, &entry, gimli::DW_AT_decl_line)?
+            .and_then(|v| v.udata_value());
+
+        Ok(Variable {
+            path: unit.qualified(index),
+            location,
+            ty,
+            scope,
+            line,
+        })
+    }
+
+    /// Variable and parameter DIEs whose path `name` is, or is a tail of,
+    /// as (unit, index, path), in DIE order.
+    fn named(&self, name: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+        self.by_name.get(name).into_iter().flatten().map(|&v| {
+            let (u, i, path) = &self.variables[v];
+            (*u, *i, path.as_str())
+        })
+    }
+
+    /// Whether DIE `index` of unit `u` sits inside a function or block.
+    fn in_scope_tag(&self, u: usize, index: usize) -> bool {
+        let unit = &self.units[u];
+        unit.ancestors(index)
+            .any(|a| is_scope_tag(unit.nodes[a].tag))
+    }
+
+    /// Every static at a fixed address, sorted by address, for mapping
+    /// addresses back to variables.
+    pub fn statics(&self) -> Vec<Variable> {
+        let mut paths: Vec<&str> = self
+            .variables
+            .iter()
+            .filter(|&&(u, i, _)| {
+                self.units[u].nodes[i].tag == gimli::DW_TAG_variable && !self.in_scope_tag(u, i)
+            })
+            .map(|(_, _, path)| path.as_str())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        let mut statics: Vec<Variable> = paths
+            .iter()
+            .filter_map(|path| self.resolve(path, None).ok())
+            .filter(|v| matches!(v.location, Location::Address(_)))
+            .collect();
+        statics.sort_by_key(|v| v.location.address(|_| None));
+        statics
+    }
+
+    fn find_static(&self, name: &str) -> Result<Option<(usize, usize, Location)>> {
+        let mut found: Vec<(usize, usize, String)> = Vec::new();
+        for (u, i, path) in self.named(name) {
+            let node = &self.units[u].nodes[i];
+            if node.tag != gimli::DW_TAG_variable
+                || self.in_scope_tag(u, i)
+                || found.iter().any(|(_, _, p)| p == path)
+            {
+                continue;
+            }
+            // declarations without storage
+            let entry = self.units[u].unit.entry(node.offset)?;
+            if entry.attr(gimli::DW_AT_location).is_some() {
+                found.push((u, i, path.to_string()));
+            }
+        }
+        match found.len() {
+            0 => Ok(None),
+            1 => {
+                let (u, i, path) = found.remove(0);
+                let location = self
+                    .location(u, i, None, None)?
+                    .ok_or(Error::OptimizedOut(path))?;
+                Ok(Some((u, i, location)))
+            }
+            _ => Err(Error::Ambiguous(
+                name.into(),
+                found.into_iter().map(|(_, _, p)| p).collect(),
+            )),
+        }
+    }
+
+    fn find_local(&self, name: &str, pc: u64) -> Result<Option<(usize, usize, Location)>> {
+        // innermost scope wins, i.e. the candidate with the most scope ancestors
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (u, i, _) in self.named(name) {
+            let unit = &self.units[u];
+            let scopes: Vec<usize> = unit
+                .ancestors(i)
+                .filter(|&a| is_scope_tag(unit.nodes[a].tag))
+                .collect();
+            if scopes.is_empty() {
+                continue;
+            }
+            let mut in_scope = true;
+            for &scope in &scopes {
+                if !self.contains_pc(u, scope, pc)? {
+                    in_scope = false;
+                    break;
+                }
+            }
+            if in_scope && best.is_none_or(|(_, _, depth)| scopes.len() > depth) {
+                best = Some((u, i, scopes.len()));
+            }
+        }
+        let Some((u, i, _)) = best else {
+            return Ok(None);
+        };
+
+        let unit = &self.units[u];
+        let frame_base = match unit.function(i) {
+            Some(f) => self.location(u, f, Some(pc), None).ok().flatten(),
+            None => None,
+        };
+        match self.location(u, i, Some(pc), frame_base.as_ref())? {
+            Some(location) => Ok(Some((u, i, location))),
+            None => Err(Error::OptimizedOut(unit.qualified(i))),
+        }
+    }
+
+    fn is_local_anywhere(&self, name: &str) -> bool {
+        self.named(name)
+            .any(|(u, i, _)| self.units[u].function(i).is_some())
+    }
+
+    pub(crate) fn contains_pc(&self, u: usize, index: usize, pc: u64) -> Result<bool> {
+        let unit = &self.units[u];
+        let entry = unit.unit.entry(unit.nodes[index].offset)?;
+        let mut ranges = self.dwarf.die_ranges(&unit.unit, &entry)?;
+        while let Some(range) = ranges.next()? {
+            if (range.begin..range.end).contains(&pc) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// `DW_AT_location` (or `DW_AT_frame_base` for functions) at `pc`.
+    /// `None` when there is none, or no location list entry covers `pc`.
+    fn location(
+        &self,
+        u: usize,
+        index: usize,
+        pc: Option<u64>,
+        frame_base: Option<&Location>,
+    ) -> Result<Option<Location>> {
+        let unit = &self.units[u];
+        let entry = unit.unit.entry(unit.nodes[index].offset)?;
+        let at = if unit.nodes[index].tag == gimli::DW_TAG_subprogram {
+            gimli::DW_AT_frame_base
+        } else {
+            gimli::DW_AT_location
+        };
+        let encoding = unit.unit.encoding();
+        let expr = match entry.attr_value(at) {
+            None => return Ok(None),
+            Some(AttributeValue::Exprloc(expr)) => expr,
+            Some(value) => {
+                let Some(pc) = pc else {
+                    return Err(Error::Unsupported("location list without a PC".into()));
+                };
+                let Some(mut list) = self.dwarf.attr_locations(&unit.unit, value)? else {
+                    return Ok(None);
+                };
+                let mut hit = None;
+                while let Some(item) = list.next()? {
+                    if (item.range.begin..item.range.end).contains(&pc) {
+                        hit = Some(item.data);
+                        break;
+                    }
+                }
+                match hit {
+                    Some(expr) => expr,
+                    None => return Ok(None),
+                }
+            }
+        };
+        if expr.0.is_empty() {
+            return Ok(None);
+        }
+        evaluate(expr, encoding, frame_base).map(Some)
+    }
+
+    /// Attribute of an entry, or of its `DW_AT_abstract_origin` for
+    /// inlined copies that only carry the location.
+    fn attr_or_origin(
+        &self,
+        u: usize,
+        entry: &gimli::DebuggingInformationEntry<R>,
+        at: DwAt,
+    ) -> Result<Option<AttributeValue<R>>> {
+        if let Some(value) = entry.attr_value(at) {
+            return Ok(Some(value));
+        }
+        match entry.attr_value(gimli::DW_AT_abstract_origin) {
+            Some(AttributeValue::UnitRef(origin)) => {
+                Ok(self.units[u].unit.entry(origin)?.attr_value(at))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn ty(&self, u: usize, offset: UnitOffset, depth: usize) -> Result<Type> {
+        let unit = &self.units[u];
+        let entry = unit.unit.entry(offset)?;
+        let index = unit.node(offset);
+        let name = index.map(|i| unit.qualified(i)).unwrap_or_default();
+        let size = entry
+            .attr_value(gimli::DW_AT_byte_size)
+            .and_then(|v| v.udata_value());
+        let inner = |depth: usize| -> Result<Option<Type>> {
+            match entry.attr_value(gimli::DW_AT_type) {
+                Some(AttributeValue::UnitRef(target)) if depth < MAX_TYPE_DEPTH => {
+                    self.ty(u, target, depth + 1).map(Some)
+                }
+                _ => Ok(None),
+            }
+        };
+        let opaque = |name: String| Type {
+            name,
+            size: size.unwrap_or(0),
+            kind: TypeKind::Opaque,
+        };
+
+        Ok(match entry.tag() {
+            gimli::DW_TAG_base_type => {
+                let encoding = match entry.attr_value(gimli::DW_AT_encoding) {
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => Encoding::Bool,
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_signed)) => Encoding::Signed,
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_unsigned)) => Encoding::Unsigned,
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_float)) => Encoding::Float,
+                    Some(AttributeValue::Encoding(gimli::DW_ATE_UTF)) => Encoding::Char,
+                    _ => Encoding::Other,
+                };
+                Type {
+                    name,
+                    size: size.unwrap_or(0),
+                    kind: TypeKind::Base(encoding),
+                }
+            }
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type | gimli::DW_TAG_class_type => {
+                let Some(index) = index else {
+                    return Ok(opaque(name));
+                };
+                let children: Vec<usize> = unit.children(index).collect();
+                if children
+                    .iter()
+                    .any(|&c| unit.nodes[c].tag == gimli::DW_TAG_variant_part)
+                {
+                    return Ok(opaque(name));
+                }
+                let mut members = Vec::new();
+                for c in children {
+                    if unit.nodes[c].tag != gimli::DW_TAG_member {
+                        continue;
+                    }
+                    let member = unit.unit.entry(unit.nodes[c].offset)?;
+                    let offset = member
+                        .attr_value(gimli::DW_AT_data_member_location)
+                        .and_then(|v| v.udata_value())
+                        .unwrap_or(0);
+                    let ty = match member.attr_value(gimli::DW_AT_type) {
+                        Some(AttributeValue::UnitRef(t)) if depth < MAX_TYPE_DEPTH => {
+                            self.ty(u, t, depth + 1)?
+                        }
+                        _ => opaque("?".into()),
+                    };
+                    members.push(Member {
+                        name: unit.nodes[c].name.clone().unwrap_or_default(),
+                        offset,
+                        ty,
+                    });
+                }
+                Type {
+                    name,
+                    size: size.unwrap_or(0),
+                    kind: TypeKind::Struct(members),
+                }
+            }
+            gimli::DW_TAG_array_type => {
+                let Some(element) = inner(depth)? else {
+                    return Ok(opaque(name));
+                };
+                let mut count = 0;
+                if let Some(index) = index {
+                    for c in unit.children(index) {
+                        let sub = unit.unit.entry(unit.nodes[c].offset)?;
+                        if let Some(n) = sub
+                            .attr_value(gimli::DW_AT_count)
+                            .and_then(|v| v.udata_value())
+                        {
+                            count = n;
+                        } else if let Some(upper) = sub
+                            .attr_value(gimli::DW_AT_upper_bound)
+                            .and_then(|v| v.udata_value())
+                        {
+                            count = upper + 1;
+                        }
+                    }
+                }
+                Type {
+                    name: format!("[{}; {}]", element.name, count),
+                    size: size.unwrap_or(element.size * count),
+                    kind: TypeKind::Array {
+                        element: Box::new(element),
+                        count,
+                    },
+                }
+            }
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+                let target = match entry.attr_value(gimli::DW_AT_type) {
+                    Some(AttributeValue::UnitRef(t)) => unit.node(t).map(|i| unit.qualified(i)),
+                    _ => None,
+                }
+                .unwrap_or_else(|| "()".into());
+                Type {
+                    name: if name.is_empty() {
+                        format!("*const {}", target)
+                    } else {
+                        name
+                    },
+                    size: size.unwrap_or(u64::from(self.address_size)),
+                    kind: TypeKind::Pointer { target },
+                }
+            }
+            gimli::DW_TAG_enumeration_type => {
+                let mut variants = Vec::new();
+                if let Some(index) = index {
+                    for c in unit.children(index) {
+                        if unit.nodes[c].tag != gimli::DW_TAG_enumerator {
+                            continue;
+                        }
+                        let e = unit.unit.entry(unit.nodes[c].offset)?;
+                        let value = match e.attr_value(gimli::DW_AT_const_value) {
+                            Some(AttributeValue::Sdata(v)) => v,
+                            Some(v) => v.udata_value().unwrap_or(0) as i64,
+                            None => 0,
+                        };
+                        variants.push((unit.nodes[c].name.clone().unwrap_or_default(), value));
+                    }
+                }
+                Type {
+                    name,
+                    size: size.unwrap_or(0),
+                    kind: TypeKind::Enum(variants),
+                }
+            }
+            gimli::DW_TAG_typedef => match inner(depth)? {
+                Some(target) => Type { name, ..target },
+                None => opaque(name),
+            },
+            gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_atomic_type => {
+                inner(depth)?.unwrap_or_else(|| opaque(name))
+            }
+            _ => opaque(name),
+        })
+    }
+}
+
+fn apply(mut variable: Variable, access: Access) -> Result<Variable> {
+    match access {
+        Access::Field(field) => {
+            let member = variable.ty.member(&field).cloned().ok_or_else(|| {
+                Error::Path(format!(
+                    "`{}` of type {} has no field `{}`",
+                    variable.path, variable.ty.name, field
+                ))
+            })?;
+            variable.location = variable.location.offset(member.offset)?;
+            variable.path = format!("{}.{}", variable.path, field);
+            variable.ty = member.ty;
+        }
+        Access::Index(i) => {
+            let TypeKind::Array { element, count } = &variable.ty.kind else {
+                return Err(Error::Path(format!(
+                    "`{}` of type {} is not an array",
+                    variable.path, variable.ty.name
+                )));
+            };
+            if i >= *count {
+                return Err(Error::Path(format!(
+                    "index {} out of bounds for `{}` of length {}",
+                    i, variable.path, count
+                )));
+            }
+            let element = (**element).clone();
+            variable.location = variable.location.offset(i * element.size)?;
+            variable.path = format!("{}[{}]", variable.path, i);
+            variable.ty = element;
+        }
+    }
+    Ok(variable)
+}
@@ -0,0 +1,303 @@
+//! What source an address came from: function, file and line, with the
+//! calls inlined there, the way `addr2line -i` reports them.
+
+use std::fmt;
+
+use gimli::{AttributeValue, ColumnType, Reader};
+
+use crate::error::Result;
+use crate::resolve::{DebugInfo, R};
+
+/// One function at an address. An address inside inlined code has one
+/// frame per inlined call plus the function they were inlined into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFrame {
+    /// Demangled, without the hash.
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    /// Inlined into the frame after it.
+    pub inlined: bool,
+}
+
+impl fmt::Display for SourceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.function.as_deref().unwrap_or("??"))?;
+        if let Some(file) = &self.file {
+            write!(f, " at {}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        if self.inlined {
+            f.write_str(" (inlined)")?;
+        }
+        Ok(())
+    }
+}
+
+/// A file, line and column, either from the line table or a call site.
+type Position = (Option<String>, Option<u64>, Option<u64>);
+
+impl DebugInfo {
+    /// Frames at `pc`, innermost first: the inlined calls covering it,
+    /// then the function they are in. From the ELF symbol table alone
+    /// when there is no DWARF for it; empty when nothing is known.
+    pub fn source_frames(&self, pc: u64) -> Result<Vec<SourceFrame>> {
+        let Some((u, function)) = self.subprogram_at(pc)? else {
+            let (file, line, column) = self.line_at(None, pc)?;
+            let function = self.function_at(pc).map(|(name, _)| name.to_string());
+            if function.is_none() && file.is_none() {
+                return Ok(Vec::new());
+            }
+            return Ok(vec![SourceFrame {
+                function,
+                file,
+                line,
+                column,
+                inlined: false,
+            }]);
+        };
+
+        let unit = &self.units[u];
+        // nested inlines contain each other, so the deeper the inner
+        let mut chain: Vec<(usize, usize)> = Vec::new();
+        for i in function + 1..unit.nodes[function].end {
+            if unit.nodes[i].tag != gimli::DW_TAG_inlined_subroutine {
+                continue;
+            }
+            let depth = unit.ancestors(i).take_while(|&a| a != function).count();
+            if self.contains_pc(u, i, pc)? {
+                chain.push((depth, i));
+            }
+        }
+        chain.sort_unstable();
+
+        let mut frames = Vec::new();
+        let (mut file, mut line, mut column) = self.line_at(Some(u), pc)?;
+        for &index in chain.iter().map(|(_, i)| i).rev().chain([&function]) {
+            frames.push(SourceFrame {
+                function: self.function_name(u, index)?,
+                file,
+                line,
+                column,
+                inlined: index != function,
+            });
+            // the frame outside this one is where it was inlined
+            (file, line, column) = self.call_site(u, index)?;
+        }
+        Ok(frames)
+    }
+
+    /// Where to break for `line` of `file`: the line that has code, the
+    /// first at or after `line` like GDB picks, and the address each run
+    /// of rows for it starts at. `file` may be the path on another machine;
+    /// the line table files sharing the most trailing path components with
+    /// it, the file name at least, are the ones searched.
+    pub fn line_addresses(&self, file: &str, line: u64) -> Result<Option<(u64, Vec<u64>)>> {
+        // (line, address) of every run of rows in a matching file
+        let mut rows: Vec<(u64, u64)> = Vec::new();
+        let mut best = 1;
+        for u in 0..self.units.len() {
+            let Some(program) = self.units[u].unit.line_program.clone() else {
+                continue;
+            };
+            let mut names: Vec<(u64, usize)> = Vec::new();
+            let mut previous: Option<(u64, u64)> = None;
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                let index = row.file_index();
+                let shared = match names.iter().find(|(i, _)| *i == index) {
+                    Some(&(_, shared)) => shared,
+                    None => {
+                        let shared = match self.file_name(u, header, index)? {
+                            Some(name) => shared_components(&name, file),
+                            None => 0,
+                        };
+                        names.push((index, shared));
+                        shared
+                    }
+                };
+                let current = (index, row.line().map_or(0, |l| l.get()));
+                if row.end_sequence() {
+                    previous = None;
+                    continue;
+                }
+                if previous == Some(current) || shared < best || current.1 < line {
+                    previous = Some(current);
+                    continue;
+                }
+                if shared > best {
+                    best = shared;
+                    rows.clear();
+                }
+                rows.push((current.1, row.address()));
+                previous = Some(current);
+            }
+        }
+        let Some(found) = rows.iter().map(|&(l, _)| l).min() else {
+            return Ok(None);
+        };
+        let mut addresses: Vec<u64> = rows
+            .into_iter()
+            .filter(|&(l, _)| l == found)
+            .map(|(_, addr)| addr)
+            .collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        Ok(Some((found, addresses)))
+    }
+
+    /// The concrete function whose code covers `pc`.
+    fn subprogram_at(&self, pc: u64) -> Result<Option<(usize, usize)>> {
+        for (u, unit) in self.units.iter().enumerate() {
+            for (i, node) in unit.nodes.iter().enumerate() {
+                if node.tag == gimli::DW_TAG_subprogram && self.contains_pc(u, i, pc)? {
+                    return Ok(Some((u, i)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Linkage name demangled, else the path in the DIE tree, following
+    /// `DW_AT_abstract_origin` and `DW_AT_specification`.
+    fn function_name(&self, u: usize, index: usize) -> Result<Option<String>> {
+        let unit = &self.units[u];
+        let mut offset = unit.nodes[index].offset;
+        // origin of a specification at most, guards against cycles
+        for _ in 0..3 {
+            let entry = unit.unit.entry(offset)?;
+            for at in [gimli::DW_AT_linkage_name, gimli::DW_AT_MIPS_linkage_name] {
+                if let Some(value) = entry.attr_value(at) {
+                    let raw = self.dwarf.attr_string(&unit.unit, value)?;
+                    let raw = raw.to_string_lossy()?;
+                    return Ok(Some(format!("{:#}", rustc_demangle::demangle(&raw))));
+                }
+            }
+            let origin = [gimli::DW_AT_abstract_origin, gimli::DW_AT_specification]
+                .into_iter()
+                .find_map(|at| match entry.attr_value(at) {
+                    Some(AttributeValue::UnitRef(origin)) => Some(origin),
+                    _ => None,
+                });
+            match origin {
+                Some(origin) => offset = origin,
+                None => break,
+            }
+        }
+        Ok(unit
+            .node(offset)
+            .filter(|&i| unit.nodes[i].name.is_some())
+            .map(|i| unit.qualified(i)))
+    }
+
+    /// `DW_AT_call_file` and `DW_AT_call_line` of an inlined call.
+    fn call_site(&self, u: usize, index: usize) -> Result<Position> {
+        let unit = &self.units[u];
+        let entry = unit.unit.entry(unit.nodes[index].offset)?;
+        let udata = |at| entry.attr_value(at).and_then(|v| v.udata_value());
+        let file = match (
+            entry.attr_value(gimli::DW_AT_call_file),
+            &unit.unit.line_program,
+        ) {
+            (Some(AttributeValue::FileIndex(n)), Some(program)) => {
+                self.file_name(u, program.header(), n)?
+            }
+            _ => None,
+        };
+        Ok((
+            file,
+            udata(gimli::DW_AT_call_line),
+            udata(gimli::DW_AT_call_column),
+        ))
+    }
+
+    /// The line table row covering `pc`, in unit `u` or any unit.
+    fn line_at(&self, u: Option<usize>, pc: u64) -> Result<Position> {
+        let units = match u {
+            Some(u) => u..u + 1,
+            None => 0..self.units.len(),
+        };
+        for u in units {
+            let Some(program) = self.units[u].unit.line_program.clone() else {
+                continue;
+            };
+            let mut rows = program.rows();
+            let mut prev: Option<(u64, u64, Option<u64>, ColumnType)> = None;
+            while let Some((header, row)) = rows.next_row()? {
+                if let Some((address, file, line, column)) = prev {
+                    if (address..row.address()).contains(&pc) {
+                        let column = match column {
+                            ColumnType::LeftEdge => None,
+                            ColumnType::Column(c) => Some(c.get()),
+                        };
+                        return Ok((self.file_name(u, header, file)?, line, column));
+                    }
+                }
+                prev = match row.end_sequence() {
+                    true => None,
+                    false => Some((
+                        row.address(),
+                        row.file_index(),
+                        row.line().map(|l| l.get()),
+                        row.column(),
+                    )),
+                };
+            }
+        }
+        Ok((None, None, None))
+    }
+
+    /// File `index` of a line table with its directory, relative ones
+    /// joined to the compilation directory.
+    fn file_name(
+        &self,
+        u: usize,
+        header: &gimli::LineProgramHeader<R>,
+        index: u64,
+    ) -> Result<Option<String>> {
+        let unit = &self.units[u];
+        let Some(file) = header.file(index) else {
+            return Ok(None);
+        };
+        let string = |value| -> Result<String> {
+            Ok(self
+                .dwarf
+                .attr_string(&unit.unit, value)?
+                .to_string_lossy()?
+                .into_owned())
+        };
+        let mut path = string(file.path_name())?;
+        if !path.starts_with('/') {
+            if let Some(dir) = file.directory(header) {
+                path = join(&string(dir)?, &path);
+            }
+        }
+        if !path.starts_with('/') {
+            if let Some(comp_dir) = &unit.unit.comp_dir {
+                path = join(&comp_dir.to_string_lossy()?, &path);
+            }
+        }
+        Ok(Some(path))
+    }
+}
+
+fn join(dir: &str, file: &str) -> String {
+    match dir {
+        "" => file.to_string(),
+        dir => format!("{}/{}", dir.trim_end_matches('/'), file),
+    }
+}
+
+/// How many trailing path components `a` and `b` have in common.
+fn shared_components(a: &str, b: &str) -> usize {
+    a.rsplit(['/', '\\'])
+        .zip(b.rsplit(['/', '\\']))
+        .take_while(|(x, y)| x == y && !x.is_empty())
+        .count()
+}
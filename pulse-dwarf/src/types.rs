@@ -0,0 +1,179 @@
+//! Type layouts, enough to compute field offsets and print a map.
+
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Bool,
+    Signed,
+    Unsigned,
+    Float,
+    Char,
+    Other,
+}
+
+/// Little-endian `bytes` as a number of `encoding`, hex for anything
+/// that is not a scalar of up to 8 bytes.
+pub fn format_scalar(bytes: &[u8], encoding: Option<Encoding>) -> String {
+    let hex = || bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    if bytes.is_empty() || bytes.len() > 8 {
+        return hex();
+    }
+    let mut raw = [0u8; 8];
+    raw[..bytes.len()].copy_from_slice(bytes);
+    let unsigned = u64::from_le_bytes(raw);
+    let bits = bytes.len() as u32 * 8;
+    match encoding {
+        Some(Encoding::Unsigned | Encoding::Char) => unsigned.to_string(),
+        Some(Encoding::Bool) => (unsigned != 0).to_string(),
+        Some(Encoding::Signed) => {
+            let shift = 64 - bits;
+            (((unsigned << shift) as i64) >> shift).to_string()
+        }
+        Some(Encoding::Float) if bits == 32 => f32::from_bits(unsigned as u32).to_string(),
+        Some(Encoding::Float) if bits == 64 => f64::from_bits(unsigned).to_string(),
+        _ => hex(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub offset: u64,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeKind {
+    Base(Encoding),
+    /// Structs, unions and tuples; union members all sit at offset 0.
+    Struct(Vec<Member>),
+    Array {
+        element: Box<Type>,
+        count: u64,
+    },
+    /// Pointers and references, the target is not expanded.
+    Pointer {
+        target: String,
+    },
+    /// C-like enums with their discriminants.
+    Enum(Vec<(String, i64)>),
+    /// Data-carrying enums and anything else we only know the size of.
+    Opaque,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Type {
+    /// Qualified name, e.g. `pulse_core::mailbox::Mailbox`.
+    pub name: String,
+    pub size: u64,
+    pub kind: TypeKind,
+}
+
+impl Type {
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        match &self.kind {
+            // tuple fields are `__0`, `__1`, ... in rustc's DWARF
+            TypeKind::Struct(members) => members
+                .iter()
+                .find(|m| m.name == name || m.name.strip_prefix("__") == Some(name)),
+            _ => None,
+        }
+    }
+
+    /// The innermost field covering byte `offset`: the access path that
+    /// leads to it (`.records[3].cycle`, empty for `self`), where it starts
+    /// and its type.
+    ///
+    /// Newtypes like `AtomicU32` count as leaves, so the path stops at
+    /// `.sequence` rather than `.sequence.v.value`. Padding belongs to the
+    /// enclosing struct.
+    pub fn field_at(&self, offset: u64) -> Option<(String, u64, &Type)> {
+        if offset >= self.size {
+            return None;
+        }
+        if self.wrapped().is_some() {
+            return Some((String::new(), 0, self));
+        }
+        let (access, inner, start) = match &self.kind {
+            TypeKind::Struct(members) => {
+                let Some(member) = members
+                    .iter()
+                    .find(|m| (m.offset..m.offset + m.ty.size).contains(&offset))
+                else {
+                    return Some((String::new(), 0, self));
+                };
+                // tuple fields are `__0`, `__1`, ... in rustc's DWARF
+                let name = member
+                    .name
+                    .strip_prefix("__")
+                    .filter(|n| n.parse::<u32>().is_ok())
+                    .unwrap_or(&member.name);
+                (format!(".{}", name), &member.ty, member.offset)
+            }
+            TypeKind::Array { element, count } if element.size > 0 => {
+                let index = offset / element.size;
+                if index >= *count {
+                    return None;
+                }
+                (format!("[{}]", index), &**element, index * element.size)
+            }
+            _ => return Some((String::new(), 0, self)),
+        };
+        let (rest, at, leaf) = inner
+            .field_at(offset - start)
+            .unwrap_or((String::new(), 0, inner));
+        Some((access + &rest, start + at, leaf))
+    }
+
+    /// Encoding of a scalar, looking through newtypes.
+    pub fn scalar(&self) -> Option<Encoding> {
+        match &self.kind {
+            TypeKind::Base(encoding) => Some(*encoding),
+            _ => self.wrapped()?.scalar(),
+        }
+    }
+
+    /// The only field of a struct that is nothing but that field.
+    fn wrapped(&self) -> Option<&Type> {
+        match &self.kind {
+            TypeKind::Struct(members) => match members.as_slice() {
+                [only] if only.offset == 0 && only.ty.size == self.size => Some(&only.ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Offset/name/type map, one line per leaf and struct, nested by indent.
+    pub fn layout(&self) -> String {
+        let mut out = String::new();
+        self.write_layout(&mut out, 0, 0);
+        out
+    }
+
+    fn write_layout(&self, out: &mut String, offset: u64, depth: usize) {
+        if let TypeKind::Struct(members) = &self.kind {
+            for member in members {
+                let at = offset + member.offset;
+                let _ = writeln!(
+                    out,
+                    "{:>#8x}  {:indent$}{}: {} ({} bytes)",
+                    at,
+                    "",
+                    member.name,
+                    member.ty.name,
+                    member.ty.size,
+                    indent = depth * 2
+                );
+                member.ty.write_layout(out, at, depth + 1);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} bytes)", self.name, self.size)
+    }
+}
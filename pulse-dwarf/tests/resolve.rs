@@ -0,0 +1,302 @@
+use pulse_dwarf::{DebugInfo, Error, Location, Scope, TypeKind};
+use pulse_mock::fixture;
+
+fn info() -> DebugInfo {
+    DebugInfo::parse(&fixture::ghost_trigger()).unwrap()
+}
+
+#[test]
+fn statics_by_path_and_suffix() {
+    let info = info();
+    let full = info
+        .resolve("ghost_trigger::injection::CYCLE_MS", None)
+        .unwrap();
+    assert_eq!(full.location, Location::Address(fixture::CYCLE_MS));
+    assert_eq!(full.scope, Scope::Static);
+    assert_eq!(full.ty.name, "core::sync::atomic::AtomicU32");
+    assert_eq!(info.resolve("injection::CYCLE_MS", None).unwrap(), full);
+    assert_eq!(info.resolve("CYCLE_MS", Some(fixture::MAIN)).unwrap(), full);
+
+    let value = info.resolve("CYCLE_MS.v.value", None).unwrap();
+    assert_eq!(value.location, Location::Address(fixture::CYCLE_MS));
+    assert_eq!(
+        value.ty.kind,
+        TypeKind::Base(pulse_dwarf::Encoding::Unsigned)
+    );
+
+    assert!(matches!(
+        info.resolve("TRIGGER::CYCLE_MS", None),
+        Err(Error::NotFound(_))
+    ));
+}
+
+#[test]
+fn fields_and_elements() {
+    let info = info();
+    let ack = info.resolve("PULSE_MAILBOX.ack", None).unwrap();
+    assert_eq!(ack.location, Location::Address(fixture::PULSE_MAILBOX + 20));
+    assert_eq!(ack.path, "ghost_trigger::injection::PULSE_MAILBOX.ack");
+
+    let signal = info.resolve("PULSE_AUDIT.records[3].signal", None).unwrap();
+    assert_eq!(
+        signal.location,
+        Location::Address(fixture::PULSE_AUDIT + 16 + 3 * 28 + 8)
+    );
+
+    let audit = info.resolve("PULSE_AUDIT", None).unwrap();
+    let (field, start, ty) = audit.ty.field_at(16 + 3 * 28 + 9).unwrap();
+    assert_eq!(field, ".records[3].signal");
+    assert_eq!(start, 16 + 3 * 28 + 8);
+    assert_eq!(ty.name, "core::sync::atomic::AtomicU32");
+    assert_eq!(ty.scalar(), Some(pulse_dwarf::Encoding::Unsigned));
+    assert_eq!(audit.ty.field_at(audit.ty.size), None);
+
+    let statics: Vec<String> = info.statics().into_iter().map(|v| v.path).collect();
+    assert_eq!(
+        statics,
+        [
+            "ghost_trigger::injection::PULSE_MAILBOX",
+            "ghost_trigger::injection::CYCLE_MS",
+            "ghost_trigger::injection::PULSE_AUDIT",
+        ]
+    );
+
+    assert!(matches!(
+        info.resolve("PULSE_AUDIT.records[32]", None),
+        Err(Error::Path(_))
+    ));
+    assert!(matches!(
+        info.resolve("PULSE_MAILBOX.nope", None),
+        Err(Error::Path(_))
+    ));
+    assert!(matches!(
+        info.resolve("PULSE_MAILBOX[0]", None),
+        Err(Error::Path(_))
+    ));
+}
+
+#[test]
+fn location_list_follows_the_pc() {
+    let info = info();
+    let spilled = info
+        .resolve("threat_detected", Some(fixture::MAIN + 0x20))
+        .unwrap();
+    assert_eq!(
+        spilled.location,
+        Location::Memory {
+            register: 1,
+            offset: 12
+        }
+    );
+    assert_eq!(spilled.location.to_string(), "[a1 + 12]");
+    assert_eq!(
+        spilled
+            .location
+            .address(|r| (r == 1).then_some(0x3ffe_1f00)),
+        Some(0x3ffe_1f0c)
+    );
+    assert_eq!(
+        spilled.scope,
+        Scope::Local {
+            function: "ghost_trigger::main".into()
+        }
+    );
+    assert_eq!(spilled.line, Some(44));
+
+    let live = info
+        .resolve("main::threat_detected", Some(fixture::MAIN_SPILLED))
+        .unwrap();
+    assert_eq!(live.location, Location::Register(8));
+    assert_eq!(live.ty.size, 1);
+
+    assert!(matches!(
+        info.resolve("threat_detected", Some(fixture::MAIN_DEAD)),
+        Err(Error::OptimizedOut(_))
+    ));
+    assert!(matches!(
+        info.resolve("threat_detected", None),
+        Err(Error::NeedsPc(_))
+    ));
+    assert!(matches!(
+        info.resolve("threat_detected", Some(fixture::DETECTOR_CYCLE)),
+        Err(Error::NotFound(_))
+    ));
+}
+
+#[test]
+fn block_locals_and_layout() {
+    let info = info();
+    assert!(matches!(
+        info.resolve("detector", Some(fixture::MAIN + 0x20)),
+        Err(Error::NotFound(_))
+    ));
+    let counter = info
+        .resolve("detector.counter", Some(fixture::MAIN_LOOP))
+        .unwrap();
+    assert_eq!(
+        counter.location,
+        Location::Memory {
+            register: 1,
+            offset: 36
+        }
+    );
+
+    let audit = info.resolve("PULSE_AUDIT", None).unwrap();
+    assert_eq!(audit.ty.size, pulse_core::audit::AUDIT_SIZE as u64);
+    let layout = audit.ty.layout();
+    assert!(layout.contains("     0xc  head: core::sync::atomic::AtomicU32 (4 bytes)"));
+    assert!(layout.contains("    0x10  records: [pulse_core::audit::AuditRecord; 32] (896 bytes)"));
+}
+
+#[test]
+fn inlined_copies_are_named_by_their_origin() {
+    let info = info();
+    let sequence = info
+        .resolve("sequence", Some(fixture::MAIN_TAKE + 4))
+        .unwrap();
+    assert_eq!(
+        sequence.path,
+        "pulse_core::mailbox::{impl#0}::take::sequence"
+    );
+    assert_eq!(sequence.location, Location::Register(9));
+    assert_eq!(sequence.ty.name, "u32");
+    assert_eq!(sequence.line, Some(106));
+    assert_eq!(
+        sequence.scope,
+        Scope::Local {
+            function: "ghost_trigger::main".into()
+        }
+    );
+    assert!(info
+        .locals(fixture::MAIN_TAKE + 4)
+        .unwrap()
+        .iter()
+        .any(|v| v.path.ends_with("take::sequence")));
+
+    // only inside the inlined call
+    assert!(matches!(
+        info.resolve("take::sequence", Some(fixture::MAIN + 0x40)),
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        info.resolve("sequence", None),
+        Err(Error::NeedsPc(_))
+    ));
+}
+
+#[test]
+fn function_symbols() {
+    let info = info();
+    assert_eq!(
+        info.symbol("pulse_core::detector::Detector::cycle"),
+        Some(fixture::DETECTOR_CYCLE)
+    );
+    assert_eq!(
+        info.symbol("Detector::cycle"),
+        Some(fixture::DETECTOR_CYCLE)
+    );
+    assert_eq!(info.symbol("app_main"), Some(fixture::APP_MAIN));
+    assert_eq!(info.symbol("ghost_trigger::main"), Some(fixture::MAIN));
+    assert_eq!(info.symbol("tor::cycle"), None);
+
+    assert_eq!(
+        info.function_at(fixture::MAIN + 0x43),
+        Some(("ghost_trigger::main", 0x43))
+    );
+    assert_eq!(
+        info.function_at(fixture::DETECTOR_CYCLE),
+        Some(("pulse_core::detector::Detector::cycle", 0))
+    );
+    assert_eq!(info.function_at(fixture::DETECTOR_CYCLE + 0x100), None);
+    assert_eq!(info.function_at(fixture::DATA), None);
+}
+
+#[test]
+fn source_lines_and_inlined_calls() {
+    let info = info();
+    let main_rs = format!("{}/src/main.rs", fixture::COMP_DIR);
+    let frames = info.source_frames(fixture::MAIN + 0x40).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].function.as_deref(), Some("ghost_trigger::main"));
+    assert_eq!(frames[0].file.as_deref(), Some(main_rs.as_str()));
+    assert_eq!(frames[0].line, Some(47));
+    assert!(!frames[0].inlined);
+
+    // the inlined call first, by its demangled linkage name
+    let frames: Vec<String> = info
+        .source_frames(fixture::MAIN_TAKE + 4)
+        .unwrap()
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            format!(
+                "pulse_core::mailbox::Mailbox::take at {}/src/mailbox.rs:88 (inlined)",
+                fixture::PULSE_CORE_DIR
+            ),
+            format!("ghost_trigger::main at {}:49:33", main_rs),
+        ]
+    );
+
+    // no DIE for it: the symbol table names it, the line table places it
+    let cycle = info.source_frames(fixture::DETECTOR_CYCLE + 0x24).unwrap();
+    assert_eq!(
+        cycle[0].to_string(),
+        format!(
+            "pulse_core::detector::Detector::cycle at {}/src/detector.rs:131",
+            fixture::PULSE_CORE_DIR
+        )
+    );
+    let app_main = info.source_frames(fixture::APP_MAIN + 0x20).unwrap();
+    assert_eq!(app_main[0].to_string(), "app_main");
+    assert!(info.source_frames(fixture::DATA).unwrap().is_empty());
+}
+
+#[test]
+fn frame_locals_and_breakpoint_lines() {
+    let info = info();
+    let names = |pc| -> Vec<String> {
+        info.locals(pc)
+            .unwrap()
+            .into_iter()
+            .map(|v| format!("{} at {}", v.path, v.location))
+            .collect()
+    };
+    assert!(names(fixture::MAIN).is_empty());
+    assert_eq!(
+        names(fixture::MAIN + 0x40),
+        ["ghost_trigger::main::threat_detected at [a1 + 12]"]
+    );
+    assert_eq!(
+        names(fixture::MAIN_LOOP),
+        [
+            "ghost_trigger::main::threat_detected at a8",
+            "ghost_trigger::main::detector at [a1 + 32]",
+        ]
+    );
+    let detector = info.locals(fixture::MAIN_LOOP).unwrap().remove(1);
+    assert_eq!(detector.line, Some(55));
+    assert_eq!(detector.ty.member("counter").unwrap().offset, 4);
+    assert!(names(fixture::DETECTOR_CYCLE).is_empty());
+
+    // a checkout elsewhere still finds the build's files
+    let main_rs = "/home/dev/oxide-pulse/ghost-trigger/src/main.rs";
+    assert_eq!(
+        info.line_addresses(main_rs, 40).unwrap(),
+        Some((40, vec![fixture::MAIN]))
+    );
+    // no code on 53, the breakpoint moves down to 55
+    assert_eq!(
+        info.line_addresses(main_rs, 53).unwrap(),
+        Some((55, vec![fixture::MAIN_LOOP]))
+    );
+    assert_eq!(
+        info.line_addresses("pulse-core/src/detector.rs", 120)
+            .unwrap(),
+        Some((120, vec![fixture::DETECTOR_CYCLE]))
+    );
+    assert_eq!(info.line_addresses(main_rs, 71).unwrap(), None);
+    assert_eq!(info.line_addresses("src/lib.rs", 1).unwrap(), None);
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Rsp(pulse_rsp::Error),
+    Elf(String),
+    /// A kernel symbol the decoder needs is not in the ELF.
+    NotFreeRtos(String),
+    /// Kernel structures that do not hold together.
+    Corrupt(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Elf(e) => write!(f, "bad ELF: {}", e),
+            Error::NotFreeRtos(symbol) => {
+                write!(f, "no FreeRTOS kernel in the ELF: {} not found", symbol)
+            }
+            Error::Corrupt(what) => write!(f, "inconsistent kernel state: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Rsp(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
@@ -0,0 +1,101 @@
+//! The kernel's task lists, found through the ELF symbol table.
+
+use std::io::{Read, Write};
+
+use object::{Object, ObjectSymbol};
+
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+
+/// Target memory the decoder reads, a live debug link or a dump of one.
+pub trait Memory {
+    fn read(&mut self, addr: u64, len: usize) -> Result<Vec<u8>>;
+
+    /// Little-endian word, the only integer the kernel structures need.
+    fn read_u32(&mut self, addr: u64) -> Result<u32> {
+        let b = self.read(addr, 4)?;
+        match b[..] {
+            [a, b, c, d] => Ok(u32::from_le_bytes([a, b, c, d])),
+            _ => Err(Error::Corrupt(format!("short read at {:#x}", addr))),
+        }
+    }
+}
+
+impl<S: Read + Write> Memory for pulse_rsp::Client<S> {
+    fn read(&mut self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        Ok(self.read_memory(addr, len)?)
+    }
+}
+
+/// Which state a task is in by the list holding its state item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    Ready,
+    /// `xPendingReadyList`: readied while the scheduler was suspended.
+    Pending,
+    Delayed,
+    /// Suspended, or blocked with no timeout if the event item is in use.
+    Suspended,
+    /// `xTasksWaitingTermination`: deleted, not yet cleaned up by idle.
+    Terminating,
+}
+
+/// Kernel symbols of one firmware build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kernel {
+    /// `pxCurrentTCBs[core]`, one slot per core.
+    pub current: Vec<u64>,
+    /// Every task list with what being in it means.
+    pub lists: Vec<(u64, ListKind)>,
+    /// `uxCurrentNumberOfTasks`.
+    pub task_count: Option<u64>,
+}
+
+/// `pxCurrentTCB` before ESP-IDF v5.2 renamed it, an array all the same.
+const CURRENT: [&str; 2] = ["pxCurrentTCBs", "pxCurrentTCB"];
+const READY: &str = "pxReadyTasksLists";
+
+impl Kernel {
+    /// Find the kernel in an ELF. Array lengths come from the symbol
+    /// sizes, so the core count and `configMAX_PRIORITIES` follow the
+    /// build.
+    pub fn from_elf(elf: &[u8], layout: &Layout) -> Result<Self> {
+        let file = object::File::parse(elf).map_err(|e| Error::Elf(e.to_string()))?;
+        let symbol = |name: &str| {
+            file.symbols()
+                .find(|s| s.name() == Ok(name))
+                .map(|s| (s.address(), s.size()))
+        };
+        // one entry for a symbol without a size
+        let array = |(at, size): (u64, u64), stride: u64| {
+            (0..(size / stride).max(1)).map(move |i| at + i * stride)
+        };
+
+        let current = CURRENT
+            .iter()
+            .find_map(|name| symbol(name))
+            .ok_or_else(|| Error::NotFreeRtos(CURRENT[0].into()))?;
+        let ready = symbol(READY).ok_or_else(|| Error::NotFreeRtos(READY.into()))?;
+
+        let mut lists: Vec<(u64, ListKind)> = array(ready, layout.list_size)
+            .map(|at| (at, ListKind::Ready))
+            .collect();
+        for (name, kind) in [
+            ("xPendingReadyList", ListKind::Pending),
+            ("xDelayedTaskList1", ListKind::Delayed),
+            ("xDelayedTaskList2", ListKind::Delayed),
+            ("xSuspendedTaskList", ListKind::Suspended),
+            ("xTasksWaitingTermination", ListKind::Terminating),
+        ] {
+            if let Some(found) = symbol(name) {
+                lists.extend(array(found, layout.list_size).map(|at| (at, kind)));
+            }
+        }
+
+        Ok(Self {
+            current: array(current, 4).collect(),
+            lists,
+            task_count: symbol("uxCurrentNumberOfTasks").map(|(at, _)| at),
+        })
+    }
+}
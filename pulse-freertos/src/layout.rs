@@ -0,0 +1,79 @@
+//! Where the kernel keeps things inside its structures.
+
+/// `tskNO_AFFINITY`, the `xCoreID` of a task either core may run.
+pub const NO_AFFINITY: u32 = 0x7fff_ffff;
+/// `tskSTACK_FILL_BYTE`, what new stacks are filled with.
+pub const STACK_FILL: u8 = 0xa5;
+
+/// Byte offsets into `List_t`, `ListItem_t` and `TCB_t`.
+///
+/// The defaults are ESP-IDF v5's FreeRTOS on an ESP32 with 32-bit ticks
+/// and no trace or mutex fields before `xCoreID`, which is ghost-trigger's
+/// sdkconfig. Other configurations move the later `TCB_t` fields; set
+/// them from `ptype /o TCB_t` in GDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// `sizeof(List_t)`, the stride of the per-priority and per-core arrays.
+    pub list_size: u64,
+    /// `List_t::xListEnd`.
+    pub list_end: u64,
+    /// `ListItem_t::pxNext`.
+    pub item_next: u64,
+    /// `ListItem_t::pvOwner`.
+    pub item_owner: u64,
+    /// `ListItem_t::pvContainer`.
+    pub item_container: u64,
+    /// `TCB_t::pxTopOfStack`.
+    pub top_of_stack: u64,
+    /// `TCB_t::xEventListItem`.
+    pub event_item: u64,
+    /// `TCB_t::uxPriority`.
+    pub priority: u64,
+    /// `TCB_t::pxStack`.
+    pub stack: u64,
+    /// `TCB_t::pcTaskName` and `configMAX_TASK_NAME_LEN`.
+    pub name: u64,
+    pub name_len: u64,
+    /// `TCB_t::xCoreID`, absent on single-core builds.
+    pub core: Option<u64>,
+    /// `TCB_t::pxEndOfStack`, absent without `configRECORD_STACK_HIGH_ADDRESS`.
+    pub end_of_stack: Option<u64>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            list_size: 20,
+            list_end: 8,
+            item_next: 4,
+            item_owner: 12,
+            item_container: 16,
+            top_of_stack: 0,
+            event_item: 24,
+            priority: 44,
+            stack: 48,
+            name: 52,
+            name_len: 16,
+            core: Some(68),
+            end_of_stack: Some(72),
+        }
+    }
+}
+
+impl Layout {
+    /// Bytes of `TCB_t` to read to see every field the layout names.
+    pub fn tcb_size(&self) -> u64 {
+        [
+            self.top_of_stack + 4,
+            self.event_item + self.item_container + 4,
+            self.priority + 4,
+            self.stack + 4,
+            self.name + self.name_len,
+            self.core.map_or(0, |at| at + 4),
+            self.end_of_stack.map_or(0, |at| at + 4),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+    }
+}
@@ -0,0 +1,37 @@
+//! FreeRTOS task lists of a halted ghost-trigger, without OpenOCD's RTOS
+//! support.
+//!
+//! `info threads` only shows tasks when OpenOCD was started with
+//! `-rtos FreeRTOS` and GDB is driving. [`tasks`] walks the kernel's own
+//! structures instead: it finds `pxCurrentTCBs` and the ready, delayed,
+//! suspended and terminating lists through the ELF symbol table, follows
+//! the list items to each `TCB_t` and reads its name, priority, state,
+//! core affinity, stack and stack high-water mark over the debug link.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use pulse_freertos::{tasks, Kernel, Layout};
+//!
+//! let elf = std::fs::read("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let layout = Layout::default();
+//! let kernel = Kernel::from_elf(&elf, &layout)?;
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! gdb.interrupt()?;
+//! println!("{}", pulse_freertos::HEADER);
+//! for task in tasks(&mut gdb, &kernel, &layout)? {
+//!     println!("{}", task);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod kernel;
+pub mod layout;
+pub mod task;
+
+pub use error::{Error, Result};
+pub use kernel::{Kernel, ListKind, Memory};
+pub use layout::{Layout, NO_AFFINITY, STACK_FILL};
+pub use task::{tasks, State, Task, HEADER};
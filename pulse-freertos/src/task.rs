@@ -0,0 +1,231 @@
+//! Tasks decoded from their control blocks.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::kernel::{Kernel, ListKind, Memory};
+use crate::layout::{Layout, NO_AFFINITY, STACK_FILL};
+
+/// More items than this in one list means it is not a list.
+const MAX_TASKS: u32 = 256;
+/// High-water scans read the stack in pieces this big, from the bottom.
+const SCAN_CHUNK: u64 = 0x100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// `pxCurrentTCBs[core]`.
+    Running(u32),
+    Ready,
+    /// Waiting for a tick, an event or both.
+    Blocked,
+    Suspended,
+    /// Deleted, its memory not yet freed by the idle task.
+    Deleted,
+}
+
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Running(_) => "running",
+            State::Ready => "ready",
+            State::Blocked => "blocked",
+            State::Suspended => "suspended",
+            State::Deleted => "deleted",
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Running(core) => write!(f, "running/{}", core),
+            other => f.write_str(other.name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Address of the `TCB_t`, FreeRTOS' task handle.
+    pub tcb: u64,
+    pub name: String,
+    pub state: State,
+    pub priority: u32,
+    /// Core the task is pinned to, `None` for either.
+    pub affinity: Option<u32>,
+    /// Saved stack pointer. Stale for running tasks, whose live one is `a1`.
+    pub top_of_stack: u64,
+    /// Lowest stack address, `pxStack`.
+    pub stack: u64,
+    pub stack_size: Option<u64>,
+    /// Bytes at the bottom of the stack never written, what
+    /// `uxTaskGetStackHighWaterMark` returns.
+    pub high_water: Option<u64>,
+}
+
+/// Column headings for [`Task`]'s `Display`.
+pub const HEADER: &str = "TCB         NAME              STATE      PRIO  CORE   STACK    FREE";
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_dash = |v: Option<u64>| v.map_or("-".to_string(), |v| v.to_string());
+        let core = self.affinity.map_or("any".to_string(), |c| c.to_string());
+        write!(
+            f,
+            "{:#010x}  {:<16}  {:<9}  {:>4}  {:>4}  {:>6}  {:>6}",
+            self.tcb,
+            self.name,
+            self.state.to_string(),
+            self.priority,
+            core,
+            or_dash(self.stack_size),
+            or_dash(self.high_water),
+        )
+    }
+}
+
+/// Walk every task list and decode the tasks in them, running tasks
+/// first, then by priority.
+///
+/// The target has to be halted: a list the kernel is in the middle of
+/// updating is reported as [`Error::Corrupt`].
+pub fn tasks<M: Memory + ?Sized>(
+    mem: &mut M,
+    kernel: &Kernel,
+    layout: &Layout,
+) -> Result<Vec<Task>> {
+    let mut current = Vec::with_capacity(kernel.current.len());
+    for &slot in &kernel.current {
+        current.push(u64::from(mem.read_u32(slot)?));
+    }
+
+    let mut found: Vec<(u64, ListKind)> = Vec::new();
+    for &(list, kind) in &kernel.lists {
+        for tcb in owners(mem, list, layout)? {
+            if !found.iter().any(|&(t, _)| t == tcb) {
+                found.push((tcb, kind));
+            }
+        }
+    }
+    // before the scheduler starts, or caught between list moves
+    for &tcb in &current {
+        if tcb != 0 && !found.iter().any(|&(t, _)| t == tcb) {
+            found.push((tcb, ListKind::Ready));
+        }
+    }
+
+    let mut tasks = Vec::with_capacity(found.len());
+    for (tcb, kind) in found {
+        let running = current.iter().position(|&t| t == tcb);
+        tasks.push(task(mem, tcb, kind, running, layout)?);
+    }
+    tasks.sort_by_key(|t| {
+        let running = match t.state {
+            State::Running(core) => core,
+            _ => u32::MAX,
+        };
+        (running, std::cmp::Reverse(t.priority), t.name.clone())
+    });
+    Ok(tasks)
+}
+
+/// `pvOwner` of every item in a list, from `xListEnd.pxNext` around.
+fn owners<M: Memory + ?Sized>(mem: &mut M, list: u64, layout: &Layout) -> Result<Vec<u64>> {
+    let count = mem.read_u32(list)?;
+    if count > MAX_TASKS {
+        return Err(Error::Corrupt(format!(
+            "list at {:#x} claims {} items",
+            list, count
+        )));
+    }
+    let end = list + layout.list_end;
+    let mut item = u64::from(mem.read_u32(end + layout.item_next)?);
+    let mut owners = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        if item == end || item == 0 {
+            break;
+        }
+        owners.push(u64::from(mem.read_u32(item + layout.item_owner)?));
+        item = u64::from(mem.read_u32(item + layout.item_next)?);
+    }
+    if owners.len() != count as usize || item != end {
+        return Err(Error::Corrupt(format!(
+            "list at {:#x} has {} items linked, {} counted",
+            list,
+            owners.len(),
+            count
+        )));
+    }
+    Ok(owners)
+}
+
+fn task<M: Memory + ?Sized>(
+    mem: &mut M,
+    tcb: u64,
+    kind: ListKind,
+    running: Option<usize>,
+    layout: &Layout,
+) -> Result<Task> {
+    let raw = mem.read(tcb, layout.tcb_size() as usize)?;
+    let word = |at: u64| {
+        let at = at as usize;
+        raw.get(at..at + 4)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    let name_at = layout.name as usize;
+    let name = raw
+        .get(name_at..name_at + layout.name_len as usize)
+        .unwrap_or_default();
+    let name = name.split(|&b| b == 0).next().unwrap_or_default();
+
+    let state = match (running, kind) {
+        (Some(core), _) => State::Running(core as u32),
+        (None, ListKind::Ready | ListKind::Pending) => State::Ready,
+        (None, ListKind::Delayed) => State::Blocked,
+        // portMAX_DELAY waits park tasks in the suspended list
+        (None, ListKind::Suspended) => match word(layout.event_item + layout.item_container) {
+            0 => State::Suspended,
+            _ => State::Blocked,
+        },
+        (None, ListKind::Terminating) => State::Deleted,
+    };
+
+    let stack = u64::from(word(layout.stack));
+    let stack_size = layout.end_of_stack.map(|at| {
+        // pxEndOfStack is the last 8-byte aligned slot
+        (u64::from(word(at)) + 1)
+            .saturating_sub(stack)
+            .next_multiple_of(8)
+    });
+    let high_water = match stack_size {
+        Some(size) => Some(high_water(mem, stack, size)?),
+        None => None,
+    };
+
+    Ok(Task {
+        tcb,
+        name: String::from_utf8_lossy(name).into_owned(),
+        state,
+        priority: word(layout.priority),
+        affinity: layout.core.map(&word).filter(|&core| core != NO_AFFINITY),
+        top_of_stack: u64::from(word(layout.top_of_stack)),
+        stack,
+        stack_size,
+        high_water,
+    })
+}
+
+/// Fill bytes from the bottom of the stack up to the first overwritten one.
+fn high_water<M: Memory + ?Sized>(mem: &mut M, stack: u64, size: u64) -> Result<u64> {
+    let mut free = 0;
+    while free < size {
+        let len = SCAN_CHUNK.min(size - free);
+        let chunk = mem.read(stack + free, len as usize)?;
+        match chunk.iter().position(|&b| b != STACK_FILL) {
+            Some(at) => return Ok(free + at as u64),
+            None => free += len,
+        }
+    }
+    Ok(free)
+}
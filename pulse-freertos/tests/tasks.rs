@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use pulse_freertos::{tasks, Error, Kernel, Layout, ListKind, Memory, State, HEADER, NO_AFFINITY};
+use pulse_mock::{fixture, freertos, Image, Options, Server};
+use pulse_rsp::Client;
+
+/// Sparse memory for hand-built kernels, unmapped bytes read as zero.
+#[derive(Default)]
+struct Words(BTreeMap<u64, u8>);
+
+impl Words {
+    fn put(&mut self, addr: u64, value: impl Into<u64>) {
+        let value = value.into() as u32;
+        for (i, b) in value.to_le_bytes().into_iter().enumerate() {
+            self.0.insert(addr + i as u64, b);
+        }
+    }
+}
+
+impl Memory for Words {
+    fn read(&mut self, addr: u64, len: usize) -> pulse_freertos::Result<Vec<u8>> {
+        Ok((addr..addr + len as u64)
+            .map(|a| self.0.get(&a).copied().unwrap_or(0))
+            .collect())
+    }
+}
+
+#[test]
+fn tasks_on_the_mock() {
+    let elf = fixture::ghost_trigger();
+    let layout = Layout::default();
+    let kernel = Kernel::from_elf(&elf, &layout).unwrap();
+    assert_eq!(kernel.current, [fixture::KERNEL, fixture::KERNEL + 4]);
+    let ready = kernel
+        .lists
+        .iter()
+        .filter(|(_, kind)| *kind == ListKind::Ready)
+        .count();
+    assert_eq!(ready as u64, fixture::MAX_PRIORITIES);
+
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+
+    let found = tasks(&mut gdb, &kernel, &layout).unwrap();
+    let summary: Vec<(&str, State, u32, Option<u32>)> = found
+        .iter()
+        .map(|t| (t.name.as_str(), t.state, t.priority, t.affinity))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("main", State::Running(0), 1, Some(0)),
+            ("IDLE1", State::Running(1), 0, Some(1)),
+            ("ipc0", State::Blocked, 24, Some(0)),
+            ("ipc1", State::Suspended, 24, Some(1)),
+            ("esp_timer", State::Blocked, 22, Some(0)),
+            ("IDLE0", State::Ready, 0, Some(0)),
+        ]
+    );
+    for task in &found {
+        let model = freertos::TASKS
+            .iter()
+            .find(|t| t.name == task.name)
+            .unwrap();
+        assert_eq!(task.stack_size, Some(model.stack_size));
+        assert_eq!(task.high_water, Some(model.stack_size - model.used));
+    }
+    assert_eq!(found[0].stack, pulse_mock::cpu::STACK_BASE);
+
+    let line = found[0].to_string();
+    assert!(line.starts_with("0x3ffb8"), "{}", line);
+    assert!(line.contains("main              running/0     1     0"));
+    assert_eq!(line.len(), HEADER.len());
+}
+
+#[test]
+fn hand_built_lists() {
+    let layout = Layout::default();
+    let (ready, suspended, tcb): (u64, u64, u64) = (0x1000, 0x1100, 0x2000);
+    let kernel = Kernel {
+        current: vec![0x0ff0],
+        lists: vec![(ready, ListKind::Ready), (suspended, ListKind::Suspended)],
+        task_count: None,
+    };
+    let mut mem = Words::default();
+    // one ready task with no affinity and no stack end recorded
+    mem.put(ready, 1u32);
+    mem.put(ready + 12, tcb + 4);
+    mem.put(tcb + 8, ready + 8);
+    mem.put(tcb + 16, tcb);
+    mem.put(tcb + 44, 5u32);
+    for (i, b) in b"worker".iter().enumerate() {
+        mem.0.insert(tcb + 52 + i as u64, *b);
+    }
+    mem.put(tcb + 68, NO_AFFINITY);
+    mem.put(suspended + 12, suspended + 8);
+
+    let no_end = Layout {
+        end_of_stack: None,
+        ..layout.clone()
+    };
+    let found = tasks(&mut mem, &kernel, &no_end).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].name, "worker");
+    assert_eq!(found[0].state, State::Ready);
+    assert_eq!(found[0].affinity, None);
+    assert_eq!(found[0].high_water, None);
+    assert!(found[0].to_string().contains(" any "));
+
+    // a count the links do not back up
+    mem.put(suspended, 2u32);
+    assert!(matches!(
+        tasks(&mut mem, &kernel, &layout),
+        Err(Error::Corrupt(_))
+    ));
+    // a list that loops without coming back to its end
+    mem.put(suspended, 1u32);
+    mem.put(suspended + 12, tcb + 4);
+    mem.put(tcb + 8, tcb + 4);
+    assert!(matches!(
+        tasks(&mut mem, &kernel, &layout),
+        Err(Error::Corrupt(_))
+    ));
+}
+
+#[test]
+fn needs_the_kernel_symbols() {
+    assert!(matches!(
+        Kernel::from_elf(b"not an elf", &Layout::default()),
+        Err(Error::Elf(_))
+    ));
+    assert_eq!(Layout::default().tcb_size(), 76);
+}
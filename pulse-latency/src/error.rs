@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Rsp(pulse_rsp::Error),
+    Dwarf(pulse_dwarf::Error),
+    /// The firmware never printed what we were waiting for.
+    Timeout(String),
+    /// `PULSE_MAILBOX` is not a mailbox this version speaks.
+    Mailbox(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Rsp(e) => write!(f, "debugger: {}", e),
+            Error::Dwarf(e) => write!(f, "{}", e),
+            Error::Timeout(what) => write!(f, "timed out waiting for {}", what),
+            Error::Mailbox(what) => write!(f, "PULSE_MAILBOX at {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            Error::Timeout(_) | Error::Mailbox(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_dwarf::Error> for Error {
+    fn from(e: pulse_dwarf::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
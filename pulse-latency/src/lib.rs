@@ -0,0 +1,31 @@
+//! Injection-to-response latency for ghost-trigger.
+//!
+//! Halts the running target, posts a threat through `PULSE_MAILBOX`,
+//! resumes it and waits for `THREAT DETECTED`, many times over. Each
+//! [`Sample`] has the halt duration, the detection cycles that passed,
+//! the host-side end-to-end latency (mailbox write to the log line
+//! arriving) and, from the `PULSE_AUDIT` timestamp, the firmware-side
+//! latency (request consumed to the line logged). [`Report`] summarizes
+//! them as distributions.
+//!
+//! ```no_run
+//! # fn main() -> pulse_latency::Result<()> {
+//! use pulse_latency::{measure, Options, Symbols};
+//!
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! let symbols = Symbols { mailbox: 0x3ffb_0000, audit: Some(0x3ffb_0040) };
+//! let serial = pulse_latency::spawn_serial("/dev/ttyUSB0")?;
+//! let report = measure(&mut gdb, symbols, Some(serial), Options::default())?;
+//! print!("{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod measure;
+pub mod stats;
+
+pub use error::{Error, Result};
+pub use measure::{measure, spawn_serial, Options, Report, Sample, Stamped, Symbols};
+pub use stats::Summary;
@@ -0,0 +1,85 @@
+use std::process::ExitCode;
+use std::time::Duration;
+
+use pulse_core::audit::AUDIT_SYMBOL;
+use pulse_core::mailbox::MAILBOX_SYMBOL;
+use pulse_dwarf::DebugInfo;
+use pulse_latency::{measure, spawn_serial, Options, Symbols};
+use pulse_rsp::Client;
+
+const USAGE: &str =
+    "usage: pulse-latency [--target HOST:PORT] [--serial DEV] [--count N] [--payload N] <ghost-trigger ELF>";
+
+fn main() -> ExitCode {
+    let mut target = format!("127.0.0.1:{}", pulse_rsp::DEFAULT_PORT);
+    let mut serial = None;
+    let mut options = Options::default();
+    let mut elf = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => match args.next() {
+                Some(v) => target = v,
+                None => return usage(),
+            },
+            "--serial" => match args.next() {
+                Some(v) => serial = Some(v),
+                None => return usage(),
+            },
+            "--count" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => options.injections = v,
+                None => return usage(),
+            },
+            "--payload" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => options.payload = v,
+                None => return usage(),
+            },
+            "-h" | "--help" => return usage(),
+            path => elf = Some(path.to_string()),
+        }
+    }
+    let Some(elf) = elf else {
+        return usage();
+    };
+
+    match run(&target, serial.as_deref(), &elf, options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(
+    target: &str,
+    serial: Option<&str>,
+    elf: &str,
+    options: Options,
+) -> pulse_latency::Result<()> {
+    let debug = DebugInfo::from_file(elf)?;
+    let mailbox = debug.symbol(MAILBOX_SYMBOL).ok_or_else(|| {
+        pulse_dwarf::Error::NotFound(format!("{} (hardened build?)", MAILBOX_SYMBOL))
+    })?;
+    let symbols = Symbols {
+        mailbox,
+        audit: debug.symbol(AUDIT_SYMBOL),
+    };
+    let serial = match serial {
+        Some(path) => Some(spawn_serial(path)?),
+        None => None,
+    };
+
+    let mut gdb = Client::connect(target)?;
+    gdb.set_timeout(Some(Duration::from_secs(5)))?;
+    gdb.handshake()?;
+    let report = measure(&mut gdb, symbols, serial, options)?;
+    print!("{}", report);
+    Ok(())
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
@@ -0,0 +1,323 @@
+//! Repeated mailbox injections, timed from both ends.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulse_core::audit::{AuditEntry, AuditLog, AUDIT_SIZE};
+use pulse_core::mailbox::{check_header, Signal, OFFSET_MAGIC, OFFSET_VERSION};
+use pulse_core::mailbox::{OFFSET_PAYLOAD, OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_core::Event as Detector;
+use pulse_log::{Entry, Event, Parser, Reader};
+use pulse_rsp::Client;
+
+use crate::error::{Error, Result};
+use crate::stats::Summary;
+
+/// Granularity of host timestamps for console lines that arrive over GDB.
+const POLL: Duration = Duration::from_millis(5);
+/// Replies to halts and memory accesses.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A log entry and the host time it arrived.
+pub type Stamped = (Instant, Entry);
+
+/// Parse a serial device on a background thread, stamping each entry as
+/// it arrives.
+pub fn spawn_serial(path: impl AsRef<Path>) -> std::io::Result<Receiver<Stamped>> {
+    let port = std::fs::File::open(path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for entry in Reader::new(port) {
+            let Ok(entry) = entry else { break };
+            if tx.send((Instant::now(), entry)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub injections: usize,
+    /// Mailbox payload, threat cycles to report.
+    pub payload: u32,
+    /// Longest wait for the firmware to report or settle.
+    pub timeout: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            injections: 10,
+            payload: 1,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Target addresses the measurement needs.
+#[derive(Debug, Clone, Copy)]
+pub struct Symbols {
+    pub mailbox: u64,
+    /// `PULSE_AUDIT`, for the firmware-side timestamp. Builds without it
+    /// only get host-side numbers.
+    pub audit: Option<u64>,
+}
+
+/// One injection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub sequence: u32,
+    /// From asking the target to halt until it runs again.
+    pub halt: Duration,
+    /// Last cycle logged before the halt to the cycle that reported the threat.
+    pub cycles: u32,
+    /// From the write that publishes the request to the THREAT DETECTED line
+    /// arriving on the host.
+    pub end_to_end: Duration,
+    /// From the firmware consuming the request (its audit timestamp) to it
+    /// logging THREAT DETECTED, both on the target clock. Millisecond
+    /// resolution, the log timestamp has no finer.
+    pub firmware: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub samples: Vec<Sample>,
+}
+
+impl Report {
+    pub fn halt(&self) -> Option<Summary> {
+        Summary::of(self.samples.iter().map(|s| millis(s.halt)))
+    }
+
+    pub fn cycles(&self) -> Option<Summary> {
+        Summary::of(self.samples.iter().map(|s| f64::from(s.cycles)))
+    }
+
+    pub fn end_to_end(&self) -> Option<Summary> {
+        Summary::of(self.samples.iter().map(|s| millis(s.end_to_end)))
+    }
+
+    pub fn firmware(&self) -> Option<Summary> {
+        Summary::of(self.samples.iter().filter_map(|s| s.firmware.map(millis)))
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, " seq   halt ms  cycles  end-to-end ms  firmware ms")?;
+        for s in &self.samples {
+            let firmware = s
+                .firmware
+                .map_or_else(|| "-".into(), |d| format!("{:.1}", millis(d)));
+            writeln!(
+                f,
+                "{:>4} {:>9.1} {:>7} {:>14.1} {:>12}",
+                s.sequence,
+                millis(s.halt),
+                s.cycles,
+                millis(s.end_to_end),
+                firmware
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<16} {:>4} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "", "n", "min", "p50", "p95", "max", "mean"
+        )?;
+        for (name, summary) in [
+            ("halt (ms)", self.halt()),
+            ("cycles", self.cycles()),
+            ("end-to-end (ms)", self.end_to_end()),
+            ("firmware (ms)", self.firmware()),
+        ] {
+            if let Some(summary) = summary {
+                writeln!(f, "{:<16} {}", name, summary)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Inject `options.injections` threats through the mailbox on a running
+/// target and time each one.
+///
+/// Between injections it waits for the detector to report Secure again.
+/// The log is read from `O` packets and, if given, `serial`; whichever
+/// shows a line first wins. With `symbols.audit` the target is halted
+/// once more after each THREAT DETECTED to read that injection's audit
+/// record before the ring wraps. Detaches when done.
+pub fn measure(
+    gdb: &mut Client,
+    symbols: Symbols,
+    serial: Option<Receiver<Stamped>>,
+    options: Options,
+) -> Result<Report> {
+    let mut session = Session {
+        gdb,
+        serial,
+        parser: Parser::new(),
+        console: String::new(),
+        last: None,
+    };
+    session.resume()?;
+
+    let mut samples = Vec::new();
+    for _ in 0..options.injections {
+        session.wait_for("System secure.", options.timeout, |e| {
+            matches!(e, Event::Detector(Detector::Secure { .. }))
+        })?;
+
+        let halt_at = Instant::now();
+        session.halt()?;
+        let cycle = session.last.and_then(|e| e.cycle()).unwrap_or(0);
+        let mailbox = symbols.mailbox;
+        let gdb = &mut *session.gdb;
+        let magic = gdb.read_u32(mailbox + u64::from(OFFSET_MAGIC))?;
+        let version = gdb.read_u32(mailbox + u64::from(OFFSET_VERSION))?;
+        check_header(magic, version)
+            .map_err(|why| Error::Mailbox(format!("{:#010x}: {}", mailbox, why)))?;
+        let sequence = gdb
+            .read_u32(mailbox + u64::from(OFFSET_SEQUENCE))?
+            .wrapping_add(1);
+        gdb.write_u32(mailbox + u64::from(OFFSET_SIGNAL), Signal::Threat.id())?;
+        gdb.write_u32(mailbox + u64::from(OFFSET_PAYLOAD), options.payload)?;
+        gdb.write_u32(mailbox + u64::from(OFFSET_SEQUENCE), sequence)?;
+        let written = Instant::now();
+        session.resume()?;
+        let halt = halt_at.elapsed();
+
+        let (seen, entry) = session.wait_for("THREAT DETECTED", options.timeout, |e| {
+            matches!(e, Event::Detector(Detector::ThreatDetected { .. }))
+        })?;
+        let threat_cycle = entry.event.and_then(|e| e.cycle()).unwrap_or(cycle);
+
+        // the audit ring only holds AUDIT_CAPACITY records, read this
+        // injection's before later ones can overwrite it
+        let firmware = match symbols.audit {
+            Some(audit) => {
+                session.halt()?;
+                let bytes = session.gdb.read_memory(audit, AUDIT_SIZE)?;
+                session.resume()?;
+                let entries = AuditLog::decode(&bytes).unwrap_or_default();
+                firmware_latency(&entries, sequence, entry.line.timestamp_ms)
+            }
+            None => None,
+        };
+        samples.push(Sample {
+            sequence,
+            halt,
+            cycles: threat_cycle.saturating_sub(cycle),
+            end_to_end: seen.saturating_duration_since(written),
+            firmware,
+        });
+    }
+
+    session.halt()?;
+    let _ = session.gdb.detach();
+
+    Ok(Report { samples })
+}
+
+fn firmware_latency(
+    entries: &[AuditEntry],
+    sequence: u32,
+    logged_ms: Option<u64>,
+) -> Option<Duration> {
+    let consumed = entries
+        .iter()
+        .rev()
+        .find(|e| e.sequence == sequence && e.signal == Signal::Threat.id())?;
+    let logged_us = logged_ms? * 1000;
+    // the log timestamp is truncated to the millisecond
+    Some(Duration::from_micros(
+        logged_us.saturating_sub(consumed.timestamp_us / 1000 * 1000),
+    ))
+}
+
+struct Session<'a> {
+    gdb: &'a mut Client,
+    serial: Option<Receiver<Stamped>>,
+    parser: Parser,
+    /// GDB console bytes not yet split into lines.
+    console: String,
+    /// Latest firmware event seen.
+    last: Option<Event>,
+}
+
+impl Session<'_> {
+    /// Entries that arrived since the last call, oldest first.
+    fn pump(&mut self) -> Vec<Stamped> {
+        let now = Instant::now();
+        let mut out = Vec::new();
+        self.console
+            .push_str(&String::from_utf8_lossy(&self.gdb.take_console()));
+        while let Some(end) = self.console.find('\n') {
+            let line: String = self.console.drain(..=end).collect();
+            if let Some(entry) = self.parser.line(&line) {
+                out.push((now, entry));
+            }
+        }
+        if let Some(serial) = &self.serial {
+            out.extend(serial.try_iter());
+        }
+        out.sort_by_key(|(at, _)| *at);
+        if let Some(event) = out.iter().rev().find_map(|(_, e)| e.event) {
+            self.last = Some(event);
+        }
+        out
+    }
+
+    /// Run until an event matching `want` is logged.
+    fn wait_for(
+        &mut self,
+        what: &str,
+        timeout: Duration,
+        want: impl Fn(&Event) -> bool,
+    ) -> Result<Stamped> {
+        let start = Instant::now();
+        loop {
+            match self.gdb.wait_stop() {
+                Ok(_) => {
+                    // a breakpoint someone else left, keep going
+                    self.gdb.resume()?;
+                }
+                Err(e) if e.is_timeout() => {}
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(hit) = self
+                .pump()
+                .into_iter()
+                .find(|(_, e)| e.event.as_ref().is_some_and(&want))
+            {
+                return Ok(hit);
+            }
+            if start.elapsed() > timeout {
+                return Err(Error::Timeout(what.into()));
+            }
+        }
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.gdb.set_timeout(Some(POLL))?;
+        self.gdb.resume()?;
+        Ok(())
+    }
+
+    fn halt(&mut self) -> Result<()> {
+        self.gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+        self.gdb.interrupt()?;
+        self.pump();
+        Ok(())
+    }
+}
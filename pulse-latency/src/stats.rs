@@ -0,0 +1,45 @@
+//! Distributions over the samples.
+
+use std::fmt;
+
+/// Order statistics of one metric.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Summary {
+    /// `None` for no values. Percentiles are nearest-rank.
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Summary> {
+        let mut values: Vec<f64> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let rank =
+            |p: f64| values[((p * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1];
+        Some(Summary {
+            count: values.len(),
+            min: values[0],
+            median: rank(0.5),
+            p95: rank(0.95),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>4} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+            self.count, self.min, self.median, self.p95, self.max, self.mean
+        )
+    }
+}
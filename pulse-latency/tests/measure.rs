@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use pulse_core::audit::AUDIT_CAPACITY;
+use pulse_latency::{measure, Options, Summary, Symbols};
+use pulse_mock::{fixture, Image, Options as MockOptions, Server};
+use pulse_rsp::Client;
+
+#[test]
+fn summary_percentiles() {
+    let s = Summary::of((1..=20).map(f64::from)).unwrap();
+    assert_eq!((s.count, s.min, s.max), (20, 1.0, 20.0));
+    assert_eq!((s.median, s.p95), (10.0, 19.0));
+    assert_eq!(s.mean, 10.5);
+    assert_eq!(Summary::of([7.0]).unwrap().p95, 7.0);
+    assert_eq!(Summary::of([]), None);
+}
+
+#[test]
+fn injections_on_the_mock() {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        MockOptions { time_scale: 1.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+    gdb.write_u32(fixture::CYCLE_MS, 10).unwrap();
+
+    let symbols = Symbols {
+        mailbox: fixture::PULSE_MAILBOX,
+        audit: Some(fixture::PULSE_AUDIT),
+    };
+    // more than the audit ring holds, early records get overwritten
+    let injections = AUDIT_CAPACITY + 2;
+    let options = Options {
+        injections,
+        timeout: Duration::from_secs(5),
+        ..Options::default()
+    };
+    let report = measure(&mut gdb, symbols, None, options).unwrap();
+
+    let sequences: Vec<u32> = report.samples.iter().map(|s| s.sequence).collect();
+    assert_eq!(sequences, (1..=injections as u32).collect::<Vec<_>>());
+    for sample in &report.samples {
+        // consumed on the first cycle after the resume
+        assert_eq!(sample.cycles, 1, "{}", report);
+        assert!(sample.end_to_end >= Duration::from_millis(1), "{}", report);
+        // the mock logs on the cycle it consumes the request
+        assert_eq!(sample.firmware, Some(Duration::ZERO));
+    }
+    assert_eq!(report.end_to_end().unwrap().count, injections);
+    assert_eq!(report.firmware().unwrap().count, injections);
+    assert!(report.to_string().contains("end-to-end (ms)"));
+}
@@ -0,0 +1,100 @@
+//! ghost-trigger's own log lines as typed events.
+
+use pulse_core::{Event as DetectorEvent, ThreatState, Transition};
+
+use crate::line::LogLine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// `System altered!`, once per boot.
+    Boot,
+    /// A line `pulse_core::LogSink` printed.
+    Detector(DetectorEvent),
+}
+
+impl Event {
+    pub fn cycle(&self) -> Option<u32> {
+        match self {
+            Event::Boot => None,
+            Event::Detector(event) => Some(event.cycle()),
+        }
+    }
+}
+
+/// A parsed line and the event it carries, if it is one of ours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub line: LogLine,
+    pub event: Option<Event>,
+}
+
+/// Turns lines into entries, keeping the context a single line lacks.
+///
+/// `Engaging backup protocols...` carries no cycle number; it takes the
+/// cycle of the line before it, which the detector always prints in the
+/// same cycle.
+#[derive(Debug, Default, Clone)]
+pub struct Parser {
+    cycle: u32,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one line of serial output, `None` if it is not a log line.
+    pub fn line(&mut self, text: &str) -> Option<Entry> {
+        let line = LogLine::parse(text)?;
+        let event = self.event(&line.message);
+        match event {
+            Some(Event::Boot) => self.cycle = 0,
+            Some(Event::Detector(e)) => self.cycle = e.cycle(),
+            None => {}
+        }
+        Some(Entry { line, event })
+    }
+
+    fn event(&self, message: &str) -> Option<Event> {
+        let message = message.trim();
+        if message == "System altered!" {
+            return Some(Event::Boot);
+        }
+        if message == "Engaging backup protocols..." {
+            return Some(Event::Detector(DetectorEvent::EngagingBackup {
+                cycle: self.cycle,
+            }));
+        }
+
+        let (text, cycle) = split_cycle(message)?;
+        let event = if text == "System secure." {
+            DetectorEvent::Secure { cycle }
+        } else if text.trim_matches(|c| c == '!' || c == ' ') == "THREAT DETECTED" {
+            DetectorEvent::ThreatDetected { cycle }
+        } else {
+            let (from, to) = text.strip_prefix("State ")?.split_once(" -> ")?;
+            DetectorEvent::Transition(Transition {
+                cycle,
+                from: state(from)?,
+                to: state(to)?,
+            })
+        };
+        Some(Event::Detector(event))
+    }
+}
+
+/// `text [Cycle: N]` into `text` and `N`.
+fn split_cycle(message: &str) -> Option<(&str, u32)> {
+    let (text, cycle) = message.strip_suffix(']')?.rsplit_once("[Cycle: ")?;
+    Some((text.trim_end(), cycle.parse().ok()?))
+}
+
+fn state(name: &str) -> Option<ThreatState> {
+    ThreatState::ALL.into_iter().find(|s| s.name() == name)
+}
+
+/// Parse a recorded log, skipping lines that are not log lines.
+pub fn parse_log(text: &str) -> Vec<Entry> {
+    let mut parser = Parser::new();
+    text.lines().filter_map(|l| parser.line(l)).collect()
+}
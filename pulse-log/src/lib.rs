@@ -0,0 +1,30 @@
+//! ESP-IDF serial log parser for ghost-trigger.
+//!
+//! Splits `E (3338) pulse_core::sink:  !! THREAT DETECTED !! [Cycle: 4]`
+//! into level, timestamp, tag and message (colors and `\r` removed), and
+//! turns the detector's own lines back into [`pulse_core::Event`]s with
+//! their cycle numbers, so tests can assert on what the board printed
+//! instead of eyeballing `espflash monitor`.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use pulse_core::Event as Detector;
+//! use pulse_log::{Event, Reader};
+//!
+//! let port = std::fs::File::open("/dev/ttyUSB0")?;
+//! for entry in Reader::new(port) {
+//!     if let Some(Event::Detector(Detector::ThreatDetected { cycle })) = entry?.event {
+//!         println!("threat on cycle {}", cycle);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod event;
+pub mod line;
+pub mod reader;
+
+pub use event::{parse_log, Entry, Event, Parser};
+pub use line::{strip_ansi, LogLine};
+pub use reader::{Lines, Reader};
@@ -0,0 +1,120 @@
+//! One ESP-IDF log line.
+
+use std::fmt;
+
+use log::Level;
+
+/// A line as printed by the ESP-IDF logger, colors removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub level: Level,
+    /// Milliseconds since boot for `(1338)`, or since midnight for the
+    /// `(12:34:56.789)` system-time format. `None` for lines without one.
+    pub timestamp_ms: Option<u64>,
+    /// Module path or component, e.g. `pulse_core::sink` or `boot`.
+    pub tag: String,
+    pub message: String,
+}
+
+impl LogLine {
+    /// Parse `E (1338) tag: message`, with or without ANSI colors and a
+    /// trailing `\r`. Also takes the `ERROR - message` form used in the
+    /// docs. Anything else (ROM bootloader output, panics) is `None`.
+    pub fn parse(line: &str) -> Option<LogLine> {
+        let line = strip_ansi(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        parse_idf(line).or_else(|| parse_short(line))
+    }
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = &self.level.as_str()[..1];
+        match self.timestamp_ms {
+            Some(ms) => write!(f, "{} ({}) {}: {}", letter, ms, self.tag, self.message),
+            None => write!(f, "{} {}: {}", letter, self.tag, self.message),
+        }
+    }
+}
+
+fn level(letter: char) -> Option<Level> {
+    Some(match letter {
+        'E' => Level::Error,
+        'W' => Level::Warn,
+        'I' => Level::Info,
+        'D' => Level::Debug,
+        'V' => Level::Trace,
+        _ => return None,
+    })
+}
+
+/// `1338` or `12:34:56.789`.
+fn timestamp(text: &str) -> Option<u64> {
+    if let Ok(ms) = text.parse() {
+        return Some(ms);
+    }
+    let (hms, millis) = text.split_once('.')?;
+    let mut parts = hms.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, m, s) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(((h * 60 + m) * 60 + s) * 1000 + millis.parse::<u64>().ok()?)
+}
+
+fn parse_idf(line: &str) -> Option<LogLine> {
+    let mut chars = line.chars();
+    let level = level(chars.next()?)?;
+    let rest = chars.as_str().strip_prefix(" (")?;
+    let (stamp, rest) = rest.split_once(") ")?;
+    // module paths contain `::`, the tag ends at the first `: `
+    let (tag, message) = match rest.split_once(": ") {
+        Some((tag, message)) => (tag, message),
+        None => (rest.strip_suffix(':')?, ""),
+    };
+    Some(LogLine {
+        level,
+        timestamp_ms: Some(timestamp(stamp)?),
+        tag: tag.to_string(),
+        message: message.to_string(),
+    })
+}
+
+fn parse_short(line: &str) -> Option<LogLine> {
+    let (word, message) = line.split_once(" - ")?;
+    let level = match word.trim_end() {
+        "ERROR" => Level::Error,
+        "WARN" => Level::Warn,
+        "INFO" => Level::Info,
+        "DEBUG" => Level::Debug,
+        "TRACE" => Level::Trace,
+        _ => return None,
+    };
+    Some(LogLine {
+        level,
+        timestamp_ms: None,
+        tag: String::new(),
+        message: message.to_string(),
+    })
+}
+
+/// Drop `ESC [ ... <letter>` sequences, which is all the IDF logger emits.
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        if chars.clone().next() == Some('[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
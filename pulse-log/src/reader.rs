@@ -0,0 +1,114 @@
+//! Entries from a live byte stream.
+
+use std::io::{self, Read};
+
+use crate::event::{Entry, Parser};
+
+/// EIO, what a pseudo-terminal master reads once the other side closed.
+const EIO: i32 = 5;
+
+/// Iterates over the raw lines arriving on a serial port, pty or file.
+///
+/// Reads whatever the stream has, so lines split across reads (as they
+/// are on a UART) come out whole, line ending included. A partial last
+/// line comes out at end of stream.
+pub struct Lines<R> {
+    inner: R,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Lines<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let end = match self.buf.iter().position(|&b| b == b'\n') {
+            Some(end) => end + 1,
+            None if self.eof && !self.buf.is_empty() => self.buf.len(),
+            None => return None,
+        };
+        let line: Vec<u8> = self.buf.drain(..end).collect();
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+impl<R: Read> Iterator for Lines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.take_line() {
+                return Some(Ok(line));
+            }
+            if self.eof {
+                return None;
+            }
+            let mut chunk = [0u8; 512];
+            match self.inner.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.raw_os_error() == Some(EIO) => self.eof = true,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterates over the log entries arriving on a serial port, pty or file.
+///
+/// [`Lines`] through a [`Parser`]: non-log lines are skipped; a partial
+/// last line is parsed at end of stream.
+pub struct Reader<R> {
+    lines: Lines<R>,
+    parser: Parser,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            lines: Lines::new(inner),
+            parser: Parser::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.lines.get_ref()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.lines.into_inner()
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            match line {
+                Ok(line) => {
+                    if let Some(entry) = self.parser.line(&line) {
+                        return Some(Ok(entry));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
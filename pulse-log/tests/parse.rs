@@ -0,0 +1,139 @@
+use std::io::{self, Read};
+
+use log::Level;
+use pulse_core::{Event as Detector, ThreatState, Transition};
+use pulse_log::{parse_log, Event, LogLine, Parser, Reader};
+
+const RECORDED: &str = include_str!("data/threat.log");
+
+#[test]
+fn line_formats() {
+    let line = LogLine::parse(
+        "\x1b[0;31mE (3338) pulse_core::sink:  !! THREAT DETECTED !! [Cycle: 4]\x1b[0m\r",
+    )
+    .unwrap();
+    assert_eq!(line.level, Level::Error);
+    assert_eq!(line.timestamp_ms, Some(3338));
+    assert_eq!(line.tag, "pulse_core::sink");
+    assert_eq!(line.message, " !! THREAT DETECTED !! [Cycle: 4]");
+    assert_eq!(
+        line.to_string(),
+        "E (3338) pulse_core::sink:  !! THREAT DETECTED !! [Cycle: 4]"
+    );
+
+    let system_time = LogLine::parse("I (12:01:02.345) boot: ready").unwrap();
+    assert_eq!(system_time.timestamp_ms, Some(43_262_345));
+
+    let short = LogLine::parse("WARN  - Engaging backup protocols...").unwrap();
+    assert_eq!(short.level, Level::Warn);
+    assert_eq!(short.timestamp_ms, None);
+    assert_eq!(short.message, "Engaging backup protocols...");
+
+    for noise in [
+        "ets Jun  8 2016 00:22:57",
+        "rst:0x1 (POWERON_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)",
+        "X (12) tag: message",
+        "I (12x) tag: message",
+        "",
+    ] {
+        assert_eq!(LogLine::parse(noise), None, "{:?}", noise);
+    }
+}
+
+#[test]
+fn detector_events() {
+    let mut parser = Parser::new();
+    let mut event = |line: &str| parser.line(line).and_then(|e| e.event);
+
+    assert_eq!(
+        event("I (338) ghost_trigger: System altered!"),
+        Some(Event::Boot)
+    );
+    assert_eq!(
+        event("I (338) pulse_core::sink: System secure. [Cycle: 1]"),
+        Some(Event::Detector(Detector::Secure { cycle: 1 }))
+    );
+    assert_eq!(
+        event("ERROR - !! THREAT DETECTED !! [Cycle: 42]"),
+        Some(Event::Detector(Detector::ThreatDetected { cycle: 42 }))
+    );
+    assert_eq!(
+        event("WARN  - Engaging backup protocols..."),
+        Some(Event::Detector(Detector::EngagingBackup { cycle: 42 }))
+    );
+    assert_eq!(
+        event("I (5338) pulse_core::sink: State BackupEngaged -> Cooldown [Cycle: 6]"),
+        Some(Event::Detector(Detector::Transition(Transition {
+            cycle: 6,
+            from: ThreatState::BackupEngaged,
+            to: ThreatState::Cooldown,
+        })))
+    );
+    assert_eq!(
+        event("I (5338) pulse_core::sink: State Calm -> Cooldown [Cycle: 6]"),
+        None
+    );
+    assert_eq!(
+        event("I (31) boot: ESP-IDF v5.3.3 2nd stage bootloader"),
+        None
+    );
+}
+
+#[test]
+fn recorded_boot_and_injection() {
+    let entries = parse_log(RECORDED);
+    assert_eq!(entries[0].line.tag, "boot");
+    assert!(entries
+        .iter()
+        .any(|e| e.line.level == Level::Warn && e.line.tag == "spi_flash"));
+
+    let events: Vec<Event> = entries.iter().filter_map(|e| e.event).collect();
+    assert_eq!(events[0], Event::Boot);
+    let threat = entries
+        .iter()
+        .find(|e| {
+            matches!(
+                e.event,
+                Some(Event::Detector(Detector::ThreatDetected { .. }))
+            )
+        })
+        .unwrap();
+    assert_eq!(threat.event.unwrap().cycle(), Some(4));
+    assert_eq!(threat.line.timestamp_ms, Some(3338));
+    assert!(events.contains(&Event::Detector(Detector::EngagingBackup { cycle: 4 })));
+    assert_eq!(
+        events.last(),
+        Some(&Event::Detector(Detector::Secure { cycle: 7 }))
+    );
+}
+
+/// Hands out one byte per read, like a slow UART.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((&b, rest)) if !buf.is_empty() => {
+                buf[0] = b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn reader_reassembles_lines() {
+    let streamed: Vec<_> = Reader::new(Trickle(RECORDED.as_bytes()))
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(streamed, parse_log(RECORDED));
+
+    // no newline after the last line
+    let tail = "I (1) a: one\r\nnoise\r\nE (2) b: two";
+    let messages: Vec<String> = Reader::new(tail.as_bytes())
+        .map(|e| e.unwrap().line.message)
+        .collect();
+    assert_eq!(messages, ["one", "two"]);
+}
@@ -0,0 +1,300 @@
+//! ESP-IDF core dumps of the model's state, for core dump decoders.
+//!
+//! [`elf`] writes what ESP-IDF v5's panic handler writes with
+//! `CONFIG_ESP_COREDUMP_DATA_FORMAT_ELF`: an `ET_CORE` file with a
+//! `PT_NOTE` segment (the dump info, one `NT_PRSTATUS` per task and the
+//! exception registers) and a `PT_LOAD` per TCB, stack and captured DRAM
+//! region. [`partition`] wraps it the way it sits in the `coredump` flash
+//! partition, [`uart`] prints that the way `CONFIG_ESP_COREDUMP_ENABLE_TO_UART`
+//! does.
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::cpu::Cpu;
+use crate::fixture;
+use crate::freertos::{placement, Kernel, TASKS, TCB_SIZE};
+use crate::image::Image;
+
+/// `NT_PRSTATUS`, one per task, named `CORE`.
+pub const NT_PRSTATUS: u32 = 1;
+/// `ESP_CORE_DUMP_INFO`: dump version and the app ELF's SHA-256.
+pub const INFO_TYPE: u32 = 8266;
+/// `EXTRA_INFO`: the crashed task and the exception registers.
+pub const EXTRA_INFO_TYPE: u32 = 677;
+
+/// ESP32, ELF format, CRC32 checksum.
+pub const VERSION_ELF_CRC32: u32 = 0x0102;
+
+/// Xtensa special register numbers `EXTRA_INFO` tags values with.
+pub const SR_EPC1: u32 = 177;
+pub const SR_EXCCAUSE: u32 = 232;
+pub const SR_EXCVADDR: u32 = 238;
+
+/// What ESP-IDF prints around a core dump on the UART.
+pub const UART_START: &str = "================= CORE DUMP START =================";
+pub const UART_END: &str = "================= CORE DUMP END =================";
+
+/// `elf_prstatus` up to `pr_reg`, on 32-bit targets.
+const PRSTATUS_SIZE: usize = 72;
+/// `pr_pid` in `elf_prstatus`, which ESP-IDF sets to the TCB address.
+const PRSTATUS_PID: usize = 24;
+
+/// One task's registers as its `NT_PRSTATUS` holds them. ESP-IDF spills
+/// the register windows first, so `a0`-`a15` are the task's own frame
+/// with `windowbase` 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskRegisters {
+    pub pc: u32,
+    pub ps: u32,
+    pub a: [u32; 16],
+}
+
+/// The task that crashed and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crash {
+    /// Name in [`TASKS`].
+    pub task: &'static str,
+    pub registers: TaskRegisters,
+    pub exccause: u32,
+    pub excvaddr: u32,
+}
+
+/// The core dump of a `cpu` booted with the model's kernel. Tasks other
+/// than the crashed one are parked at `parked_pc` on their saved stack
+/// pointer; `dram` regions are captured as well.
+pub fn elf(
+    cpu: &Cpu,
+    crash: &Crash,
+    parked_pc: u32,
+    dram: &[(u64, u64)],
+    app_sha256: &str,
+) -> Vec<u8> {
+    let word = |addr: u64| {
+        cpu.peek(addr, 4)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut notes = Vec::new();
+    let mut info = VERSION_ELF_CRC32.to_le_bytes().to_vec();
+    info.extend_from_slice(app_sha256.as_bytes());
+    info.push(0);
+    note(&mut notes, "ESP_CORE_DUMP_INFO", INFO_TYPE, &info);
+
+    let mut loads = Vec::new();
+    let mut crashed_tcb = 0;
+    for (i, task) in TASKS.iter().enumerate() {
+        let (tcb, _, top) = placement(i);
+        let saved_sp = word(tcb);
+        let registers = match task.name == crash.task {
+            true => {
+                crashed_tcb = tcb as u32;
+                crash.registers
+            }
+            false => {
+                let mut a = [0; 16];
+                a[1] = saved_sp;
+                TaskRegisters {
+                    pc: parked_pc,
+                    ps: 0x0006_0020,
+                    a,
+                }
+            }
+        };
+        let mut status = vec![0u8; PRSTATUS_SIZE];
+        status[PRSTATUS_PID..PRSTATUS_PID + 4].copy_from_slice(&(tcb as u32).to_le_bytes());
+        // pc, ps, lbeg, lend, lcount, sar, windowstart, windowbase
+        for value in [registers.pc, registers.ps, 0, 0, 0, 0, 1, 0] {
+            status.extend_from_slice(&value.to_le_bytes());
+        }
+        status.extend_from_slice(&[0; 56 * 4]);
+        for n in 0..64 {
+            let value = registers.a.get(n).copied().unwrap_or(0);
+            status.extend_from_slice(&value.to_le_bytes());
+        }
+        note(&mut notes, "CORE", NT_PRSTATUS, &status);
+
+        loads.push((tcb, TCB_SIZE));
+        // from below the base save area of the innermost frame up
+        let sp = u64::from(registers.a[1].min(saved_sp)).saturating_sub(16);
+        loads.push((sp, top - sp));
+    }
+    loads.extend_from_slice(dram);
+
+    let mut extra = crashed_tcb.to_le_bytes().to_vec();
+    for (sr, value) in [
+        (SR_EXCCAUSE, crash.exccause),
+        (SR_EXCVADDR, crash.excvaddr),
+        (SR_EPC1, crash.registers.pc),
+    ] {
+        extra.extend_from_slice(&sr.to_le_bytes());
+        extra.extend_from_slice(&value.to_le_bytes());
+    }
+    note(&mut notes, "EXTRA_INFO", EXTRA_INFO_TYPE, &extra);
+
+    let segments: Vec<(u64, Vec<u8>)> = loads
+        .into_iter()
+        .map(|(addr, len)| {
+            let data = cpu
+                .peek(addr, len as usize)
+                .unwrap_or_else(|| vec![0; len as usize]);
+            (addr, data)
+        })
+        .collect();
+    write_core(&notes, &segments)
+}
+
+/// `elf` as the `coredump` partition holds it: the header ESP-IDF v5.3
+/// writes (`data_len`, `version`, `tasks_num`, `tcb_sz`, `mem_segs_num`,
+/// `chip_rev`), the ELF, and a CRC32 of both.
+pub fn partition(elf: &[u8]) -> Vec<u8> {
+    let segments = u32::from(u16::from_le_bytes([elf[44], elf[45]])) - 1;
+    let len = 24 + elf.len() + 4;
+    let mut out = Vec::with_capacity(len);
+    for value in [
+        len as u32,
+        VERSION_ELF_CRC32,
+        TASKS.len() as u32,
+        TCB_SIZE as u32,
+        segments,
+        3,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(elf);
+    let crc = crc32fast::hash(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// `partition` printed to the UART: base64 lines between the markers.
+pub fn uart(partition: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(partition);
+    let mut out = format!("{}\r\n", UART_START);
+    for chunk in encoded.as_bytes().chunks(72) {
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is ASCII"));
+        out.push_str("\r\n");
+    }
+    out.push_str(UART_END);
+    out.push_str("\r\n");
+    out
+}
+
+/// Where `main` faults in [`ghost_trigger`]: a load from NULL in the
+/// detector loop, `threat_detected` live in `a8`.
+pub const CRASH_PC: u64 = fixture::MAIN_LOOP + 0x40;
+pub const CRASH_SP: u64 = 0x3ffe_1ec0;
+/// What `detector.counter` holds in its frame at the crash.
+pub const CRASH_COUNTER: u32 = 7;
+/// `LoadProhibited`.
+pub const CRASH_EXCCAUSE: u32 = 28;
+
+/// The core dump the ghost-trigger fixture leaves after `main` took a
+/// `LoadProhibited` at [`CRASH_PC`], called from `app_main`, with a
+/// threat detected and the detector on its 7th count. The other tasks
+/// are parked at the start of `.flash.text`; DRAM `.data` and `.bss` are
+/// captured.
+pub fn ghost_trigger() -> Vec<u8> {
+    let elf = fixture::ghost_trigger();
+    let image = Image::parse(&elf).expect("fixture ELF parses");
+    let mut cpu = Cpu::new(&image);
+    if let Some(kernel) = Kernel::new(&image) {
+        kernel.boot(&mut cpu);
+    }
+
+    // main's base save area: app_main's a0 and a1, app_main the task entry
+    let app_main_sp = CRASH_SP as u32 + 0x40;
+    cpu.poke(CRASH_SP - 16, &0u32.to_le_bytes());
+    cpu.poke(CRASH_SP - 12, &app_main_sp.to_le_bytes());
+    // `detector` at [a1 + 32]: `timing.cycle_ms`, then `counter`
+    cpu.poke(CRASH_SP + 32, &fixture::CYCLE_MS_DEFAULT.to_le_bytes());
+    cpu.poke(CRASH_SP + 36, &CRASH_COUNTER.to_le_bytes());
+
+    let mut a = [0; 16];
+    a[0] = (2 << 30) | (fixture::APP_MAIN as u32 + 0x23);
+    a[1] = CRASH_SP as u32;
+    a[8] = 1;
+    let crash = Crash {
+        task: "main",
+        registers: TaskRegisters {
+            pc: CRASH_PC as u32,
+            ps: 0x0006_0030,
+            a,
+        },
+        exccause: CRASH_EXCCAUSE,
+        excvaddr: 0,
+    };
+    let dram: Vec<(u64, u64)> = [".dram0.data", ".dram0.bss"]
+        .into_iter()
+        .filter_map(|name| image.region(name))
+        .map(|r| (r.addr, r.data.len() as u64))
+        .collect();
+    let sha: String = Sha256::digest(&elf)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    self::elf(&cpu, &crash, fixture::TEXT as u32, &dram, &sha)
+}
+
+/// One ELF note: sizes, type, then name and descriptor padded to words.
+fn note(out: &mut Vec<u8>, name: &str, kind: u32, desc: &[u8]) {
+    for value in [name.len() as u32 + 1, desc.len() as u32, kind] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.resize(out.len().next_multiple_of(4), 0);
+    out.extend_from_slice(desc);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+/// A 32-bit little-endian Xtensa `ET_CORE`: the header, the program
+/// headers, the notes, then every segment's bytes.
+fn write_core(notes: &[u8], segments: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    const EHDR: usize = 52;
+    const PHDR: usize = 32;
+    let phnum = segments.len() + 1;
+    let mut out = Vec::new();
+    out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 1, 1, 1, 0]);
+    out.resize(16, 0);
+    let half = |out: &mut Vec<u8>, v: u16| out.extend_from_slice(&v.to_le_bytes());
+    let word = |out: &mut Vec<u8>, v: u32| out.extend_from_slice(&v.to_le_bytes());
+    half(&mut out, 4); // ET_CORE
+    half(&mut out, 94); // EM_XTENSA
+    word(&mut out, 1);
+    word(&mut out, 0); // e_entry
+    word(&mut out, EHDR as u32); // e_phoff
+    word(&mut out, 0); // e_shoff
+    word(&mut out, 0x300); // e_flags
+    half(&mut out, EHDR as u16);
+    half(&mut out, PHDR as u16);
+    half(&mut out, phnum as u16);
+    for _ in 0..3 {
+        half(&mut out, 0); // no sections
+    }
+
+    let mut offset = EHDR + PHDR * phnum;
+    let mut phdr = |out: &mut Vec<u8>, kind: u32, addr: u64, len: usize, flags: u32| {
+        for value in [
+            kind,
+            offset as u32,
+            addr as u32,
+            addr as u32,
+            len as u32,
+            len as u32,
+            flags,
+            4,
+        ] {
+            word(out, value);
+        }
+        offset += len;
+    };
+    phdr(&mut out, 4, 0, notes.len(), 4); // PT_NOTE, PF_R
+    for (addr, data) in segments {
+        phdr(&mut out, 1, *addr, data.len(), 6); // PT_LOAD, PF_R | PF_W
+    }
+    out.extend_from_slice(notes);
+    for (_, data) in segments {
+        out.extend_from_slice(data);
+    }
+    out
+}
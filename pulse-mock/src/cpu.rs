@@ -0,0 +1,343 @@
+//! Emulated target state: memory, registers and debug points.
+//!
+//! There is no instruction set emulation. A [`Firmware`](crate::Firmware)
+//! model drives the target by announcing which code address it is at with
+//! [`Cpu::exec`] and by reading and writing memory through the `Cpu`, which
+//! is where breakpoints and watchpoints are checked.
+
+use pulse_rsp::{StopReason, WatchKind};
+
+use crate::image::{Image, Region};
+
+/// Register numbers, as in the target description the server hands out.
+/// `pc` and the 64 physical `ar` registers are what `g` returns, the
+/// special registers after them are read one by one with `p`.
+pub const REG_PC: usize = 0;
+pub const REG_AR0: usize = 1;
+pub const AR_COUNT: usize = 64;
+pub const G_REGISTERS: usize = REG_AR0 + AR_COUNT;
+pub const REG_SAR: usize = 68;
+/// Which `ar` register is `a0`, in units of four.
+pub const REG_WINDOWBASE: usize = 69;
+/// One bit per group of four `ar` registers holding a live frame.
+pub const REG_WINDOWSTART: usize = 70;
+pub const REG_PS: usize = 73;
+pub const REG_EPC1: usize = 75;
+pub const REG_EXCCAUSE: usize = 79;
+pub const REG_EXCVADDR: usize = 80;
+pub const REG_CCOUNT: usize = 81;
+pub const REGISTER_COUNT: usize = 86;
+/// `a0`..`a15` of the current window, which OpenOCD also lists: views
+/// of the `ar` registers [`REG_WINDOWBASE`] selects, not registers of
+/// their own.
+pub const REG_A0: usize = REGISTER_COUNT;
+pub const A_COUNT: usize = 16;
+
+/// Hardware reset: interrupts masked, exception mode.
+const PS_RESET: u32 = 0x1f;
+/// Running application code: `WOE` and `UM` set, interrupt level 0.
+const PS_APP: u32 = 0x0004_0020;
+/// Groups of four `ar` registers `WINDOWBASE` counts in.
+const WINDOWS: u32 = (AR_COUNT / 4) as u32;
+
+/// CPU clock, what `CCOUNT` counts.
+pub const CPU_MHZ: u64 = 240;
+/// Cycles charged for each [`Cpu::exec`], there being no instructions.
+const CYCLES_PER_EXEC: u64 = 1000;
+
+/// A small stack in DRAM so frame-relative locals have somewhere to live.
+pub const STACK_BASE: u64 = 0x3ffe_0000;
+pub const STACK_SIZE: usize = 0x2000;
+
+/// Heap for the FreeRTOS task control blocks and the other tasks' stacks.
+pub const HEAP_BASE: u64 = 0x3ffb_8000;
+pub const HEAP_SIZE: usize = 0x4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub kind: WatchKind,
+    pub addr: u64,
+    pub len: u64,
+}
+
+impl Watchpoint {
+    fn matches(&self, write: bool, addr: u64, len: u64) -> bool {
+        let kind = matches!(
+            (self.kind, write),
+            (WatchKind::Access, _) | (WatchKind::Write, true) | (WatchKind::Read, false)
+        );
+        kind && addr < self.addr + self.len && self.addr < addr + len
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cpu {
+    regions: Vec<Region>,
+    pristine: Vec<Region>,
+    pub registers: [u32; REGISTER_COUNT],
+    pub breakpoints: Vec<u64>,
+    pub watchpoints: Vec<Watchpoint>,
+    hit: Option<StopReason>,
+    console: Vec<u8>,
+    delay_ms: u64,
+    now_ms: u64,
+    cycles: u64,
+}
+
+impl Cpu {
+    pub fn new(image: &Image) -> Self {
+        let mut regions = image.regions.clone();
+        for (name, addr, size) in [
+            ("stack", STACK_BASE, STACK_SIZE),
+            ("heap", HEAP_BASE, HEAP_SIZE),
+        ] {
+            regions.push(Region {
+                name: name.into(),
+                addr,
+                data: vec![0; size],
+                writable: true,
+                executable: false,
+            });
+        }
+        let mut cpu = Self {
+            pristine: regions.clone(),
+            regions,
+            registers: [0; REGISTER_COUNT],
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            hit: None,
+            console: Vec::new(),
+            delay_ms: 0,
+            now_ms: 0,
+            cycles: 0,
+        };
+        cpu.reset();
+        cpu
+    }
+
+    /// Back to the image contents, debug points are kept like on real hardware.
+    pub fn reset(&mut self) {
+        self.regions = self.pristine.clone();
+        self.registers = [0; REGISTER_COUNT];
+        self.registers[REG_WINDOWSTART] = 1;
+        self.registers[REG_PS] = PS_RESET;
+        self.set_a(1, (STACK_BASE + STACK_SIZE as u64 - 0x100) as u32);
+        self.hit = None;
+        self.console.clear();
+        self.delay_ms = 0;
+        self.now_ms = 0;
+        self.cycles = 0;
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn pc(&self) -> u64 {
+        u64::from(self.registers[REG_PC])
+    }
+
+    /// Milliseconds since reset, what `esp_log_timestamp()` would say.
+    pub fn now_ms(&self) -> u64 {
+        self.now_ms
+    }
+
+    /// The `CCOUNT` special register, wrapping like the real one.
+    pub fn ccount(&self) -> u32 {
+        self.cycles as u32
+    }
+
+    /// Debugger read, no watchpoints.
+    pub fn peek(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let region = self.regions.iter().find(|r| r.contains(addr, len as u64))?;
+        let at = (addr - region.addr) as usize;
+        Some(region.data[at..at + len].to_vec())
+    }
+
+    /// Debugger write, no watchpoints.
+    pub fn poke(&mut self, addr: u64, data: &[u8]) -> bool {
+        let Some(region) = self
+            .regions
+            .iter_mut()
+            .find(|r| r.contains(addr, data.len() as u64))
+        else {
+            return false;
+        };
+        let at = (addr - region.addr) as usize;
+        region.data[at..at + data.len()].copy_from_slice(data);
+        true
+    }
+
+    /// Firmware is now executing at `addr`.
+    pub fn exec(&mut self, addr: u64) {
+        self.registers[REG_PC] = addr as u32;
+        self.cycles += CYCLES_PER_EXEC;
+        if self.hit.is_none() && self.breakpoints.contains(&addr) {
+            self.hit = Some(StopReason::HwBreak);
+        }
+    }
+
+    /// Physical `ar` register holding `a{n}` of the current window.
+    fn windowed(&self, n: usize) -> usize {
+        REG_AR0 + (self.registers[REG_WINDOWBASE] as usize * 4 + n) % AR_COUNT
+    }
+
+    /// `a{n}` of the current window.
+    pub fn a(&self, n: usize) -> u32 {
+        self.registers[self.windowed(n)]
+    }
+
+    pub fn set_a(&mut self, n: usize, value: u32) {
+        let at = self.windowed(n);
+        self.registers[at] = value;
+    }
+
+    /// A register by number, including the `a` views. `None` past the end.
+    pub fn register(&self, n: usize) -> Option<u32> {
+        match n {
+            REG_CCOUNT => Some(self.ccount()),
+            _ if n < REGISTER_COUNT => Some(self.registers[n]),
+            _ if n < REG_A0 + A_COUNT => Some(self.a(n - REG_A0)),
+            _ => None,
+        }
+    }
+
+    pub fn set_register(&mut self, n: usize, value: u32) -> bool {
+        match n {
+            _ if n < REGISTER_COUNT => self.registers[n] = value,
+            _ if n < REG_A0 + A_COUNT => self.set_a(n - REG_A0, value),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Windowed `call8` from `from` into `to`, with a `frame` byte stack
+    /// frame: the window rotates by two, the return address goes to the
+    /// callee's `a0` with the window increment in its top two bits. The
+    /// caller's registers stay in the register file; only frames the new
+    /// window would overwrite are spilled, like a window overflow does.
+    pub fn call(&mut self, from: u64, to: u64, frame: u32) {
+        let callee_sp = self.a(1) - frame;
+        let base = (self.registers[REG_WINDOWBASE] + 2) % WINDOWS;
+        // the callee's a0..a15 span four groups
+        for group in 0..4 {
+            self.spill((base + group) % WINDOWS);
+        }
+
+        self.registers[REG_WINDOWBASE] = base;
+        self.registers[REG_WINDOWSTART] |= 1 << base;
+        // CALLINC: the window increment `entry` rotates by
+        self.registers[REG_PS] = PS_APP | (2 << 16);
+        // call8 returns 3 bytes past the call instruction
+        self.set_a(0, (2 << 30) | ((from as u32 + 3) & 0x3fff_ffff));
+        self.set_a(1, callee_sp);
+        self.exec(to);
+    }
+
+    /// Window overflow of the live frame starting at group `base`, if
+    /// there is one: its `a0`..`a3` go to the base save area below the
+    /// stack pointer of the frame it called, the next live one up.
+    fn spill(&mut self, base: u32) {
+        let start = self.registers[REG_WINDOWSTART];
+        if start & (1 << base) == 0 {
+            return;
+        }
+        let Some(callee) = (1..WINDOWS)
+            .map(|up| (base + up) % WINDOWS)
+            .find(|&w| start & (1 << w) != 0)
+        else {
+            return;
+        };
+        let ar = |w: u32, n: u32| REG_AR0 + ((w * 4 + n) as usize % AR_COUNT);
+        let save = u64::from(self.registers[ar(callee, 1)]) - 16;
+        for n in 0..4 {
+            let value = self.registers[ar(base, n)];
+            self.poke(save + 4 * u64::from(n), &value.to_le_bytes());
+        }
+        self.registers[REG_WINDOWSTART] &= !(1 << base);
+    }
+
+    /// `retw` to the frame [`Cpu::call`] left: the window rotates back to
+    /// the caller's, reloaded from its base save area first if it was
+    /// spilled (a window underflow).
+    pub fn ret(&mut self) {
+        let ra = u64::from(self.a(0));
+        let sp = u64::from(self.a(1));
+        let base = self.registers[REG_WINDOWBASE];
+        let caller = (base + WINDOWS - 2) % WINDOWS;
+        if self.registers[REG_WINDOWSTART] & (1 << caller) == 0 {
+            for n in 0..4 {
+                let value = self.peek(sp - 16 + 4 * n as u64, 4).unwrap_or_default();
+                let word = value.try_into().map_or(0, u32::from_le_bytes);
+                self.registers[REG_AR0 + (caller as usize * 4 + n) % AR_COUNT] = word;
+            }
+            self.registers[REG_WINDOWSTART] |= 1 << caller;
+        }
+        self.registers[REG_WINDOWSTART] &= !(1 << base);
+        self.registers[REG_WINDOWBASE] = caller;
+        self.exec((ra & 0x3fff_ffff) | 0x4000_0000);
+    }
+
+    /// Firmware read. Unmapped memory reads as zero.
+    pub fn read(&mut self, addr: u64, len: usize) -> Vec<u8> {
+        self.watch(false, addr, len as u64);
+        self.peek(addr, len).unwrap_or_else(|| vec![0; len])
+    }
+
+    /// Firmware write. Writes to unmapped memory are dropped.
+    pub fn write(&mut self, addr: u64, data: &[u8]) {
+        self.watch(true, addr, data.len() as u64);
+        self.poke(addr, data);
+    }
+
+    pub fn read_u32(&mut self, addr: u64) -> u32 {
+        let b = self.read(addr, 4);
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+    }
+
+    pub fn write_u32(&mut self, addr: u64, value: u32) {
+        self.write(addr, &value.to_le_bytes());
+    }
+
+    fn watch(&mut self, write: bool, addr: u64, len: u64) {
+        if self.hit.is_some() {
+            return;
+        }
+        if let Some(w) = self
+            .watchpoints
+            .iter()
+            .find(|w| w.matches(write, addr, len))
+        {
+            self.hit = Some(StopReason::Watch {
+                kind: w.kind,
+                addr: w.addr,
+            });
+        }
+    }
+
+    /// The debug event raised since the last call, if any.
+    pub fn take_hit(&mut self) -> Option<StopReason> {
+        self.hit.take()
+    }
+
+    /// Append a line to the serial console.
+    pub fn print(&mut self, line: &str) {
+        self.console.extend_from_slice(line.as_bytes());
+        self.console.push(b'\n');
+    }
+
+    pub fn take_console(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.console)
+    }
+
+    /// Firmware sleeps, the server decides how long that takes in wall time.
+    pub fn delay_ms(&mut self, ms: u64) {
+        self.delay_ms += ms;
+        self.now_ms += ms;
+        self.cycles += ms * CPU_MHZ * 1000;
+    }
+
+    pub fn take_delay_ms(&mut self) -> u64 {
+        std::mem::take(&mut self.delay_ms)
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The firmware image did not parse.
+    Elf(String),
+    Rsp(pulse_rsp::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Elf(what) => write!(f, "bad ELF: {}", what),
+            Error::Rsp(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Elf(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
@@ -0,0 +1,354 @@
+//! Behavioural models of the code running on the mock target.
+
+use std::cell::Cell;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+
+use pulse_core::audit::{AuditEntry, AUDIT_HEADER_SIZE, AUDIT_RECORD_SIZE, AUDIT_SYMBOL};
+use pulse_core::mailbox::{Signal, MAILBOX_SYMBOL, OFFSET_ACK, OFFSET_PAYLOAD};
+use pulse_core::mailbox::{OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_core::{
+    AuditLog, Auditor, Clock, Detector, Dwell, Event, Mailbox, MailboxSource, Reading,
+    ThreatSource, Timing,
+};
+
+use crate::cpu::Cpu;
+use crate::freertos::Kernel;
+use crate::image::Image;
+
+/// What runs on the target between debugger stops.
+pub trait Firmware: Send {
+    /// Called on connect and on `monitor reset`, after memory was reloaded.
+    fn reset(&mut self, cpu: &mut Cpu);
+
+    /// Run a short stretch of code. The server checks for debug events and
+    /// paces [`Cpu::delay_ms`] sleeps between calls.
+    fn step(&mut self, cpu: &mut Cpu);
+}
+
+/// Symbols of the real firmware the model touches, looked up in the image.
+const MAIN_SYMBOLS: [&str; 2] = ["ghost_trigger::main", "app_main"];
+const CYCLE_SYMBOL: &str = "pulse_core::detector::Detector::cycle";
+const CYCLE_MS_SYMBOL: &str = "ghost_trigger::injection::CYCLE_MS";
+const APP_MAIN_SYMBOL: &str = "app_main";
+
+/// Where the calls sit inside their callers, and the callee stack frames,
+/// so backtraces have plausible frames to unwind.
+const APP_MAIN_CALL: u64 = 0x20;
+const MAIN_CALL: u64 = 0x40;
+const MAIN_FRAME: u32 = 0x40;
+const CYCLE_FRAME: u32 = 0x60;
+
+/// ESP-IDF log tags, `EspLogger` uses the `log` target.
+const TAG_MAIN: &str = "ghost_trigger";
+const TAG_SINK: &str = "pulse_core::sink";
+const TAG_MAILBOX: &str = "pulse_core::mailbox";
+const TAG_AUDIT: &str = "pulse_core::audit";
+
+thread_local! {
+    // `Auditor` takes a plain fn for its clock
+    static NOW_US: Cell<u64> = const { Cell::new(0) };
+}
+
+fn now_us() -> u64 {
+    NOW_US.with(Cell::get)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Boot,
+    /// Loop head: re-read `CYCLE_MS`.
+    Head,
+    /// Call into `Detector::cycle`.
+    Call,
+    /// Poll the mailbox, step the state machine, log.
+    Detect,
+    Sleep,
+}
+
+/// Sensor with nothing attached, threats only come in through the mailbox.
+struct Quiet;
+
+impl ThreatSource for Quiet {
+    fn poll(&mut self) -> Reading {
+        Reading::Clear
+    }
+}
+
+/// Records the detector's delay instead of sleeping, the model sleeps in
+/// its own phase so the server can pace it.
+struct Deferred(u32);
+
+impl Clock for Deferred {
+    fn now_us(&self) -> u64 {
+        now_us()
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0 = ms;
+    }
+}
+
+/// ghost-trigger's main loop, driven by the real `pulse-core` detector.
+///
+/// The mailbox, audit log and `CYCLE_MS` live in target memory at their
+/// ELF addresses and are synced with host-side copies around every
+/// detection cycle, so debugger writes and watchpoints see the same
+/// accesses the firmware would make.
+pub struct GhostTrigger {
+    main: u64,
+    app_main: Option<u64>,
+    cycle_fn: Option<u64>,
+    mailbox_addr: Option<u64>,
+    audit_addr: Option<u64>,
+    cycle_ms_addr: Option<u64>,
+    kernel: Option<Kernel>,
+    // borrows `mailbox` and `audit`, see `GhostTrigger::source`; declared
+    // first so it is dropped before them
+    source: MailboxSource<'static, Quiet>,
+    mailbox: Arc<Mailbox>,
+    audit: Arc<AuditLog>,
+    detector: Detector,
+    phase: Phase,
+    delay_ms: u32,
+}
+
+impl GhostTrigger {
+    pub fn new(image: &Image) -> Self {
+        let addr = |name: &str| image.symbol(name).map(|s| s.addr);
+        let mailbox = Arc::new(Mailbox::new());
+        let audit = Arc::new(AuditLog::new());
+        let main = MAIN_SYMBOLS
+            .iter()
+            .find_map(|name| addr(name))
+            .or_else(|| image.regions.iter().find(|r| r.executable).map(|r| r.addr))
+            .unwrap_or(0);
+
+        Self {
+            main,
+            app_main: addr(APP_MAIN_SYMBOL).filter(|&at| at != main),
+            cycle_fn: addr(CYCLE_SYMBOL),
+            mailbox_addr: addr(MAILBOX_SYMBOL),
+            audit_addr: addr(AUDIT_SYMBOL),
+            cycle_ms_addr: addr(CYCLE_MS_SYMBOL),
+            kernel: Kernel::new(image),
+            source: Self::source(&mailbox, &audit),
+            mailbox,
+            audit,
+            detector: Detector::new(Timing::default(), Dwell::default()),
+            phase: Phase::Boot,
+            delay_ms: 0,
+        }
+    }
+
+    pub fn detector(&self) -> &Detector {
+        &self.detector
+    }
+
+    /// The firmware's source over the model's own mailbox and audit log.
+    /// `MailboxSource` wants them borrowed for as long as it lives, which
+    /// the model stretches to `'static`: the `Arc`s keep both at the same
+    /// address for the model's lifetime, are never replaced, and outlive
+    /// the source, which is dropped first.
+    fn source(mailbox: &Arc<Mailbox>, audit: &Arc<AuditLog>) -> MailboxSource<'static, Quiet> {
+        // SAFETY: see above, the pointees outlive every use of the source
+        let (mailbox, audit) = unsafe { (&*Arc::as_ptr(mailbox), &*Arc::as_ptr(audit)) };
+        MailboxSource::new(mailbox, Quiet).with_audit(Auditor::new(audit, now_us))
+    }
+
+    fn log(cpu: &mut Cpu, level: log::Level, tag: &str, message: &str) {
+        let (letter, color) = match level {
+            log::Level::Error => ('E', "\x1b[0;31m"),
+            log::Level::Warn => ('W', "\x1b[0;33m"),
+            log::Level::Info => ('I', "\x1b[0;32m"),
+            log::Level::Debug => ('D', ""),
+            log::Level::Trace => ('V', ""),
+        };
+        let reset = if color.is_empty() { "" } else { "\x1b[0m" };
+        let line = format!(
+            "{}{} ({}) {}: {}{}",
+            color,
+            letter,
+            cpu.now_ms(),
+            tag,
+            message,
+            reset
+        );
+        cpu.print(&line);
+    }
+
+    fn head(&mut self, cpu: &mut Cpu) {
+        cpu.exec(self.main);
+        if let Some(at) = self.cycle_ms_addr {
+            let cycle_ms = cpu.read_u32(at);
+            self.detector.set_timing(Timing { cycle_ms });
+        }
+    }
+
+    fn detect(&mut self, cpu: &mut Cpu) {
+        NOW_US.with(|now| now.set(cpu.now_ms() * 1000));
+
+        if let Some(at) = self.mailbox_addr {
+            let field = |offset: u32| at + u64::from(offset);
+            let mailbox = &self.mailbox;
+            let sequence = cpu.read_u32(field(OFFSET_SEQUENCE));
+            let ack = cpu.read_u32(field(OFFSET_ACK));
+            mailbox.sequence.store(sequence, Relaxed);
+            mailbox.ack.store(ack, Relaxed);
+            // Mailbox::take only looks at the request once it is pending
+            if sequence != ack {
+                mailbox
+                    .signal
+                    .store(cpu.read_u32(field(OFFSET_SIGNAL)), Relaxed);
+                mailbox
+                    .payload
+                    .store(cpu.read_u32(field(OFFSET_PAYLOAD)), Relaxed);
+            }
+        }
+        let acked = self.mailbox.ack.load(Relaxed);
+        let total = self.audit.total();
+
+        let mut events: Vec<Event> = Vec::new();
+        let mut clock = Deferred(0);
+        self.detector
+            .cycle(&mut self.source, &mut clock, &mut events);
+        self.delay_ms = clock.0;
+
+        if let Some(at) = self.mailbox_addr {
+            let ack = self.mailbox.ack.load(Relaxed);
+            if ack != acked {
+                cpu.write_u32(at + u64::from(OFFSET_ACK), ack);
+            }
+        }
+        if self.audit.total() != total {
+            self.consumed(cpu, total);
+        }
+        for event in events {
+            Self::log(cpu, event.level(), TAG_SINK, &event.to_string());
+        }
+    }
+
+    /// Mirror new audit records to target memory and log what the firmware
+    /// would have logged while consuming them.
+    fn consumed(&mut self, cpu: &mut Cpu, since: u32) {
+        let entries = self.audit.entries();
+        let total = self.audit.total();
+        let new = (total - since) as usize;
+
+        for (i, entry) in entries.iter().enumerate().skip(entries.len() - new) {
+            let index = total as usize - entries.len() + i;
+            if let Some(at) = self.audit_addr {
+                write_record(cpu, at, index, entry);
+            }
+            match Signal::from_id(entry.signal) {
+                Signal::DumpAudit => Self::dump(cpu, &entries[..i], index),
+                Signal::Unknown(id) => Self::log(
+                    cpu,
+                    log::Level::Warn,
+                    TAG_MAILBOX,
+                    &format!(
+                        "Ignoring unknown injection signal {} [Seq: {}]",
+                        id, entry.sequence
+                    ),
+                ),
+                Signal::None | Signal::Threat => {}
+            }
+        }
+        if let Some(at) = self.audit_addr {
+            // head is bumped last, like AuditLog::record does
+            cpu.write_u32(at + 12, total);
+        }
+    }
+
+    /// `AuditLog::dump` as it ran, before the dump request itself was recorded.
+    fn dump(cpu: &mut Cpu, retained: &[AuditEntry], total: usize) {
+        Self::log(
+            cpu,
+            log::Level::Info,
+            TAG_AUDIT,
+            &format!(
+                "Injection audit: {} retained, {} total",
+                retained.len(),
+                total
+            ),
+        );
+        for entry in retained {
+            Self::log(cpu, log::Level::Info, TAG_AUDIT, &entry.to_string());
+        }
+    }
+}
+
+fn write_record(cpu: &mut Cpu, base: u64, index: usize, entry: &AuditEntry) {
+    let capacity = pulse_core::audit::AUDIT_CAPACITY;
+    let at = base + (AUDIT_HEADER_SIZE + (index % capacity) * AUDIT_RECORD_SIZE) as u64;
+    let words = [
+        entry.sequence,
+        entry.cycle,
+        entry.signal,
+        entry.old,
+        entry.new,
+        entry.timestamp_us as u32,
+        (entry.timestamp_us >> 32) as u32,
+    ];
+    for (i, word) in words.into_iter().enumerate() {
+        cpu.write_u32(at + 4 * i as u64, word);
+    }
+}
+
+impl Firmware for GhostTrigger {
+    fn reset(&mut self, cpu: &mut Cpu) {
+        // a fresh boot: statics back to their initial values, new detector,
+        // and the tasks ESP-IDF would have started by the time main runs
+        if let Some(kernel) = &self.kernel {
+            kernel.boot(cpu);
+        }
+        let mailbox = &self.mailbox;
+        for field in [
+            &mailbox.sequence,
+            &mailbox.signal,
+            &mailbox.payload,
+            &mailbox.ack,
+        ] {
+            field.store(0, Relaxed);
+        }
+        self.audit.clear();
+        self.source = Self::source(&self.mailbox, &self.audit);
+        self.detector = Detector::new(Timing::default(), Dwell::default());
+        self.phase = Phase::Boot;
+        self.delay_ms = 0;
+    }
+
+    fn step(&mut self, cpu: &mut Cpu) {
+        self.phase = match self.phase {
+            Phase::Boot => {
+                match self.app_main {
+                    Some(app_main) => cpu.call(app_main + APP_MAIN_CALL, self.main, MAIN_FRAME),
+                    None => cpu.exec(self.main),
+                }
+                Self::log(cpu, log::Level::Info, TAG_MAIN, "System altered!");
+                Phase::Head
+            }
+            Phase::Head => {
+                self.head(cpu);
+                Phase::Call
+            }
+            Phase::Call => {
+                if let Some(at) = self.cycle_fn {
+                    cpu.call(self.main + MAIN_CALL, at, CYCLE_FRAME);
+                }
+                Phase::Detect
+            }
+            Phase::Detect => {
+                self.detect(cpu);
+                Phase::Sleep
+            }
+            Phase::Sleep => {
+                if self.cycle_fn.is_some() {
+                    cpu.ret();
+                }
+                cpu.delay_ms(u64::from(self.delay_ms));
+                Phase::Head
+            }
+        };
+    }
+}
@@ -0,0 +1,622 @@
+//! A synthetic ghost-trigger ELF for tests and demos.
+//!
+//! Real firmware needs the xtensa toolchain to build. This image has the
+//! same section names, ESP32 addresses and exported symbols as a
+//! ghost-trigger build with the default `injection` feature, with filler
+//! where the code would be. The DWARF describes the statics and a few
+//! locals of `main` the way rustc lays them out.
+
+use gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, Expression, FileId, LineProgram, LineString,
+    Location, LocationList, Sections, UnitEntryId,
+};
+use gimli::{LittleEndian, Register};
+use object::build::elf::{Builder, SectionData};
+use object::elf;
+use object::Endianness;
+
+use pulse_core::audit::{
+    AUDIT_CAPACITY, AUDIT_HEADER_SIZE, AUDIT_MAGIC, AUDIT_RECORD_SIZE, AUDIT_SIZE, AUDIT_VERSION,
+};
+use pulse_core::injection::{Kind, RawEntry, TABLE_SECTION};
+use pulse_core::mailbox::{MAILBOX_MAGIC, MAILBOX_SIZE, MAILBOX_VERSION};
+
+pub const TEXT: u64 = 0x400d_0020;
+pub const APP_MAIN: u64 = 0x400d_0100;
+pub const MAIN: u64 = 0x400d_0180;
+pub const DETECTOR_CYCLE: u64 = 0x400d_0400;
+const TEXT_SIZE: usize = 0x800;
+
+pub const RODATA: u64 = 0x3f40_0020;
+pub const CYCLE_MS_NAME: u64 = RODATA;
+pub const INJECTION_TABLE: u64 = 0x3f40_0100;
+
+pub const DATA: u64 = 0x3ffb_0000;
+pub const PULSE_MAILBOX: u64 = DATA;
+pub const CYCLE_MS: u64 = DATA + 0x20;
+pub const PULSE_AUDIT: u64 = DATA + 0x40;
+
+pub const BSS: u64 = 0x3ffb_1000;
+const BSS_SIZE: u64 = 0x400;
+
+/// FreeRTOS kernel state in `.bss`, which the mock fills in at boot.
+pub const KERNEL: u64 = BSS + 0x100;
+/// `configMAX_PRIORITIES` of ESP-IDF's default config.
+pub const MAX_PRIORITIES: u64 = 25;
+/// ESP-IDF v5 kernel symbols: name, offset from [`KERNEL`] and size.
+/// `List_t` is 20 bytes, the per-core arrays have two entries.
+pub const KERNEL_SYMBOLS: [(&str, u64, u64); 11] = [
+    ("pxCurrentTCBs", 0x000, 8),
+    ("pxReadyTasksLists", 0x008, MAX_PRIORITIES * 20),
+    ("xDelayedTaskList1", 0x1fc, 20),
+    ("xDelayedTaskList2", 0x210, 20),
+    ("pxDelayedTaskList", 0x224, 4),
+    ("pxOverflowDelayedTaskList", 0x228, 4),
+    ("xPendingReadyList", 0x22c, 40),
+    ("xTasksWaitingTermination", 0x254, 20),
+    ("xSuspendedTaskList", 0x268, 20),
+    ("uxCurrentNumberOfTasks", 0x27c, 4),
+    ("uxTopUsedPriority", 0x280, 4),
+];
+
+pub const CYCLE_MS_DEFAULT: u32 = 1000;
+
+const MAIN_SYMBOL: &str = "_ZN13ghost_trigger4main17h0123456789abcdefE";
+const CYCLE_SYMBOL: &str = "_ZN10pulse_core8detector8Detector5cycle17h0123456789abcdefE";
+const CYCLE_MS_SYMBOL: &str = "_ZN13ghost_trigger9injection8CYCLE_MS17h0123456789abcdefE";
+const TAKE_SYMBOL: &str = "_ZN10pulse_core7mailbox7Mailbox4take17h0123456789abcdefE";
+
+fn words(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// The ELF file, as `cargo build` would leave it in `target/`.
+pub fn ghost_trigger() -> Vec<u8> {
+    let mut b = Builder::new(Endianness::Little, false);
+    b.header.e_type = elf::ET_EXEC;
+    b.header.e_machine = elf::EM_XTENSA;
+    b.header.e_entry = APP_MAIN;
+
+    // `entry` (0x36 ..) then `retw.n` (0x1df0) for each function, zeros between
+    let mut text = vec![0u8; TEXT_SIZE];
+    for f in [APP_MAIN, MAIN, DETECTOR_CYCLE] {
+        let at = (f - TEXT) as usize;
+        text[at..at + 5].copy_from_slice(&[0x36, 0x41, 0x00, 0xf0, 0x1d]);
+    }
+
+    let mut rodata = b"CYCLE_MS".to_vec();
+    rodata.resize((INJECTION_TABLE - RODATA) as usize, 0);
+
+    let mut table = Vec::with_capacity(RawEntry::stride(4));
+    table.extend_from_slice(&10i64.to_le_bytes());
+    table.extend_from_slice(&60_000i64.to_le_bytes());
+    table.extend(words(&[
+        CYCLE_MS_NAME as u32,
+        8,
+        CYCLE_MS as u32,
+        Kind::U32 as u32,
+        4,
+    ]));
+    table.resize(RawEntry::stride(4), 0);
+
+    let mut data = vec![0u8; (PULSE_AUDIT - DATA) as usize + AUDIT_SIZE];
+    data[..8].copy_from_slice(&words(&[MAILBOX_MAGIC, MAILBOX_VERSION]));
+    let cycle_ms = (CYCLE_MS - DATA) as usize;
+    data[cycle_ms..cycle_ms + 4].copy_from_slice(&CYCLE_MS_DEFAULT.to_le_bytes());
+    let audit = (PULSE_AUDIT - DATA) as usize;
+    data[audit..audit + 12].copy_from_slice(&words(&[
+        AUDIT_MAGIC,
+        AUDIT_VERSION,
+        AUDIT_CAPACITY as u32,
+    ]));
+
+    let alloc = u64::from(elf::SHF_ALLOC);
+    let sections = [
+        (
+            ".flash.text",
+            TEXT,
+            alloc | u64::from(elf::SHF_EXECINSTR),
+            Some(text),
+        ),
+        (".flash.rodata", RODATA, alloc, Some(rodata)),
+        (TABLE_SECTION, INJECTION_TABLE, alloc, Some(table)),
+        (
+            ".dram0.data",
+            DATA,
+            alloc | u64::from(elf::SHF_WRITE),
+            Some(data),
+        ),
+        (".dram0.bss", BSS, alloc | u64::from(elf::SHF_WRITE), None),
+    ];
+    let mut ids = Vec::new();
+    for (i, (name, addr, flags, bytes)) in sections.into_iter().enumerate() {
+        let section = b.sections.add();
+        section.name = name.into();
+        section.sh_flags = flags;
+        section.sh_addr = addr;
+        section.sh_addralign = 4;
+        // file offsets only have to be increasing and past the headers
+        section.sh_offset = 0x1000 * (i as u64 + 1);
+        match bytes {
+            Some(bytes) => {
+                section.sh_type = elf::SHT_PROGBITS;
+                section.sh_size = bytes.len() as u64;
+                section.data = SectionData::Data(bytes.into());
+            }
+            None => {
+                section.sh_type = elf::SHT_NOBITS;
+                section.sh_size = BSS_SIZE;
+                section.data = SectionData::UninitializedData(BSS_SIZE);
+            }
+        }
+        ids.push(section.id());
+    }
+
+    let symbols = [
+        ("app_main", 0, APP_MAIN, 0x80, elf::STT_FUNC),
+        (MAIN_SYMBOL, 0, MAIN, 0x280, elf::STT_FUNC),
+        (CYCLE_SYMBOL, 0, DETECTOR_CYCLE, 0x100, elf::STT_FUNC),
+        (
+            "PULSE_MAILBOX",
+            3,
+            PULSE_MAILBOX,
+            u64::from(MAILBOX_SIZE),
+            elf::STT_OBJECT,
+        ),
+        (CYCLE_MS_SYMBOL, 3, CYCLE_MS, 4, elf::STT_OBJECT),
+        (
+            "PULSE_AUDIT",
+            3,
+            PULSE_AUDIT,
+            AUDIT_SIZE as u64,
+            elf::STT_OBJECT,
+        ),
+    ];
+    let kernel = KERNEL_SYMBOLS
+        .iter()
+        .map(|&(name, offset, size)| (name, 4, KERNEL + offset, size, elf::STT_OBJECT));
+    for (name, section, addr, size, kind) in symbols.into_iter().chain(kernel) {
+        let symbol = b.symbols.add();
+        symbol.name = name.into();
+        symbol.section = Some(ids[section]);
+        symbol.st_info = (elf::STB_GLOBAL << 4) | kind;
+        symbol.st_value = addr;
+        symbol.st_size = size;
+    }
+
+    let debug = debug_sections();
+    let mut offset = 0x1000 * (ids.len() as u64 + 1);
+    for (name, bytes) in debug {
+        let section = b.sections.add();
+        section.name = name.into();
+        section.sh_type = elf::SHT_PROGBITS;
+        section.sh_offset = offset;
+        section.sh_size = bytes.len() as u64;
+        offset += (bytes.len() as u64).next_multiple_of(0x1000);
+        section.data = SectionData::Data(bytes.into());
+    }
+
+    for (name, data, sh_type) in [
+        (".symtab", SectionData::Symbol, elf::SHT_SYMTAB),
+        (".strtab", SectionData::String, elf::SHT_STRTAB),
+        (".shstrtab", SectionData::SectionString, elf::SHT_STRTAB),
+    ] {
+        let section = b.sections.add();
+        section.name = name.into();
+        section.sh_type = sh_type;
+        section.data = data;
+    }
+
+    let mut out = Vec::new();
+    b.write(&mut out).expect("fixture ELF is well formed");
+    out
+}
+
+/// `threat_detected` is spilled to the frame until `MAIN_SPILLED`, then
+/// lives in `a8` until `MAIN_DEAD`, after which it has no location.
+pub const MAIN_SPILLED: u64 = MAIN + 0x80;
+pub const MAIN_DEAD: u64 = MAIN + 0x200;
+/// The lexical block holding `detector`.
+pub const MAIN_LOOP: u64 = MAIN + 0x100;
+/// `Mailbox::take`, inlined into `main` at `src/main.rs:49`.
+pub const MAIN_TAKE: u64 = MAIN + 0x60;
+const MAIN_TAKE_SIZE: u64 = 0x20;
+
+/// Where the fixture's sources were built, the line program's directory.
+pub const COMP_DIR: &str = "/build/ghost-trigger";
+/// Where `pulse-core` sits next to it.
+pub const PULSE_CORE_DIR: &str = "/build/pulse-core";
+
+/// `src/main.rs` and `pulse-core`'s sources, for `DW_AT_call_file`.
+struct Files {
+    main: FileId,
+}
+
+/// A line table row: `(offset, file, line)`, file 0 being `src/main.rs`.
+type Row = (u64, usize, u64);
+
+/// Rows for `main` and `Detector::cycle`. `app_main` is C and has none.
+fn line_program(encoding: gimli::Encoding) -> (LineProgram, Files) {
+    let string = |s: &str| LineString::String(s.as_bytes().to_vec());
+    let mut program = LineProgram::new(
+        encoding,
+        gimli::LineEncoding::default(),
+        string(COMP_DIR),
+        None,
+        string("src/main.rs"),
+        None,
+    );
+    let comp_dir = program.default_directory();
+    let core_dir = program.add_directory(string(PULSE_CORE_DIR));
+    let files = [
+        program.add_file(string("src/main.rs"), comp_dir, None),
+        program.add_file(string("src/detector.rs"), core_dir, None),
+        program.add_file(string("src/mailbox.rs"), core_dir, None),
+    ];
+    let sequences: [(u64, u64, &[Row]); 2] = [
+        (
+            MAIN,
+            0x280,
+            &[
+                (0x00, 0, 40),
+                (0x40, 0, 47),
+                (MAIN_TAKE - MAIN, 2, 88),
+                (MAIN_TAKE - MAIN + MAIN_TAKE_SIZE, 0, 52),
+                (MAIN_LOOP - MAIN, 0, 55),
+                (MAIN_DEAD - MAIN, 0, 70),
+            ],
+        ),
+        (DETECTOR_CYCLE, 0x100, &[(0x00, 1, 120), (0x20, 1, 131)]),
+    ];
+    for (start, size, rows) in sequences {
+        program.begin_sequence(Some(Address::Constant(start)));
+        for &(offset, file, line) in rows {
+            let row = program.row();
+            row.address_offset = offset;
+            row.file = files[file];
+            row.line = line;
+            program.generate_row();
+        }
+        program.end_sequence(size);
+    }
+    (program, Files { main: files[0] })
+}
+
+fn debug_sections() -> Vec<(&'static str, Vec<u8>)> {
+    let encoding = gimli::Encoding {
+        format: gimli::Format::Dwarf32,
+        version: 4,
+        address_size: 4,
+    };
+    let mut dwarf = DwarfUnit::new(encoding);
+    let (program, files) = line_program(encoding);
+    dwarf.unit.line_program = program;
+    let unit = &mut dwarf.unit;
+    let root = unit.root();
+    for (at, value) in [
+        (gimli::DW_AT_name, "src/main.rs"),
+        (gimli::DW_AT_comp_dir, COMP_DIR),
+    ] {
+        unit.get_mut(root)
+            .set(at, AttributeValue::String(value.into()));
+    }
+
+    let mut add = |parent: UnitEntryId, tag, attrs: Vec<(gimli::DwAt, AttributeValue)>| {
+        let id = unit.add(parent, tag);
+        for (at, value) in attrs {
+            unit.get_mut(id).set(at, value);
+        }
+        id
+    };
+    let name = |s: &str| (gimli::DW_AT_name, AttributeValue::String(s.into()));
+    let size = |n: u64| (gimli::DW_AT_byte_size, AttributeValue::Udata(n));
+    let ty = |id: UnitEntryId| (gimli::DW_AT_type, AttributeValue::UnitRef(id));
+    let at = |offset: u64| {
+        (
+            gimli::DW_AT_data_member_location,
+            AttributeValue::Udata(offset),
+        )
+    };
+    let base = |encoding| (gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+    let exprloc = |f: &dyn Fn(&mut Expression)| {
+        let mut e = Expression::new();
+        f(&mut e);
+        (gimli::DW_AT_location, AttributeValue::Exprloc(e))
+    };
+
+    let u32_ty = add(
+        root,
+        gimli::DW_TAG_base_type,
+        vec![name("u32"), size(4), base(gimli::DW_ATE_unsigned)],
+    );
+    let bool_ty = add(
+        root,
+        gimli::DW_TAG_base_type,
+        vec![name("bool"), size(1), base(gimli::DW_ATE_boolean)],
+    );
+
+    // core::sync::atomic::AtomicU32 { v: core::cell::UnsafeCell<u32> { value } }
+    let core = add(root, gimli::DW_TAG_namespace, vec![name("core")]);
+    let cell = add(core, gimli::DW_TAG_namespace, vec![name("cell")]);
+    let unsafe_cell = add(
+        cell,
+        gimli::DW_TAG_structure_type,
+        vec![name("UnsafeCell<u32>"), size(4)],
+    );
+    add(
+        unsafe_cell,
+        gimli::DW_TAG_member,
+        vec![name("value"), ty(u32_ty), at(0)],
+    );
+    let sync = add(core, gimli::DW_TAG_namespace, vec![name("sync")]);
+    let atomic = add(sync, gimli::DW_TAG_namespace, vec![name("atomic")]);
+    let atomic_u32 = add(
+        atomic,
+        gimli::DW_TAG_structure_type,
+        vec![name("AtomicU32"), size(4)],
+    );
+    add(
+        atomic_u32,
+        gimli::DW_TAG_member,
+        vec![name("v"), ty(unsafe_cell), at(0)],
+    );
+
+    let pulse_core = add(root, gimli::DW_TAG_namespace, vec![name("pulse_core")]);
+    let mailbox_ns = add(pulse_core, gimli::DW_TAG_namespace, vec![name("mailbox")]);
+    let mailbox = add(
+        mailbox_ns,
+        gimli::DW_TAG_structure_type,
+        vec![name("Mailbox"), size(u64::from(MAILBOX_SIZE))],
+    );
+    for (i, field) in ["magic", "version", "sequence", "signal", "payload", "ack"]
+        .into_iter()
+        .enumerate()
+    {
+        add(
+            mailbox,
+            gimli::DW_TAG_member,
+            vec![name(field), ty(atomic_u32), at(4 * i as u64)],
+        );
+    }
+
+    let audit_ns = add(pulse_core, gimli::DW_TAG_namespace, vec![name("audit")]);
+    let record = add(
+        audit_ns,
+        gimli::DW_TAG_structure_type,
+        vec![name("AuditRecord"), size(AUDIT_RECORD_SIZE as u64)],
+    );
+    for (i, field) in [
+        "sequence",
+        "cycle",
+        "signal",
+        "old",
+        "new",
+        "timestamp_lo",
+        "timestamp_hi",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        add(
+            record,
+            gimli::DW_TAG_member,
+            vec![name(field), ty(atomic_u32), at(4 * i as u64)],
+        );
+    }
+    let records = add(root, gimli::DW_TAG_array_type, vec![ty(record)]);
+    add(
+        records,
+        gimli::DW_TAG_subrange_type,
+        vec![(
+            gimli::DW_AT_count,
+            AttributeValue::Udata(AUDIT_CAPACITY as u64),
+        )],
+    );
+    let audit_log = add(
+        audit_ns,
+        gimli::DW_TAG_structure_type,
+        vec![name("AuditLog"), size(AUDIT_SIZE as u64)],
+    );
+    for (i, field) in ["magic", "version", "capacity", "head"]
+        .into_iter()
+        .enumerate()
+    {
+        add(
+            audit_log,
+            gimli::DW_TAG_member,
+            vec![name(field), ty(atomic_u32), at(4 * i as u64)],
+        );
+    }
+    add(
+        audit_log,
+        gimli::DW_TAG_member,
+        vec![name("records"), ty(records), at(AUDIT_HEADER_SIZE as u64)],
+    );
+
+    let detector_ns = add(pulse_core, gimli::DW_TAG_namespace, vec![name("detector")]);
+    let timing = add(
+        detector_ns,
+        gimli::DW_TAG_structure_type,
+        vec![name("Timing"), size(4)],
+    );
+    add(
+        timing,
+        gimli::DW_TAG_member,
+        vec![name("cycle_ms"), ty(u32_ty), at(0)],
+    );
+    let detector = add(
+        detector_ns,
+        gimli::DW_TAG_structure_type,
+        vec![name("Detector"), size(12)],
+    );
+    add(
+        detector,
+        gimli::DW_TAG_member,
+        vec![name("timing"), ty(timing), at(0)],
+    );
+    add(
+        detector,
+        gimli::DW_TAG_member,
+        vec![name("counter"), ty(u32_ty), at(4)],
+    );
+
+    let ghost_trigger = add(root, gimli::DW_TAG_namespace, vec![name("ghost_trigger")]);
+    let injection = add(
+        ghost_trigger,
+        gimli::DW_TAG_namespace,
+        vec![name("injection")],
+    );
+    for (var, var_ty, addr) in [
+        ("PULSE_MAILBOX", mailbox, PULSE_MAILBOX),
+        ("CYCLE_MS", atomic_u32, CYCLE_MS),
+        ("PULSE_AUDIT", audit_log, PULSE_AUDIT),
+    ] {
+        add(
+            injection,
+            gimli::DW_TAG_variable,
+            vec![
+                name(var),
+                ty(var_ty),
+                exprloc(&|e| e.op_addr(Address::Constant(addr))),
+            ],
+        );
+    }
+
+    let mut frame_base = Expression::new();
+    frame_base.op_reg(Register(1));
+    let main = add(
+        ghost_trigger,
+        gimli::DW_TAG_subprogram,
+        vec![
+            name("main"),
+            (
+                gimli::DW_AT_low_pc,
+                AttributeValue::Address(Address::Constant(MAIN)),
+            ),
+            (gimli::DW_AT_high_pc, AttributeValue::Udata(0x280)),
+            (gimli::DW_AT_frame_base, AttributeValue::Exprloc(frame_base)),
+        ],
+    );
+
+    let mut spilled = Expression::new();
+    spilled.op_fbreg(12);
+    let mut in_register = Expression::new();
+    in_register.op_reg(Register(8));
+    let threat_detected = unit.locations.add(LocationList(vec![
+        Location::StartEnd {
+            begin: Address::Constant(MAIN + 0x10),
+            end: Address::Constant(MAIN_SPILLED),
+            data: spilled,
+        },
+        Location::StartEnd {
+            begin: Address::Constant(MAIN_SPILLED),
+            end: Address::Constant(MAIN_DEAD),
+            data: in_register,
+        },
+    ]));
+    let var = unit.add(main, gimli::DW_TAG_variable);
+    for (at, value) in [
+        name("threat_detected"),
+        ty(bool_ty),
+        (gimli::DW_AT_decl_line, AttributeValue::Udata(44)),
+        (
+            gimli::DW_AT_location,
+            AttributeValue::LocationListRef(threat_detected),
+        ),
+    ] {
+        unit.get_mut(var).set(at, value);
+    }
+
+    let block = unit.add(main, gimli::DW_TAG_lexical_block);
+    unit.get_mut(block).set(
+        gimli::DW_AT_low_pc,
+        AttributeValue::Address(Address::Constant(MAIN_LOOP)),
+    );
+    unit.get_mut(block).set(
+        gimli::DW_AT_high_pc,
+        AttributeValue::Udata(MAIN_DEAD - MAIN_LOOP),
+    );
+    let mut slot = Expression::new();
+    slot.op_breg(Register(1), 32);
+    let var = unit.add(block, gimli::DW_TAG_variable);
+    for (at, value) in [
+        name("detector"),
+        ty(detector),
+        (gimli::DW_AT_decl_line, AttributeValue::Udata(55)),
+        (gimli::DW_AT_location, AttributeValue::Exprloc(slot)),
+    ] {
+        unit.get_mut(var).set(at, value);
+    }
+
+    // rustc keeps methods under `{impl#N}` and names them by linkage name
+    let impl_ns = unit.add(mailbox_ns, gimli::DW_TAG_namespace);
+    unit.get_mut(impl_ns)
+        .set(gimli::DW_AT_name, AttributeValue::String("{impl#0}".into()));
+    let take = unit.add(impl_ns, gimli::DW_TAG_subprogram);
+    for (at, value) in [
+        name("take"),
+        (
+            gimli::DW_AT_linkage_name,
+            AttributeValue::String(TAKE_SYMBOL.into()),
+        ),
+        (
+            gimli::DW_AT_inline,
+            AttributeValue::Inline(gimli::DW_INL_inlined),
+        ),
+    ] {
+        unit.get_mut(take).set(at, value);
+    }
+    let sequence = unit.add(take, gimli::DW_TAG_variable);
+    for (at, value) in [
+        name("sequence"),
+        ty(u32_ty),
+        (gimli::DW_AT_decl_line, AttributeValue::Udata(106)),
+    ] {
+        unit.get_mut(sequence).set(at, value);
+    }
+    let inlined = unit.add(main, gimli::DW_TAG_inlined_subroutine);
+    for (at, value) in [
+        (gimli::DW_AT_abstract_origin, AttributeValue::UnitRef(take)),
+        (
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(MAIN_TAKE)),
+        ),
+        (gimli::DW_AT_high_pc, AttributeValue::Udata(MAIN_TAKE_SIZE)),
+        (
+            gimli::DW_AT_call_file,
+            AttributeValue::FileIndex(Some(files.main)),
+        ),
+        (gimli::DW_AT_call_line, AttributeValue::Udata(49)),
+        (gimli::DW_AT_call_column, AttributeValue::Udata(33)),
+    ] {
+        unit.get_mut(inlined).set(at, value);
+    }
+    // the inlined copy has only the origin and where it lives
+    let mut in_a9 = Expression::new();
+    in_a9.op_reg(Register(9));
+    let copy = unit.add(inlined, gimli::DW_TAG_variable);
+    for (at, value) in [
+        (
+            gimli::DW_AT_abstract_origin,
+            AttributeValue::UnitRef(sequence),
+        ),
+        (gimli::DW_AT_location, AttributeValue::Exprloc(in_a9)),
+    ] {
+        unit.get_mut(copy).set(at, value);
+    }
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    dwarf
+        .write(&mut sections)
+        .expect("fixture DWARF is well formed");
+    let mut out = Vec::new();
+    sections
+        .for_each(|id, data| {
+            if !data.slice().is_empty() {
+                out.push((id.name(), data.slice().to_vec()));
+            }
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    out
+}
@@ -0,0 +1,309 @@
+//! The FreeRTOS kernel state of a running ghost-trigger, frozen.
+//!
+//! The mock has no scheduler. At boot [`Kernel::boot`] writes the task control
+//! blocks, their stacks and the kernel's task lists into target memory at
+//! the image's kernel symbols, laid out like ESP-IDF v5's FreeRTOS on an
+//! ESP32, so task list decoders have the same structures to walk as on
+//! the board. Nothing changes them afterwards.
+
+use crate::cpu::{Cpu, HEAP_BASE, STACK_BASE, STACK_SIZE};
+use crate::image::{Image, Symbol};
+
+/// `ListItem_t` and `List_t` are both 20 bytes with 32-bit ticks.
+const LIST_SIZE: u64 = 20;
+/// `xListEnd` inside a `List_t`, after `uxNumberOfItems` and `pxIndex`.
+const LIST_END: u64 = 8;
+/// `TCB_t` fields.
+const TCB_STATE_ITEM: u64 = 4;
+const TCB_EVENT_ITEM: u64 = 24;
+const TCB_PRIORITY: u64 = 44;
+const TCB_STACK: u64 = 48;
+const TCB_NAME: u64 = 52;
+const TCB_CORE: u64 = 68;
+const TCB_END_OF_STACK: u64 = 72;
+const TCB_STRIDE: u64 = 0x80;
+const NAME_LEN: usize = 16;
+
+/// Kernel objects the model writes.
+const SYMBOLS: [&str; 11] = [
+    "pxCurrentTCBs",
+    "pxReadyTasksLists",
+    "xDelayedTaskList1",
+    "xDelayedTaskList2",
+    "pxDelayedTaskList",
+    "pxOverflowDelayedTaskList",
+    "xPendingReadyList",
+    "xTasksWaitingTermination",
+    "xSuspendedTaskList",
+    "uxCurrentNumberOfTasks",
+    "uxTopUsedPriority",
+];
+
+/// What `prvInitialiseNewTask` fills stacks with.
+pub const STACK_FILL: u8 = 0xa5;
+/// `xCoreID` of a task that runs on either core.
+pub const NO_AFFINITY: u32 = 0x7fff_ffff;
+
+/// Stack the `main` task has touched below its reset stack pointer,
+/// headroom for the call frames the firmware model pushes.
+const MAIN_USED: u64 = 0x200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// In a ready list and `pxCurrentTCBs[core]`.
+    Running(u32),
+    Ready,
+    /// In the delayed list, waiting for a tick.
+    Delayed,
+    /// In the suspended list, blocked on a queue with no timeout.
+    Waiting,
+    Suspended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Task {
+    pub name: &'static str,
+    pub priority: u32,
+    pub core: u32,
+    pub stack_size: u64,
+    /// Bytes at the top of the stack that are no longer fill.
+    pub used: u64,
+    pub state: State,
+}
+
+/// ghost-trigger's tasks, in creation order, see [`placement`].
+pub const TASKS: [Task; 6] = [
+    Task {
+        name: "ipc0",
+        priority: 24,
+        core: 0,
+        stack_size: 0x400,
+        used: 0x1a0,
+        state: State::Waiting,
+    },
+    Task {
+        name: "ipc1",
+        priority: 24,
+        core: 1,
+        stack_size: 0x400,
+        used: 0x140,
+        state: State::Suspended,
+    },
+    Task {
+        name: "esp_timer",
+        priority: 22,
+        core: 0,
+        stack_size: 0xe00,
+        used: 0x2c0,
+        state: State::Delayed,
+    },
+    Task {
+        name: "main",
+        priority: 1,
+        core: 0,
+        stack_size: STACK_SIZE as u64,
+        used: 0x100 + MAIN_USED,
+        state: State::Running(0),
+    },
+    Task {
+        name: "IDLE0",
+        priority: 0,
+        core: 0,
+        stack_size: 0x600,
+        used: 0x150,
+        state: State::Ready,
+    },
+    Task {
+        name: "IDLE1",
+        priority: 0,
+        core: 1,
+        stack_size: 0x600,
+        used: 0x150,
+        state: State::Running(1),
+    },
+];
+
+/// `sizeof(TCB_t)` as far as the model fills it, and their spacing.
+pub const TCB_SIZE: u64 = TCB_STRIDE;
+
+/// Where `TASKS[i]` lives once booted: its `TCB_t` and the bounds of its
+/// stack. `main` runs on the reset stack, the rest on the heap after the
+/// TCBs, in creation order.
+pub fn placement(i: usize) -> (u64, u64, u64) {
+    let tcb = HEAP_BASE + TCB_STRIDE * i as u64;
+    let task = &TASKS[i];
+    let stack = match task.name {
+        "main" => STACK_BASE,
+        _ => {
+            let below: u64 = TASKS[..i]
+                .iter()
+                .filter(|t| t.name != "main")
+                .map(|t| t.stack_size)
+                .sum();
+            HEAP_BASE + TCB_STRIDE * TASKS.len() as u64 + below
+        }
+    };
+    (tcb, stack, stack + task.stack_size)
+}
+
+fn put(cpu: &mut Cpu, addr: u64, value: u64) {
+    cpu.poke(addr, &(value as u32).to_le_bytes());
+}
+
+/// An empty list: `pxIndex` and both links of `xListEnd` point at `xListEnd`.
+fn empty(cpu: &mut Cpu, list: u64) {
+    let end = list + LIST_END;
+    put(cpu, list, 0);
+    put(cpu, list + 4, end);
+    put(cpu, end, 0xffff_ffff);
+    put(cpu, end + 4, end);
+    put(cpu, end + 8, end);
+}
+
+/// `vListInsertEnd` for each item in turn, `items` being `(item, owner, value)`.
+fn fill(cpu: &mut Cpu, list: u64, items: &[(u64, u64, u64)]) {
+    empty(cpu, list);
+    let end = list + LIST_END;
+    let mut previous = end;
+    for &(item, owner, value) in items {
+        put(cpu, item, value);
+        put(cpu, item + 4, end);
+        put(cpu, item + 8, previous);
+        put(cpu, item + 12, owner);
+        put(cpu, item + 16, list);
+        put(cpu, previous + 4, item);
+        put(cpu, end + 8, item);
+        previous = item;
+    }
+    put(cpu, list, items.len() as u64);
+}
+
+/// The kernel's symbols in an image.
+#[derive(Debug, Clone)]
+pub struct Kernel {
+    symbols: Vec<Symbol>,
+}
+
+impl Kernel {
+    /// `None` if the image has no FreeRTOS in it.
+    pub fn new(image: &Image) -> Option<Self> {
+        let kernel = Self {
+            symbols: image
+                .symbols
+                .iter()
+                .filter(|s| SYMBOLS.contains(&s.name.as_str()))
+                .cloned()
+                .collect(),
+        };
+        // the current task and the ready lists are what make it FreeRTOS
+        let found = SYMBOLS[..2]
+            .iter()
+            .all(|name| kernel.symbol(name).is_some());
+        found.then_some(kernel)
+    }
+
+    fn symbol(&self, name: &str) -> Option<(u64, u64)> {
+        self.symbols
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| (s.addr, s.size))
+    }
+
+    /// Write the kernel state to freshly reset memory.
+    pub fn boot(&self, cpu: &mut Cpu) {
+        let symbol = |name: &str| self.symbol(name);
+        let (Some((current, _)), Some((ready, ready_size))) =
+            (symbol("pxCurrentTCBs"), symbol("pxReadyTasksLists"))
+        else {
+            return;
+        };
+        let priorities = ready_size / LIST_SIZE;
+        let lists: Vec<u64> = (0..priorities)
+            .map(|p| ready + p * LIST_SIZE)
+            .chain(
+                [
+                    "xDelayedTaskList1",
+                    "xDelayedTaskList2",
+                    "xTasksWaitingTermination",
+                    "xSuspendedTaskList",
+                ]
+                .into_iter()
+                .filter_map(|name| symbol(name).map(|(at, _)| at)),
+            )
+            .chain(
+                symbol("xPendingReadyList")
+                    .map(|(at, size)| (0..size / LIST_SIZE).map(move |i| at + i * LIST_SIZE))
+                    .into_iter()
+                    .flatten(),
+            )
+            .collect();
+        for list in lists {
+            empty(cpu, list);
+        }
+
+        let delayed = symbol("xDelayedTaskList1").map(|(at, _)| at);
+        for (name, list) in [
+            ("pxDelayedTaskList", delayed),
+            (
+                "pxOverflowDelayedTaskList",
+                symbol("xDelayedTaskList2").map(|(at, _)| at),
+            ),
+        ] {
+            if let (Some((at, _)), Some(list)) = (symbol(name), list) {
+                put(cpu, at, list);
+            }
+        }
+
+        let mut ready_items: Vec<Vec<(u64, u64, u64)>> = vec![Vec::new(); priorities as usize];
+        let (mut delayed_items, mut suspended_items) = (Vec::new(), Vec::new());
+        for (i, task) in TASKS.iter().enumerate() {
+            let (tcb, stack, top) = placement(i);
+            cpu.poke(
+                stack,
+                &vec![STACK_FILL; (task.stack_size - task.used) as usize],
+            );
+
+            put(cpu, tcb, top - task.used);
+            put(cpu, tcb + TCB_PRIORITY, u64::from(task.priority));
+            put(cpu, tcb + TCB_STACK, stack);
+            let mut name = [0u8; NAME_LEN];
+            name[..task.name.len()].copy_from_slice(task.name.as_bytes());
+            cpu.poke(tcb + TCB_NAME, &name);
+            put(cpu, tcb + TCB_CORE, u64::from(task.core));
+            put(cpu, tcb + TCB_END_OF_STACK, top - 8);
+
+            let state_item = (tcb + TCB_STATE_ITEM, tcb, 0);
+            match task.state {
+                State::Running(core) => {
+                    put(cpu, current + 4 * u64::from(core), tcb);
+                    ready_items[task.priority as usize].push(state_item);
+                }
+                State::Ready => ready_items[task.priority as usize].push(state_item),
+                State::Delayed => delayed_items.push((tcb + TCB_STATE_ITEM, tcb, 1000)),
+                State::Waiting => {
+                    // blocked on a queue: the event item sits in the queue's
+                    // waiting list, anything non-null will do for decoders
+                    put(cpu, tcb + TCB_EVENT_ITEM + 16, HEAP_BASE + 0x3ff0);
+                    suspended_items.push(state_item);
+                }
+                State::Suspended => suspended_items.push(state_item),
+            }
+        }
+
+        for (priority, items) in ready_items.iter().enumerate() {
+            fill(cpu, ready + priority as u64 * LIST_SIZE, items);
+        }
+        if let Some(at) = delayed {
+            fill(cpu, at, &delayed_items);
+        }
+        if let Some((at, _)) = symbol("xSuspendedTaskList") {
+            fill(cpu, at, &suspended_items);
+        }
+        if let Some((at, _)) = symbol("uxCurrentNumberOfTasks") {
+            put(cpu, at, TASKS.len() as u64);
+        }
+        if let Some((at, _)) = symbol("uxTopUsedPriority") {
+            put(cpu, at, priorities - 1);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! The parts of the firmware ELF a target would have in memory.
+
+use object::{Object, ObjectSection, ObjectSymbol, SectionFlags, SymbolKind};
+
+use crate::error::{Error, Result};
+
+/// One allocated section, loaded at its link address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub addr: u64,
+    /// File contents, zeros for `.bss`-like sections.
+    pub data: Vec<u8>,
+    pub writable: bool,
+    pub executable: bool,
+}
+
+impl Region {
+    pub fn end(&self) -> u64 {
+        self.addr + self.data.len() as u64
+    }
+
+    pub fn contains(&self, addr: u64, len: u64) -> bool {
+        addr >= self.addr && addr.saturating_add(len) <= self.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Name as it appears in the symbol table.
+    pub name: String,
+    /// Demangled path without the hash, e.g. `ghost_trigger::injection::CYCLE_MS`.
+    pub path: String,
+    pub addr: u64,
+    pub size: u64,
+    pub function: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    pub regions: Vec<Region>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Image {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let file = object::File::parse(data).map_err(|e| Error::Elf(e.to_string()))?;
+
+        let mut regions = Vec::new();
+        for section in file.sections() {
+            let SectionFlags::Elf { sh_flags } = section.flags() else {
+                continue;
+            };
+            if sh_flags & u64::from(object::elf::SHF_ALLOC) == 0 || section.size() == 0 {
+                continue;
+            }
+            let mut bytes = section
+                .data()
+                .map_err(|e| Error::Elf(e.to_string()))?
+                .to_vec();
+            // SHT_NOBITS sections have no file data
+            bytes.resize(section.size() as usize, 0);
+            regions.push(Region {
+                name: section.name().unwrap_or_default().to_string(),
+                addr: section.address(),
+                data: bytes,
+                writable: sh_flags & u64::from(object::elf::SHF_WRITE) != 0,
+                executable: sh_flags & u64::from(object::elf::SHF_EXECINSTR) != 0,
+            });
+        }
+        regions.sort_by_key(|r| r.addr);
+
+        let symbols = file
+            .symbols()
+            .filter(|s| s.is_definition() && s.address() != 0)
+            .filter_map(|s| {
+                let name = s.name().ok()?.to_string();
+                Some(Symbol {
+                    path: format!("{:#}", rustc_demangle::demangle(&name)),
+                    name,
+                    addr: s.address(),
+                    size: s.size(),
+                    function: s.kind() == SymbolKind::Text,
+                })
+            })
+            .collect();
+
+        Ok(Self { regions, symbols })
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Look a symbol up by its raw or demangled name.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|s| s.name == name)
+            .or_else(|| self.symbols.iter().find(|s| s.path == name))
+    }
+
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.iter().find(|r| r.name == name)
+    }
+}
@@ -0,0 +1,33 @@
+//! Mock GDB RSP target for host-side debugger tooling.
+//!
+//! Loads a ghost-trigger ELF, maps its allocated sections as target memory
+//! and answers the GDB remote protocol on a TCP port the way OpenOCD does
+//! for a real ESP32 behind an ESP-Prog. Instead of executing xtensa code
+//! it runs a behavioural model of the firmware ([`GhostTrigger`]) built on
+//! the same `pulse-core` detector, so mailbox injections, breakpoints on
+//! function symbols, watchpoints on statics and the serial log all behave
+//! like the board, with no board attached.
+//!
+//! ```no_run
+//! # fn main() -> pulse_mock::Result<()> {
+//! let image = pulse_mock::Image::from_file("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let server = pulse_mock::Server::bind("127.0.0.1:3333", image, Default::default())?;
+//! server.serve()
+//! # }
+//! ```
+
+pub mod coredump;
+pub mod cpu;
+pub mod error;
+pub mod firmware;
+pub mod fixture;
+pub mod freertos;
+pub mod image;
+pub mod server;
+pub mod tdesc;
+
+pub use cpu::Cpu;
+pub use error::{Error, Result};
+pub use firmware::{Firmware, GhostTrigger};
+pub use image::{Image, Region, Symbol};
+pub use server::{Options, Server, Session};
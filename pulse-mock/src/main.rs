@@ -0,0 +1,59 @@
+use std::process::ExitCode;
+
+use pulse_mock::{fixture, Image, Options, Server};
+
+const USAGE: &str =
+    "usage: pulse-mock [--port N] [--time-scale X] (<ghost-trigger ELF> | --fixture)";
+
+fn main() -> ExitCode {
+    let mut port = pulse_rsp::DEFAULT_PORT;
+    let mut options = Options::default();
+    let mut image = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => port = v,
+                None => return usage(),
+            },
+            "--time-scale" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => options.time_scale = v,
+                None => return usage(),
+            },
+            "--fixture" => image = Image::parse(&fixture::ghost_trigger()).ok(),
+            "-h" | "--help" => return usage(),
+            path => match Image::from_file(path) {
+                Ok(i) => image = Some(i),
+                Err(e) => {
+                    eprintln!("{}: {}", path, e);
+                    return ExitCode::FAILURE;
+                }
+            },
+        }
+    }
+    let Some(image) = image else {
+        return usage();
+    };
+
+    let server = match Server::bind(("127.0.0.1", port), image, options) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("cannot listen on port {}: {}", port, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("mock ESP32 target listening on 127.0.0.1:{}", port);
+    match server.serve() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
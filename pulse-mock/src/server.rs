@@ -0,0 +1,413 @@
+//! The GDB side: answers RSP packets the way OpenOCD's server does.
+
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use pulse_rsp::packet::{self, Conn, Incoming};
+use pulse_rsp::stop::signal::{SIGINT, SIGTRAP};
+use pulse_rsp::{StopReason, StopReply, WatchKind};
+
+use crate::cpu::{Cpu, Watchpoint, G_REGISTERS, REG_PC};
+use crate::error::Result;
+use crate::firmware::{Firmware, GhostTrigger};
+use crate::image::Image;
+use crate::tdesc;
+
+/// FreeRTOS task id reported for the firmware's main task.
+pub const MAIN_THREAD: u64 = 1;
+
+/// How often a running target checks for Ctrl-C while it sleeps.
+const POLL_SLICE: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    /// Wall-clock seconds per target second, `0.0` runs as fast as possible.
+    pub time_scale: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { time_scale: 1.0 }
+    }
+}
+
+/// Listens like OpenOCD on port 3333 and serves one client at a time.
+pub struct Server {
+    listener: TcpListener,
+    image: Image,
+    options: Options,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A, image: Image, options: Options) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            image,
+            options,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve the next client until it detaches or disconnects. Every
+    /// connection starts from a freshly reset target, halted at boot.
+    pub fn serve_one(&self) -> Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        self.session(stream)
+    }
+
+    /// Serve clients forever. A client that goes away badly is logged and
+    /// the next one served; only the listener failing ends it.
+    pub fn serve(&self) -> Result<()> {
+        loop {
+            let (stream, peer) = self.listener.accept()?;
+            if let Err(e) = self.session(stream) {
+                eprintln!("{}: {}", peer, e);
+            }
+        }
+    }
+
+    fn session(&self, stream: TcpStream) -> Result<()> {
+        let firmware = GhostTrigger::new(&self.image);
+        Session::new(stream, Cpu::new(&self.image), firmware, self.options)?.run()
+    }
+
+    /// Serve a single client on a background thread, for tests.
+    pub fn spawn(self) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let addr = self.local_addr()?;
+        Ok((addr, thread::spawn(move || self.serve_one())))
+    }
+}
+
+/// One connected debugger.
+pub struct Session<F> {
+    conn: Conn<TcpStream>,
+    cpu: Cpu,
+    firmware: F,
+    options: Options,
+    no_ack: bool,
+    last_stop: StopReply,
+}
+
+impl<F: Firmware> Session<F> {
+    pub fn new(stream: TcpStream, mut cpu: Cpu, mut firmware: F, options: Options) -> Result<Self> {
+        stream.set_nodelay(true)?;
+        firmware.reset(&mut cpu);
+        let last_stop = stop(&cpu, SIGTRAP, None);
+        Ok(Self {
+            conn: Conn::new(stream),
+            cpu,
+            firmware,
+            options,
+            no_ack: false,
+            last_stop,
+        })
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        loop {
+            let body = match self.conn.read() {
+                Ok(Incoming::Packet { body, valid }) => {
+                    if !self.no_ack {
+                        self.conn.write_raw(if valid { b"+" } else { b"-" })?;
+                        if !valid {
+                            continue;
+                        }
+                    }
+                    packet::decode(&body)?
+                }
+                // already halted
+                Ok(Incoming::Interrupt) | Ok(Incoming::Ack) | Ok(Incoming::Nak) => continue,
+                Err(pulse_rsp::Error::Closed) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            match self.handle(&body)? {
+                Some(reply) => self.send(&reply)?,
+                None => return Ok(()),
+            }
+            // the OK itself still goes out in ack mode
+            if body == b"QStartNoAckMode" {
+                self.no_ack = true;
+            }
+        }
+    }
+
+    fn send(&mut self, reply: &[u8]) -> Result<()> {
+        self.conn.write_packet(&packet::escape(reply))?;
+        if !self.no_ack {
+            // the client acks every reply; a NAK gets one resend
+            if let Incoming::Nak = self.conn.read()? {
+                self.conn.write_packet(&packet::escape(reply))?;
+                self.conn.read()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reply to one packet, `None` ends the session.
+    fn handle(&mut self, body: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some((&cmd, args)) = body.split_first() else {
+            return Ok(Some(Vec::new()));
+        };
+        let text = String::from_utf8_lossy(args);
+        let reply = match cmd {
+            b'q' => self.query(&text)?,
+            b'Q' if text == "StartNoAckMode" => b"OK".to_vec(),
+            b'?' => self.last_stop.encode(),
+            b'H' | b'T' => b"OK".to_vec(),
+            b'g' => {
+                let bytes: Vec<u8> = self.cpu.registers[..G_REGISTERS]
+                    .iter()
+                    .flat_map(|r| r.to_le_bytes())
+                    .collect();
+                packet::to_hex(&bytes).into_bytes()
+            }
+            b'p' => match parse_hex(&text).and_then(|n| self.cpu.register(n as usize)) {
+                Some(value) => packet::to_hex(&value.to_le_bytes()).into_bytes(),
+                None => b"E45".to_vec(),
+            },
+            b'P' => {
+                let written = text.split_once('=').and_then(|(n, v)| {
+                    let v: [u8; 4] = packet::from_hex(v.as_bytes()).ok()?.try_into().ok()?;
+                    let n = parse_hex(n)? as usize;
+                    self.cpu
+                        .set_register(n, u32::from_le_bytes(v))
+                        .then_some(())
+                });
+                reply_ok(written, b"E45")
+            }
+            b'm' => match addr_len(&text).and_then(|(a, l)| self.cpu.peek(a, l as usize)) {
+                Some(bytes) => packet::to_hex(&bytes).into_bytes(),
+                None => b"E14".to_vec(),
+            },
+            b'M' => {
+                let written = text.split_once(':').and_then(|(head, hex)| {
+                    let (addr, len) = addr_len(head)?;
+                    let data = packet::from_hex(hex.as_bytes()).ok()?;
+                    (data.len() as u64 == len && self.cpu.poke(addr, &data)).then_some(())
+                });
+                reply_ok(written, b"E14")
+            }
+            b'X' => {
+                let colon = args.iter().position(|&b| b == b':');
+                let written = colon.and_then(|at| {
+                    let (addr, len) = addr_len(&String::from_utf8_lossy(&args[..at]))?;
+                    let data = &args[at + 1..];
+                    (data.len() as u64 == len && self.cpu.poke(addr, data)).then_some(())
+                });
+                reply_ok(written, b"E14")
+            }
+            b'Z' | b'z' => self.point(cmd == b'Z', &text),
+            b'c' => self.resume(false)?,
+            b's' => self.resume(true)?,
+            b'v' => self.verbose(&text)?,
+            b'D' => {
+                self.send(b"OK")?;
+                return Ok(None);
+            }
+            b'k' => return Ok(None),
+            _ => Vec::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    fn query(&mut self, text: &str) -> Result<Vec<u8>> {
+        Ok(match text.split_once(':').map_or(text, |(q, _)| q) {
+            "Supported" => {
+                b"PacketSize=1000;QStartNoAckMode+;vContSupported+;swbreak+;hwbreak+;qXfer:features:read+"
+                    .to_vec()
+            }
+            "Attached" => b"1".to_vec(),
+            "C" => format!("QC{:x}", MAIN_THREAD).into_bytes(),
+            "fThreadInfo" => format!("m{:x}", MAIN_THREAD).into_bytes(),
+            "sThreadInfo" => b"l".to_vec(),
+            "Xfer" => xfer(text),
+            _ if text.starts_with("Rcmd,") => {
+                let cmd = packet::from_hex(&text.as_bytes()["Rcmd,".len()..])?;
+                let output = self.monitor(&String::from_utf8_lossy(&cmd));
+                if !output.is_empty() {
+                    self.send(format!("O{}", packet::to_hex(output.as_bytes())).as_bytes())?;
+                }
+                b"OK".to_vec()
+            }
+            _ => Vec::new(),
+        })
+    }
+
+    /// The OpenOCD `monitor` commands scripts commonly use.
+    fn monitor(&mut self, cmd: &str) -> String {
+        match cmd.trim() {
+            "reset" | "reset halt" | "reset init" => {
+                self.cpu.reset();
+                self.firmware.reset(&mut self.cpu);
+                self.last_stop = stop(&self.cpu, SIGTRAP, None);
+                String::new()
+            }
+            "halt" => String::new(),
+            "reg ccount" => format!("ccount (/32): {:#010x}\n", self.cpu.ccount()),
+            other => format!("mock target: ignoring `{}`\n", other),
+        }
+    }
+
+    fn verbose(&mut self, text: &str) -> Result<Vec<u8>> {
+        if text == "Cont?" {
+            return Ok(b"vCont;c;C;s;S".to_vec());
+        }
+        match text.strip_prefix("Cont;") {
+            // one task, so the first action is the one that applies to it
+            Some(actions) => match actions.bytes().next() {
+                Some(b'c' | b'C') => self.resume(false),
+                Some(b's' | b'S') => self.resume(true),
+                _ => Ok(b"E01".to_vec()),
+            },
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn point(&mut self, insert: bool, text: &str) -> Vec<u8> {
+        let mut parts = text.split(',');
+        let (Some(kind), Some(addr), Some(len)) = (
+            parts.next().and_then(parse_hex),
+            parts.next().and_then(parse_hex),
+            parts.next().and_then(parse_hex),
+        ) else {
+            return b"E01".to_vec();
+        };
+        let watch = match kind {
+            0 | 1 => None,
+            2 => Some(WatchKind::Write),
+            3 => Some(WatchKind::Read),
+            4 => Some(WatchKind::Access),
+            _ => return Vec::new(),
+        };
+        match (watch, insert) {
+            (None, true) => {
+                if !self.cpu.breakpoints.contains(&addr) {
+                    self.cpu.breakpoints.push(addr);
+                }
+            }
+            (None, false) => self.cpu.breakpoints.retain(|&b| b != addr),
+            (Some(kind), true) => self.cpu.watchpoints.push(Watchpoint { kind, addr, len }),
+            (Some(kind), false) => {
+                let point = Watchpoint { kind, addr, len };
+                self.cpu.watchpoints.retain(|w| *w != point);
+            }
+        }
+        b"OK".to_vec()
+    }
+
+    /// Run the firmware until a debug event, Ctrl-C or the end of a step.
+    ///
+    /// A single step runs one [`Firmware::step`], the smallest unit the
+    /// model has, not one instruction.
+    fn resume(&mut self, single: bool) -> Result<Vec<u8>> {
+        let reply = loop {
+            self.firmware.step(&mut self.cpu);
+            self.flush_console()?;
+            if let Some(reason) = self.cpu.take_hit() {
+                break stop(&self.cpu, SIGTRAP, Some(reason));
+            }
+            if single {
+                break stop(&self.cpu, SIGTRAP, None);
+            }
+            if self.sleep()? {
+                break stop(&self.cpu, SIGINT, None);
+            }
+        };
+        let encoded = reply.encode();
+        self.last_stop = reply;
+        Ok(encoded)
+    }
+
+    fn flush_console(&mut self) -> Result<()> {
+        let console = self.cpu.take_console();
+        if !console.is_empty() {
+            self.send(format!("O{}", packet::to_hex(&console)).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Sit out the firmware's delay in wall time. True if interrupted.
+    fn sleep(&mut self) -> Result<bool> {
+        let ms = self.cpu.take_delay_ms();
+        let wall = Duration::from_secs_f64(ms as f64 / 1000.0 * self.options.time_scale);
+        let until = Instant::now() + wall;
+        loop {
+            if self.interrupted()? {
+                return Ok(true);
+            }
+            let now = Instant::now();
+            if now >= until {
+                return Ok(false);
+            }
+            thread::sleep(POLL_SLICE.min(until - now));
+        }
+    }
+
+    fn interrupted(&mut self) -> Result<bool> {
+        if !self.conn.has_buffered() {
+            self.conn.get_ref().set_nonblocking(true)?;
+            let mut byte = [0u8];
+            let peeked = self.conn.get_ref().peek(&mut byte);
+            self.conn.get_ref().set_nonblocking(false)?;
+            match peeked {
+                Ok(0) => return Err(pulse_rsp::Error::Closed.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        // only Ctrl-C and stray acks arrive while the target runs
+        Ok(matches!(self.conn.read()?, Incoming::Interrupt))
+    }
+}
+
+fn stop(cpu: &Cpu, signal: u8, reason: Option<StopReason>) -> StopReply {
+    StopReply::Signal {
+        signal,
+        thread: Some(MAIN_THREAD),
+        reason,
+        registers: vec![(REG_PC as u32, cpu.registers[REG_PC].to_le_bytes().to_vec())],
+    }
+}
+
+/// `qXfer:features:read:ANNEX:OFFSET,LENGTH`, `m` while more follows.
+fn xfer(text: &str) -> Vec<u8> {
+    let mut parts = text.splitn(5, ':');
+    let (Some("Xfer"), Some("features"), Some("read"), Some(annex), Some(range)) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return Vec::new();
+    };
+    let (Some(xml), Some((offset, len))) = (tdesc::annex(annex), addr_len(range)) else {
+        return b"E00".to_vec();
+    };
+    let start = (offset as usize).min(xml.len());
+    let end = start.saturating_add(len as usize).min(xml.len());
+    let mut reply = vec![if end < xml.len() { b'm' } else { b'l' }];
+    reply.extend_from_slice(&xml.as_bytes()[start..end]);
+    reply
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text, 16).ok()
+}
+
+fn addr_len(text: &str) -> Option<(u64, u64)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+fn reply_ok(done: Option<()>, err: &[u8]) -> Vec<u8> {
+    match done {
+        Some(()) => b"OK".to_vec(),
+        None => err.to_vec(),
+    }
+}
@@ -0,0 +1,92 @@
+//! The target description the server hands out over `qXfer:features:read`.
+//!
+//! Shaped like OpenOCD's for an ESP32 core: a `target.xml` that includes
+//! the core feature, `pc` and the physical `ar` registers first (what `g`
+//! returns), then the special registers the mock models, then the `a`
+//! registers of the current window.
+
+use std::fmt::Write as _;
+
+use crate::cpu::{AR_COUNT, A_COUNT, G_REGISTERS, REG_A0};
+
+pub const TARGET_XML: &str = "target.xml";
+const CORE_XML: &str = "xtensa-core.xml";
+
+/// Special registers in register number order, from [`G_REGISTERS`].
+const SPECIALS: [&str; 21] = [
+    "lbeg",
+    "lend",
+    "lcount",
+    "sar",
+    "windowbase",
+    "windowstart",
+    "configid0",
+    "configid1",
+    "ps",
+    "threadptr",
+    "epc1",
+    "epc2",
+    "epc3",
+    "epc4",
+    "exccause",
+    "excvaddr",
+    "ccount",
+    "intenable",
+    "interrupt",
+    "vecbase",
+    "debugcause",
+];
+
+/// Contents of one annex, `None` for names the target does not have.
+pub fn annex(name: &str) -> Option<String> {
+    match name {
+        TARGET_XML => Some(format!(
+            concat!(
+                "<?xml version=\"1.0\"?>\n",
+                "<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n",
+                "<target version=\"1.0\">\n",
+                "  <architecture>xtensa</architecture>\n",
+                "  <xi:include href=\"{}\"/>\n",
+                "</target>\n"
+            ),
+            CORE_XML
+        )),
+        CORE_XML => Some(core()),
+        _ => None,
+    }
+}
+
+fn core() -> String {
+    let mut xml =
+        String::from("<?xml version=\"1.0\"?>\n<!-- ESP32 core, as pulse-mock models it -->\n");
+    xml.push_str("<feature name=\"org.gnu.gdb.xtensa.core\">\n");
+    xml.push_str(
+        "  <reg name=\"pc\" bitsize=\"32\" regnum=\"0\" type=\"code_ptr\" group=\"general\"/>\n",
+    );
+    // register numbers count on from the previous one when left out
+    for n in 0..AR_COUNT {
+        let _ = writeln!(
+            xml,
+            "  <reg name=\"ar{}\" bitsize=\"32\" type=\"uint32\" group=\"general\"/>",
+            n
+        );
+    }
+    for (i, name) in SPECIALS.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "  <reg name=\"{}\" bitsize=\"32\" regnum=\"{}\" type=\"uint32\" group=\"system\"/>",
+            name,
+            G_REGISTERS + i
+        );
+    }
+    for n in 0..A_COUNT {
+        let _ = writeln!(
+            xml,
+            "  <reg name=\"a{}\" bitsize=\"32\" regnum=\"{}\" type=\"uint32\" group=\"general\"/>",
+            n,
+            REG_A0 + n
+        );
+    }
+    xml.push_str("</feature>\n");
+    xml
+}
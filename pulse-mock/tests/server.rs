@@ -0,0 +1,172 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use pulse_core::audit::{AuditLog, AUDIT_SIZE};
+use pulse_core::injection::RawEntry;
+use pulse_core::mailbox::{MAILBOX_MAGIC, OFFSET_ACK, OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_mock::{fixture, Image, Options, Server};
+use pulse_rsp::packet;
+use pulse_rsp::{BreakpointKind, Client, Error, StopReason, WatchKind};
+
+fn image() -> Image {
+    Image::parse(&fixture::ghost_trigger()).unwrap()
+}
+
+fn connect(time_scale: f64) -> Client {
+    let server = Server::bind("127.0.0.1:0", image(), Options { time_scale }).unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+    gdb
+}
+
+/// Continue until the console has printed `needle`, at most `limit` stops.
+fn run_until(gdb: &mut Client, needle: &str, limit: usize) -> String {
+    let mut console = String::new();
+    for _ in 0..limit {
+        gdb.cont().unwrap();
+        console.push_str(&String::from_utf8_lossy(&gdb.take_console()));
+        if console.contains(needle) {
+            return console;
+        }
+    }
+    panic!("{:?} never showed up in:\n{}", needle, console);
+}
+
+#[test]
+fn image_has_firmware_layout() {
+    let image = image();
+    let data = image.region(".dram0.data").unwrap();
+    assert!(data.writable && !data.executable);
+    assert_eq!(image.region(".dram0.bss").unwrap().data, vec![0; 0x400]);
+
+    let cycle_ms = image.symbol("ghost_trigger::injection::CYCLE_MS").unwrap();
+    assert_eq!(cycle_ms.addr, fixture::CYCLE_MS);
+    assert!(image.symbol("app_main").unwrap().function);
+
+    let table = image.region(pulse_core::injection::TABLE_SECTION).unwrap();
+    let entries = RawEntry::parse_table(&table.data, 4);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].addr, fixture::CYCLE_MS);
+    assert!(entries[0].in_range(1000) && !entries[0].in_range(5));
+}
+
+#[test]
+fn serve_outlives_a_bad_client() {
+    let server = Server::bind("127.0.0.1:0", image(), Options { time_scale: 0.0 }).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+
+    // a dangling escape, with a good checksum, fails that session
+    let mut bad = TcpStream::connect(addr).unwrap();
+    bad.write_all(&packet::frame(b"m}")).unwrap();
+    let mut rest = Vec::new();
+    let _ = bad.read_to_end(&mut rest);
+
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+    assert_eq!(gdb.read_u32(fixture::PULSE_MAILBOX).unwrap(), MAILBOX_MAGIC);
+}
+
+#[test]
+fn sections_are_memory() {
+    let mut gdb = connect(0.0);
+    assert_eq!(gdb.read_u32(fixture::PULSE_MAILBOX).unwrap(), MAILBOX_MAGIC);
+    assert_eq!(gdb.read_u32(fixture::CYCLE_MS).unwrap(), 1000);
+
+    gdb.write_u32(fixture::BSS, 0x1234_5678).unwrap();
+    gdb.write_memory_binary(fixture::BSS + 4, b"$#}*").unwrap();
+    assert_eq!(
+        gdb.read_memory(fixture::BSS, 8).unwrap(),
+        [0x78, 0x56, 0x34, 0x12, b'$', b'#', b'}', b'*']
+    );
+
+    assert!(matches!(gdb.read_memory(0x1000, 4), Err(Error::Remote(_))));
+    assert_eq!(gdb.read_registers().unwrap().len(), 65 * 4);
+}
+
+#[test]
+fn breakpoints_on_function_symbols() {
+    let mut gdb = connect(0.0);
+    gdb.insert_point(BreakpointKind::Hardware, fixture::DETECTOR_CYCLE, 2)
+        .unwrap();
+
+    for _ in 0..3 {
+        let stop = gdb.cont().unwrap();
+        assert_eq!(stop.reason(), Some(StopReason::HwBreak));
+        assert_eq!(stop.register(0), Some(fixture::DETECTOR_CYCLE));
+    }
+    let console = String::from_utf8(gdb.take_console()).unwrap();
+    assert!(console.contains("System altered!"));
+    assert_eq!(console.matches("System secure.").count(), 2);
+
+    gdb.remove_point(BreakpointKind::Hardware, fixture::DETECTOR_CYCLE, 2)
+        .unwrap();
+    assert_eq!(gdb.step().unwrap().signal(), Some(5));
+}
+
+#[test]
+fn mailbox_injection_round_trip() {
+    let mut gdb = connect(0.0);
+    let mailbox = fixture::PULSE_MAILBOX;
+    let ack = mailbox + u64::from(OFFSET_ACK);
+
+    gdb.write_u32(mailbox + u64::from(OFFSET_SIGNAL), 1)
+        .unwrap();
+    gdb.write_u32(mailbox + u64::from(OFFSET_SEQUENCE), 1)
+        .unwrap();
+    gdb.insert_point(BreakpointKind::WriteWatch, ack, 4)
+        .unwrap();
+
+    let stop = gdb.cont().unwrap();
+    assert_eq!(
+        stop.reason(),
+        Some(StopReason::Watch {
+            kind: WatchKind::Write,
+            addr: ack
+        })
+    );
+    assert_eq!(gdb.read_u32(ack).unwrap(), 1);
+    gdb.remove_point(BreakpointKind::WriteWatch, ack, 4)
+        .unwrap();
+
+    gdb.insert_point(BreakpointKind::Hardware, fixture::MAIN, 2)
+        .unwrap();
+    let console = run_until(&mut gdb, "Engaging backup protocols...", 5);
+    assert!(console.contains("\x1b[0;31mE (0) pulse_core::sink:  !! THREAT DETECTED !! [Cycle: 1]"));
+
+    let audit = gdb.read_memory(fixture::PULSE_AUDIT, AUDIT_SIZE).unwrap();
+    let entries = AuditLog::decode(&audit).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].sequence, entries[0].signal), (1, 1));
+}
+
+#[test]
+fn cycle_ms_paces_the_target() {
+    let mut gdb = connect(1.0);
+    gdb.write_u32(fixture::CYCLE_MS, 10).unwrap();
+    gdb.insert_point(BreakpointKind::Hardware, fixture::MAIN, 2)
+        .unwrap();
+    let console = run_until(&mut gdb, "[Cycle: 3]", 5);
+    assert!(console.contains("I (20) pulse_core::sink: System secure. [Cycle: 3]"));
+}
+
+#[test]
+fn interrupt_and_reset() {
+    let mut gdb = connect(1.0);
+    gdb.write_u32(fixture::CYCLE_MS, 60_000).unwrap();
+    gdb.resume().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(gdb.interrupt().unwrap().signal(), Some(2));
+
+    gdb.monitor("reset halt").unwrap();
+    assert_eq!(gdb.read_u32(fixture::CYCLE_MS).unwrap(), 1000);
+    assert!(gdb
+        .monitor("flash write_image")
+        .unwrap()
+        .contains("ignoring"));
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::target::Target;
+
+/// OpenOCD's default Tcl RPC port.
+pub const DEFAULT_PORT: u16 = 6666;
+
+/// Ends every command and every reply.
+pub const TERMINATOR: u8 = 0x1a;
+
+/// Quote `s` as one Tcl word, whatever it contains.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        if matches!(c, '\\' | '"' | '$' | '[' | ']' | '{' | '}') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+/// Blocking Tcl RPC client.
+pub struct Client<S = TcpStream> {
+    stream: S,
+    buf: Vec<u8>,
+}
+
+impl Client<TcpStream> {
+    /// Connect to OpenOCD, e.g. on `localhost:6666`.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+
+    /// Read timeout for every reply, `None` blocks forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.stream.set_read_timeout(timeout)?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Evaluate a Tcl script and return its result verbatim.
+    ///
+    /// The RPC server does not say whether the script failed, an error
+    /// message comes back like any other result. Most commands also print
+    /// their output instead of returning it; [`exec`](Self::exec) covers both.
+    pub fn eval(&mut self, script: &str) -> Result<String> {
+        let mut out = Vec::with_capacity(script.len() + 1);
+        out.extend_from_slice(script.as_bytes());
+        out.push(TERMINATOR);
+        self.stream.write_all(&out)?;
+        self.stream.flush()?;
+
+        loop {
+            if let Some(end) = self.buf.iter().position(|&b| b == TERMINATOR) {
+                let reply: Vec<u8> = self.buf.drain(..=end).take(end).collect();
+                return Ok(String::from_utf8_lossy(&reply).into_owned());
+            }
+            let mut chunk = [0; 4096];
+            let n = self.stream.read(&mut chunk)?;
+            if n == 0 {
+                return Err(Error::Closed);
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Run an OpenOCD command, returning what it printed, or
+    /// [`Error::Command`] if it raised an error.
+    pub fn exec(&mut self, command: &str) -> Result<String> {
+        let script = format!(
+            "format \"%d %s\" [catch {{capture {}}} r] $r",
+            quote(command)
+        );
+        let reply = self.eval(&script)?;
+        match reply.split_once(' ').unwrap_or((&reply, "")) {
+            ("0", output) => Ok(output.to_string()),
+            ("1", message) => Err(Error::Command {
+                command: command.to_string(),
+                message: message.trim().to_string(),
+            }),
+            _ => Err(Error::protocol(format!("unexpected reply {:?}", reply))),
+        }
+    }
+
+    pub fn version(&mut self) -> Result<String> {
+        Ok(self.eval("version")?.trim().to_string())
+    }
+
+    /// Halt the current target, waiting for it to stop.
+    pub fn halt(&mut self) -> Result<()> {
+        self.exec("halt").map(drop)
+    }
+
+    /// Resume the current target, at `addr` if given.
+    pub fn resume(&mut self, addr: Option<u64>) -> Result<()> {
+        match addr {
+            Some(addr) => self.exec(&format!("resume {:#x}", addr)),
+            None => self.exec("resume"),
+        }
+        .map(drop)
+    }
+
+    pub fn step(&mut self) -> Result<()> {
+        self.exec("step").map(drop)
+    }
+
+    /// Reset and stop at the reset vector.
+    pub fn reset_halt(&mut self) -> Result<()> {
+        self.exec("reset halt").map(drop)
+    }
+
+    /// Reset and let the firmware boot.
+    pub fn reset_run(&mut self) -> Result<()> {
+        self.exec("reset run").map(drop)
+    }
+
+    /// Wait up to `timeout` for the current target to halt.
+    pub fn wait_halt(&mut self, timeout: Duration) -> Result<()> {
+        self.exec(&format!("wait_halt {}", timeout.as_millis()))
+            .map(drop)
+    }
+
+    pub fn targets(&mut self) -> Result<Vec<Target>> {
+        Target::parse_table(&self.exec("targets")?)
+    }
+
+    /// The target commands apply to, with its state.
+    pub fn current_target(&mut self) -> Result<Target> {
+        self.targets()?
+            .into_iter()
+            .find(|t| t.current)
+            .ok_or_else(|| Error::protocol("no current target"))
+    }
+
+    /// Select the target later commands apply to, e.g. `esp32.cpu1`.
+    pub fn select_target(&mut self, name: &str) -> Result<()> {
+        self.exec(&format!("targets {}", quote(name))).map(drop)
+    }
+
+    /// `count` words from `addr`.
+    pub fn read_u32s(&mut self, addr: u64, count: usize) -> Result<Vec<u32>> {
+        let output = self.exec(&format!("mdw {:#x} {}", addr, count))?;
+        let words = parse_dump(&output, u32::from_str_radix)?;
+        if words.len() != count {
+            return Err(Error::protocol(format!(
+                "asked for {} words, got {}",
+                count,
+                words.len()
+            )));
+        }
+        Ok(words)
+    }
+
+    pub fn read_u32(&mut self, addr: u64) -> Result<u32> {
+        Ok(self.read_u32s(addr, 1)?[0])
+    }
+
+    /// Works on a running target when the adapter allows it; ESP32 needs
+    /// to be halted.
+    pub fn write_u32(&mut self, addr: u64, value: u32) -> Result<()> {
+        self.exec(&format!("mww {:#x} {:#x}", addr, value))
+            .map(drop)
+    }
+
+    pub fn read_memory(&mut self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let output = self.exec(&format!("mdb {:#x} {}", addr, len))?;
+        let bytes = parse_dump(&output, u8::from_str_radix)?;
+        if bytes.len() != len {
+            return Err(Error::protocol(format!(
+                "asked for {} bytes, got {}",
+                len,
+                bytes.len()
+            )));
+        }
+        Ok(bytes)
+    }
+
+    /// Bytes in one `write_memory` command (OpenOCD 0.12 and later).
+    pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut command = format!("write_memory {:#x} 8 {{", addr);
+        for (i, b) in data.iter().enumerate() {
+            if i > 0 {
+                command.push(' ');
+            }
+            let _ = write!(command, "{:#04x}", b);
+        }
+        command.push('}');
+        self.exec(&command).map(drop)
+    }
+
+    /// Flash an image with `program`. `address` is needed for raw `.bin`
+    /// files; ELF and hex carry their own.
+    pub fn program(
+        &mut self,
+        image: impl AsRef<Path>,
+        address: Option<u64>,
+        verify: bool,
+    ) -> Result<()> {
+        let mut command = format!("program {}", quote(&image.as_ref().to_string_lossy()));
+        if verify {
+            command.push_str(" verify");
+        }
+        if let Some(address) = address {
+            let _ = write!(command, " {:#x}", address);
+        }
+        self.exec(&command).map(drop)
+    }
+
+    /// Compare flash against an image without writing it.
+    pub fn verify_image(&mut self, image: impl AsRef<Path>, offset: u64) -> Result<()> {
+        self.exec(&format!(
+            "verify_image {} {:#x}",
+            quote(&image.as_ref().to_string_lossy()),
+            offset
+        ))
+        .map(drop)
+    }
+}
+
+/// Values from `mdw`/`mdb` output, `0x3ffb0000: 00000001 00000002 ...`
+/// lines. Anything else OpenOCD logs around them is skipped.
+fn parse_dump<T>(
+    output: &str,
+    parse: fn(&str, u32) -> std::result::Result<T, std::num::ParseIntError>,
+) -> Result<Vec<T>> {
+    let mut values = Vec::new();
+    for line in output.lines() {
+        let Some((addr, rest)) = line.split_once(": ") else {
+            continue;
+        };
+        if !addr.trim().starts_with("0x") {
+            continue;
+        }
+        for word in rest.split_whitespace() {
+            values.push(
+                parse(word, 16)
+                    .map_err(|_| Error::protocol(format!("bad dump line {:?}", line)))?,
+            );
+        }
+    }
+    Ok(values)
+}
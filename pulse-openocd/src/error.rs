@@ -0,0 +1,59 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The peer closed the connection.
+    Closed,
+    /// The command raised a Tcl error, with OpenOCD's message.
+    Command {
+        command: String,
+        message: String,
+    },
+    /// Output did not parse.
+    Protocol(String),
+}
+
+impl Error {
+    pub(crate) fn protocol(what: impl Into<String>) -> Self {
+        Error::Protocol(what.into())
+    }
+
+    /// True for read timeouts on a socket with a timeout set.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Error::Io(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Closed => f.write_str("connection closed by remote"),
+            Error::Command { command, message } => write!(f, "`{}` failed: {}", command, message),
+            Error::Protocol(what) => write!(f, "protocol error: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Error::Closed
+        } else {
+            Error::Io(e)
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! OpenOCD Tcl RPC client.
+//!
+//! Besides its GDB server OpenOCD listens for Tcl commands on port 6666,
+//! each one terminated by `0x1a` in both directions. That is enough to
+//! halt, resume, reset, read and write memory, list targets and flash an
+//! image without a GDB process attached:
+//!
+//! ```no_run
+//! use pulse_openocd::{Client, State};
+//!
+//! # fn main() -> pulse_openocd::Result<()> {
+//! let mut ocd = Client::connect(("localhost", pulse_openocd::DEFAULT_PORT))?;
+//! ocd.halt()?;
+//! let sequence = ocd.read_u32(0x3ffb_0008)?;
+//! ocd.write_u32(0x3ffb_000c, 1)?; // Signal::Threat
+//! ocd.write_u32(0x3ffb_0008, sequence.wrapping_add(1))?;
+//! ocd.resume(None)?;
+//! assert_eq!(ocd.current_target()?.state, State::Running);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Like `pulse-rsp` it is blocking and generic over `Read + Write`.
+
+pub mod client;
+pub mod error;
+pub mod target;
+
+pub use client::{quote, Client, DEFAULT_PORT, TERMINATOR};
+pub use error::{Error, Result};
+pub use target::{State, Target};
@@ -0,0 +1,101 @@
+//! The `targets` table.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// `curstate` of a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    Running,
+    Halted,
+    Reset,
+    DebugRunning,
+    Unknown,
+    /// Anything newer OpenOCD versions add.
+    Other(String),
+}
+
+impl State {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "running" => State::Running,
+            "halted" => State::Halted,
+            "reset" => State::Reset,
+            "debug-running" => State::DebugRunning,
+            "unknown" => State::Unknown,
+            other => State::Other(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            State::Running => "running",
+            State::Halted => "halted",
+            State::Reset => "reset",
+            State::DebugRunning => "debug-running",
+            State::Unknown => "unknown",
+            State::Other(s) => s,
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One row of `targets`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub index: usize,
+    /// Marked `*`, the one commands apply to.
+    pub current: bool,
+    /// E.g. `esp32.cpu0`.
+    pub name: String,
+    /// Driver, e.g. `esp32`.
+    pub kind: String,
+    pub endian: String,
+    pub tap: String,
+    pub state: State,
+}
+
+impl Target {
+    /// Parse `targets` output:
+    ///
+    /// ```text
+    ///     TargetName         Type       Endian TapName            State
+    /// --  ------------------ ---------- ------ ------------------ ------------
+    ///  0* esp32.cpu0         esp32      little esp32.cpu0         halted
+    ///  1  esp32.cpu1         esp32      little esp32.cpu1         halted
+    /// ```
+    pub fn parse_table(text: &str) -> Result<Vec<Target>> {
+        let rows = text
+            .lines()
+            .skip_while(|line| !line.trim_start().starts_with("--"))
+            .skip(1)
+            .filter(|line| !line.trim().is_empty());
+        rows.map(Self::parse_row).collect()
+    }
+
+    fn parse_row(line: &str) -> Result<Target> {
+        let bad = || Error::protocol(format!("bad `targets` row: {:?}", line));
+        let mut words = line.split_whitespace();
+        let index = words.next().ok_or_else(bad)?;
+        let (index, current) = match index.strip_suffix('*') {
+            Some(index) => (index, true),
+            None => (index, false),
+        };
+        let mut next = || words.next().map(str::to_string).ok_or_else(bad);
+        Ok(Target {
+            index: index.parse().map_err(|_| bad())?,
+            current,
+            name: next()?,
+            kind: next()?,
+            endian: next()?,
+            tap: next()?,
+            state: State::parse(&next()?),
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use pulse_openocd::{Client, Error, State, Target, TERMINATOR};
+
+const WRAP_HEAD: &str = "format \"%d %s\" [catch {capture \"";
+const WRAP_TAIL: &str = "\"} r] $r";
+
+/// Fake OpenOCD: one target, a sparse byte memory and a log of the
+/// commands it ran. Replies go out a few bytes at a time so the client
+/// has to reassemble them.
+struct Fake {
+    memory: HashMap<u64, u8>,
+    halted: bool,
+    pc: u64,
+    seen: Vec<String>,
+}
+
+impl Fake {
+    fn new() -> Self {
+        Self {
+            memory: HashMap::new(),
+            halted: false,
+            pc: 0x400d_1000,
+            seen: Vec::new(),
+        }
+    }
+
+    fn eval(&mut self, script: &str) -> String {
+        match script
+            .strip_prefix(WRAP_HEAD)
+            .and_then(|s| s.strip_suffix(WRAP_TAIL))
+        {
+            Some(quoted) => match self.command(&unescape(quoted)) {
+                Ok(out) => format!("0 {}", out),
+                Err(message) => format!("1 {}", message),
+            },
+            None if script == "version" => "Open On-Chip Debugger v0.12.0-esp32-20240318".into(),
+            None => format!("invalid command name \"{}\"", script),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        self.seen.push(command.to_string());
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let num = |s: &str| u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap();
+        match words.as_slice() {
+            ["halt"] => {
+                self.halted = true;
+                Ok(format!(
+                    "[esp32.cpu0] Target halted, PC={:#010X}\n",
+                    self.pc
+                ))
+            }
+            ["resume", rest @ ..] => {
+                if let [addr] = rest {
+                    self.pc = num(addr);
+                }
+                self.halted = false;
+                Ok(String::new())
+            }
+            ["reset", "halt"] => {
+                self.halted = true;
+                self.pc = 0x4000_0400;
+                Ok(String::new())
+            }
+            ["targets"] => {
+                let state = if self.halted { "halted" } else { "running" };
+                Ok(format!(
+                    "    TargetName         Type       Endian TapName            State       \n\
+                     --  ------------------ ---------- ------ ------------------ ------------\n \
+                     0* esp32.cpu0         esp32      little esp32.cpu0         {}\n \
+                     1  esp32.cpu1         esp32      little esp32.cpu1         {}\n",
+                    state, state
+                ))
+            }
+            ["mww", addr, value] if self.halted => {
+                let value = num(value) as u32;
+                for (i, b) in value.to_le_bytes().into_iter().enumerate() {
+                    self.memory.insert(num(addr) + i as u64, b);
+                }
+                Ok(String::new())
+            }
+            [dump @ ("mdw" | "mdb"), addr, count] if self.halted => {
+                let (width, per_line) = if *dump == "mdw" { (4, 8) } else { (1, 16) };
+                let count: u64 = count.parse().unwrap();
+                let mut out = String::new();
+                for i in 0..count {
+                    let at = num(addr) + i * width;
+                    if i % per_line == 0 {
+                        out.push_str(&format!("{:#010x}:", at));
+                    }
+                    let mut value = 0u64;
+                    for b in (0..width).rev() {
+                        value = value << 8 | u64::from(*self.memory.get(&(at + b)).unwrap_or(&0));
+                    }
+                    out.push_str(&format!(" {:0w$x}", value, w = width as usize * 2));
+                    if i % per_line == per_line - 1 || i == count - 1 {
+                        out.push_str(" \n");
+                    }
+                }
+                Ok(out)
+            }
+            ["mww", ..] | ["mdw", ..] | ["mdb", ..] => {
+                Err(format!("Target not halted\nin procedure '{}'", words[0]))
+            }
+            ["write_memory", addr, "8", ..] => {
+                let list = command.split_once('{').unwrap().1.trim_end_matches('}');
+                for (i, b) in list.split_whitespace().enumerate() {
+                    self.memory.insert(num(addr) + i as u64, num(b) as u8);
+                }
+                Ok(String::new())
+            }
+            ["program", ..] => Ok("** Programming Finished **\n** Verified OK **\n".into()),
+            _ => Err(format!("invalid command name \"{}\"", words[0])),
+        }
+    }
+}
+
+fn unescape(quoted: &str) -> String {
+    let mut out = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' { chars.next().unwrap() } else { c });
+    }
+    out
+}
+
+fn serve(fake: Fake) -> (Client, Arc<Mutex<Fake>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let fake = Arc::new(Mutex::new(fake));
+    let shared = Arc::clone(&fake);
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut pending = Vec::new();
+        let mut chunk = [0; 256];
+        loop {
+            match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => pending.extend_from_slice(&chunk[..n]),
+            }
+            while let Some(end) = pending.iter().position(|&b| b == TERMINATOR) {
+                let script: Vec<u8> = pending.drain(..=end).take(end).collect();
+                let mut reply = shared
+                    .lock()
+                    .unwrap()
+                    .eval(&String::from_utf8(script).unwrap())
+                    .into_bytes();
+                reply.push(TERMINATOR);
+                for piece in reply.chunks(7) {
+                    stream.write_all(piece).unwrap();
+                    stream.flush().unwrap();
+                }
+            }
+        }
+    });
+
+    (Client::new(TcpStream::connect(addr).unwrap()), fake)
+}
+
+#[test]
+fn parses_the_targets_table() {
+    let text = "    TargetName         Type       Endian TapName            State       \n\
+                --  ------------------ ---------- ------ ------------------ ------------\n \
+                0  esp32.cpu0         esp32      little esp32.cpu0         running\n \
+                1* esp32.cpu1         esp32      little esp32.cpu1         debug-running\n";
+    let targets = Target::parse_table(text).unwrap();
+    assert_eq!(targets.len(), 2);
+    assert_eq!((targets[0].index, targets[0].current), (0, false));
+    assert_eq!(targets[1].name, "esp32.cpu1");
+    assert_eq!(targets[1].kind, "esp32");
+    assert_eq!(targets[1].state, State::DebugRunning);
+    assert!(targets[1].current);
+    assert!(Target::parse_table("--  ----\n 0* esp32.cpu0\n").is_err());
+}
+
+#[test]
+fn halt_resume_and_state() {
+    let (mut ocd, fake) = serve(Fake::new());
+    assert!(ocd.version().unwrap().starts_with("Open On-Chip Debugger"));
+    assert_eq!(ocd.current_target().unwrap().state, State::Running);
+
+    ocd.halt().unwrap();
+    let targets = ocd.targets().unwrap();
+    assert_eq!(targets.len(), 2);
+    assert!(targets.iter().all(|t| t.state == State::Halted));
+
+    ocd.resume(Some(0x400d_2000)).unwrap();
+    assert_eq!(ocd.current_target().unwrap().state, State::Running);
+    ocd.reset_halt().unwrap();
+    assert_eq!(ocd.current_target().unwrap().state, State::Halted);
+
+    let fake = fake.lock().unwrap();
+    assert_eq!(fake.pc, 0x4000_0400);
+    assert!(fake.seen.contains(&"resume 0x400d2000".to_string()));
+}
+
+#[test]
+fn memory_round_trip() {
+    let (mut ocd, _) = serve(Fake::new());
+    ocd.halt().unwrap();
+
+    ocd.write_u32(0x3ffb_0008, 0xdead_beef).unwrap();
+    assert_eq!(ocd.read_u32(0x3ffb_0008).unwrap(), 0xdead_beef);
+
+    // more than one dump line each
+    let data: Vec<u8> = (0..40).collect();
+    ocd.write_memory(0x3ffb_1000, &data).unwrap();
+    assert_eq!(ocd.read_memory(0x3ffb_1000, 40).unwrap(), data);
+    let words = ocd.read_u32s(0x3ffb_1000, 10).unwrap();
+    assert_eq!(words[0], 0x0302_0100);
+    assert_eq!(words[9], 0x2726_2524);
+}
+
+#[test]
+fn command_errors_carry_the_message() {
+    let (mut ocd, _) = serve(Fake::new());
+    match ocd.write_u32(0x3ffb_0008, 1) {
+        Err(Error::Command { command, message }) => {
+            assert_eq!(command, "mww 0x3ffb0008 0x1");
+            assert!(message.starts_with("Target not halted"), "{}", message);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(
+        ocd.exec("esp appimage_offset"),
+        Err(Error::Command { .. })
+    ));
+    // the connection is still usable afterwards
+    ocd.halt().unwrap();
+}
+
+#[test]
+fn paths_are_quoted_as_one_word() {
+    let (mut ocd, fake) = serve(Fake::new());
+    ocd.program("/tmp/my build/ghost[1]{x}.bin", Some(0x10000), true)
+        .unwrap();
+    assert_eq!(
+        fake.lock().unwrap().seen.last().unwrap(),
+        r#"program "/tmp/my build/ghost\[1\]\{x\}.bin" verify 0x10000"#
+    );
+}
+
+#[test]
+fn closed_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || drop(listener.accept().unwrap()));
+    let mut ocd = Client::new(TcpStream::connect(addr).unwrap());
+    assert!(matches!(ocd.halt(), Err(Error::Closed) | Err(Error::Io(_))));
+}
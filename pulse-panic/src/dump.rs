@@ -0,0 +1,307 @@
+//! Panic dumps as ESP-IDF's panic handler prints them.
+//!
+//! ```text
+//! Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled.
+//!
+//! Core  0 register dump:
+//! PC      : 0x400d0400  PS      : 0x00060530  A0      : 0x800d01c3  A1      : 0x3ffe1e60
+//! ...
+//! EXCCAUSE: 0x0000001c  EXCVADDR: 0x00000000  LBEG    : 0x4000c2e0  LEND    : 0x4000c2f6
+//!
+//! Backtrace: 0x400d0400:0x3ffe1e60 0x400d01c0:0x3ffe1ec0 |<-CORRUPTED
+//!
+//! ELF file SHA256: 0123456789abcdef
+//!
+//! Rebooting...
+//! ```
+//!
+//! A Rust `panic!` prints `thread 'main' panicked at src/main.rs:42:5:`
+//! and its message first, then `abort() was called at PC ...` and the
+//! backtrace, without a register dump.
+
+use pulse_log::{strip_ansi, LogLine};
+use sha2::{Digest, Sha256};
+
+/// Why the panic handler ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cause {
+    /// `Guru Meditation Error`: an unhandled CPU exception such as
+    /// `LoadProhibited`, or a watchdog like `Interrupt wdt timeout on CPU1`.
+    Exception(String),
+    /// `abort()`, which is where a Rust panic ends up.
+    Abort { pc: u64 },
+    /// FreeRTOS' stack overflow check fired for `task`.
+    StackOverflow { task: String },
+    /// Only the Rust panic message was seen.
+    Panic,
+}
+
+/// `thread 'main' panicked at src/main.rs:42:5:` and the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustPanic {
+    pub thread: Option<String>,
+    pub location: Option<String>,
+    pub message: String,
+}
+
+/// One panic, as far as it was printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicDump {
+    pub cause: Cause,
+    pub core: Option<u32>,
+    pub panic: Option<RustPanic>,
+    /// The register dump in print order, names as printed (`PC`, `A0`,
+    /// `EXCCAUSE`...).
+    pub registers: Vec<(String, u32)>,
+    /// `(pc, sp)` pairs, the first where it happened. The panic handler
+    /// already moved return addresses back onto their `callN`.
+    pub backtrace: Vec<(u64, u64)>,
+    /// The walk stopped at a frame that did not make sense.
+    pub corrupted: bool,
+    /// Leading hex digits of the ELF's SHA-256, as the app image recorded it.
+    pub elf_sha256: Option<String>,
+    /// The lines of the dump, colors removed.
+    pub text: Vec<String>,
+}
+
+impl PanicDump {
+    fn new(cause: Cause) -> Self {
+        Self {
+            cause,
+            core: None,
+            panic: None,
+            registers: Vec::new(),
+            backtrace: Vec::new(),
+            corrupted: false,
+            elf_sha256: None,
+            text: Vec::new(),
+        }
+    }
+
+    /// A register of the dump by name, ignoring case.
+    pub fn register(&self, name: &str) -> Option<u32> {
+        self.registers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, value)| value)
+    }
+
+    /// Whether `elf` is the image that panicked. `None` when the dump did
+    /// not say.
+    pub fn elf_matches(&self, elf: &[u8]) -> Option<bool> {
+        let printed = self.elf_sha256.as_deref()?.to_ascii_lowercase();
+        let digest: String = Sha256::digest(elf)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(!printed.is_empty() && digest.starts_with(&printed))
+    }
+}
+
+/// Picks panic dumps out of a serial log, one line at a time.
+///
+/// A dump ends at `Rebooting...` or `CPU halted.`, at the start of a
+/// core dump printed to the UART, at the ROM's boot banner, at the next
+/// log line, or with [`Parser::finish`] at the end of the log.
+#[derive(Debug, Default, Clone)]
+pub struct Parser {
+    current: Option<PanicDump>,
+    /// The panic location line ended in `:`, the message follows.
+    message_next: bool,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one line. Returns a dump when this line completed one.
+    pub fn line(&mut self, text: &str) -> Option<PanicDump> {
+        let line = strip_ansi(text);
+        let line = line.trim_end_matches(['\r', '\n']);
+        let trimmed = line.trim();
+
+        if self.message_next && !trimmed.is_empty() {
+            self.message_next = false;
+            if let Some(panic) = self.current.as_mut().and_then(|d| d.panic.as_mut()) {
+                panic.message = trimmed.to_string();
+                self.push_text(line);
+                return None;
+            }
+        }
+
+        if let Some(start) = start(trimmed) {
+            // a panic! and the abort() it ends in are one dump
+            let done = match &mut self.current {
+                Some(dump) if dump.backtrace.is_empty() => {
+                    merge(dump, start);
+                    None
+                }
+                _ => {
+                    let done = self.finish();
+                    self.current = Some(start);
+                    done
+                }
+            };
+            self.message_next =
+                trimmed.ends_with(':') && self.current.as_ref().is_some_and(|d| d.panic.is_some());
+            self.push_text(line);
+            return done;
+        }
+
+        let dump = self.current.as_mut()?;
+        if is_end(trimmed) {
+            if matches!(trimmed, "Rebooting..." | "CPU halted.") {
+                dump.text.push(line.to_string());
+            }
+            return self.finish();
+        }
+        if LogLine::parse(line).is_some() {
+            return self.finish();
+        }
+        dump.text.push(line.to_string());
+
+        if let Some(rest) = trimmed.strip_prefix("Backtrace:") {
+            dump.corrupted |= rest.contains("CORRUPTED");
+            dump.backtrace.extend(
+                rest.split_whitespace()
+                    .filter_map(|pair| pair.split_once(':'))
+                    .filter_map(|(pc, sp)| Some((hex(pc)?, hex(sp)?))),
+            );
+        } else if let Some(sha) = trimmed.strip_prefix("ELF file SHA256:") {
+            dump.elf_sha256 = Some(sha.trim().to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("Core ") {
+            if let Some(core) = rest.strip_suffix("register dump:") {
+                dump.core = core.trim().parse().ok().or(dump.core);
+            }
+        } else {
+            dump.registers.extend(registers(trimmed));
+        }
+        None
+    }
+
+    /// The dump in progress at the end of the log, if any.
+    pub fn finish(&mut self) -> Option<PanicDump> {
+        self.message_next = false;
+        let mut dump = self.current.take()?;
+        while dump.text.last().is_some_and(|l| l.trim().is_empty()) {
+            dump.text.pop();
+        }
+        Some(dump)
+    }
+
+    fn push_text(&mut self, line: &str) {
+        if let Some(dump) = &mut self.current {
+            dump.text.push(line.to_string());
+        }
+    }
+}
+
+/// Every dump in a recorded log.
+pub fn parse_dumps(text: &str) -> Vec<PanicDump> {
+    let mut parser = Parser::new();
+    let mut dumps: Vec<PanicDump> = text.lines().filter_map(|l| parser.line(l)).collect();
+    dumps.extend(parser.finish());
+    dumps
+}
+
+/// The line that opens a dump, as the dump it opens.
+fn start(line: &str) -> Option<PanicDump> {
+    if let Some(rest) = line.strip_prefix("Guru Meditation Error:") {
+        // `Core  0 panic'ed (LoadProhibited). Exception was unhandled.`
+        let core = rest
+            .trim_start()
+            .strip_prefix("Core")
+            .and_then(|r| r.split_whitespace().next())
+            .and_then(|n| n.parse().ok());
+        let reason = rest
+            .split_once('(')
+            .and_then(|(_, r)| r.rsplit_once(')'))
+            .map_or(rest.trim(), |(reason, _)| reason);
+        let mut dump = PanicDump::new(Cause::Exception(reason.to_string()));
+        dump.core = core;
+        return Some(dump);
+    }
+    if let Some(rest) = line.strip_prefix("abort() was called at PC ") {
+        // `0x400d5c3b on core 0`
+        let mut words = rest.split_whitespace();
+        let pc = hex(words.next()?)?;
+        let mut dump = PanicDump::new(Cause::Abort { pc });
+        dump.core = words.nth(2).and_then(|n| n.parse().ok());
+        return Some(dump);
+    }
+    if let Some(rest) = line.strip_prefix("***ERROR*** A stack overflow in task ") {
+        let task = rest.strip_suffix(" has been detected.").unwrap_or(rest);
+        return Some(PanicDump::new(Cause::StackOverflow {
+            task: task.to_string(),
+        }));
+    }
+    let (head, tail) = line.split_once(" panicked at ")?;
+    let thread = head
+        .strip_prefix("thread '")
+        .and_then(|t| t.strip_suffix('\''))
+        .map(str::to_string);
+    let (location, message) = match tail.strip_prefix('\'') {
+        // before Rust 1.73: `panicked at 'message', src/main.rs:42:5`
+        Some(quoted) => match quoted.rsplit_once("', ") {
+            Some((message, location)) => (Some(location), message),
+            None => (None, quoted),
+        },
+        None => (Some(tail.trim_end_matches(':')), ""),
+    };
+    let mut dump = PanicDump::new(Cause::Panic);
+    dump.panic = Some(RustPanic {
+        thread,
+        location: location.map(str::to_string),
+        message: message.to_string(),
+    });
+    Some(dump)
+}
+
+/// Fold a later opening line into the dump it belongs to.
+fn merge(dump: &mut PanicDump, later: PanicDump) {
+    if later.cause != Cause::Panic {
+        dump.cause = later.cause;
+    }
+    dump.core = later.core.or(dump.core);
+    if later.panic.is_some() {
+        dump.panic = later.panic;
+    }
+}
+
+fn is_end(line: &str) -> bool {
+    matches!(line, "Rebooting..." | "CPU halted.")
+        || line.contains("CORE DUMP START")
+        || line.starts_with("ets ")
+        || line.starts_with("rst:0x")
+}
+
+/// `PC      : 0x400d0400  PS      : 0x00060530 ...`: a name, a colon
+/// with or without padding before it, then the value. Empty for lines
+/// that are anything else.
+fn registers(line: &str) -> Vec<(String, u32)> {
+    let mut out = Vec::new();
+    let mut words = line.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        let name = match word.strip_suffix(':') {
+            Some(name) => name,
+            None if words.next_if_eq(&":").is_some() => word,
+            None => return Vec::new(),
+        };
+        let value = words.next().filter(|w| w.starts_with("0x")).and_then(hex);
+        match value {
+            Some(value) if !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric()) => {
+                out.push((name.to_string(), value as u32))
+            }
+            _ => return Vec::new(),
+        }
+    }
+    out
+}
+
+fn hex(text: &str) -> Option<u64> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))?;
+    u64::from_str_radix(digits, 16).ok()
+}
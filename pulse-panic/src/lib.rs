@@ -0,0 +1,31 @@
+//! ESP-IDF panic dumps from ghost-trigger's serial log, symbolized.
+//!
+//! When the firmware panics, the panic handler prints a `Guru Meditation
+//! Error` or a Rust panic message, a register dump and a line of
+//! `Backtrace: 0x400d1234:0x3ffb5e40 ...` pairs, which otherwise go
+//! through `addr2line` one by one. [`Parser`] picks the dumps out of the
+//! log as it streams by and [`CrashReport`] resolves every address against
+//! the ELF's DWARF: demangled Rust names, file and line, and the calls
+//! inlined at each address.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use pulse_panic::{parse_dumps, CrashReport};
+//!
+//! let elf = std::fs::read("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let debug = pulse_dwarf::DebugInfo::parse(&elf)?;
+//! let log = std::fs::read_to_string("serial.log")?;
+//! for dump in parse_dumps(&log) {
+//!     let mut report = CrashReport::new(dump, Some(&debug));
+//!     report.check_elf(&elf);
+//!     print!("{}", report);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod dump;
+pub mod report;
+
+pub use dump::{parse_dumps, Cause, PanicDump, Parser, RustPanic};
+pub use report::{symbolize, write_backtrace, CrashReport, Frame};
@@ -0,0 +1,196 @@
+//! A panic dump with its backtrace symbolized against the ELF.
+
+use std::fmt;
+
+use pulse_dwarf::DebugInfo;
+use pulse_xtensa::{exccause_name, Ps};
+
+use crate::dump::{Cause, PanicDump};
+
+/// One function of the backtrace. An address in inlined code gives one
+/// frame per inlined call, all with the same `index`, `pc` and `sp`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Position in the printed backtrace, 0 where it happened.
+    pub index: usize,
+    pub pc: u64,
+    pub sp: u64,
+    pub function: Option<String>,
+    /// From the start of the function's symbol; `None` for inlined calls.
+    pub offset: Option<u64>,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub inlined: bool,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x} ", self.pc)?;
+        f.write_str(self.function.as_deref().unwrap_or("??"))?;
+        if let Some(offset) = self.offset.filter(|&o| o != 0) {
+            write!(f, "+{:#x}", offset)?;
+        }
+        if let Some(file) = &self.file {
+            write!(f, " at {}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        if self.inlined {
+            f.write_str(" (inlined)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The frames of backtrace entry `index`: one per inlined call at `pc`,
+/// then the function they are in. A single frame with the address only
+/// when DWARF cannot describe it or there is no `debug`.
+pub fn symbolize(index: usize, pc: u64, sp: u64, debug: Option<&DebugInfo>) -> Vec<Frame> {
+    let raw = Frame {
+        index,
+        pc,
+        sp,
+        function: None,
+        offset: None,
+        file: None,
+        line: None,
+        column: None,
+        inlined: false,
+    };
+    let Some(debug) = debug else {
+        return vec![raw];
+    };
+    // a frame DWARF cannot describe still has its address
+    let source = debug.source_frames(pc).unwrap_or_default();
+    if source.is_empty() {
+        return vec![raw];
+    }
+    source
+        .into_iter()
+        .map(|s| Frame {
+            offset: match s.inlined {
+                true => None,
+                false => debug.function_at(pc).map(|(_, offset)| offset),
+            },
+            function: s.function,
+            file: s.file,
+            line: s.line,
+            column: s.column,
+            inlined: s.inlined,
+            ..raw.clone()
+        })
+        .collect()
+}
+
+/// `  #N  frame` rows, the inlined calls of one entry under its number.
+pub fn write_backtrace(f: &mut fmt::Formatter<'_>, frames: &[Frame]) -> fmt::Result {
+    let mut last = None;
+    for frame in frames {
+        match last == Some(frame.index) {
+            true => write!(f, "      ")?,
+            false => write!(f, "  #{:<3}", frame.index)?,
+        }
+        writeln!(f, "{}", frame)?;
+        last = Some(frame.index);
+    }
+    Ok(())
+}
+
+/// What to hand an engineer instead of the raw dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    pub dump: PanicDump,
+    pub frames: Vec<Frame>,
+    /// Whether the ELF symbolized against is the one that panicked, see
+    /// [`CrashReport::check_elf`].
+    pub elf_matches: Option<bool>,
+}
+
+impl CrashReport {
+    /// Symbolize every backtrace address, innermost inlined call first.
+    /// Without debug info the frames carry addresses only.
+    pub fn new(dump: PanicDump, debug: Option<&DebugInfo>) -> Self {
+        let frames = dump
+            .backtrace
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &(pc, sp))| symbolize(index, pc, sp, debug))
+            .collect();
+        Self {
+            dump,
+            frames,
+            elf_matches: None,
+        }
+    }
+
+    /// Compare `elf` with the SHA-256 the dump printed.
+    pub fn check_elf(&mut self, elf: &[u8]) {
+        self.elf_matches = self.dump.elf_matches(elf);
+    }
+
+    /// One line: what happened and where.
+    pub fn summary(&self) -> String {
+        let core = |core: Option<u32>| core.map_or(String::new(), |c| format!(" on core {}", c));
+        let mut out = match &self.dump.cause {
+            Cause::Exception(reason) => {
+                format!("Guru Meditation{}: {}", core(self.dump.core), reason)
+            }
+            Cause::Abort { pc } => format!("abort() at {:#010x}{}", pc, core(self.dump.core)),
+            Cause::StackOverflow { task } => format!("stack overflow in task {}", task),
+            Cause::Panic => "panic".to_string(),
+        };
+        if let Some(frame) = self.frames.iter().find(|f| f.function.is_some()) {
+            out.push_str(" in ");
+            out.push_str(frame.function.as_deref().unwrap_or_default());
+        }
+        out
+    }
+
+    /// `EXCCAUSE` with its name.
+    pub fn exccause(&self) -> Option<(u32, Option<&'static str>)> {
+        let cause = self.dump.register("EXCCAUSE")?;
+        Some((cause, exccause_name(cause)))
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        if let Some(panic) = &self.dump.panic {
+            let thread = panic
+                .thread
+                .as_deref()
+                .map_or(String::new(), |t| format!("thread '{}' ", t));
+            let location = panic.location.as_deref().unwrap_or("??");
+            writeln!(f, "  {}panicked at {}: {}", thread, location, panic.message)?;
+        }
+        if let Some((cause, name)) = self.exccause() {
+            write!(f, "  EXCCAUSE {} {}", cause, name.unwrap_or("(unknown)"))?;
+            match self.dump.register("EXCVADDR") {
+                Some(vaddr) => writeln!(f, ", EXCVADDR {:#010x}", vaddr)?,
+                None => writeln!(f)?,
+            }
+        }
+        if let Some(ps) = self.dump.register("PS") {
+            writeln!(f, "  PS {:#010x} {}", ps, Ps(ps))?;
+        }
+        if self.elf_matches == Some(false) {
+            writeln!(
+                f,
+                "  warning: the ELF is not the one that panicked (SHA256 {}), symbols may be wrong",
+                self.dump.elf_sha256.as_deref().unwrap_or_default()
+            )?;
+        }
+        writeln!(f, "backtrace:")?;
+        write_backtrace(f, &self.frames)?;
+        if self.dump.corrupted {
+            writeln!(f, "  |<-CORRUPTED")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use pulse_dwarf::DebugInfo;
+use pulse_mock::fixture;
+use pulse_panic::{parse_dumps, Cause, CrashReport, Parser};
+use sha2::{Digest, Sha256};
+
+/// What ESP-IDF v5 prints for a load from NULL in `Detector::cycle`,
+/// called from `Mailbox::take` inlined into `main`.
+fn guru_meditation(elf_sha256: &str) -> String {
+    format!(
+        "I (3338) pulse_core::sink: System secure. [Cycle: 3]\r
+Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled.\r
+\r
+Core  0 register dump:\r
+PC      : 0x400d0400  PS      : 0x00060530  A0      : 0x800d01e7  A1      : 0x3ffe1e60  \r
+A2      : 0x00000000  A3      : 0x3ffb0000  A4      : 0x00000001  A5      : 0x00000000  \r
+EXCCAUSE: 0x0000001c  EXCVADDR: 0x00000000  LBEG    : 0x4000c2e0  LEND    : 0x4000c2f6  \r
+\r
+\r
+Backtrace: 0x400d0400:0x3ffe1e60 0x{:08x}:0x3ffe1ec0 0x400d0120:0x3ffe1f00 0x40081234:0x3ffe1f20 |<-CORRUPTED\r
+\r
+\r
+ELF file SHA256: {}\r
+\r
+Rebooting...\r
+ets Jun  8 2016 00:22:57\r
+",
+        fixture::MAIN_TAKE + 4,
+        elf_sha256
+    )
+}
+
+#[test]
+fn guru_meditation_symbolized() {
+    let elf = fixture::ghost_trigger();
+    let sha: String = Sha256::digest(&elf)
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let dumps = parse_dumps(&guru_meditation(&sha));
+    assert_eq!(dumps.len(), 1);
+    let dump = &dumps[0];
+    assert_eq!(dump.cause, Cause::Exception("LoadProhibited".into()));
+    assert_eq!(dump.core, Some(0));
+    assert_eq!(dump.register("pc"), Some(0x400d_0400));
+    assert_eq!(dump.register("EXCCAUSE"), Some(28));
+    assert_eq!(dump.registers.len(), 12);
+    assert_eq!(dump.backtrace.len(), 4);
+    assert!(dump.corrupted);
+    assert_eq!(
+        dump.text.first().unwrap(),
+        "Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled."
+    );
+    assert_eq!(dump.text.last().unwrap(), "Rebooting...");
+
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let mut report = CrashReport::new(dump.clone(), Some(&debug));
+    report.check_elf(&elf);
+    assert_eq!(report.elf_matches, Some(true));
+    assert_eq!(
+        report.summary(),
+        "Guru Meditation on core 0: LoadProhibited in pulse_core::detector::Detector::cycle"
+    );
+    let functions: Vec<(usize, Option<&str>, bool)> = report
+        .frames
+        .iter()
+        .map(|f| (f.index, f.function.as_deref(), f.inlined))
+        .collect();
+    assert_eq!(
+        functions,
+        [
+            (0, Some("pulse_core::detector::Detector::cycle"), false),
+            (1, Some("pulse_core::mailbox::Mailbox::take"), true),
+            (1, Some("ghost_trigger::main"), false),
+            (2, Some("app_main"), false),
+            (3, None, false),
+        ]
+    );
+    assert_eq!(report.frames[2].offset, Some(0x64));
+    assert_eq!(report.frames[2].line, Some(49));
+
+    let text = report.to_string();
+    assert!(text.contains("  EXCCAUSE 28 LoadProhibited, EXCVADDR 0x00000000\n"));
+    assert!(text.contains("  PS 0x00060530 INTLEVEL=0 EXCM=1 UM=1 RING=0 OWB=5 CALLINC=2 WOE=1\n"));
+    assert!(text.contains(&format!(
+        "  #1  0x400d01e4 pulse_core::mailbox::Mailbox::take at {}/src/mailbox.rs:88 (inlined)\n      0x400d01e4 ghost_trigger::main+0x64 at {}/src/main.rs:49:33\n",
+        fixture::PULSE_CORE_DIR,
+        fixture::COMP_DIR
+    )));
+    assert!(text.contains("  #3  0x40081234 ??\n  |<-CORRUPTED\n"));
+    assert!(!text.contains("warning"));
+
+    let mut stale = CrashReport::new(dump.clone(), Some(&debug));
+    stale.check_elf(b"some other build");
+    assert_eq!(stale.elf_matches, Some(false));
+    assert!(stale
+        .to_string()
+        .contains("warning: the ELF is not the one that panicked"));
+}
+
+#[test]
+fn rust_panic_and_its_abort_are_one_dump() {
+    let log = "\x1b[0;32mI (120) ghost_trigger: System altered!\x1b[0m
+thread 'main' panicked at src/main.rs:49:33:
+called `Option::unwrap()` on a `None` value
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+abort() was called at PC 0x400d01e4 on core 0
+
+
+Backtrace: 0x40081a2b:0x3ffe1e00 0x400d01e4:0x3ffe1ec0
+
+ELF file SHA256: 0123456789abcdef
+";
+    let dumps = parse_dumps(log);
+    assert_eq!(dumps.len(), 1);
+    let dump = &dumps[0];
+    assert_eq!(
+        dump.cause,
+        Cause::Abort {
+            pc: fixture::MAIN_TAKE + 4
+        }
+    );
+    assert_eq!(dump.core, Some(0));
+    let panic = dump.panic.as_ref().unwrap();
+    assert_eq!(panic.thread.as_deref(), Some("main"));
+    assert_eq!(panic.location.as_deref(), Some("src/main.rs:49:33"));
+    assert_eq!(panic.message, "called `Option::unwrap()` on a `None` value");
+    assert!(dump.registers.is_empty());
+    assert!(!dump.corrupted);
+
+    // no ELF at hand: addresses only
+    let report = CrashReport::new(dump.clone(), None);
+    assert_eq!(report.summary(), "abort() at 0x400d01e4 on core 0");
+    assert!(report.to_string().contains(
+        "  thread 'main' panicked at src/main.rs:49:33: called `Option::unwrap()` on a `None` value\n"
+    ));
+    assert!(report.to_string().ends_with("  #1  0x400d01e4 ??\n"));
+    assert_eq!(
+        report.dump.elf_matches(&fixture::ghost_trigger()),
+        Some(false)
+    );
+}
+
+#[test]
+fn dumps_in_a_running_log() {
+    let mut parser = Parser::new();
+    let mut dumps = Vec::new();
+    for line in [
+        "thread 'main' panicked at 'index out of bounds', src/audit.rs:12:9",
+        "***ERROR*** A stack overflow in task esp_timer has been detected.",
+        "",
+        "Backtrace: 0x40081a2b:0x3ffe1e00 0x400d0400:0x3ffe1e60",
+        "E (10) boot: next boot",
+        "Guru Meditation Error: Core  1 panic'ed (Interrupt wdt timeout on CPU1). ",
+        "Backtrace:0x400d0120:0x3ffe1f00",
+        "Guru Meditation Error: Core  0 panic'ed (Double exception). ",
+    ] {
+        dumps.extend(parser.line(line));
+    }
+    assert_eq!(dumps.len(), 2);
+    assert_eq!(
+        dumps[0].cause,
+        Cause::StackOverflow {
+            task: "esp_timer".into()
+        }
+    );
+    let panic = dumps[0].panic.as_ref().unwrap();
+    assert_eq!(panic.message, "index out of bounds");
+    assert_eq!(panic.location.as_deref(), Some("src/audit.rs:12:9"));
+    assert_eq!(dumps[0].backtrace.len(), 2);
+    assert_eq!(
+        dumps[1].cause,
+        Cause::Exception("Interrupt wdt timeout on CPU1".into())
+    );
+    assert_eq!(dumps[1].core, Some(1));
+    assert_eq!(
+        dumps[1].backtrace,
+        [(fixture::APP_MAIN + 0x20, 0x3ffe_1f00)]
+    );
+
+    let last = parser.finish().unwrap();
+    assert_eq!(last.cause, Cause::Exception("Double exception".into()));
+    assert!(last.backtrace.is_empty());
+    assert_eq!(parser.finish(), None);
+}
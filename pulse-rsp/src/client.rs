@@ -0,0 +1,422 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::packet::{self, Conn, Incoming};
+use crate::stop::StopReply;
+
+/// OpenOCD's default GDB port.
+pub const DEFAULT_PORT: u16 = 3333;
+
+/// Retransmits before giving up on a NAKed packet.
+const MAX_RETRIES: usize = 3;
+
+/// `Z`/`z` point types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointKind {
+    Software = 0,
+    Hardware = 1,
+    WriteWatch = 2,
+    ReadWatch = 3,
+    AccessWatch = 4,
+}
+
+/// One `vCont` action, optionally limited to a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    Continue(Option<u64>),
+    Step(Option<u64>),
+}
+
+/// Bits of `qSupported` we care about, and the `vCont?` actions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Features {
+    pub packet_size: Option<usize>,
+    pub no_ack_mode: bool,
+    pub target_xml: bool,
+    /// Actions `vCont?` listed, like `c` and `s`; empty without `vCont`.
+    pub vcont: Vec<String>,
+    /// Everything the stub reported, verbatim.
+    pub raw: Vec<String>,
+}
+
+/// Blocking GDB remote client.
+pub struct Client<S = TcpStream> {
+    conn: Conn<S>,
+    no_ack: bool,
+    packet_size: usize,
+    console: Vec<u8>,
+}
+
+impl Client<TcpStream> {
+    /// Connect to a GDB server, e.g. OpenOCD on `localhost:3333`.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+
+    /// Read timeout for every reply, `None` blocks forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.conn.get_ref().set_read_timeout(timeout)?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            conn: Conn::new(stream),
+            no_ack: false,
+            packet_size: 256,
+            console: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.conn.get_ref()
+    }
+
+    pub fn is_no_ack(&self) -> bool {
+        self.no_ack
+    }
+
+    /// `qSupported` exchange, then switch to no-ack mode if offered and
+    /// ask `vCont?` which resume actions the stub takes.
+    pub fn handshake(&mut self) -> Result<Features> {
+        let reply = self.request(b"qSupported:multiprocess-;swbreak+;hwbreak+;vContSupported+")?;
+        let mut features = Features::default();
+        for item in String::from_utf8_lossy(&reply).split(';') {
+            match item.split_once('=') {
+                Some(("PacketSize", size)) => {
+                    features.packet_size = usize::from_str_radix(size, 16).ok();
+                }
+                _ => match item {
+                    "QStartNoAckMode+" => features.no_ack_mode = true,
+                    "qXfer:features:read+" => features.target_xml = true,
+                    _ => {}
+                },
+            }
+            features.raw.push(item.to_string());
+        }
+        if let Some(size) = features.packet_size {
+            self.packet_size = size;
+        }
+        if features.no_ack_mode {
+            self.start_no_ack_mode()?;
+        }
+        features.vcont = self.vcont_actions()?;
+        Ok(features)
+    }
+
+    /// `vCont?`: the actions `vCont` accepts, empty if it is not supported.
+    /// `vContSupported+` in the `qSupported` reply is only the stub
+    /// echoing that GDB would ask.
+    pub fn vcont_actions(&mut self) -> Result<Vec<String>> {
+        let reply = match self.request(b"vCont?") {
+            Ok(reply) => reply,
+            Err(Error::Remote(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(match reply.strip_prefix(b"vCont") {
+            Some(actions) => String::from_utf8_lossy(actions)
+                .split(';')
+                .filter(|action| !action.is_empty())
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    pub fn start_no_ack_mode(&mut self) -> Result<()> {
+        self.expect_ok(b"QStartNoAckMode")?;
+        self.no_ack = true;
+        Ok(())
+    }
+
+    /// Send one packet and return the decoded reply body.
+    ///
+    /// `E NN` becomes [`Error::Remote`], an empty reply is returned as is.
+    pub fn request(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        self.send(payload)?;
+        let reply = self.receive()?;
+        if let Some(code) = error_code(&reply) {
+            return Err(Error::Remote(code));
+        }
+        Ok(reply)
+    }
+
+    fn expect_ok(&mut self, payload: &[u8]) -> Result<()> {
+        match self.request(payload)?.as_slice() {
+            b"OK" => Ok(()),
+            b"" => Err(Error::Unsupported(
+                String::from_utf8_lossy(payload).into_owned(),
+            )),
+            other => Err(Error::protocol(format!(
+                "expected OK, got {:?}",
+                String::from_utf8_lossy(other)
+            ))),
+        }
+    }
+
+    fn send(&mut self, payload: &[u8]) -> Result<()> {
+        for _ in 0..=MAX_RETRIES {
+            self.conn.write_packet(payload)?;
+            if self.no_ack {
+                return Ok(());
+            }
+            loop {
+                match self.conn.read()? {
+                    Incoming::Ack => return Ok(()),
+                    Incoming::Nak => break,
+                    // a reply to something else, or junk - not our ack
+                    Incoming::Packet { .. } | Incoming::Interrupt => {}
+                }
+            }
+        }
+        Err(Error::Nak)
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>> {
+        loop {
+            match self.conn.read()? {
+                Incoming::Packet { body, valid } => {
+                    if !self.no_ack {
+                        self.conn.write_raw(if valid { b"+" } else { b"-" })?;
+                    }
+                    if valid || self.no_ack {
+                        return packet::decode(&body);
+                    }
+                }
+                // stray acks for earlier packets
+                Incoming::Ack | Incoming::Nak | Incoming::Interrupt => {}
+            }
+        }
+    }
+
+    /// `m`: read `len` bytes, split into packet-sized requests.
+    pub fn read_memory(&mut self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let chunk = self.max_chunk(2, 20);
+        let mut out = Vec::with_capacity(len);
+        while out.len() < len {
+            let at = addr + out.len() as u64;
+            let want = chunk.min(len - out.len());
+            let reply = self.request(format!("m{:x},{:x}", at, want).as_bytes())?;
+            let bytes = packet::from_hex(&reply)?;
+            if bytes.is_empty() {
+                return Err(Error::protocol(format!("empty read at {:#x}", at)));
+            }
+            out.extend_from_slice(&bytes);
+        }
+        out.truncate(len);
+        Ok(out)
+    }
+
+    /// `M`: hex-encoded write.
+    pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> Result<()> {
+        let chunk = self.max_chunk(2, 24);
+        for (i, part) in data.chunks(chunk.max(1)).enumerate() {
+            let at = addr + (i * chunk) as u64;
+            let cmd = format!("M{:x},{:x}:{}", at, part.len(), packet::to_hex(part));
+            self.expect_ok(cmd.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// `X`: binary write, half the bytes of `M` on the wire.
+    pub fn write_memory_binary(&mut self, addr: u64, data: &[u8]) -> Result<()> {
+        // worst case every byte needs escaping
+        let chunk = self.max_chunk(2, 24);
+        for (i, part) in data.chunks(chunk.max(1)).enumerate() {
+            let at = addr + (i * chunk) as u64;
+            let mut cmd = format!("X{:x},{:x}:", at, part.len()).into_bytes();
+            cmd.extend(packet::escape(part));
+            self.expect_ok(&cmd)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_u32(&mut self, addr: u64) -> Result<u32> {
+        let bytes = self.read_memory(addr, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn write_u32(&mut self, addr: u64, value: u32) -> Result<()> {
+        self.write_memory(addr, &value.to_le_bytes())
+    }
+
+    /// `Z`: set a breakpoint or watchpoint. `len` is the kind field, the
+    /// instruction size for breakpoints or the watched length for watchpoints.
+    pub fn insert_point(&mut self, kind: BreakpointKind, addr: u64, len: usize) -> Result<()> {
+        self.expect_ok(format!("Z{},{:x},{:x}", kind as u8, addr, len).as_bytes())
+    }
+
+    /// `z`: remove a breakpoint or watchpoint.
+    pub fn remove_point(&mut self, kind: BreakpointKind, addr: u64, len: usize) -> Result<()> {
+        self.expect_ok(format!("z{},{:x},{:x}", kind as u8, addr, len).as_bytes())
+    }
+
+    /// `?`: why the target is stopped.
+    pub fn halt_reason(&mut self) -> Result<StopReply> {
+        let reply = self.request(b"?")?;
+        StopReply::parse(&reply)
+    }
+
+    /// `g`: all registers, raw.
+    pub fn read_registers(&mut self) -> Result<Vec<u8>> {
+        let reply = self.request(b"g")?;
+        packet::from_hex(&reply)
+    }
+
+    /// `p`: one register, raw target-endian bytes.
+    pub fn read_register(&mut self, reg: u32) -> Result<Vec<u8>> {
+        let reply = self.request(format!("p{:x}", reg).as_bytes())?;
+        if reply.is_empty() {
+            return Err(Error::Unsupported(format!("p{:x}", reg)));
+        }
+        packet::from_hex(&reply)
+    }
+
+    /// `P`: write one register.
+    pub fn write_register(&mut self, reg: u32, value: &[u8]) -> Result<()> {
+        self.expect_ok(format!("P{:x}={}", reg, packet::to_hex(value)).as_bytes())
+    }
+
+    /// `monitor <cmd>` through `qRcmd`, returns the console output.
+    pub fn monitor(&mut self, cmd: &str) -> Result<String> {
+        self.send(format!("qRcmd,{}", packet::to_hex(cmd.as_bytes())).as_bytes())?;
+        let mut output = Vec::new();
+        loop {
+            let reply = self.receive()?;
+            if let Some(code) = error_code(&reply) {
+                return Err(Error::Remote(code));
+            }
+            match reply.as_slice() {
+                b"OK" => break,
+                [b'O', hex @ ..] if !hex.is_empty() => output.extend(packet::from_hex(hex)?),
+                // some stubs answer with the output hex and no O prefix
+                hex => {
+                    output.extend(packet::from_hex(hex)?);
+                    break;
+                }
+            }
+        }
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// `c`: continue and block until the target stops.
+    pub fn cont(&mut self) -> Result<StopReply> {
+        self.send(b"c")?;
+        self.wait_stop()
+    }
+
+    /// `s`: single step.
+    pub fn step(&mut self) -> Result<StopReply> {
+        self.send(b"s")?;
+        self.wait_stop()
+    }
+
+    /// `vCont` with the given actions, e.g. step one thread and continue the rest.
+    pub fn vcont(&mut self, actions: &[Resume]) -> Result<StopReply> {
+        self.send(vcont_packet(actions).as_bytes())?;
+        self.wait_stop()
+    }
+
+    /// Start `c` without waiting, pair with [`Client::wait_stop`] or
+    /// [`Client::interrupt`].
+    pub fn resume(&mut self) -> Result<()> {
+        self.send(b"c")
+    }
+
+    /// Ctrl-C, the stop reply follows once the target halts.
+    pub fn interrupt(&mut self) -> Result<StopReply> {
+        self.conn.write_raw(&[0x03])?;
+        self.wait_stop()
+    }
+
+    /// Wait for the stop reply to a resume, collecting `O` console output.
+    pub fn wait_stop(&mut self) -> Result<StopReply> {
+        loop {
+            if let Some(stop) = self.poll_stop()? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// One packet of [`Client::wait_stop`]: the stop reply, or `None` for
+    /// console output. Lets a caller with a deadline look at the clock
+    /// while a chatty target keeps the connection busy.
+    pub fn poll_stop(&mut self) -> Result<Option<StopReply>> {
+        let reply = self.receive()?;
+        match reply.as_slice() {
+            [b'O', hex @ ..] if !hex.is_empty() => {
+                self.console.extend(packet::from_hex(hex)?);
+                Ok(None)
+            }
+            body => StopReply::parse(body).map(Some),
+        }
+    }
+
+    /// `D`: let the target run on without a debugger.
+    pub fn detach(&mut self) -> Result<()> {
+        self.expect_ok(b"D")
+    }
+
+    /// Console output the stub sent while the target was running.
+    pub fn take_console(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.console)
+    }
+
+    /// `qXfer:<object>:read:<annex>` until the stub says it is done.
+    pub fn read_xfer(&mut self, object: &str, annex: &str) -> Result<Vec<u8>> {
+        let chunk = self.max_chunk(1, 32);
+        let mut out = Vec::new();
+        loop {
+            let cmd = format!(
+                "qXfer:{}:read:{}:{:x},{:x}",
+                object,
+                annex,
+                out.len(),
+                chunk
+            );
+            let reply = self.request(cmd.as_bytes())?;
+            match reply.split_first() {
+                Some((b'm', data)) => out.extend_from_slice(data),
+                Some((b'l', data)) => {
+                    out.extend_from_slice(data);
+                    return Ok(out);
+                }
+                _ => return Err(Error::Unsupported(format!("qXfer:{}:read", object))),
+            }
+        }
+    }
+
+    /// Bytes of payload that fit in one packet at `per_byte` wire bytes each.
+    fn max_chunk(&self, per_byte: usize, overhead: usize) -> usize {
+        (self.packet_size.saturating_sub(overhead) / per_byte).max(1)
+    }
+}
+
+fn error_code(reply: &[u8]) -> Option<u8> {
+    match reply {
+        [b'E', a, b] => packet::parse_hex_u64(&[*a, *b]).ok().map(|c| c as u8),
+        _ => None,
+    }
+}
+
+pub(crate) fn vcont_packet(actions: &[Resume]) -> String {
+    let mut cmd = String::from("vCont");
+    for action in actions {
+        let (letter, thread) = match *action {
+            Resume::Continue(thread) => ('c', thread),
+            Resume::Step(thread) => ('s', thread),
+        };
+        cmd.push(';');
+        cmd.push(letter);
+        if let Some(thread) = thread {
+            cmd.push_str(&format!(":{:x}", thread));
+        }
+    }
+    cmd
+}
@@ -0,0 +1,33 @@
+//! GDB Remote Serial Protocol client for scripted injection.
+//!
+//! Speaks the subset of RSP that OpenOCD's GDB server (port 3333) offers for
+//! an ESP32: memory reads and writes, breakpoints and watchpoints, resume and
+//! stop replies. Injection campaigns that used to be `.gdb` scripts can be
+//! written against [`Client`] instead:
+//!
+//! ```no_run
+//! use pulse_rsp::Client;
+//!
+//! # fn main() -> pulse_rsp::Result<()> {
+//! let mut gdb = Client::connect(("localhost", pulse_rsp::DEFAULT_PORT))?;
+//! gdb.handshake()?;
+//! gdb.interrupt()?;
+//! let sequence = gdb.read_u32(0x3ffb_0008)?;
+//! gdb.write_u32(0x3ffb_000c, 1)?; // Signal::Threat
+//! gdb.write_u32(0x3ffb_0008, sequence.wrapping_add(1))?;
+//! gdb.resume()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Everything is blocking and generic over `Read + Write`, so tests run the
+//! client against an in-process stand-in server on a loopback socket.
+
+pub mod client;
+pub mod error;
+pub mod packet;
+pub mod stop;
+
+pub use client::{BreakpointKind, Client, Features, Resume, DEFAULT_PORT};
+pub use error::{Error, Result};
+pub use stop::{StopReason, StopReply, WatchKind};
@@ -0,0 +1,204 @@
+//! Packet framing: `$payload#cs`, binary escaping and run-length decoding.
+
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
+
+/// Characters that must be escaped inside a packet body.
+const ESCAPED: [u8; 4] = [b'$', b'#', b'}', b'*'];
+
+/// Modulo-256 sum of the payload bytes.
+pub fn checksum(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Frame `payload` (already escaped) as `$payload#cs`.
+pub fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 4);
+    out.push(b'$');
+    out.extend_from_slice(payload);
+    out.push(b'#');
+    out.extend_from_slice(format!("{:02x}", checksum(payload)).as_bytes());
+    out
+}
+
+/// Escape binary data for `X` writes and binary replies.
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for &b in data {
+        if ESCAPED.contains(&b) {
+            out.push(b'}');
+            out.push(b ^ 0x20);
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Undo `}` escaping and `*` run-length encoding of a received body.
+pub fn decode(body: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            b'}' => {
+                let b = *body
+                    .get(i + 1)
+                    .ok_or_else(|| Error::protocol("dangling escape"))?;
+                out.push(b ^ 0x20);
+                i += 2;
+            }
+            b'*' => {
+                let last = *out
+                    .last()
+                    .ok_or_else(|| Error::protocol("run-length without a byte to repeat"))?;
+                let count = body
+                    .get(i + 1)
+                    .ok_or_else(|| Error::protocol("dangling run-length"))?;
+                // repeat count is encoded as count + 29, on top of the one byte already there
+                let repeat = count
+                    .checked_sub(29)
+                    .ok_or_else(|| Error::protocol("bad run-length count"))?;
+                out.extend(std::iter::repeat_n(last, repeat as usize));
+                i += 2;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &[u8]) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(Error::protocol("odd-length hex"));
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    Error::protocol(format!("bad hex {:?}", String::from_utf8_lossy(pair)))
+                })
+        })
+        .collect()
+}
+
+/// Parse a big-endian hex number as used in addresses, lengths and ids.
+pub fn parse_hex_u64(hex: &[u8]) -> Result<u64> {
+    std::str::from_utf8(hex)
+        .ok()
+        .and_then(|s| u64::from_str_radix(s, 16).ok())
+        .ok_or_else(|| Error::protocol(format!("bad number {:?}", String::from_utf8_lossy(hex))))
+}
+
+/// What came off the wire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incoming {
+    Ack,
+    Nak,
+    /// Ctrl-C from a client asking the target to stop.
+    Interrupt,
+    /// Body with escapes still in place and whether its checksum matched.
+    Packet {
+        body: Vec<u8>,
+        valid: bool,
+    },
+}
+
+/// Byte-level transport shared by the client and the stand-in servers.
+pub struct Conn<S> {
+    stream: S,
+    buf: Vec<u8>,
+    pos: usize,
+    /// What arrived of a packet after its `$`, kept when a read timeout
+    /// cuts it short so the next [`Conn::read`] picks up where it stopped.
+    partial: Option<Vec<u8>>,
+}
+
+impl<S: Read + Write> Conn<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buf: Vec::new(),
+            pos: 0,
+            partial: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        if self.pos == self.buf.len() {
+            self.buf.resize(4096, 0);
+            self.pos = 0;
+            let read = self.stream.read(&mut self.buf);
+            // nothing buffered if the read failed or timed out
+            self.buf.truncate(*read.as_ref().unwrap_or(&0));
+            if read? == 0 {
+                return Err(Error::Closed);
+            }
+        }
+        let b = self.buf[self.pos];
+        self.pos += 1;
+        Ok(b)
+    }
+
+    /// Bytes already read from the stream but not consumed yet.
+    pub fn has_buffered(&self) -> bool {
+        self.pos < self.buf.len()
+    }
+
+    /// Next ack, interrupt or packet. Line noise between packets is skipped.
+    /// A timeout in the middle of a packet keeps what was read of it.
+    pub fn read(&mut self) -> Result<Incoming> {
+        if self.partial.is_none() {
+            loop {
+                match self.read_byte()? {
+                    b'+' => return Ok(Incoming::Ack),
+                    b'-' => return Ok(Incoming::Nak),
+                    0x03 => return Ok(Incoming::Interrupt),
+                    b'$' => break,
+                    _ => {}
+                }
+            }
+            self.partial = Some(Vec::new());
+        }
+        loop {
+            let b = self.read_byte()?;
+            let packet = self.partial.as_mut().expect("started above");
+            packet.push(b);
+            // bodies never hold a bare `#`, so the first one is 3 from the end
+            if let [.., b'#', _, _] = packet[..] {
+                let mut body = self.partial.take().expect("started above");
+                let cs = body.split_off(body.len() - 3);
+                let valid = parse_hex_u64(&cs[1..]).ok() == Some(u64::from(checksum(&body)));
+                return Ok(Incoming::Packet { body, valid });
+            }
+        }
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.stream.write_all(bytes)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    pub fn write_packet(&mut self, payload: &[u8]) -> Result<()> {
+        self.write_raw(&frame(payload))
+    }
+}
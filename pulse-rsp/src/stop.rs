@@ -0,0 +1,178 @@
+//! Stop-reply packets (`S`, `T`, `W`, `X`).
+
+use crate::error::{Error, Result};
+use crate::packet::{from_hex, parse_hex_u64, to_hex};
+
+/// Signal numbers GDB uses in stop replies.
+pub mod signal {
+    pub const SIGINT: u8 = 2;
+    pub const SIGTRAP: u8 = 5;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+/// Why a `T` stop happened, when the stub says so.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Watch { kind: WatchKind, addr: u64 },
+    SwBreak,
+    HwBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReply {
+    Signal {
+        signal: u8,
+        thread: Option<u64>,
+        reason: Option<StopReason>,
+        /// Expedited registers as (number, raw target-endian bytes).
+        registers: Vec<(u32, Vec<u8>)>,
+    },
+    Exited(u8),
+    Terminated(u8),
+}
+
+impl StopReply {
+    pub fn parse(body: &[u8]) -> Result<Self> {
+        let bad = || {
+            Error::protocol(format!(
+                "bad stop reply {:?}",
+                String::from_utf8_lossy(body)
+            ))
+        };
+        let (&kind, rest) = body.split_first().ok_or_else(bad)?;
+        let code = || -> Result<u8> {
+            let hex = rest.get(..2).ok_or_else(bad)?;
+            Ok(parse_hex_u64(hex)? as u8)
+        };
+
+        match kind {
+            b'S' => Ok(StopReply::Signal {
+                signal: code()?,
+                thread: None,
+                reason: None,
+                registers: Vec::new(),
+            }),
+            b'W' => Ok(StopReply::Exited(code()?)),
+            b'X' => Ok(StopReply::Terminated(code()?)),
+            b'T' => {
+                let signal = code()?;
+                let mut thread = None;
+                let mut reason = None;
+                let mut registers = Vec::new();
+
+                for pair in rest[2..].split(|&b| b == b';').filter(|p| !p.is_empty()) {
+                    let (key, value) = match pair.iter().position(|&b| b == b':') {
+                        Some(at) => (&pair[..at], &pair[at + 1..]),
+                        None => (pair, &[][..]),
+                    };
+                    match key {
+                        b"thread" => {
+                            // may be p<pid>.<tid>, only the thread part matters here
+                            let tid = value.rsplit(|&b| b == b'.').next().unwrap_or(value);
+                            thread = Some(parse_hex_u64(tid.strip_prefix(b"p").unwrap_or(tid))?);
+                        }
+                        b"watch" | b"rwatch" | b"awatch" => {
+                            let kind = match key {
+                                b"watch" => WatchKind::Write,
+                                b"rwatch" => WatchKind::Read,
+                                _ => WatchKind::Access,
+                            };
+                            reason = Some(StopReason::Watch {
+                                kind,
+                                addr: parse_hex_u64(value)?,
+                            });
+                        }
+                        b"swbreak" => reason = Some(StopReason::SwBreak),
+                        b"hwbreak" => reason = Some(StopReason::HwBreak),
+                        _ => {
+                            // register numbers are plain hex, anything else is an
+                            // extension (core:, library:, ...) we do not need
+                            if let Ok(reg) = parse_hex_u64(key) {
+                                registers.push((reg as u32, from_hex(value)?));
+                            }
+                        }
+                    }
+                }
+                Ok(StopReply::Signal {
+                    signal,
+                    thread,
+                    reason,
+                    registers,
+                })
+            }
+            _ => Err(bad()),
+        }
+    }
+
+    /// Reply body as a stub sends it, the inverse of [`StopReply::parse`].
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            StopReply::Exited(code) => format!("W{:02x}", code).into_bytes(),
+            StopReply::Terminated(code) => format!("X{:02x}", code).into_bytes(),
+            StopReply::Signal {
+                signal,
+                thread,
+                reason,
+                registers,
+            } => {
+                let mut out = format!("T{:02x}", signal);
+                for (reg, bytes) in registers {
+                    out.push_str(&format!("{:02x}:{};", reg, to_hex(bytes)));
+                }
+                if let Some(thread) = thread {
+                    out.push_str(&format!("thread:{:x};", thread));
+                }
+                match reason {
+                    Some(StopReason::Watch { kind, addr }) => {
+                        let key = match kind {
+                            WatchKind::Write => "watch",
+                            WatchKind::Read => "rwatch",
+                            WatchKind::Access => "awatch",
+                        };
+                        out.push_str(&format!("{}:{:x};", key, addr));
+                    }
+                    Some(StopReason::SwBreak) => out.push_str("swbreak:;"),
+                    Some(StopReason::HwBreak) => out.push_str("hwbreak:;"),
+                    None => {}
+                }
+                out.into_bytes()
+            }
+        }
+    }
+
+    pub fn signal(&self) -> Option<u8> {
+        match self {
+            StopReply::Signal { signal, .. } => Some(*signal),
+            _ => None,
+        }
+    }
+
+    pub fn reason(&self) -> Option<StopReason> {
+        match self {
+            StopReply::Signal { reason, .. } => *reason,
+            _ => None,
+        }
+    }
+
+    /// Expedited value of register `reg` as a little-endian number.
+    pub fn register(&self, reg: u32) -> Option<u64> {
+        match self {
+            StopReply::Signal { registers, .. } => {
+                registers.iter().find(|(n, _)| *n == reg).map(|(_, bytes)| {
+                    bytes
+                        .iter()
+                        .take(8)
+                        .rev()
+                        .fold(0u64, |acc, &b| acc << 8 | u64::from(b))
+                })
+            }
+            _ => None,
+        }
+    }
+}
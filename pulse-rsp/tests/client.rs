@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use pulse_rsp::packet::{self, Conn, Incoming};
+use pulse_rsp::{BreakpointKind, Client, Error, Resume, StopReason, StopReply, WatchKind};
+
+/// Minimal stub: a sparse byte memory, a point table and a log of every
+/// packet it received. An empty reply means "not supported".
+struct Stub {
+    memory: HashMap<u64, u8>,
+    points: Vec<String>,
+    seen: Vec<String>,
+    no_ack: bool,
+    /// NAK this many packets before accepting one.
+    nak: usize,
+    /// Answer `vCont?`, not just echo `vContSupported+`.
+    vcont: bool,
+}
+
+impl Stub {
+    fn new() -> Self {
+        Self {
+            memory: HashMap::new(),
+            points: Vec::new(),
+            seen: Vec::new(),
+            no_ack: false,
+            nak: 0,
+            vcont: true,
+        }
+    }
+
+    fn reply(&mut self, body: &[u8]) -> Vec<Vec<u8>> {
+        let text = String::from_utf8_lossy(body).into_owned();
+        let (cmd, args) = text.split_at(1);
+        let out: Vec<Vec<u8>> = match cmd {
+            "q" if args.starts_with("Supported") => {
+                vec![b"PacketSize=40;QStartNoAckMode+;vContSupported+".to_vec()]
+            }
+            "Q" if args == "StartNoAckMode" => vec![b"OK".to_vec()],
+            "m" => {
+                let (addr, len) = args.split_once(',').unwrap();
+                let addr = u64::from_str_radix(addr, 16).unwrap();
+                let len = usize::from_str_radix(len, 16).unwrap();
+                if self.memory.is_empty() {
+                    vec![b"E14".to_vec()]
+                } else {
+                    let bytes: Vec<u8> = (0..len as u64)
+                        .map(|i| *self.memory.get(&(addr + i)).unwrap_or(&0))
+                        .collect();
+                    vec![packet::to_hex(&bytes).into_bytes()]
+                }
+            }
+            "M" => {
+                let (head, hex) = args.split_once(':').unwrap();
+                let addr = u64::from_str_radix(head.split(',').next().unwrap(), 16).unwrap();
+                for (i, b) in packet::from_hex(hex.as_bytes())
+                    .unwrap()
+                    .into_iter()
+                    .enumerate()
+                {
+                    self.memory.insert(addr + i as u64, b);
+                }
+                vec![b"OK".to_vec()]
+            }
+            "X" => {
+                let colon = body.iter().position(|&b| b == b':').unwrap();
+                let addr = u64::from_str_radix(args.split(',').next().unwrap(), 16).unwrap();
+                let data = packet::decode(&body[colon + 1..]).unwrap();
+                for (i, b) in data.into_iter().enumerate() {
+                    self.memory.insert(addr + i as u64, b);
+                }
+                vec![b"OK".to_vec()]
+            }
+            "Z" => {
+                self.points.push(args.to_string());
+                vec![b"OK".to_vec()]
+            }
+            "z" => {
+                self.points.retain(|p| p != args);
+                vec![b"OK".to_vec()]
+            }
+            "c" => vec![
+                format!("O{}", packet::to_hex(b"cycle 3\n")).into_bytes(),
+                b"T05watch:3ffb0008;thread:p1.2;".to_vec(),
+            ],
+            "s" => vec![b"S05".to_vec()],
+            "v" if args == "Cont?" && self.vcont => vec![b"vCont;c;C;s;S".to_vec()],
+            "v" if args.starts_with("Cont;") => vec![b"T0501:78563412;swbreak:;".to_vec()],
+            // run-length encoded "0000000000"
+            "g" => vec![b"0*&".to_vec()],
+            _ => vec![Vec::new()],
+        };
+        self.seen.push(text);
+        out
+    }
+}
+
+fn serve(stub: Stub) -> (Client, Arc<Mutex<Stub>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stub = Arc::new(Mutex::new(stub));
+    let shared = Arc::clone(&stub);
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Conn::new(stream);
+        loop {
+            let body = match conn.read() {
+                Ok(Incoming::Packet { body, valid }) => {
+                    let mut stub = shared.lock().unwrap();
+                    if !stub.no_ack {
+                        if !valid || stub.nak > 0 {
+                            stub.nak = stub.nak.saturating_sub(1);
+                            conn.write_raw(b"-").unwrap();
+                            continue;
+                        }
+                        conn.write_raw(b"+").unwrap();
+                    }
+                    body
+                }
+                Ok(Incoming::Interrupt) => b"?".to_vec(),
+                Ok(_) => continue,
+                Err(_) => return,
+            };
+            let mut stub = shared.lock().unwrap();
+            let replies = stub.reply(&body);
+            for reply in replies {
+                conn.write_packet(&reply).unwrap();
+                if !stub.no_ack {
+                    assert_eq!(conn.read().unwrap(), Incoming::Ack);
+                }
+            }
+            if body == b"QStartNoAckMode" {
+                stub.no_ack = true;
+            }
+        }
+    });
+
+    let client = Client::new(TcpStream::connect(addr).unwrap());
+    (client, stub)
+}
+
+#[test]
+fn framing_and_checksum() {
+    assert_eq!(packet::checksum(b"OK"), 0x9a);
+    assert_eq!(packet::frame(b"m0,4"), b"$m0,4#fd");
+    assert_eq!(packet::escape(b"a$#}*"), b"a}\x04}\x03}]}\x0a");
+    assert_eq!(packet::decode(b"}\x04x* ").unwrap(), b"$xxxx");
+}
+
+#[test]
+fn handshake_enters_no_ack_mode() {
+    let (mut gdb, stub) = serve(Stub::new());
+    let features = gdb.handshake().unwrap();
+    assert_eq!(features.packet_size, Some(0x40));
+    assert!(features.no_ack_mode);
+    assert_eq!(features.vcont, ["c", "C", "s", "S"]);
+    assert!(gdb.is_no_ack());
+
+    gdb.write_u32(0x3ffb_0008, 7).unwrap();
+    assert_eq!(gdb.read_u32(0x3ffb_0008).unwrap(), 7);
+    assert!(stub.lock().unwrap().no_ack);
+
+    // echoing vContSupported+ is not supporting vCont
+    let (mut gdb, _) = serve(Stub {
+        vcont: false,
+        ..Stub::new()
+    });
+    let features = gdb.handshake().unwrap();
+    assert!(features.raw.iter().any(|f| f == "vContSupported+"));
+    assert!(features.vcont.is_empty());
+}
+
+#[test]
+fn packet_split_by_a_timeout_survives() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let target = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let framed = packet::frame(b"T05thread:1;");
+        stream.write_all(&framed[..6]).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        stream.write_all(&framed[6..]).unwrap();
+        thread::sleep(Duration::from_millis(300));
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .unwrap();
+    let mut conn = Conn::new(stream);
+    let mut timeouts = 0;
+    let incoming = loop {
+        match conn.read() {
+            Err(e) if e.is_timeout() => timeouts += 1,
+            other => break other.unwrap(),
+        }
+    };
+    assert!(timeouts > 0);
+    assert_eq!(
+        incoming,
+        Incoming::Packet {
+            body: b"T05thread:1;".to_vec(),
+            valid: true
+        }
+    );
+    target.join().unwrap();
+}
+
+#[test]
+fn memory_is_chunked_to_packet_size() {
+    let (mut gdb, stub) = serve(Stub::new());
+    gdb.handshake().unwrap();
+
+    let data: Vec<u8> = (0..100).collect();
+    gdb.write_memory(0x1000, &data).unwrap();
+    assert_eq!(gdb.read_memory(0x1000, 100).unwrap(), data);
+
+    // binary writes escape the protocol characters
+    gdb.write_memory_binary(0x2000, b"$#}*").unwrap();
+    assert_eq!(gdb.read_memory(0x2000, 4).unwrap(), b"$#}*");
+
+    let seen = &stub.lock().unwrap().seen;
+    assert!(seen.iter().filter(|p| p.starts_with('M')).count() > 1);
+    assert!(seen.iter().filter(|p| p.starts_with('m')).count() > 1);
+}
+
+#[test]
+fn nak_is_retransmitted_in_ack_mode() {
+    let mut stub = Stub::new();
+    stub.nak = 2;
+    let (mut gdb, stub) = serve(stub);
+
+    gdb.write_u32(0x10, 0xdead_beef).unwrap();
+    assert_eq!(gdb.read_u32(0x10).unwrap(), 0xdead_beef);
+    assert!(!gdb.is_no_ack());
+    assert_eq!(stub.lock().unwrap().seen.len(), 2);
+}
+
+#[test]
+fn nak_gives_up_eventually() {
+    let mut stub = Stub::new();
+    stub.nak = usize::MAX;
+    let (mut gdb, _) = serve(stub);
+    assert!(matches!(gdb.write_u32(0x10, 1), Err(Error::Nak)));
+}
+
+#[test]
+fn remote_errors_and_unsupported() {
+    let (mut gdb, _) = serve(Stub::new());
+    assert!(matches!(gdb.read_memory(0, 4), Err(Error::Remote(0x14))));
+    assert!(matches!(
+        gdb.write_register(0, &[0; 4]),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let (mut gdb, stub) = serve(Stub::new());
+    gdb.insert_point(BreakpointKind::Hardware, 0x400d_1234, 3)
+        .unwrap();
+    gdb.insert_point(BreakpointKind::WriteWatch, 0x3ffb_0008, 4)
+        .unwrap();
+    assert_eq!(
+        stub.lock().unwrap().points,
+        ["1,400d1234,3", "2,3ffb0008,4"]
+    );
+
+    gdb.remove_point(BreakpointKind::Hardware, 0x400d_1234, 3)
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().points, ["2,3ffb0008,4"]);
+}
+
+#[test]
+fn resume_and_stop_replies() {
+    let (mut gdb, stub) = serve(Stub::new());
+    gdb.handshake().unwrap();
+
+    let stop = gdb.cont().unwrap();
+    assert_eq!(stop.signal(), Some(5));
+    assert_eq!(
+        stop.reason(),
+        Some(StopReason::Watch {
+            kind: WatchKind::Write,
+            addr: 0x3ffb_0008
+        })
+    );
+    assert!(matches!(
+        stop,
+        StopReply::Signal {
+            thread: Some(2),
+            ..
+        }
+    ));
+    assert_eq!(gdb.take_console(), b"cycle 3\n");
+
+    assert_eq!(gdb.step().unwrap().signal(), Some(5));
+
+    let stop = gdb
+        .vcont(&[Resume::Step(Some(1)), Resume::Continue(None)])
+        .unwrap();
+    assert_eq!(stop.reason(), Some(StopReason::SwBreak));
+    assert_eq!(stop.register(1), Some(0x1234_5678));
+    assert!(stub
+        .lock()
+        .unwrap()
+        .seen
+        .contains(&"vCont;s:1;c".to_string()));
+
+    assert_eq!(gdb.read_registers().unwrap(), [0; 5]);
+}
+
+#[test]
+fn stop_reply_parsing() {
+    assert_eq!(StopReply::parse(b"W00").unwrap(), StopReply::Exited(0));
+    assert_eq!(StopReply::parse(b"X09").unwrap(), StopReply::Terminated(9));
+    let stop = StopReply::parse(b"T02thread:1;core:0;rwatch:10;").unwrap();
+    assert_eq!(
+        stop.reason(),
+        Some(StopReason::Watch {
+            kind: WatchKind::Read,
+            addr: 0x10
+        })
+    );
+    assert!(StopReply::parse(b"Q").is_err());
+
+    // stubs built on this crate encode what the client parses
+    let stop = StopReply::parse(b"T0500:34120040;thread:1;awatch:3ffb0014;").unwrap();
+    assert_eq!(StopReply::parse(&stop.encode()).unwrap(), stop);
+}
@@ -0,0 +1,648 @@
+//! Randomized fault-injection campaigns.
+//!
+//! A campaign names what may be injected and over which values; every run
+//! draws a few injections at random cycles, plays them as a scenario and
+//! classifies what the firmware did. The first failing case of each kind
+//! is then shrunk to the smallest scenario that still fails that way.
+//!
+//! ```toml
+//! name = "timing and mailbox robustness"
+//! elf = "target/xtensa-esp32-espidf/debug/ghost-trigger"
+//! seed = 7            # drawn from the clock and printed if left out
+//! runs = 50
+//! injections = 3      # up to this many per run
+//! cycles = [1, 10]    # the cycles they land on
+//! settle = 5          # cycles to keep watching after the last one
+//! timeout_ms = 60000  # per run
+//!
+//! [[var]]
+//! path = "CYCLE_MS"   # no `range`: the one declared with `injection_point!`
+//!
+//! [[mailbox]]
+//! signals = [0, 255]
+//!
+//! [response]          # optional, a line each run must print
+//! log = "THREAT DETECTED"
+//! within_ms = 3000
+//! ```
+
+use std::fmt;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use object::{Object, ObjectSection};
+use pulse_core::injection::{RawEntry, TABLE_SECTION};
+use pulse_core::mailbox::Signal;
+use pulse_dwarf::DebugInfo;
+use pulse_rsp::Client;
+use toml::{Table, Value};
+
+use crate::error::{Error, Result};
+use crate::outcome::{classify, Outcome, FAULT_MARKERS};
+use crate::runner::{run, Report};
+use crate::scenario::{
+    Action, Expect, Fields, Injection, Point, Scenario, Trigger, CYCLE_SYMBOL, DEFAULT_WITHIN,
+};
+
+const DEFAULT_RUNS: u64 = 20;
+const DEFAULT_CYCLES: (u64, u64) = (1, 10);
+const DEFAULT_SETTLE: u64 = 3;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_SHRINK_RUNS: usize = 50;
+
+/// What a campaign injects into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Knob {
+    /// `[[var]]`: a variable path, written through DWARF.
+    Var(String),
+    /// `[[mailbox]]`: a request with a random signal id.
+    Mailbox { payload: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injectable {
+    pub knob: Knob,
+    /// Inclusive. `None` for a variable that takes the range it was
+    /// declared with, see [`Campaign::fill_ranges`].
+    pub range: Option<(i64, i64)>,
+    /// Where to stop to inject, the cycle breakpoint if `None`.
+    pub at: Option<Point>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Campaign {
+    pub name: String,
+    pub target: Option<String>,
+    pub elf: Option<PathBuf>,
+    pub serial: Option<PathBuf>,
+    pub cycle: Point,
+    /// `None` picks one from the clock.
+    pub seed: Option<u64>,
+    pub runs: u64,
+    /// Most injections in one run, at least one is drawn.
+    pub injections: usize,
+    /// Inclusive range of the cycles injections land on.
+    pub cycles: (u64, u64),
+    /// Detection cycles to watch after the one the last injection went into.
+    pub settle: u64,
+    /// Per run.
+    pub timeout: Duration,
+    /// Runs one shrink may take.
+    pub shrink_runs: usize,
+    pub response: Option<Expect>,
+    pub injectables: Vec<Injectable>,
+}
+
+/// One injection of a generated case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Poke {
+    /// Index into [`Campaign::injectables`].
+    pub injectable: usize,
+    pub cycle: u64,
+    pub value: i64,
+}
+
+impl Campaign {
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: Table = text.parse()?;
+        let mut fields = Fields::new(table, "campaign");
+
+        let mut campaign = Campaign {
+            name: fields.string("name")?.unwrap_or_else(|| "campaign".into()),
+            target: fields.string("target")?,
+            elf: fields.string("elf")?.map(PathBuf::from),
+            serial: fields.string("serial")?.map(PathBuf::from),
+            cycle: fields
+                .point("cycle")?
+                .unwrap_or_else(|| Point::Symbol(CYCLE_SYMBOL.into())),
+            seed: fields.u64("seed")?,
+            runs: fields.u64("runs")?.unwrap_or(DEFAULT_RUNS),
+            injections: fields.u64("injections")?.unwrap_or(1) as usize,
+            cycles: match range(&mut fields, "cycles")? {
+                Some((lo, hi)) if lo >= 1 => (lo as u64, hi as u64),
+                Some(_) => return Err(fields.invalid("cycle counts start at 1")),
+                None => DEFAULT_CYCLES,
+            },
+            settle: fields.u64("settle")?.unwrap_or(DEFAULT_SETTLE),
+            timeout: fields.millis("timeout_ms")?.unwrap_or(DEFAULT_TIMEOUT),
+            shrink_runs: fields
+                .u64("shrink_runs")?
+                .map_or(DEFAULT_SHRINK_RUNS, |n| n as usize),
+            response: None,
+            injectables: Vec::new(),
+        };
+        if campaign.runs == 0 || campaign.injections == 0 {
+            return Err(fields.invalid("`runs` and `injections` must be at least 1"));
+        }
+
+        if let Some(value) = fields.take("response") {
+            let Value::Table(table) = value else {
+                return Err(fields.wrong_type("response", "a table"));
+            };
+            let mut response = Fields::new(table, "[response]");
+            let log = response
+                .string("log")?
+                .ok_or_else(|| response.invalid("needs `log`"))?;
+            let within = response.millis("within_ms")?.unwrap_or(DEFAULT_WITHIN);
+            response.finish()?;
+            campaign.response = Some(Expect {
+                log,
+                within,
+                absent: false,
+            });
+        }
+        for var in fields.tables("var")? {
+            let mut var = Fields::new(var, "[[var]]");
+            let path = var
+                .string("path")?
+                .ok_or_else(|| var.invalid("needs `path`"))?;
+            let injectable = Injectable {
+                knob: Knob::Var(path),
+                range: range(&mut var, "range")?,
+                at: var.point("at")?,
+            };
+            var.finish()?;
+            campaign.injectables.push(injectable);
+        }
+        for mailbox in fields.tables("mailbox")? {
+            let mut mailbox = Fields::new(mailbox, "[[mailbox]]");
+            let signals = range(&mut mailbox, "signals")?.unwrap_or((0, 255));
+            if signals.0 < 0 || signals.1 > i64::from(u32::MAX) {
+                return Err(mailbox.invalid("`signals` are 32-bit signal ids"));
+            }
+            let payload = mailbox.u64("payload")?.unwrap_or(0);
+            let payload =
+                u32::try_from(payload).map_err(|_| mailbox.invalid("`payload` out of range"))?;
+            let injectable = Injectable {
+                knob: Knob::Mailbox { payload },
+                range: Some(signals),
+                at: mailbox.point("at")?,
+            };
+            mailbox.finish()?;
+            campaign.injectables.push(injectable);
+        }
+        if campaign.injectables.is_empty() {
+            return Err(fields.invalid("needs a `[[var]]` or a `[[mailbox]]`"));
+        }
+        fields.finish()?;
+        Ok(campaign)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Give every `[[var]]` without a `range` the one it was registered
+    /// with in the ELF's injection table. An explicit `range` must stay
+    /// inside the registered one, [`apply`](crate::apply) refuses writes
+    /// outside it.
+    pub fn fill_ranges(&mut self, elf: &[u8]) -> Result<()> {
+        let declared = injection_points(elf)?;
+        for injectable in &mut self.injectables {
+            let Knob::Var(path) = &injectable.knob else {
+                continue;
+            };
+            let entry = declared
+                .iter()
+                .find(|(name, _)| path == name || path.ends_with(&format!("::{}", name)));
+            match (injectable.range, entry) {
+                (None, Some((_, entry))) => injectable.range = Some((entry.min, entry.max)),
+                (None, None) => {
+                    return Err(Error::Scenario(format!(
+                        "[[var]]: `{}` has no `range` and is not a registered injection point",
+                        path
+                    )))
+                }
+                (Some((lo, hi)), Some((_, entry)))
+                    if !entry.in_range(lo) || !entry.in_range(hi) =>
+                {
+                    return Err(Error::Scenario(format!(
+                        "[[var]]: `{}` range [{}, {}] leaves its declared range({}, {})",
+                        path, lo, hi, entry.min, entry.max
+                    )))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn range(&self, poke: &Poke) -> (i64, i64) {
+        self.injectables[poke.injectable]
+            .range
+            .unwrap_or((i64::MIN, i64::MAX))
+    }
+
+    /// Draw one case: one to `injections` pokes, in cycle order.
+    pub fn generate(&self, rng: &mut Rng) -> Vec<Poke> {
+        let count = 1 + rng.below(self.injections as u64) as usize;
+        let mut case: Vec<Poke> = (0..count)
+            .map(|_| {
+                let injectable = rng.below(self.injectables.len() as u64) as usize;
+                let (lo, hi) = self.injectables[injectable]
+                    .range
+                    .unwrap_or((i64::MIN, i64::MAX));
+                Poke {
+                    injectable,
+                    cycle: rng.between(self.cycles.0 as i64, self.cycles.1 as i64) as u64,
+                    value: rng.value(lo, hi),
+                }
+            })
+            .collect();
+        case.sort_by_key(|p| p.cycle);
+        case
+    }
+
+    /// The scenario that plays `case`: a fresh reset, the injections, the
+    /// response if any and every [`FAULT_MARKERS`] line expected absent,
+    /// ending `settle` cycles after the last injection.
+    pub fn scenario(&self, name: &str, case: &[Poke]) -> Scenario {
+        let last = case.iter().map(|p| p.cycle).max().unwrap_or(1);
+        let injections = case
+            .iter()
+            .map(|poke| {
+                let injectable = &self.injectables[poke.injectable];
+                let action = match &injectable.knob {
+                    Knob::Var(path) => Action::Write {
+                        var: path.clone(),
+                        value: poke.value as u64,
+                    },
+                    Knob::Mailbox { payload } => Action::Mailbox {
+                        signal: Signal::from_id(poke.value as u32),
+                        payload: *payload,
+                    },
+                };
+                Injection {
+                    trigger: Trigger::Cycle(poke.cycle),
+                    at: injectable.at.clone(),
+                    actions: vec![action],
+                }
+            })
+            .collect();
+        let faults = FAULT_MARKERS.iter().map(|log| Expect {
+            log: log.to_string(),
+            within: DEFAULT_WITHIN,
+            absent: true,
+        });
+        Scenario {
+            name: name.to_string(),
+            target: self.target.clone(),
+            elf: self.elf.clone(),
+            serial: self.serial.clone(),
+            reset: true,
+            cycle: self.cycle.clone(),
+            timeout: self.timeout,
+            until_cycle: Some(last + self.settle + 1),
+            injections,
+            expectations: self.response.iter().cloned().chain(faults).collect(),
+        }
+    }
+
+    /// `cycle 3: CYCLE_MS = 0, cycle 7: mailbox 9`
+    pub fn describe(&self, case: &[Poke]) -> String {
+        let pokes: Vec<String> = case
+            .iter()
+            .map(|poke| match &self.injectables[poke.injectable].knob {
+                Knob::Var(path) => format!("cycle {}: {} = {}", poke.cycle, path, poke.value),
+                Knob::Mailbox { .. } => format!("cycle {}: mailbox {}", poke.cycle, poke.value),
+            })
+            .collect();
+        pokes.join(", ")
+    }
+}
+
+/// `[min, max]`
+fn range(fields: &mut Fields, key: &str) -> Result<Option<(i64, i64)>> {
+    match fields.take(key) {
+        None => Ok(None),
+        Some(Value::Array(items)) => match items.as_slice() {
+            [Value::Integer(lo), Value::Integer(hi)] if lo <= hi => Ok(Some((*lo, *hi))),
+            _ => Err(fields.wrong_type(key, "`[min, max]`")),
+        },
+        Some(_) => Err(fields.wrong_type(key, "`[min, max]`")),
+    }
+}
+
+/// Every entry of the ELF's `injection_point!` table, by name.
+pub fn injection_points(elf: &[u8]) -> Result<Vec<(String, RawEntry)>> {
+    let file = object::File::parse(elf).map_err(|e| Error::Elf(e.to_string()))?;
+    let Some(table) = file.section_by_name(TABLE_SECTION) else {
+        return Ok(Vec::new());
+    };
+    let data = table.data().map_err(|e| Error::Elf(e.to_string()))?;
+    let ptr_width = if file.is_64() { 8 } else { 4 };
+
+    let read = |addr: u64, len: u64| {
+        file.sections().find_map(|section| {
+            let offset = addr.checked_sub(section.address())?;
+            let data = section.data().ok()?;
+            data.get(offset as usize..(offset + len) as usize)
+        })
+    };
+    Ok(RawEntry::parse_table(data, ptr_width)
+        .into_iter()
+        .filter_map(|entry| {
+            let name = read(entry.name_addr, entry.name_len)?;
+            Some((String::from_utf8_lossy(name).into_owned(), entry))
+        })
+        .collect())
+}
+
+/// xorshift64*, so a seed replays the same campaign on any host.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64 the seed, xorshift must not start at zero
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`, `n` > 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Uniform in `lo..=hi`.
+    pub fn between(&mut self, lo: i64, hi: i64) -> i64 {
+        let span = (hi as i128 - lo as i128 + 1) as u128;
+        (lo as i128 + (u128::from(self.next_u64()) % span) as i128) as i64
+    }
+
+    /// A value in `lo..=hi`, one time in four an edge of it (either end,
+    /// or zero) since that is where firmware tends to break.
+    pub fn value(&mut self, lo: i64, hi: i64) -> i64 {
+        if self.below(4) == 0 {
+            let edges = [lo, hi, simplest((lo, hi))];
+            edges[self.below(3) as usize]
+        } else {
+            self.between(lo, hi)
+        }
+    }
+}
+
+/// The value a shrink moves towards: zero, or the end of the range nearest it.
+fn simplest((lo, hi): (i64, i64)) -> i64 {
+    0.clamp(lo, hi)
+}
+
+/// `from` moved towards `to`: all the way first, then half, a quarter...
+fn towards(from: i64, to: i64) -> impl Iterator<Item = i64> {
+    let distance = from as i128 - to as i128;
+    iter::successors(Some(distance), |d| Some(d / 2))
+        .take_while(|&d| d != 0)
+        .map(move |d| (from as i128 - d) as i64)
+}
+
+/// Shrink a case that failed with `outcome` to a smaller one that still
+/// fails the same way: drop pokes, move them to earlier cycles and their
+/// values towards zero, as long as `attempt` reproduces it and at most
+/// `budget` attempts are made. Returns the smallest case, how it failed and
+/// the attempts used.
+pub fn shrink(
+    campaign: &Campaign,
+    case: Vec<Poke>,
+    outcome: Outcome,
+    budget: usize,
+    mut attempt: impl FnMut(&[Poke]) -> Result<Outcome>,
+) -> Result<(Vec<Poke>, Outcome, usize)> {
+    let mut best = (case, outcome);
+    let mut tries = 0;
+    let mut test = |candidate: Vec<Poke>, best: &mut (Vec<Poke>, Outcome)| -> Result<bool> {
+        if tries >= budget {
+            return Ok(false);
+        }
+        tries += 1;
+        let outcome = attempt(&candidate)?;
+        let kept = outcome.same_kind(&best.1);
+        if kept {
+            *best = (candidate, outcome);
+        }
+        Ok(kept)
+    };
+
+    let mut progress = true;
+    while progress {
+        progress = false;
+        for i in (0..best.0.len()).rev() {
+            if best.0.len() > 1 {
+                let mut candidate = best.0.clone();
+                candidate.remove(i);
+                progress |= test(candidate, &mut best)?;
+            }
+        }
+        for i in 0..best.0.len() {
+            'cycle: loop {
+                for cycle in towards(best.0[i].cycle as i64, campaign.cycles.0 as i64) {
+                    let mut candidate = best.0.clone();
+                    candidate[i].cycle = cycle as u64;
+                    if test(candidate, &mut best)? {
+                        progress = true;
+                        continue 'cycle;
+                    }
+                }
+                break;
+            }
+            let to = simplest(campaign.range(&best.0[i]));
+            'value: loop {
+                for value in towards(best.0[i].value, to) {
+                    let mut candidate = best.0.clone();
+                    candidate[i].value = value;
+                    if test(candidate, &mut best)? {
+                        progress = true;
+                        continue 'value;
+                    }
+                }
+                break;
+            }
+        }
+    }
+    best.0.sort_by_key(|p| p.cycle);
+    Ok((best.0, best.1, tries))
+}
+
+/// One generated case and what it did.
+#[derive(Debug, Clone)]
+pub struct CaseRun {
+    /// 1-based.
+    pub index: u64,
+    /// An attempt at shrinking run `index`.
+    pub shrinking: bool,
+    pub case: Vec<Poke>,
+    pub outcome: Outcome,
+    pub report: Report,
+}
+
+/// The smallest case found for one kind of failure.
+#[derive(Debug, Clone)]
+pub struct Shrunk {
+    /// The run it was shrunk from.
+    pub from: u64,
+    pub case: Vec<Poke>,
+    pub outcome: Outcome,
+    /// Runs the shrink took.
+    pub attempts: usize,
+    /// Replays it, with [`Scenario::to_toml`] for a file.
+    pub scenario: Scenario,
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignReport {
+    pub campaign: Campaign,
+    pub seed: u64,
+    pub runs: Vec<CaseRun>,
+    pub shrunk: Vec<Shrunk>,
+}
+
+impl CampaignReport {
+    pub fn failed(&self) -> bool {
+        self.runs.iter().any(|r| r.outcome.failed())
+    }
+
+    /// Runs per outcome kind, in the order first seen.
+    pub fn counts(&self) -> Vec<(&'static str, usize)> {
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for run in &self.runs {
+            match counts
+                .iter_mut()
+                .find(|(kind, _)| *kind == run.outcome.kind())
+            {
+                Some((_, n)) => *n += 1,
+                None => counts.push((run.outcome.kind(), 1)),
+            }
+        }
+        counts
+    }
+}
+
+impl fmt::Display for CampaignReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self
+            .counts()
+            .iter()
+            .map(|(kind, n)| format!("{} {}", n, kind))
+            .collect();
+        writeln!(
+            f,
+            "{} (seed {}): {} runs, {}",
+            self.campaign.name,
+            self.seed,
+            self.runs.len(),
+            counts.join(", ")
+        )?;
+        for run in self.runs.iter().filter(|r| r.outcome.failed()) {
+            writeln!(
+                f,
+                "  run {:>3}: {} after {}",
+                run.index,
+                run.outcome,
+                self.campaign.describe(&run.case)
+            )?;
+        }
+        for shrunk in &self.shrunk {
+            writeln!(
+                f,
+                "  minimal {} (from run {}, {} runs): {}",
+                shrunk.outcome.kind(),
+                shrunk.from,
+                shrunk.attempts,
+                self.campaign.describe(&shrunk.case)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `campaign` and shrink the first failure of each kind.
+///
+/// `connect` is called for a handshaken client before every run, since a
+/// scenario detaches when it ends. Output left on `serial` by the previous
+/// run is dropped first. `progress` sees each run as it completes,
+/// shrinking attempts included.
+pub fn fuzz(
+    campaign: &Campaign,
+    debug: &DebugInfo,
+    points: &[(String, RawEntry)],
+    mut connect: impl FnMut() -> Result<Client>,
+    serial: Option<&Receiver<Vec<u8>>>,
+    mut progress: impl FnMut(&CaseRun),
+) -> Result<CampaignReport> {
+    if let Some(injectable) = campaign.injectables.iter().find(|i| i.range.is_none()) {
+        return Err(Error::Scenario(format!(
+            "{:?} has no range, see Campaign::fill_ranges",
+            injectable.knob
+        )));
+    }
+    let seed = campaign.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    let mut rng = Rng::new(seed);
+
+    let mut play = |index: u64, shrinking: bool, name: &str, case: &[Poke]| -> Result<CaseRun> {
+        if let Some(serial) = serial {
+            while serial.try_recv().is_ok() {}
+        }
+        let scenario = campaign.scenario(name, case);
+        let mut gdb = connect()?;
+        let report = run(&scenario, &mut gdb, debug, points, serial)?;
+        let run = CaseRun {
+            index,
+            shrinking,
+            case: case.to_vec(),
+            outcome: classify(&report, &scenario, Some(debug)),
+            report,
+        };
+        progress(&run);
+        Ok(run)
+    };
+
+    let mut runs = Vec::new();
+    for index in 1..=campaign.runs {
+        let case = campaign.generate(&mut rng);
+        let name = format!("{} #{}", campaign.name, index);
+        runs.push(play(index, false, &name, &case)?);
+    }
+
+    let mut shrunk: Vec<Shrunk> = Vec::new();
+    for failed in runs.iter().filter(|r| r.outcome.failed()) {
+        if shrunk.iter().any(|s| s.outcome.same_kind(&failed.outcome)) {
+            continue;
+        }
+        let name = format!("{}: minimal {}", campaign.name, failed.outcome.kind());
+        let (case, outcome, attempts) = shrink(
+            campaign,
+            failed.case.clone(),
+            failed.outcome.clone(),
+            campaign.shrink_runs,
+            |candidate| Ok(play(failed.index, true, &name, candidate)?.outcome),
+        )?;
+        shrunk.push(Shrunk {
+            from: failed.index,
+            scenario: campaign.scenario(&name, &case),
+            case,
+            outcome,
+            attempts,
+        });
+    }
+
+    Ok(CampaignReport {
+        campaign: campaign.clone(),
+        seed,
+        runs,
+        shrunk,
+    })
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Why a scenario could not be run. A scenario that runs and does not see
+/// what it expects is a failed [`Report`](crate::Report), not an error.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    /// Well-formed TOML that is not a valid scenario.
+    Scenario(String),
+    Elf(String),
+    Rsp(pulse_rsp::Error),
+    Dwarf(pulse_dwarf::Error),
+    Xtensa(pulse_xtensa::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Toml(e) => write!(f, "bad TOML: {}", e),
+            Error::Scenario(what) => write!(f, "bad scenario: {}", what),
+            Error::Elf(e) => write!(f, "bad ELF: {}", e),
+            Error::Rsp(e) => write!(f, "debugger: {}", e),
+            Error::Dwarf(e) => write!(f, "{}", e),
+            Error::Xtensa(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Toml(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            Error::Xtensa(e) => Some(e),
+            Error::Scenario(_) | Error::Elf(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Toml(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_dwarf::Error> for Error {
+    fn from(e: pulse_dwarf::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
+
+impl From<pulse_xtensa::Error> for Error {
+    fn from(e: pulse_xtensa::Error) -> Self {
+        match e {
+            pulse_xtensa::Error::Rsp(e) => Error::Rsp(e),
+            e => Error::Xtensa(e),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Declarative injection scenarios for ghost-trigger.
+//!
+//! A scenario is a TOML file naming where to stop, when to inject (on a
+//! given detection cycle, every N cycles or after a log line), what to
+//! write (a mailbox request or named variables, resolved through DWARF),
+//! and which serial lines must follow within how long. [`run`] plays it
+//! against OpenOCD or `pulse-mock` and returns a [`Report`]; the
+//! `pulse-scenario` binary exits non-zero when any scenario fails.
+//!
+//! A [`Campaign`] generates scenarios instead: random values for named
+//! variables or mailbox signals at random cycles. [`fuzz`] runs them,
+//! [`classify`]s each run from the log and the cycle count, and
+//! [`shrink`]s the first failure of each kind to a minimal scenario.
+//!
+//! ```no_run
+//! # fn main() -> pulse_scenario::Result<()> {
+//! let scenario = pulse_scenario::Scenario::from_file("pulse-scenario/scenarios/threat_on_cycle_10.toml")?;
+//! let elf = std::fs::read("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let debug = pulse_dwarf::DebugInfo::parse(&elf)?;
+//! let points = pulse_scenario::injection_points(&elf)?;
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! let report = pulse_scenario::run(&scenario, &mut gdb, &debug, &points, None)?;
+//! print!("{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod campaign;
+pub mod error;
+pub mod outcome;
+pub mod runner;
+pub mod scenario;
+
+pub use campaign::{
+    fuzz, injection_points, shrink, Campaign, CampaignReport, CaseRun, Injectable, Knob, Poke, Rng,
+};
+pub use error::{Error, Result};
+pub use outcome::{classify, Outcome, FAULT_MARKERS};
+pub use runner::{apply, open_serial, run, stop_pc, variable_address, Injected, Report, REG_PC};
+pub use scenario::{Action, Expect, Injection, Point, Scenario, Trigger};
@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use pulse_dwarf::DebugInfo;
+use pulse_rsp::Client;
+use pulse_scenario::{injection_points, open_serial, Campaign, CaseRun, Scenario};
+
+const USAGE: &str = "\
+usage: pulse-scenario [--target HOST:PORT] [--elf PATH] [--serial DEV] <scenario.toml>...
+       pulse-scenario [--target HOST:PORT] [--elf PATH] [--serial DEV] --campaign
+                      [--seed N] [--runs N] [--out DIR] <campaign.toml>...";
+
+/// Exit code for scenarios that could not run at all, as opposed to failing.
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let mut target = None;
+    let mut elf = None;
+    let mut serial = None;
+    let mut campaign = false;
+    let mut seed = None;
+    let mut runs = None;
+    let mut out = PathBuf::from(".");
+    let mut files = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => match args.next() {
+                Some(v) => target = Some(v),
+                None => return usage(),
+            },
+            "--elf" => match args.next() {
+                Some(v) => elf = Some(PathBuf::from(v)),
+                None => return usage(),
+            },
+            "--serial" => match args.next() {
+                Some(v) => serial = Some(PathBuf::from(v)),
+                None => return usage(),
+            },
+            "--campaign" => campaign = true,
+            "--seed" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => seed = Some(v),
+                None => return usage(),
+            },
+            "--runs" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => runs = Some(v),
+                None => return usage(),
+            },
+            "--out" => match args.next() {
+                Some(v) => out = PathBuf::from(v),
+                None => return usage(),
+            },
+            "-h" | "--help" => return usage(),
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return usage();
+    }
+    let link = Link {
+        target,
+        elf,
+        serial,
+    };
+    if campaign {
+        return campaigns(&link, &files, seed, runs, &out);
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        let scenario = match Scenario::from_file(file) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        };
+        match run_one(&scenario, &link) {
+            Ok(report) => {
+                print!("{}", report);
+                if !report.passed() {
+                    failed += 1;
+                    for line in report
+                        .console
+                        .lines()
+                        .rev()
+                        .take(10)
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .rev()
+                    {
+                        println!("  | {}", line);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+    println!(
+        "{} of {} scenarios passed",
+        files.len() - failed,
+        files.len()
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Where to reach the target, from the command line. Each setting
+/// overrides the file's.
+struct Link {
+    target: Option<String>,
+    elf: Option<PathBuf>,
+    serial: Option<PathBuf>,
+}
+
+impl Link {
+    fn elf(&self, file: Option<&PathBuf>) -> pulse_scenario::Result<PathBuf> {
+        self.elf.as_ref().or(file).cloned().ok_or_else(|| {
+            pulse_scenario::Error::Scenario("no ELF, set `elf` or pass --elf".into())
+        })
+    }
+
+    fn target(&self, file: Option<&str>) -> String {
+        self.target.as_deref().or(file).map_or_else(
+            || format!("127.0.0.1:{}", pulse_rsp::DEFAULT_PORT),
+            String::from,
+        )
+    }
+
+    fn serial(
+        &self,
+        file: Option<&PathBuf>,
+    ) -> pulse_scenario::Result<Option<std::sync::mpsc::Receiver<Vec<u8>>>> {
+        match self.serial.as_ref().or(file) {
+            Some(path) => Ok(Some(open_serial(path)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn connect(target: &str) -> pulse_scenario::Result<Client> {
+    let mut gdb = Client::connect(target)?;
+    gdb.set_timeout(Some(Duration::from_secs(5)))?;
+    gdb.handshake()?;
+    Ok(gdb)
+}
+
+fn run_one(scenario: &Scenario, link: &Link) -> pulse_scenario::Result<pulse_scenario::Report> {
+    let elf = std::fs::read(link.elf(scenario.elf.as_ref())?)?;
+    let debug = DebugInfo::parse(&elf)?;
+    let points = injection_points(&elf)?;
+    let serial = link.serial(scenario.serial.as_ref())?;
+    let mut gdb = connect(&link.target(scenario.target.as_deref()))?;
+    pulse_scenario::run(scenario, &mut gdb, &debug, &points, serial.as_ref())
+}
+
+/// Run each campaign, write the minimal failing scenarios to `out`.
+fn campaigns(
+    link: &Link,
+    files: &[String],
+    seed: Option<u64>,
+    runs: Option<u64>,
+    out: &Path,
+) -> ExitCode {
+    let mut failed = false;
+    for file in files {
+        match campaign(link, file, seed, runs, out) {
+            Ok(f) => failed |= f,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn campaign(
+    link: &Link,
+    file: &str,
+    seed: Option<u64>,
+    runs: Option<u64>,
+    out: &Path,
+) -> pulse_scenario::Result<bool> {
+    let mut campaign = Campaign::from_file(file)?;
+    campaign.seed = seed.or(campaign.seed);
+    campaign.runs = runs.unwrap_or(campaign.runs);
+    let elf = std::fs::read(link.elf(campaign.elf.as_ref())?)?;
+    campaign.fill_ranges(&elf)?;
+    let debug = DebugInfo::parse(&elf)?;
+    let points = injection_points(&elf)?;
+    let serial = link.serial(campaign.serial.as_ref())?;
+    let target = link.target(campaign.target.as_deref());
+
+    let total = campaign.runs;
+    let progress = |run: &CaseRun| {
+        let case = campaign.describe(&run.case);
+        if run.shrinking {
+            println!(
+                "  shrinking run {}: {} after {}",
+                run.index, run.outcome, case
+            );
+        } else {
+            println!(
+                "run {}/{}: {} after {}",
+                run.index, total, run.outcome, case
+            );
+        }
+    };
+    let report = pulse_scenario::fuzz(
+        &campaign,
+        &debug,
+        &points,
+        || connect(&target),
+        serial.as_ref(),
+        progress,
+    )?;
+    print!("{}", report);
+
+    let stem = Path::new(file)
+        .file_stem()
+        .map_or("campaign".into(), |s| s.to_string_lossy().into_owned());
+    for shrunk in &report.shrunk {
+        let path = out.join(format!(
+            "{}-{}.toml",
+            stem,
+            shrunk.outcome.kind().replace(' ', "-")
+        ));
+        let header = format!(
+            "# {}, shrunk from run {} of `{}` (seed {})\n",
+            shrunk.outcome, shrunk.from, file, report.seed
+        );
+        std::fs::write(&path, header + &shrunk.scenario.to_toml())?;
+        println!("  wrote {}", path.display());
+    }
+    Ok(report.failed())
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(EXIT_ERROR)
+}
@@ -0,0 +1,133 @@
+//! What a fault-injection run did to the firmware, read from its log and
+//! the cycle breakpoint.
+
+use std::fmt;
+use std::mem;
+
+use pulse_dwarf::DebugInfo;
+use pulse_log::strip_ansi;
+use pulse_panic::{parse_dumps, Cause, CrashReport};
+
+use crate::runner::Report;
+use crate::scenario::Scenario;
+
+/// Lines that only show up once the firmware died. Campaign scenarios
+/// expect them absent so a run stops as soon as one is printed.
+pub const FAULT_MARKERS: [&str; 4] = [
+    "Guru Meditation Error",
+    "abort() was called",
+    "***ERROR*** A stack overflow",
+    "Task watchdog got triggered",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Kept cycling and printed the response, if one was asked for.
+    Expected,
+    /// Kept cycling without printing the response.
+    NoResponse,
+    /// A panic dump or a reset nobody asked for, summarized.
+    Crash(String),
+    /// Detection cycles stopped and nothing was printed about why.
+    Hang,
+    /// The task or interrupt watchdog fired.
+    WatchdogReset(String),
+}
+
+impl Outcome {
+    pub fn failed(&self) -> bool {
+        *self != Outcome::Expected
+    }
+
+    /// Same kind of outcome, whatever the details. Shrinking keeps a
+    /// smaller case when it still fails this way.
+    pub fn same_kind(&self, other: &Outcome) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Outcome::Expected => "expected response",
+            Outcome::NoResponse => "no response",
+            Outcome::Crash(_) => "crash",
+            Outcome::Hang => "hang",
+            Outcome::WatchdogReset(_) => "watchdog reset",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Crash(what) | Outcome::WatchdogReset(what) => {
+                write!(f, "{}: {}", self.kind(), what)
+            }
+            _ => f.write_str(self.kind()),
+        }
+    }
+}
+
+/// Classify a run of `scenario`.
+///
+/// Watchdogs and crashes come from the log: panic dumps (an interrupt
+/// watchdog is a `Guru Meditation` too), the task watchdog's report, and
+/// ROM boot banners after the one the scenario's own reset printed. A run
+/// that ends before `until_cycle` hung, unless the cycles kept coming
+/// after the last injection and only the response was missing.
+pub fn classify(report: &Report, scenario: &Scenario, debug: Option<&DebugInfo>) -> Outcome {
+    let lines: Vec<String> = report
+        .console
+        .lines()
+        .map(|l| strip_ansi(l).trim().to_string())
+        .collect();
+
+    if let Some(at) = lines
+        .iter()
+        .position(|l| l.contains("Task watchdog got triggered"))
+    {
+        // `E (5312) task_wdt:  - IDLE0 (CPU 0)` for each task that starved
+        let starved: Vec<&str> = lines[at + 1..]
+            .iter()
+            .take_while(|l| l.contains("task_wdt:"))
+            .filter_map(|l| l.split_once("task_wdt:")?.1.trim().strip_prefix("- "))
+            .collect();
+        return Outcome::WatchdogReset(if starved.is_empty() {
+            "task watchdog".to_string()
+        } else {
+            format!("task watchdog, {} starved", starved.join(", "))
+        });
+    }
+    if let Some(dump) = parse_dumps(&report.console).into_iter().next() {
+        let watchdog = matches!(&dump.cause, Cause::Exception(reason)
+            if reason.to_ascii_lowercase().contains("wdt"));
+        let summary = CrashReport::new(dump, debug).summary();
+        return if watchdog {
+            Outcome::WatchdogReset(summary)
+        } else {
+            Outcome::Crash(summary)
+        };
+    }
+    // `rst:0x8 (TG1WDT_SYS_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)`
+    let skip = usize::from(scenario.reset);
+    if let Some(banner) = lines.iter().filter(|l| l.starts_with("rst:0x")).nth(skip) {
+        let reason = banner.split(',').next().unwrap_or(banner);
+        return if reason.contains("WDT") {
+            Outcome::WatchdogReset(reason.to_string())
+        } else {
+            Outcome::Crash(format!("unexpected reset, {}", reason))
+        };
+    }
+
+    let responses = scenario.expectations.iter().filter(|e| !e.absent).count();
+    let responded = report.matched.len() >= responses;
+    let ended = scenario
+        .until_cycle
+        .is_none_or(|until| report.cycles >= until);
+    let last = report.injected.last().map_or(0, |i| i.cycle);
+    match (ended, responded) {
+        (true, true) => Outcome::Expected,
+        (true, false) => Outcome::NoResponse,
+        (false, false) if report.cycles > last => Outcome::NoResponse,
+        (false, _) => Outcome::Hang,
+    }
+}
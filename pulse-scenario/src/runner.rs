@@ -0,0 +1,517 @@
+//! Drives a scenario against a live GDB stub.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulse_core::injection::RawEntry;
+use pulse_core::mailbox::{check_header, MAILBOX_SYMBOL, OFFSET_MAGIC, OFFSET_VERSION};
+use pulse_core::mailbox::{OFFSET_PAYLOAD, OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_dwarf::{DebugInfo, Encoding, Location, TypeKind, Variable};
+use pulse_rsp::{BreakpointKind, Client, StopReply};
+use pulse_xtensa::{TargetDescription, Window};
+
+use crate::error::{Error, Result};
+use crate::scenario::{Action, Expect, Point, Scenario, Trigger};
+
+/// How often deadlines are checked while the target runs between stops.
+const POLL: Duration = Duration::from_millis(100);
+
+/// GDB register number of the ESP32 program counter.
+pub const REG_PC: u32 = 0;
+
+/// GDB register holding DWARF register `reg`.
+///
+/// DWARF numbers the current frame's `a0`-`a15`; the stub numbers the 64
+/// physical `ar` registers, and which of them the frame sees moves with
+/// every call, so the window base is read first.
+fn gdb_register(gdb: &mut Client, reg: u16) -> Result<u32> {
+    let desc = TargetDescription::fetch_or_esp32(gdb)?;
+    Ok(Window::read(gdb, &desc)?.regnum(u32::from(reg)))
+}
+
+/// Read the log from a serial device on a background thread.
+pub fn open_serial(path: impl AsRef<Path>) -> std::io::Result<Receiver<Vec<u8>>> {
+    let mut port = File::open(path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0u8; 256];
+        while let Ok(n @ 1..) = port.read(&mut buf) {
+            if tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// An injection that was applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injected {
+    /// Cycle breakpoint hits so far.
+    pub cycle: u64,
+    pub pc: u64,
+    pub elapsed: Duration,
+    pub what: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub name: String,
+    pub cycles: u64,
+    pub injected: Vec<Injected>,
+    /// Positive expectations that matched, with when.
+    pub matched: Vec<(String, Duration)>,
+    /// First failed expectation, `None` if the scenario passed.
+    pub failure: Option<String>,
+    pub elapsed: Duration,
+    /// Everything the target printed.
+    pub console: String,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed() { "PASS" } else { "FAIL" };
+        writeln!(
+            f,
+            "{} {} ({} cycles, {:.1}s)",
+            verdict,
+            self.name,
+            self.cycles,
+            self.elapsed.as_secs_f64()
+        )?;
+        for i in &self.injected {
+            writeln!(f, "  cycle {:>4} @ {:#010x}: {}", i.cycle, i.pc, i.what)?;
+        }
+        for (log, at) in &self.matched {
+            writeln!(f, "  saw {:?} after {:.1}s", log, at.as_secs_f64())?;
+        }
+        if let Some(failure) = &self.failure {
+            writeln!(f, "  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `scenario` on a connected client (after `handshake`).
+///
+/// The log comes from `O` packets and, if given, `serial`. Breakpoints are
+/// removed and the target detached afterwards, pass or fail. Writes are
+/// checked against `points`, see [`apply`].
+pub fn run(
+    scenario: &Scenario,
+    gdb: &mut Client,
+    debug: &DebugInfo,
+    points: &[(String, RawEntry)],
+    serial: Option<&Receiver<Vec<u8>>>,
+) -> Result<Report> {
+    let locate = |point: &Point| match point {
+        Point::Address(addr) => Ok(*addr),
+        Point::Symbol(name) => debug
+            .symbol(name)
+            .ok_or_else(|| Error::Scenario(format!("no symbol `{}` in the ELF", name))),
+    };
+    let cycle_at = locate(&scenario.cycle)?;
+    let inject_at = scenario
+        .injections
+        .iter()
+        .map(|i| i.at.as_ref().map_or(Ok(cycle_at), locate))
+        .collect::<Result<Vec<u64>>>()?;
+    let mut breakpoints = vec![cycle_at];
+    for &at in &inject_at {
+        if !breakpoints.contains(&at) {
+            breakpoints.push(at);
+        }
+    }
+
+    gdb.set_timeout(Some(POLL))?;
+    if scenario.reset {
+        gdb.monitor("reset halt")?;
+    }
+    for &at in &breakpoints {
+        gdb.insert_point(BreakpointKind::Hardware, at, 2)?;
+    }
+
+    let mut run = Run {
+        scenario,
+        start: Instant::now(),
+        serial,
+        console: String::new(),
+        scanned: 0,
+        pending: vec![false; scenario.injections.len()],
+        next: 0,
+        clock: Instant::now(),
+        finished: false,
+        report: Report {
+            name: scenario.name.clone(),
+            cycles: 0,
+            injected: Vec::new(),
+            matched: Vec::new(),
+            failure: None,
+            elapsed: Duration::ZERO,
+            console: String::new(),
+        },
+    };
+    let outcome = run.drive(gdb, debug, points, cycle_at, &inject_at);
+
+    // leave the board running without our breakpoints, also after errors
+    for &at in &breakpoints {
+        let _ = gdb.remove_point(BreakpointKind::Hardware, at, 2);
+    }
+    let _ = gdb.detach();
+    outcome?;
+
+    run.report.elapsed = run.start.elapsed();
+    run.report.console = run.console;
+    Ok(run.report)
+}
+
+struct Run<'a> {
+    scenario: &'a Scenario,
+    start: Instant,
+    serial: Option<&'a Receiver<Vec<u8>>>,
+    console: String,
+    /// Console bytes already split into lines.
+    scanned: usize,
+    pending: Vec<bool>,
+    /// Next positive expectation to match.
+    next: usize,
+    /// When the current expectation's `within` started counting.
+    clock: Instant,
+    /// Everything expected was seen, the run reached `until_cycle`, or it
+    /// timed out with nothing left to wait for.
+    finished: bool,
+    report: Report,
+}
+
+impl Run<'_> {
+    fn expectations(&self) -> impl Iterator<Item = &Expect> {
+        self.scenario.expectations.iter().filter(|e| !e.absent)
+    }
+
+    fn done(&self) -> bool {
+        self.finished || self.report.failure.is_some()
+    }
+
+    fn drive(
+        &mut self,
+        gdb: &mut Client,
+        debug: &DebugInfo,
+        points: &[(String, RawEntry)],
+        cycle_at: u64,
+        inject_at: &[u64],
+    ) -> Result<()> {
+        loop {
+            gdb.resume()?;
+            let stop = loop {
+                match gdb.wait_stop() {
+                    Ok(stop) => break stop,
+                    Err(e) if e.is_timeout() => {
+                        self.collect(gdb);
+                        self.check_deadlines();
+                        if self.done() {
+                            gdb.interrupt()?;
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            self.collect(gdb);
+            self.check_deadlines();
+            if self.done() {
+                return Ok(());
+            }
+
+            let pc = match &stop {
+                StopReply::Signal { .. } => stop_pc(gdb, &stop)?,
+                _ => {
+                    self.fail("target exited".into());
+                    return Ok(());
+                }
+            };
+            if pc == cycle_at {
+                self.report.cycles += 1;
+                let cycle = self.report.cycles;
+                if self.scenario.until_cycle == Some(cycle) {
+                    self.end_of_run();
+                    return Ok(());
+                }
+                for (i, injection) in self.scenario.injections.iter().enumerate() {
+                    match injection.trigger {
+                        Trigger::Cycle(n) if n == cycle => self.pending[i] = true,
+                        Trigger::Every(n) if cycle.is_multiple_of(n) => self.pending[i] = true,
+                        _ => {}
+                    }
+                }
+            }
+            for (i, injection) in self.scenario.injections.iter().enumerate() {
+                if !self.pending[i] || inject_at[i] != pc {
+                    continue;
+                }
+                self.pending[i] = false;
+                for action in &injection.actions {
+                    let what = apply(gdb, debug, points, action, pc)?;
+                    self.report.injected.push(Injected {
+                        cycle: self.report.cycles,
+                        pc,
+                        elapsed: self.start.elapsed(),
+                        what,
+                    });
+                }
+                self.clock = Instant::now();
+            }
+        }
+    }
+
+    /// `until_cycle` was reached.
+    fn end_of_run(&mut self) {
+        let waiting = self.expectations().nth(self.next).map(|e| e.log.clone());
+        match waiting {
+            Some(log) => self.fail(format!(
+                "run ended on cycle {} waiting for {:?}",
+                self.report.cycles, log
+            )),
+            None => self.finished = true,
+        }
+    }
+
+    fn fail(&mut self, why: String) {
+        if self.report.failure.is_none() {
+            self.report.failure = Some(why);
+        }
+    }
+
+    /// Pull new output and run complete lines past the triggers and
+    /// expectations.
+    fn collect(&mut self, gdb: &mut Client) {
+        self.console
+            .push_str(&String::from_utf8_lossy(&gdb.take_console()));
+        if let Some(serial) = &self.serial {
+            while let Ok(bytes) = serial.try_recv() {
+                self.console.push_str(&String::from_utf8_lossy(&bytes));
+            }
+        }
+
+        while let Some(end) = self.console[self.scanned..].find('\n') {
+            let line = self.console[self.scanned..self.scanned + end].to_string();
+            self.scanned += end + 1;
+            self.line(&line);
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        for (i, injection) in self.scenario.injections.iter().enumerate() {
+            if let Trigger::Log(text) = &injection.trigger {
+                if line.contains(text.as_str()) {
+                    self.pending[i] = true;
+                }
+            }
+        }
+        if let Some(absent) = self
+            .scenario
+            .expectations
+            .iter()
+            .find(|e| e.absent && line.contains(e.log.as_str()))
+        {
+            let why = format!(
+                "saw {:?}, which should not appear: {}",
+                absent.log,
+                line.trim_end()
+            );
+            self.fail(why);
+        }
+        let next = self.expectations().nth(self.next).map(|e| e.log.clone());
+        if let Some(log) = next {
+            if line.contains(log.as_str()) {
+                self.report.matched.push((log, self.start.elapsed()));
+                self.next += 1;
+                self.clock = Instant::now();
+                let remaining = self.expectations().nth(self.next).is_some();
+                self.finished = !remaining && self.scenario.until_cycle.is_none();
+            }
+        }
+    }
+
+    fn check_deadlines(&mut self) {
+        let waiting = self
+            .expectations()
+            .nth(self.next)
+            .map(|e| (e.log.clone(), e.within));
+        if let Some((log, within)) = &waiting {
+            if self.clock.elapsed() > *within {
+                self.fail(format!(
+                    "timed out after {}ms waiting for {:?}",
+                    within.as_millis(),
+                    log
+                ));
+            }
+        }
+        if self.start.elapsed() > self.scenario.timeout {
+            match (waiting, self.scenario.until_cycle) {
+                (Some((log, _)), _) => self.fail(format!(
+                    "scenario timed out after {}ms waiting for {:?}",
+                    self.scenario.timeout.as_millis(),
+                    log
+                )),
+                (None, Some(until)) => self.fail(format!(
+                    "scenario timed out after {}ms on cycle {} of {}",
+                    self.scenario.timeout.as_millis(),
+                    self.report.cycles,
+                    until
+                )),
+                // only absent lines were being watched
+                (None, None) => self.finished = true,
+            }
+        }
+    }
+}
+
+/// PC of a stopped target, from the stop reply when it was expedited.
+pub fn stop_pc(gdb: &mut Client, stop: &StopReply) -> Result<u64> {
+    match stop.register(REG_PC) {
+        Some(pc) => Ok(pc),
+        None => Ok(read_word(&gdb.read_register(REG_PC)?)),
+    }
+}
+
+/// Address of a variable that lives in memory, reading its base register
+/// if it is frame-relative. `None` for registers and constants.
+pub fn variable_address(gdb: &mut Client, variable: &Variable) -> Result<Option<u64>> {
+    let mut base = None;
+    if let Location::Memory { register, .. } = variable.location {
+        let regnum = gdb_register(gdb, register)?;
+        base = Some(read_word(&gdb.read_register(regnum)?));
+    }
+    Ok(variable.location.address(|_| base))
+}
+
+fn read_word(bytes: &[u8]) -> u64 {
+    let mut word = [0u8; 8];
+    let n = bytes.len().min(8);
+    word[..n].copy_from_slice(&bytes[..n]);
+    u64::from_le_bytes(word)
+}
+
+/// Perform one action on a target stopped at `pc`, returning what was done.
+///
+/// A write must fit the variable's DWARF type, 0 or 1 for a `bool`, and
+/// stay inside the declared range if the variable is one of the
+/// `injection_point!`s in `points` (see [`injection_points`]).
+///
+/// [`injection_points`]: crate::injection_points
+pub fn apply(
+    gdb: &mut Client,
+    debug: &DebugInfo,
+    points: &[(String, RawEntry)],
+    action: &Action,
+    pc: u64,
+) -> Result<String> {
+    match action {
+        Action::Mailbox { signal, payload } => {
+            let mailbox = match debug.resolve(MAILBOX_SYMBOL, None) {
+                Ok(var) => var.location.address(|_| None),
+                Err(_) => debug.symbol(MAILBOX_SYMBOL),
+            }
+            .ok_or_else(|| Error::Scenario(format!("no `{}` in the ELF", MAILBOX_SYMBOL)))?;
+            let magic = gdb.read_u32(mailbox + u64::from(OFFSET_MAGIC))?;
+            let version = gdb.read_u32(mailbox + u64::from(OFFSET_VERSION))?;
+            check_header(magic, version).map_err(|why| {
+                Error::Scenario(format!("{} at {:#010x}: {}", MAILBOX_SYMBOL, mailbox, why))
+            })?;
+            let sequence = gdb
+                .read_u32(mailbox + u64::from(OFFSET_SEQUENCE))?
+                .wrapping_add(1);
+            gdb.write_u32(mailbox + u64::from(OFFSET_SIGNAL), signal.id())?;
+            gdb.write_u32(mailbox + u64::from(OFFSET_PAYLOAD), *payload)?;
+            gdb.write_u32(mailbox + u64::from(OFFSET_SEQUENCE), sequence)?;
+            Ok(format!(
+                "mailbox {:?} payload {} (sequence {})",
+                signal, payload, sequence
+            ))
+        }
+        Action::Write { var, value } => {
+            let variable = debug.resolve(var, Some(pc))?;
+            let size = variable.ty.size as usize;
+            if !(1..=8).contains(&size) {
+                return Err(Error::Scenario(format!(
+                    "cannot write {} into {}",
+                    *value as i64, variable
+                )));
+            }
+            let shown = checked_value(&variable, *value)?;
+            let bytes = &value.to_le_bytes()[..size];
+            match &variable.location {
+                Location::Register(reg) => {
+                    let regnum = gdb_register(gdb, *reg)?;
+                    let mut word = gdb.read_register(regnum)?;
+                    let n = size.min(word.len());
+                    word[..n].copy_from_slice(&bytes[..n]);
+                    gdb.write_register(regnum, &word)?;
+                }
+                Location::Value(_) => {
+                    return Err(Error::Scenario(format!(
+                        "{} is a constant at {:#010x}, nothing to write",
+                        variable.path, pc
+                    )));
+                }
+                _ => {
+                    let addr = variable_address(gdb, &variable)?
+                        .expect("memory locations have an address");
+                    let declared = points.iter().find(|(_, entry)| {
+                        entry.addr == addr && u64::from(entry.size) == variable.ty.size
+                    });
+                    if let Some((name, entry)) = declared {
+                        if !entry.in_range(shown) {
+                            return Err(Error::Scenario(format!(
+                                "{} = {} is outside {}'s declared range({}, {})",
+                                variable.path, shown, name, entry.min, entry.max
+                            )));
+                        }
+                    }
+                    gdb.write_memory(addr, bytes)?;
+                }
+            }
+            Ok(format!(
+                "{} = {} at {}",
+                variable.path, shown, variable.location
+            ))
+        }
+    }
+}
+
+/// `value` as the number it stands for in `variable`'s type, or an error
+/// if storing it would leave the variable holding something its type
+/// does not allow. Negative numbers arrive two's complement.
+fn checked_value(variable: &Variable, value: u64) -> Result<i64> {
+    let signed = value as i64;
+    let bits = variable.ty.size as u32 * 8;
+    let fits = match (&variable.ty.kind, variable.ty.scalar()) {
+        (TypeKind::Enum(variants), _) => variants.iter().any(|(_, d)| *d == signed),
+        (_, Some(Encoding::Bool)) => signed == 0 || signed == 1,
+        (_, Some(Encoding::Char)) => {
+            u32::try_from(value).is_ok_and(|c| char::from_u32(c).is_some())
+        }
+        (_, Some(Encoding::Signed)) => {
+            bits >= 64 || (-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(&signed)
+        }
+        _ => bits >= 64 || value >> bits == 0,
+    };
+    if !fits {
+        return Err(Error::Scenario(format!(
+            "{} does not fit {}",
+            signed, variable
+        )));
+    }
+    Ok(signed)
+}
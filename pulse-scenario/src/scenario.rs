@@ -0,0 +1,384 @@
+//! The scenario file format.
+//!
+//! ```toml
+//! name = "threat on the 10th cycle"
+//! elf = "target/xtensa-esp32-espidf/debug/ghost-trigger"
+//! timeout_ms = 30000
+//!
+//! [[inject]]
+//! cycle = 10
+//! mailbox = "threat"
+//!
+//! [[expect]]
+//! log = "THREAT DETECTED !! [Cycle: 10]"
+//! within_ms = 2000
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pulse_core::mailbox::Signal;
+use toml::{Table, Value};
+
+use crate::error::{Error, Result};
+
+/// Breakpoint that counts detection cycles unless the scenario names another.
+/// `Detector::cycle` is `#[inline(never)]` so the symbol survives `opt-level = "z"`.
+pub const CYCLE_SYMBOL: &str = "pulse_core::detector::Detector::cycle";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const DEFAULT_WITHIN: Duration = Duration::from_secs(10);
+
+/// A code location, by symbol or address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Point {
+    Symbol(String),
+    Address(u64),
+}
+
+/// When an injection fires. It is applied at the next stop at its
+/// breakpoint once the condition holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// Once, on the Nth hit of the cycle breakpoint (1-based).
+    Cycle(u64),
+    /// On every Nth hit of the cycle breakpoint.
+    Every(u64),
+    /// Each time a serial line contains the text.
+    Log(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Post a request through `PULSE_MAILBOX`, the way `Mailbox::post` does.
+    Mailbox { signal: Signal, payload: u32 },
+    /// Store `value` into a variable path, resolved through DWARF at the
+    /// stop PC. Negative values are two's complement; [`apply`] refuses
+    /// ones the variable's type or declared range do not allow.
+    ///
+    /// [`apply`]: crate::apply
+    Write { var: String, value: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    pub trigger: Trigger,
+    /// Where to stop to apply it, the cycle breakpoint if `None`.
+    pub at: Option<Point>,
+    /// Mailbox post first, then writes in file order.
+    pub actions: Vec<Action>,
+}
+
+/// A serial line the run must (or must not) print.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expect {
+    /// Substring of one line.
+    pub log: String,
+    /// Counted from the previous expectation's match or the latest
+    /// injection, whichever is later.
+    pub within: Duration,
+    /// Fail if the line shows up at any point of the run instead.
+    pub absent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scenario {
+    pub name: String,
+    /// `host:port` of OpenOCD or `pulse-mock`.
+    pub target: Option<String>,
+    pub elf: Option<PathBuf>,
+    /// Serial device to read the log from, for targets that do not forward
+    /// it over GDB.
+    pub serial: Option<PathBuf>,
+    /// `monitor reset halt` before starting, default on.
+    pub reset: bool,
+    pub cycle: Point,
+    /// Whole run.
+    pub timeout: Duration,
+    /// End the run on this hit of the cycle breakpoint instead of when the
+    /// last expectation matched. Expectations still pending then fail.
+    pub until_cycle: Option<u64>,
+    pub injections: Vec<Injection>,
+    /// Positive expectations must match in order.
+    pub expectations: Vec<Expect>,
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: Table = text.parse()?;
+        let mut fields = Fields::new(table, "scenario");
+
+        let scenario = Scenario {
+            name: fields.string("name")?.unwrap_or_else(|| "scenario".into()),
+            target: fields.string("target")?,
+            elf: fields.string("elf")?.map(PathBuf::from),
+            serial: fields.string("serial")?.map(PathBuf::from),
+            reset: fields.bool("reset")?.unwrap_or(true),
+            cycle: fields
+                .point("cycle")?
+                .unwrap_or_else(|| Point::Symbol(CYCLE_SYMBOL.into())),
+            timeout: fields.millis("timeout_ms")?.unwrap_or(DEFAULT_TIMEOUT),
+            until_cycle: fields.u64("until_cycle")?,
+            injections: fields
+                .tables("inject")?
+                .into_iter()
+                .map(|t| injection(Fields::new(t, "[[inject]]")))
+                .collect::<Result<_>>()?,
+            expectations: fields
+                .tables("expect")?
+                .into_iter()
+                .map(|t| expect(Fields::new(t, "[[expect]]")))
+                .collect::<Result<_>>()?,
+        };
+        if scenario.until_cycle == Some(0) {
+            return Err(fields.invalid("cycle counts start at 1"));
+        }
+        fields.finish()?;
+        Ok(scenario)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The scenario in the file format, for [`Scenario::parse`] to read back.
+    /// Keys at their default are left out.
+    pub fn to_toml(&self) -> String {
+        let quote = |s: &str| Value::String(s.to_string()).to_string();
+        let point = |p: &Point| match p {
+            Point::Symbol(name) => quote(name),
+            Point::Address(addr) => quote(&format!("{:#x}", addr)),
+        };
+        let path = |p: &PathBuf| quote(&p.to_string_lossy());
+
+        let mut out = format!("name = {}\n", quote(&self.name));
+        if let Some(target) = &self.target {
+            out += &format!("target = {}\n", quote(target));
+        }
+        if let Some(elf) = &self.elf {
+            out += &format!("elf = {}\n", path(elf));
+        }
+        if let Some(serial) = &self.serial {
+            out += &format!("serial = {}\n", path(serial));
+        }
+        if !self.reset {
+            out += "reset = false\n";
+        }
+        if self.cycle != Point::Symbol(CYCLE_SYMBOL.into()) {
+            out += &format!("cycle = {}\n", point(&self.cycle));
+        }
+        out += &format!("timeout_ms = {}\n", self.timeout.as_millis());
+        if let Some(until) = self.until_cycle {
+            out += &format!("until_cycle = {}\n", until);
+        }
+
+        for injection in &self.injections {
+            out += "\n[[inject]]\n";
+            out += &match &injection.trigger {
+                Trigger::Cycle(n) => format!("cycle = {}\n", n),
+                Trigger::Every(n) => format!("every = {}\n", n),
+                Trigger::Log(text) => format!("on_log = {}\n", quote(text)),
+            };
+            if let Some(at) = &injection.at {
+                out += &format!("at = {}\n", point(at));
+            }
+            let mut writes = Vec::new();
+            for action in &injection.actions {
+                match action {
+                    Action::Mailbox { signal, payload } => {
+                        out += &match signal {
+                            Signal::Threat => "mailbox = \"threat\"\n".to_string(),
+                            Signal::DumpAudit => "mailbox = \"dump_audit\"\n".to_string(),
+                            signal => format!("mailbox = {}\n", signal.id()),
+                        };
+                        if *payload != 0 {
+                            out += &format!("payload = {}\n", payload);
+                        }
+                    }
+                    Action::Write { var, value } => writes.push(format!(
+                        "    {{ var = {}, value = {} }},\n",
+                        quote(var),
+                        *value as i64
+                    )),
+                }
+            }
+            if !writes.is_empty() {
+                out += "write = [\n";
+                out += &writes.concat();
+                out += "]\n";
+            }
+        }
+
+        for expect in &self.expectations {
+            out += &format!("\n[[expect]]\nlog = {}\n", quote(&expect.log));
+            if expect.absent {
+                out += "absent = true\n";
+            } else {
+                out += &format!("within_ms = {}\n", expect.within.as_millis());
+            }
+        }
+        out
+    }
+}
+
+fn injection(mut fields: Fields) -> Result<Injection> {
+    let triggers = [
+        fields.u64("cycle")?.map(Trigger::Cycle),
+        fields.u64("every")?.map(Trigger::Every),
+        fields.string("on_log")?.map(Trigger::Log),
+    ];
+    let mut triggers = triggers.into_iter().flatten();
+    let trigger = match (triggers.next(), triggers.next()) {
+        (Some(Trigger::Cycle(0) | Trigger::Every(0)), _) => {
+            return Err(fields.invalid("cycle counts start at 1"))
+        }
+        (Some(trigger), None) => trigger,
+        _ => {
+            return Err(fields.invalid("needs exactly one of `cycle`, `every` or `on_log`"));
+        }
+    };
+
+    let mut actions = Vec::new();
+    if let Some(value) = fields.take("mailbox") {
+        let signal = match &value {
+            Value::String(s) if s == "threat" => Signal::Threat,
+            Value::String(s) if s == "dump_audit" => Signal::DumpAudit,
+            Value::Integer(id) => Signal::from_id(
+                u32::try_from(*id).map_err(|_| fields.invalid("`mailbox` id out of range"))?,
+            ),
+            _ => {
+                return Err(fields.invalid("`mailbox` is \"threat\", \"dump_audit\" or a signal id"))
+            }
+        };
+        let payload = fields.u64("payload")?.unwrap_or(0);
+        let payload =
+            u32::try_from(payload).map_err(|_| fields.invalid("`payload` out of range"))?;
+        actions.push(Action::Mailbox { signal, payload });
+    }
+    for write in fields.tables("write")? {
+        let mut write = Fields::new(write, "write");
+        let var = write
+            .string("var")?
+            .ok_or_else(|| write.invalid("needs `var`"))?;
+        let value = match write.take("value") {
+            Some(Value::Boolean(b)) => u64::from(b),
+            Some(Value::Integer(i)) => i as u64,
+            _ => return Err(write.invalid("`value` is an integer or a bool")),
+        };
+        write.finish()?;
+        actions.push(Action::Write { var, value });
+    }
+    if actions.is_empty() {
+        return Err(fields.invalid("needs `mailbox` or `write`"));
+    }
+
+    let at = fields.point("at")?;
+    fields.finish()?;
+    Ok(Injection {
+        trigger,
+        at,
+        actions,
+    })
+}
+
+fn expect(mut fields: Fields) -> Result<Expect> {
+    let log = fields
+        .string("log")?
+        .ok_or_else(|| fields.invalid("needs `log`"))?;
+    let expect = Expect {
+        log,
+        within: fields.millis("within_ms")?.unwrap_or(DEFAULT_WITHIN),
+        absent: fields.bool("absent")?.unwrap_or(false),
+    };
+    fields.finish()?;
+    Ok(expect)
+}
+
+/// Typed access to a TOML table that rejects keys nobody asked for, so a
+/// typo like `with_ms` is an error instead of a silently ignored timeout.
+pub(crate) struct Fields {
+    table: Table,
+    what: &'static str,
+}
+
+impl Fields {
+    pub(crate) fn new(table: Table, what: &'static str) -> Self {
+        Self { table, what }
+    }
+
+    pub(crate) fn invalid(&self, why: &str) -> Error {
+        Error::Scenario(format!("{}: {}", self.what, why))
+    }
+
+    pub(crate) fn take(&mut self, key: &str) -> Option<Value> {
+        self.table.remove(key)
+    }
+
+    pub(crate) fn wrong_type(&self, key: &str, expected: &str) -> Error {
+        self.invalid(&format!("`{}` must be {}", key, expected))
+    }
+
+    pub(crate) fn string(&mut self, key: &str) -> Result<Option<String>> {
+        match self.take(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(_) => Err(self.wrong_type(key, "a string")),
+        }
+    }
+
+    pub(crate) fn bool(&mut self, key: &str) -> Result<Option<bool>> {
+        match self.take(key) {
+            None => Ok(None),
+            Some(Value::Boolean(b)) => Ok(Some(b)),
+            Some(_) => Err(self.wrong_type(key, "true or false")),
+        }
+    }
+
+    pub(crate) fn u64(&mut self, key: &str) -> Result<Option<u64>> {
+        match self.take(key) {
+            None => Ok(None),
+            Some(Value::Integer(i)) if i >= 0 => Ok(Some(i as u64)),
+            Some(_) => Err(self.wrong_type(key, "a non-negative integer")),
+        }
+    }
+
+    pub(crate) fn millis(&mut self, key: &str) -> Result<Option<Duration>> {
+        Ok(self.u64(key)?.map(Duration::from_millis))
+    }
+
+    pub(crate) fn point(&mut self, key: &str) -> Result<Option<Point>> {
+        match self.take(key) {
+            None => Ok(None),
+            Some(Value::Integer(addr)) if addr >= 0 => Ok(Some(Point::Address(addr as u64))),
+            Some(Value::String(s)) => Ok(Some(match s.strip_prefix("0x") {
+                Some(hex) => Point::Address(
+                    u64::from_str_radix(hex, 16)
+                        .map_err(|_| self.wrong_type(key, "a symbol or an address"))?,
+                ),
+                None => Point::Symbol(s),
+            })),
+            Some(_) => Err(self.wrong_type(key, "a symbol or an address")),
+        }
+    }
+
+    pub(crate) fn tables(&mut self, key: &str) -> Result<Vec<Table>> {
+        match self.take(key) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::Table(t) => Ok(t),
+                    _ => Err(self.wrong_type(key, "an array of tables")),
+                })
+                .collect(),
+            Some(_) => Err(self.wrong_type(key, "an array of tables")),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        match self.table.keys().next() {
+            Some(key) => Err(self.invalid(&format!("unknown key `{}`", key))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::thread;
+use std::time::Duration;
+
+use pulse_dwarf::DebugInfo;
+use pulse_mock::{fixture, Image, Options, Server};
+use pulse_rsp::Client;
+use pulse_scenario::runner::Injected;
+use pulse_scenario::{classify, shrink, Campaign, Error, Outcome, Poke, Report, Rng, Scenario};
+
+fn campaign(text: &str) -> Campaign {
+    Campaign::parse(text).unwrap()
+}
+
+#[test]
+fn generates_reproducible_cases() {
+    let bundled = Campaign::from_file(format!(
+        "{}/campaigns/robustness.toml",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    assert_eq!(bundled.injectables.len(), 3);
+    assert_eq!(bundled.injectables[1].range, Some((0, u32::MAX as i64)));
+
+    let mut fuzz = campaign(
+        r#"
+        injections = 3
+
+        [[var]]
+        path = "CYCLE_MS"
+
+        [[mailbox]]
+        signals = [1, 3]
+        at = "ghost_trigger::main"
+        "#,
+    );
+    assert_eq!(fuzz.injectables[0].range, None);
+    fuzz.fill_ranges(&fixture::ghost_trigger()).unwrap();
+    assert_eq!(fuzz.injectables[0].range, Some((10, 60_000)));
+
+    let draw = |seed| {
+        let mut rng = Rng::new(seed);
+        (0..50).map(|_| fuzz.generate(&mut rng)).collect::<Vec<_>>()
+    };
+    let cases = draw(7);
+    assert_eq!(cases, draw(7));
+    assert_ne!(cases, draw(8));
+    for case in &cases {
+        assert!((1..=3).contains(&case.len()));
+        assert!(case.windows(2).all(|w| w[0].cycle <= w[1].cycle));
+        for poke in case {
+            assert!((1..=10).contains(&poke.cycle));
+            let (lo, hi) = fuzz.injectables[poke.injectable].range.unwrap();
+            assert!((lo..=hi).contains(&poke.value));
+        }
+    }
+
+    // every case is a plain scenario, and reads back from its file
+    let scenario = fuzz.scenario("case", &cases[0]);
+    assert_eq!(Scenario::parse(&scenario.to_toml()).unwrap(), scenario);
+    let last = cases[0].last().unwrap().cycle;
+    assert_eq!(scenario.until_cycle, Some(last + 4));
+
+    let mut unknown = campaign("[[var]]\npath = \"detector.counter\"");
+    match unknown.fill_ranges(&fixture::ghost_trigger()) {
+        Err(Error::Scenario(msg)) => assert!(msg.contains("not a registered injection point")),
+        other => panic!("{:?}", other),
+    }
+    let mut wider = campaign("[[var]]\npath = \"CYCLE_MS\"\nrange = [0, 100]");
+    match wider.fill_ranges(&fixture::ghost_trigger()) {
+        Err(Error::Scenario(msg)) => assert!(msg.contains("leaves its declared range(10, 60000)")),
+        other => panic!("{:?}", other),
+    }
+    for (text, why) in [
+        ("runs = 5", "needs a `[[var]]` or a `[[mailbox]]`"),
+        ("[[var]]\npath = \"x\"\nrange = [5, 1]", "`[min, max]`"),
+        ("cycles = [0, 3]\n[[mailbox]]", "start at 1"),
+        ("[[mailbox]]\nsignal = [0, 3]", "unknown key `signal`"),
+    ] {
+        match Campaign::parse(text) {
+            Err(Error::Scenario(msg)) => assert!(msg.contains(why), "{}", msg),
+            other => panic!("{:?} for {:?}", other, text),
+        }
+    }
+}
+
+#[test]
+fn classifies_recorded_logs() {
+    let fuzz = campaign(
+        r#"
+        [[mailbox]]
+        signals = [0, 3]
+
+        [response]
+        log = "THREAT DETECTED"
+        "#,
+    );
+    let scenario = fuzz.scenario(
+        "case",
+        &[Poke {
+            injectable: 0,
+            cycle: 2,
+            value: 1,
+        }],
+    );
+    assert_eq!(scenario.until_cycle, Some(6));
+    let outcome = |console: &str, cycles: u64, matched: usize| {
+        let report = Report {
+            name: "case".into(),
+            cycles,
+            injected: vec![Injected {
+                cycle: 2,
+                pc: fixture::DETECTOR_CYCLE,
+                elapsed: Duration::ZERO,
+                what: "mailbox Threat payload 0 (sequence 1)".into(),
+            }],
+            matched: vec![("THREAT DETECTED".into(), Duration::ZERO); matched],
+            failure: None,
+            elapsed: Duration::ZERO,
+            console: console.into(),
+        };
+        classify(&report, &scenario, None)
+    };
+
+    let boot = "rst:0x10 (RTCWDT_RTC_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)\n\
+                I (312) ghost_trigger: System altered!\n";
+    assert_eq!(outcome(boot, 6, 1), Outcome::Expected);
+    assert_eq!(outcome(boot, 6, 0), Outcome::NoResponse);
+    // cycles kept coming, the response did not
+    assert_eq!(outcome(boot, 4, 0), Outcome::NoResponse);
+    assert_eq!(outcome(boot, 2, 0), Outcome::Hang);
+    assert_eq!(outcome(boot, 4, 1), Outcome::Hang);
+
+    let guru = format!(
+        "{}Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled.\n\
+         Core  0 register dump:\n\
+         PC      : 0x400d02c0  PS      : 0x00060f30  A0      : 0x800d0123  A1      : 0x3ffe1ec0\n\
+         Backtrace: 0x400d02bd:0x3ffe1ec0 0x400d0120:0x3ffe1ee0\n\
+         Rebooting...\n",
+        boot
+    );
+    assert_eq!(
+        outcome(&guru, 3, 1),
+        Outcome::Crash("Guru Meditation on core 0: LoadProhibited".into())
+    );
+    let panic = "thread 'main' panicked at src/main.rs:42:5:\n\
+                 index out of bounds\n\
+                 abort() was called at PC 0x400d1234 on core 0\n";
+    assert!(
+        matches!(outcome(panic, 3, 1), Outcome::Crash(s) if s.starts_with("abort() at 0x400d1234"))
+    );
+
+    let int_wdt =
+        "Guru Meditation Error: Core  1 panic'ed (Interrupt wdt timeout on CPU1).\nRebooting...\n";
+    assert!(matches!(
+        outcome(int_wdt, 3, 1),
+        Outcome::WatchdogReset(s) if s.contains("Interrupt wdt timeout on CPU1")
+    ));
+    let task_wdt = "\x1b[0;31mE (5312) task_wdt: Task watchdog got triggered. The following tasks/users did not reset the watchdog in time:\x1b[0m\n\
+                    \x1b[0;31mE (5312) task_wdt:  - IDLE0 (CPU 0)\x1b[0m\n\
+                    \x1b[0;31mE (5312) task_wdt: Tasks currently running:\x1b[0m\n";
+    assert_eq!(
+        outcome(task_wdt, 3, 0),
+        Outcome::WatchdogReset("task watchdog, IDLE0 (CPU 0) starved".into())
+    );
+    let reboot = format!(
+        "{}rst:0x8 (TG1WDT_SYS_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)\n",
+        boot
+    );
+    assert_eq!(
+        outcome(&reboot, 6, 1),
+        Outcome::WatchdogReset("rst:0x8 (TG1WDT_SYS_RESET)".into())
+    );
+    let brownout = format!("{}rst:0xf (BROWNOUT_RST),boot:0x13\n", boot);
+    assert!(matches!(outcome(&brownout, 6, 1), Outcome::Crash(s) if s.contains("BROWNOUT")));
+}
+
+#[test]
+fn shrinks_to_the_smallest_failure() {
+    let fuzz = campaign(
+        r#"
+        [[var]]
+        path = "x"
+        range = [-1000, 1000]
+
+        [[mailbox]]
+        "#,
+    );
+    // fails once x >= 300 is written on cycle 4 or later
+    let attempt = |case: &[Poke]| {
+        let fails = case
+            .iter()
+            .any(|p| p.injectable == 0 && p.value >= 300 && p.cycle >= 4);
+        Ok(if fails {
+            Outcome::Crash("boom".into())
+        } else {
+            Outcome::Expected
+        })
+    };
+    let case = vec![
+        Poke {
+            injectable: 1,
+            cycle: 2,
+            value: 17,
+        },
+        Poke {
+            injectable: 0,
+            cycle: 6,
+            value: -40,
+        },
+        Poke {
+            injectable: 0,
+            cycle: 9,
+            value: 977,
+        },
+    ];
+    let crash = Outcome::Crash("boom".into());
+    let (minimal, outcome, tries) =
+        shrink(&fuzz, case.clone(), crash.clone(), 200, attempt).unwrap();
+    assert_eq!(
+        minimal,
+        [Poke {
+            injectable: 0,
+            cycle: 4,
+            value: 300
+        }]
+    );
+    assert_eq!(outcome, crash);
+    assert!(tries < 200, "{}", tries);
+
+    // out of budget: the best found so far
+    let (partial, _, tries) = shrink(&fuzz, case, crash, 2, attempt).unwrap();
+    assert_eq!(tries, 2);
+    assert_eq!(partial.len(), 2);
+}
+
+#[test]
+fn campaign_on_the_mock() {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let points = pulse_scenario::injection_points(&elf).unwrap();
+    let connect = || {
+        let mut gdb = Client::connect(addr)?;
+        gdb.set_timeout(Some(Duration::from_secs(5)))?;
+        gdb.handshake()?;
+        Ok(gdb)
+    };
+
+    // only signal 1 is a threat
+    let fuzz = campaign(
+        r#"
+        name = "signals"
+        seed = 11
+        runs = 6
+        cycles = [2, 6]
+        settle = 2
+        timeout_ms = 10000
+
+        [[mailbox]]
+        signals = [0, 3]
+
+        [response]
+        log = "THREAT DETECTED"
+        within_ms = 5000
+        "#,
+    );
+    let mut seen = 0;
+    let report =
+        pulse_scenario::fuzz(&fuzz, &debug, &points, connect, None, |_| seen += 1).unwrap();
+    assert_eq!(report.seed, 11);
+    assert_eq!(report.runs.len(), 6);
+    for run in &report.runs {
+        let threat = run.case.iter().any(|p| p.value == 1);
+        assert_eq!(run.outcome.failed(), !threat, "{}", run.report);
+    }
+    assert!(report.failed());
+    assert!(report.runs.iter().any(|r| !r.outcome.failed()));
+    assert_eq!(report.shrunk.len(), 1);
+    let shrunk = &report.shrunk[0];
+    assert_eq!(shrunk.outcome, Outcome::NoResponse);
+    assert_eq!(
+        shrunk.case,
+        [Poke {
+            injectable: 0,
+            cycle: 2,
+            value: 0
+        }]
+    );
+    assert_eq!(seen, 6 + shrunk.attempts);
+    assert!(report
+        .to_string()
+        .contains("minimal no response (from run "));
+
+    // the minimal case replays as a failing scenario file
+    let scenario = Scenario::parse(&shrunk.scenario.to_toml()).unwrap();
+    let mut gdb = connect().unwrap();
+    let replay = pulse_scenario::run(&scenario, &mut gdb, &debug, &points, None).unwrap();
+    assert_eq!(
+        replay.failure.as_deref(),
+        Some("run ended on cycle 5 waiting for \"THREAT DETECTED\"")
+    );
+}
@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use pulse_core::mailbox::{self, Signal};
+use pulse_dwarf::DebugInfo;
+use pulse_mock::{cpu, fixture, Image, Options, Server};
+use pulse_rsp::{BreakpointKind, Client};
+use pulse_scenario::{Action, Error, Report, Scenario, Trigger};
+
+fn run(scenario: &Scenario) -> Report {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let points = pulse_scenario::injection_points(&elf).unwrap();
+    pulse_scenario::run(scenario, &mut gdb, &debug, &points, None).unwrap()
+}
+
+fn bundled(name: &str) -> Scenario {
+    Scenario::from_file(format!(
+        "{}/scenarios/{}.toml",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+#[test]
+fn parses_the_format() {
+    let scenario = bundled("threat_on_cycle_10");
+    assert_eq!(scenario.injections.len(), 1);
+    assert_eq!(scenario.injections[0].trigger, Trigger::Cycle(10));
+    assert_eq!(
+        scenario.injections[0].actions,
+        [Action::Mailbox {
+            signal: Signal::Threat,
+            payload: 0
+        }]
+    );
+    assert_eq!(scenario.expectations[0].within, Duration::from_secs(3));
+    assert!(scenario.reset);
+
+    let writes = bundled("mailbox_by_name");
+    assert_eq!(
+        writes.injections[0].trigger,
+        Trigger::Log("System secure. [Cycle: 1]".into())
+    );
+    assert_eq!(writes.injections[0].actions.len(), 3);
+    assert!(bundled("audit_dump").expectations[3].absent);
+}
+
+#[test]
+fn rejects_bad_scenarios() {
+    for (text, why) in [
+        (
+            "[[expect]]\nlog = \"x\"\nwith_ms = 5",
+            "unknown key `with_ms`",
+        ),
+        (
+            "[[inject]]\ncycle = 1\nevery = 2\nmailbox = \"threat\"",
+            "exactly one of",
+        ),
+        ("[[inject]]\ncycle = 3", "needs `mailbox` or `write`"),
+        ("[[inject]]\nevery = 0\nmailbox = 1", "start at 1"),
+        (
+            "[[inject]]\ncycle = 3\nwrite = [{ var = \"x\", value = \"on\" }]",
+            "integer or a bool",
+        ),
+    ] {
+        match Scenario::parse(text) {
+            Err(Error::Scenario(msg)) => assert!(msg.contains(why), "{}", msg),
+            other => panic!("{:?} for {:?}", other, text),
+        }
+    }
+    assert!(matches!(Scenario::parse("name = "), Err(Error::Toml(_))));
+}
+
+#[test]
+fn bundled_scenarios_pass_on_the_mock() {
+    let report = run(&bundled("threat_on_cycle_10"));
+    assert!(report.passed(), "{}\n{}", report, report.console);
+    assert_eq!(report.injected.len(), 1);
+    assert_eq!(report.injected[0].cycle, 10);
+    assert_eq!(report.injected[0].pc, fixture::DETECTOR_CYCLE);
+    assert_eq!(report.cycles, 12);
+
+    for name in ["mailbox_by_name", "audit_dump"] {
+        let report = run(&bundled(name));
+        assert!(report.passed(), "{}\n{}", report, report.console);
+    }
+}
+
+#[test]
+fn failures_are_reported() {
+    let late = Scenario::parse(
+        r#"
+        [[expect]]
+        log = "THREAT DETECTED"
+        within_ms = 300
+        "#,
+    )
+    .unwrap();
+    let report = run(&late);
+    assert!(!report.passed());
+    assert!(report
+        .failure
+        .unwrap()
+        .contains("timed out after 300ms waiting for \"THREAT DETECTED\""));
+
+    let absent = Scenario::parse(
+        r#"
+        timeout_ms = 5000
+
+        [[inject]]
+        every = 3
+        at = "ghost_trigger::main"
+        mailbox = 9
+
+        [[expect]]
+        log = "Ignoring unknown injection signal"
+        absent = true
+        "#,
+    )
+    .unwrap();
+    let report = run(&absent);
+    assert_eq!(report.injected[0].pc, fixture::MAIN);
+    assert!(report.failure.unwrap().contains("should not appear"));
+}
+
+#[test]
+fn frame_locals_follow_the_register_window() {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+    gdb.insert_point(BreakpointKind::Hardware, fixture::MAIN, 2)
+        .unwrap();
+    gdb.cont().unwrap();
+
+    // main runs one call8 in: its a1 is ar9, not the ar1 of app_main
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let detector = debug.resolve("detector", Some(fixture::MAIN_LOOP)).unwrap();
+    let main_sp = cpu::STACK_BASE + cpu::STACK_SIZE as u64 - 0x100 - 0x40;
+    assert_eq!(
+        pulse_scenario::variable_address(&mut gdb, &detector).unwrap(),
+        Some(main_sp + 32)
+    );
+}
+
+#[test]
+fn writes_must_fit_the_type_and_declared_range() {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let points = pulse_scenario::injection_points(&elf).unwrap();
+    // `threat_detected` is still spilled to the frame here
+    let pc = fixture::MAIN + 0x20;
+    let mut write = |var: &str, value: i64| {
+        let action = Action::Write {
+            var: var.into(),
+            value: value as u64,
+        };
+        pulse_scenario::apply(&mut gdb, &debug, &points, &action, pc)
+    };
+
+    assert!(write("CYCLE_MS", 16).unwrap().contains("CYCLE_MS = 16"));
+    for (var, value, why) in [
+        (
+            "CYCLE_MS",
+            0,
+            "outside CYCLE_MS's declared range(10, 60000)",
+        ),
+        ("CYCLE_MS", 60_001, "declared range"),
+        ("CYCLE_MS", -3, "-3 does not fit"),
+        ("threat_detected", 2, "2 does not fit"),
+        ("PULSE_MAILBOX.ack", 1 << 32, "does not fit"),
+    ] {
+        match write(var, value) {
+            Err(Error::Scenario(msg)) => assert!(msg.contains(why), "{}", msg),
+            other => panic!("{} = {}: {:?}", var, value, other),
+        }
+    }
+    assert!(write("threat_detected", 1).is_ok());
+    assert_eq!(
+        gdb.read_u32(fixture::CYCLE_MS).unwrap(),
+        16,
+        "rejected writes leave the target alone"
+    );
+}
+
+#[test]
+fn mailbox_header_is_checked_first() {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let threat = Action::Mailbox {
+        signal: Signal::Threat,
+        payload: 0,
+    };
+    let sequence = fixture::PULSE_MAILBOX + u64::from(mailbox::OFFSET_SEQUENCE);
+
+    // a firmware with an older mailbox layout
+    gdb.write_u32(
+        fixture::PULSE_MAILBOX + u64::from(mailbox::OFFSET_VERSION),
+        1,
+    )
+    .unwrap();
+    match pulse_scenario::apply(&mut gdb, &debug, &[], &threat, fixture::MAIN) {
+        Err(Error::Scenario(msg)) => assert!(msg.contains("mailbox version 1"), "{}", msg),
+        other => panic!("{:?}", other),
+    }
+    // or none at all
+    gdb.write_u32(fixture::PULSE_MAILBOX, 0).unwrap();
+    match pulse_scenario::apply(&mut gdb, &debug, &[], &threat, fixture::MAIN) {
+        Err(Error::Scenario(msg)) => assert!(msg.contains("no mailbox"), "{}", msg),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(gdb.read_u32(sequence).unwrap(), 0, "nothing was posted");
+}
@@ -0,0 +1,34 @@
+//! Record-and-replay of ghost-trigger debug sessions.
+//!
+//! A weird injection result on the board is hard to look into once the
+//! board is gone. [`Recorder`] sits between the debugger (gdb, `pulse`,
+//! pulse-scenario) and OpenOCD, forwards every byte unchanged and writes
+//! each RSP packet, Ctrl-C and chunk of serial output with its timestamp
+//! to a session file. [`Replayer`] serves that file back as a fake target
+//! on a TCP port, so the same tools can be pointed at it and the bug
+//! reproduced and debugged offline on Linux.
+//!
+//! ```no_run
+//! # fn main() -> pulse_session::Result<()> {
+//! use pulse_session::{Header, Recorder, Replayer, Session};
+//!
+//! let header = Header { target: "127.0.0.1:3333".into(), serial: None };
+//! let file = std::fs::File::create("weird.session")?;
+//! Recorder::bind("127.0.0.1:3334", &header, file)?.serve_one()?;
+//!
+//! let session = Session::load("weird.session")?;
+//! let mut replay = Replayer::bind("127.0.0.1:3333", session, Default::default())?;
+//! replay.serve(|played| println!("{}", played))?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod record;
+pub mod replay;
+pub mod session;
+
+pub use error::{Error, Result};
+pub use record::Recorder;
+pub use replay::{Options, Replayed, Replayer};
+pub use session::{Entry, Event, Header, Session, SessionWriter, Side};
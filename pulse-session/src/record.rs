@@ -0,0 +1,208 @@
+//! The recording side: a proxy between the debugger and the debug server
+//! that forwards every byte unchanged and notes what went by.
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use pulse_rsp::packet::{self, Incoming};
+
+use crate::error::{Error, Result};
+use crate::session::{Entry, Event, Header, SessionWriter, Side};
+
+/// Listens for debuggers and connects each one through to the debug
+/// server, one at a time, recording both directions.
+pub struct Recorder<W: Write> {
+    listener: TcpListener,
+    target: String,
+    log: Arc<Log<W>>,
+}
+
+struct Log<W: Write> {
+    started: Instant,
+    // the lock also keeps the timestamps of the two directions in order
+    writer: Mutex<SessionWriter<W>>,
+    connections: AtomicUsize,
+}
+
+impl<W: Write> Log<W> {
+    fn event(&self, event: Event) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.entry(&Entry {
+            at: self.started.elapsed(),
+            event,
+        })
+    }
+}
+
+impl<W: Write + Send + 'static> Recorder<W> {
+    /// Listen on `addr` for debuggers of `header.target`, writing the
+    /// session to `out`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, header: &Header, out: W) -> Result<Self> {
+        let writer = SessionWriter::new(out, header)?;
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            target: header.target.clone(),
+            log: Arc::new(Log {
+                started: Instant::now(),
+                writer: Mutex::new(writer),
+                connections: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Record what `port` prints until it closes, passing it on to `tee`.
+    pub fn record_serial(
+        &self,
+        mut port: impl Read + Send + 'static,
+        mut tee: Option<Box<dyn Write + Send>>,
+    ) -> JoinHandle<Result<()>> {
+        let log = Arc::clone(&self.log);
+        thread::spawn(move || {
+            let mut buf = [0u8; 256];
+            loop {
+                let n = port.read(&mut buf)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                log.event(Event::Serial(buf[..n].to_vec()))?;
+                if let Some(tee) = &mut tee {
+                    tee.write_all(&buf[..n])?;
+                    tee.flush()?;
+                }
+            }
+        })
+    }
+
+    /// Connect the next debugger through until either end hangs up.
+    pub fn serve_one(&self) -> Result<()> {
+        let (client, _) = self.listener.accept()?;
+        let server = TcpStream::connect(&self.target)?;
+        client.set_nodelay(true)?;
+        server.set_nodelay(true)?;
+        let conn = self.log.connections.fetch_add(1, Ordering::Relaxed) + 1;
+        self.log.event(Event::Connect(conn))?;
+
+        let replies = {
+            let log = Arc::clone(&self.log);
+            let (from, to) = (server.try_clone()?, client.try_clone()?);
+            thread::spawn(move || forward(from, to, Side::Target, conn, &log))
+        };
+        let requests = forward(client, server, Side::Gdb, conn, &self.log);
+        let replies = replies.join().unwrap_or(Ok(()));
+        self.log.event(Event::Disconnect(conn))?;
+        requests.and(replies)
+    }
+
+    /// Record debuggers forever.
+    pub fn serve(&self) -> Result<()> {
+        loop {
+            self.serve_one()?;
+        }
+    }
+
+    /// Record a single debugger on a background thread, for tests.
+    pub fn spawn(self) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let addr = self.local_addr()?;
+        Ok((addr, thread::spawn(move || self.serve_one())))
+    }
+}
+
+/// Pass bytes from one end to the other until `from` closes, logging
+/// each packet before it goes on so a reply is never logged ahead of its
+/// request. Closes both ends on the way out so the other direction ends
+/// too.
+fn forward<W: Write>(
+    mut from: TcpStream,
+    mut to: TcpStream,
+    side: Side,
+    conn: usize,
+    log: &Log<W>,
+) -> Result<()> {
+    let mut framer = Framer::default();
+    let mut buf = [0u8; 4096];
+    let forwarded = loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) | Err(_) => break Ok(()),
+            Ok(n) => n,
+        };
+        let sent = buf[..n].iter().try_for_each(|&b| {
+            let Some(item) = framer.push(b) else {
+                return Ok(());
+            };
+            let event = match item {
+                // a bad checksum gets NAKed and sent again
+                Incoming::Packet { body, valid: true } => Some(Event::Packet {
+                    conn,
+                    from: side,
+                    body,
+                }),
+                Incoming::Interrupt => Some(Event::Interrupt(conn)),
+                _ => None,
+            };
+            if let Some(event) = event {
+                log.event(event)?;
+            }
+            to.write_all(&framer.raw)?;
+            framer.raw.clear();
+            Ok(())
+        });
+        // the other end hung up, or the log could not be written
+        match sent {
+            Ok(()) => {}
+            Err(Error::Io(_)) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    let _ = from.shutdown(Shutdown::Both);
+    let _ = to.shutdown(Shutdown::Both);
+    forwarded
+}
+
+/// Splits one direction into the items `Conn::read` returns, keeping
+/// the bytes of the one in progress. Line noise comes out as an ack.
+#[derive(Default)]
+struct Framer {
+    raw: Vec<u8>,
+    body: Vec<u8>,
+    checksum: Option<Vec<u8>>,
+}
+
+impl Framer {
+    fn push(&mut self, b: u8) -> Option<Incoming> {
+        self.raw.push(b);
+        if let Some(checksum) = &mut self.checksum {
+            checksum.push(b);
+            if checksum.len() < 2 {
+                return None;
+            }
+            let valid = packet::parse_hex_u64(checksum).ok()
+                == Some(u64::from(packet::checksum(&self.body)));
+            self.checksum = None;
+            return Some(Incoming::Packet {
+                body: std::mem::take(&mut self.body),
+                valid,
+            });
+        }
+        if self.raw[0] == b'$' {
+            match b {
+                b'#' => self.checksum = Some(Vec::new()),
+                b'$' if self.raw.len() == 1 => {}
+                b => self.body.push(b),
+            }
+            return None;
+        }
+        Some(match b {
+            b'-' => Incoming::Nak,
+            0x03 => Incoming::Interrupt,
+            _ => Incoming::Ack,
+        })
+    }
+}
@@ -0,0 +1,385 @@
+//! The replay side: a fake debug server that answers from a recording.
+//!
+//! Each debugger that connects gets the next recorded connection. Its
+//! packets are matched against what the recorded debugger sent, in order,
+//! and answered with what the target sent back then. Output that came
+//! while the target ran (`O` packets, the stop reply, the UART) is played
+//! back at the recorded pace, scaled by [`Options::time_scale`], and a
+//! recorded Ctrl-C waits for the debugger's own.
+//!
+//! A debugger that strays from the recording still gets answers where
+//! there are any: a packet sent again later in the recording skips ahead
+//! to it, one that was only sent earlier gets the earlier reply again, and
+//! one never sent gets the empty "not supported" reply. [`Replayed`]
+//! counts each, so a replay that did not follow the recording says so.
+
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use pulse_rsp::packet::{Conn, Incoming};
+
+use crate::error::{Error, Result};
+use crate::session::{Entry, Event, Session, Side};
+
+/// How often a playback checks for Ctrl-C while it waits.
+const POLL_SLICE: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    /// Wall-clock seconds per recorded second, `0.0` replays as fast as
+    /// possible.
+    pub time_scale: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { time_scale: 1.0 }
+    }
+}
+
+/// How closely one debugger followed its recorded connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replayed {
+    /// Which recorded connection it got, counting from 1.
+    pub connection: usize,
+    /// Packets answered in recorded order.
+    pub answered: usize,
+    /// Recorded debugger packets it never sent, skipped over.
+    pub skipped: usize,
+    /// Packets answered from earlier in the recording, out of order.
+    pub repeated: usize,
+    /// Packets the recording has no answer for.
+    pub unknown: Vec<String>,
+}
+
+impl Replayed {
+    /// True when the debugger sent exactly what was recorded.
+    pub fn faithful(&self) -> bool {
+        self.skipped == 0 && self.repeated == 0 && self.unknown.is_empty()
+    }
+}
+
+impl fmt::Display for Replayed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connection {}: {} packets answered in order",
+            self.connection, self.answered
+        )?;
+        if self.skipped > 0 {
+            write!(f, ", {} recorded packets skipped", self.skipped)?;
+        }
+        if self.repeated > 0 {
+            write!(f, ", {} answered out of order", self.repeated)?;
+        }
+        if !self.unknown.is_empty() {
+            write!(f, ", not in the recording: {}", self.unknown.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Listens like OpenOCD and plays the recorded connections back in turn.
+pub struct Replayer {
+    listener: TcpListener,
+    session: Session,
+    options: Options,
+    next: usize,
+    serial: Option<Box<dyn Write + Send>>,
+}
+
+impl Replayer {
+    pub fn bind<A: ToSocketAddrs>(addr: A, session: Session, options: Options) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            session,
+            options,
+            next: 1,
+            serial: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Play the recorded UART output to `out` alongside the packets.
+    pub fn serial_to(&mut self, out: impl Write + Send + 'static) {
+        self.serial = Some(Box::new(out));
+    }
+
+    /// Recorded connections not played yet.
+    pub fn remaining(&self) -> usize {
+        (self.session.connections() + 1).saturating_sub(self.next)
+    }
+
+    /// Play the next recorded connection to the next debugger, until it
+    /// detaches or disconnects.
+    pub fn serve_one(&mut self) -> Result<Replayed> {
+        let (stream, _) = self.listener.accept()?;
+        stream.set_nodelay(true)?;
+        let connection = self.next;
+        self.next += 1;
+        let entries = self.session.conversation(connection);
+        let mut playback = Playback {
+            conn: Conn::new(stream),
+            entries: &entries,
+            cursor: 0,
+            options: self.options,
+            serial: self.serial.as_deref_mut(),
+            no_ack: false,
+            pending: None,
+            replayed: Replayed {
+                connection,
+                ..Replayed::default()
+            },
+        };
+        match playback.run() {
+            Ok(()) | Err(Error::Rsp(pulse_rsp::Error::Closed)) => {}
+            Err(e) => return Err(e),
+        }
+        playback.finish()?;
+        Ok(playback.replayed)
+    }
+
+    /// Play every recorded connection, one debugger each.
+    pub fn serve(&mut self, mut done: impl FnMut(&Replayed)) -> Result<()> {
+        while self.remaining() > 0 {
+            done(&self.serve_one()?);
+        }
+        Ok(())
+    }
+
+    /// Play the first recorded connection on a background thread, for
+    /// tests.
+    pub fn spawn(mut self) -> Result<(SocketAddr, JoinHandle<Result<Replayed>>)> {
+        let addr = self.local_addr()?;
+        Ok((addr, thread::spawn(move || self.serve_one())))
+    }
+}
+
+struct Playback<'a> {
+    conn: Conn<TcpStream>,
+    entries: &'a [Entry],
+    /// The next recorded event to play.
+    cursor: usize,
+    options: Options,
+    serial: Option<&'a mut (dyn Write + Send + 'static)>,
+    no_ack: bool,
+    /// Read while waiting for an ack.
+    pending: Option<Incoming>,
+    replayed: Replayed,
+}
+
+impl Playback<'_> {
+    fn run(&mut self) -> Result<()> {
+        loop {
+            let incoming = match self.pending.take() {
+                Some(incoming) => incoming,
+                None => self.conn.read()?,
+            };
+            match incoming {
+                Incoming::Packet { body, valid } => {
+                    if !self.no_ack {
+                        self.conn.write_raw(if valid { b"+" } else { b"-" })?;
+                        if !valid {
+                            continue;
+                        }
+                    }
+                    if !self.answer(&body)? {
+                        return Ok(());
+                    }
+                }
+                Incoming::Interrupt => {
+                    if let Some(Event::Interrupt(_)) = self.event(self.cursor) {
+                        self.cursor += 1;
+                        self.play()?;
+                    }
+                    // else already stopped, as far as the recording goes
+                }
+                Incoming::Ack | Incoming::Nak => {}
+            }
+        }
+    }
+
+    /// The rest of the log once the debugger is gone, it is what the
+    /// board printed.
+    fn finish(&mut self) -> Result<()> {
+        for entry in &self.entries[self.cursor..] {
+            if let Event::Serial(bytes) = &entry.event {
+                self.print(bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn event(&self, at: usize) -> Option<&Event> {
+        self.entries.get(at).map(|e| &e.event)
+    }
+
+    fn sent_by_gdb(&self, at: usize, body: &[u8]) -> bool {
+        matches!(self.event(at), Some(Event::Packet { from: Side::Gdb, body: b, .. }) if b == body)
+    }
+
+    /// Answer one packet, false when it ended the session.
+    fn answer(&mut self, body: &[u8]) -> Result<bool> {
+        let ahead = (self.cursor..self.entries.len()).find(|&at| self.sent_by_gdb(at, body));
+        let before = (0..self.cursor)
+            .rev()
+            .find(|&at| self.sent_by_gdb(at, body));
+        if let Some(at) = ahead {
+            for skipped in self.cursor..at {
+                match &self.entries[skipped].event {
+                    Event::Serial(bytes) => self.print(bytes)?,
+                    Event::Packet {
+                        from: Side::Gdb, ..
+                    } => self.replayed.skipped += 1,
+                    _ => {}
+                }
+            }
+            self.cursor = at + 1;
+            self.replayed.answered += 1;
+            self.play()?;
+        } else if let Some(at) = before {
+            self.replayed.repeated += 1;
+            let replies: Vec<Vec<u8>> = self.entries[at + 1..]
+                .iter()
+                .map_while(|e| match &e.event {
+                    Event::Packet {
+                        from: Side::Target,
+                        body,
+                        ..
+                    } => Some(Some(body.clone())),
+                    Event::Serial(_) => Some(None),
+                    _ => None,
+                })
+                .flatten()
+                .collect();
+            for reply in replies {
+                self.send(&reply)?;
+            }
+        } else {
+            self.replayed
+                .unknown
+                .push(String::from_utf8_lossy(body).into_owned());
+            let detach = body.first() == Some(&b'D');
+            self.send(if detach { b"OK" } else { b"" })?;
+        }
+        // the OK itself still goes out in ack mode
+        if body == b"QStartNoAckMode" {
+            self.no_ack = true;
+        }
+        Ok(!matches!(body.first(), Some(b'D' | b'k')))
+    }
+
+    /// Send what the target sent from the cursor on, at the recorded pace,
+    /// until the recording waits for the debugger. A Ctrl-C that comes
+    /// before the recorded one plays the rest up to it without waiting.
+    fn play(&mut self) -> Result<()> {
+        let Some(start) = self.cursor.checked_sub(1).map(|at| self.entries[at].at) else {
+            return Ok(());
+        };
+        let mut start = (start, Instant::now());
+        let mut hurry = false;
+        while let Some(entry) = self.entries.get(self.cursor) {
+            match &entry.event {
+                Event::Interrupt(_) if hurry => {
+                    hurry = false;
+                    start = (entry.at, Instant::now());
+                    self.cursor += 1;
+                    continue;
+                }
+                Event::Packet {
+                    from: Side::Gdb, ..
+                }
+                | Event::Interrupt(_) => return Ok(()),
+                _ => {}
+            }
+            if !hurry {
+                let recorded = entry.at.saturating_sub(start.0);
+                let due = start.1 + recorded.mul_f64(self.options.time_scale);
+                hurry = self.wait_until(due)?;
+            }
+            match &entry.event {
+                Event::Packet { body, .. } => self.send(body)?,
+                Event::Serial(bytes) => self.print(bytes)?,
+                _ => {}
+            }
+            self.cursor += 1;
+        }
+        Ok(())
+    }
+
+    /// Sleep until `due`. True if the debugger sent Ctrl-C meanwhile.
+    fn wait_until(&mut self, due: Instant) -> Result<bool> {
+        loop {
+            if self.interrupted()? {
+                return Ok(true);
+            }
+            let now = Instant::now();
+            if now >= due {
+                return Ok(false);
+            }
+            thread::sleep(POLL_SLICE.min(due - now));
+        }
+    }
+
+    fn interrupted(&mut self) -> Result<bool> {
+        match self.pending {
+            Some(Incoming::Interrupt) => {
+                self.pending = None;
+                return Ok(true);
+            }
+            Some(_) => return Ok(false),
+            None => {}
+        }
+        if !self.conn.has_buffered() {
+            self.conn.get_ref().set_nonblocking(true)?;
+            let mut byte = [0u8];
+            let peeked = self.conn.get_ref().peek(&mut byte);
+            self.conn.get_ref().set_nonblocking(false)?;
+            match peeked {
+                Ok(0) => return Err(pulse_rsp::Error::Closed.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        match self.conn.read()? {
+            Incoming::Interrupt => Ok(true),
+            Incoming::Ack | Incoming::Nak => Ok(false),
+            packet => {
+                self.pending = Some(packet);
+                Ok(false)
+            }
+        }
+    }
+
+    /// One recorded body, escapes in place, acked unless in no-ack mode.
+    fn send(&mut self, body: &[u8]) -> Result<()> {
+        self.conn.write_packet(body)?;
+        if !self.no_ack {
+            // the client acks every reply; a NAK gets one resend
+            match self.conn.read()? {
+                Incoming::Nak => {
+                    self.conn.write_packet(body)?;
+                    self.conn.read()?;
+                }
+                Incoming::Ack => {}
+                other => self.pending = Some(other),
+            }
+        }
+        Ok(())
+    }
+
+    fn print(&mut self, bytes: &[u8]) -> Result<()> {
+        if let Some(out) = &mut self.serial {
+            out.write_all(bytes)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+//! The session file: JSON lines, a header naming the debug server and the
+//! serial port, then one object per packet, Ctrl-C, serial chunk and
+//! connection, each stamped with the time since recording started and
+//! flushed as it happens.
+//!
+//! ```text
+//! {"format":"pulse-session","version":1,"target":"127.0.0.1:3333","serial":"/dev/ttyUSB0"}
+//! {"t_ms":0.41,"connect":1}
+//! {"t_ms":0.52,"conn":1,"gdb":"qSupported:multiprocess-;swbreak+;hwbreak+;vContSupported+"}
+//! {"t_ms":0.97,"conn":1,"target":"PacketSize=4000;qXfer:features:read+;QStartNoAckMode+"}
+//! {"t_ms":1203.5,"serial":"I (5312) ghost_trigger: THREAT DETECTED\n"}
+//! ```
+//!
+//! Packet bodies are kept as they went over the wire, escapes in place;
+//! bodies and serial chunks that are not UTF-8 go in `gdb_hex`,
+//! `target_hex` and `serial_hex` instead. Acks are not recorded.
+
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use crate::error::{Error, Result};
+
+pub const FORMAT: &str = "pulse-session";
+pub const VERSION: u64 = 1;
+
+/// Which end of the RSP link sent a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The debugger: gdb, `pulse`, pulse-scenario.
+    Gdb,
+    /// The debug server: OpenOCD, or `pulse-mock`.
+    Target,
+}
+
+impl Side {
+    fn key(self) -> &'static str {
+        match self {
+            Side::Gdb => "gdb",
+            Side::Target => "target",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A debugger connected, counting from 1.
+    Connect(usize),
+    Disconnect(usize),
+    /// One packet body, escapes still in place.
+    Packet {
+        conn: usize,
+        from: Side,
+        body: Vec<u8>,
+    },
+    /// Ctrl-C from the debugger.
+    Interrupt(usize),
+    /// Bytes from the UART.
+    Serial(Vec<u8>),
+}
+
+/// An event and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Since recording started.
+    pub at: Duration,
+    pub event: Event,
+}
+
+impl Entry {
+    pub fn to_json(&self) -> Value {
+        let mut v = Map::new();
+        v.insert("t_ms".into(), json!(self.at.as_secs_f64() * 1000.0));
+        match &self.event {
+            Event::Connect(conn) => {
+                v.insert("connect".into(), json!(conn));
+            }
+            Event::Disconnect(conn) => {
+                v.insert("disconnect".into(), json!(conn));
+            }
+            Event::Packet { conn, from, body } => {
+                v.insert("conn".into(), json!(conn));
+                put_bytes(&mut v, from.key(), body);
+            }
+            Event::Interrupt(conn) => {
+                v.insert("conn".into(), json!(conn));
+                v.insert("interrupt".into(), json!(true));
+            }
+            Event::Serial(bytes) => put_bytes(&mut v, "serial", bytes),
+        }
+        Value::Object(v)
+    }
+
+    pub fn from_json(v: &Value) -> Result<Self> {
+        let at = v["t_ms"]
+            .as_f64()
+            .filter(|t| *t >= 0.0)
+            .ok_or_else(|| Error::Format("event without t_ms".into()))?;
+        let conn = || {
+            v["conn"]
+                .as_u64()
+                .map(|c| c as usize)
+                .ok_or_else(|| Error::Format(format!("event without conn: {}", v)))
+        };
+        let event = if let Some(c) = v["connect"].as_u64() {
+            Event::Connect(c as usize)
+        } else if let Some(c) = v["disconnect"].as_u64() {
+            Event::Disconnect(c as usize)
+        } else if v["interrupt"] == true {
+            Event::Interrupt(conn()?)
+        } else if let Some(body) = get_bytes(v, "gdb")? {
+            Event::Packet {
+                conn: conn()?,
+                from: Side::Gdb,
+                body,
+            }
+        } else if let Some(body) = get_bytes(v, "target")? {
+            Event::Packet {
+                conn: conn()?,
+                from: Side::Target,
+                body,
+            }
+        } else if let Some(bytes) = get_bytes(v, "serial")? {
+            Event::Serial(bytes)
+        } else {
+            return Err(Error::Format(format!("unknown event {}", v)));
+        };
+        Ok(Self {
+            at: Duration::from_secs_f64(at / 1000.0),
+            event,
+        })
+    }
+}
+
+fn put_bytes(v: &mut Map<String, Value>, key: &str, bytes: &[u8]) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => v.insert(key.into(), json!(text)),
+        Err(_) => v.insert(
+            format!("{}_hex", key),
+            json!(pulse_rsp::packet::to_hex(bytes)),
+        ),
+    };
+}
+
+fn get_bytes(v: &Value, key: &str) -> Result<Option<Vec<u8>>> {
+    if let Some(text) = v[key].as_str() {
+        return Ok(Some(text.as_bytes().to_vec()));
+    }
+    match v[format!("{}_hex", key)].as_str() {
+        Some(hex) => pulse_rsp::packet::from_hex(hex.as_bytes())
+            .map(Some)
+            .map_err(|_| Error::Format(format!("bad {}_hex {:?}", key, hex))),
+        None => Ok(None),
+    }
+}
+
+/// Where a session was recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    /// The debug server the debugger was talking to.
+    pub target: String,
+    /// The UART, if its output was recorded.
+    pub serial: Option<String>,
+}
+
+/// Writes a session file as the events come in.
+pub struct SessionWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> SessionWriter<W> {
+    pub fn new(mut out: W, header: &Header) -> Result<Self> {
+        let header = json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "target": header.target,
+            "serial": header.serial,
+        });
+        writeln!(out, "{}", header)?;
+        out.flush()?;
+        Ok(Self { out })
+    }
+
+    pub fn entry(&mut self, entry: &Entry) -> Result<()> {
+        writeln!(self.out, "{}", entry.to_json())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// A session file read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub header: Header,
+    /// In the order they happened.
+    pub entries: Vec<Entry>,
+}
+
+impl Session {
+    pub fn read_from(input: impl BufRead) -> Result<Self> {
+        let mut lines = input.lines();
+        let header: Value = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| Error::Format(e.to_string()))?,
+            None => return Err(Error::Format("empty file".into())),
+        };
+        if header["format"] != FORMAT {
+            return Err(Error::Format("not a pulse session".into()));
+        }
+        if header["version"] != VERSION {
+            return Err(Error::Format(format!(
+                "unsupported version {}",
+                header["version"]
+            )));
+        }
+        let header = Header {
+            target: header["target"].as_str().unwrap_or_default().to_string(),
+            serial: header["serial"].as_str().map(str::to_string),
+        };
+        let mut entries = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let v: Value = serde_json::from_str(&line).map_err(|e| Error::Format(e.to_string()))?;
+            entries.push(Entry::from_json(&v)?);
+        }
+        Ok(Self { header, entries })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// How many debugger connections were recorded.
+    pub fn connections(&self) -> usize {
+        self.entries
+            .iter()
+            .filter_map(|e| match e.event {
+                Event::Connect(conn) => Some(conn),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Packets, Ctrl-Cs and serial output of one connection, without the
+    /// connect and disconnect markers. Serial output belongs to the
+    /// connection open when it arrived or, between two, the one before;
+    /// anything before the first connection goes with the first.
+    pub fn conversation(&self, conn: usize) -> Vec<Entry> {
+        let mut current = 1;
+        self.entries
+            .iter()
+            .filter(|e| match &e.event {
+                Event::Connect(c) => {
+                    current = *c;
+                    false
+                }
+                Event::Disconnect(_) => false,
+                Event::Packet { conn: c, .. } | Event::Interrupt(c) => *c == conn,
+                Event::Serial(_) => current == conn,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Everything the UART printed.
+    pub fn serial(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .filter_map(|e| match &e.event {
+                Event::Serial(bytes) => Some(bytes.as_slice()),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.entries.last().map_or(Duration::ZERO, |e| e.at)
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count =
+            |pick: fn(&Event) -> bool| self.entries.iter().filter(|e| pick(&e.event)).count();
+        write!(
+            f,
+            "{} connections to {}, {} packets, {} interrupts, {} serial bytes over {:.1} s",
+            self.connections(),
+            self.header.target,
+            count(|e| matches!(e, Event::Packet { .. })),
+            count(|e| matches!(e, Event::Interrupt(_))),
+            self.serial().len(),
+            self.duration().as_secs_f64()
+        )
+    }
+}
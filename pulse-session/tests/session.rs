@@ -0,0 +1,292 @@
+use std::io::{Cursor, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulse_core::mailbox::{OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_dwarf::DebugInfo;
+use pulse_mock::{fixture, Image, Options, Server};
+use pulse_rsp::{BreakpointKind, Client, StopReply};
+use pulse_session::{replay, Entry, Error, Event, Header, Recorder, Replayer, Session, Side};
+
+/// Serial output collected by a replay.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn session_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pulse-session-{}-{}", std::process::id(), name))
+}
+
+fn mock() -> SocketAddr {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+    addr
+}
+
+fn connect(addr: SocketAddr) -> Client {
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+    gdb
+}
+
+/// Post a threat and run to the detector three times.
+fn inject_a_threat(addr: SocketAddr) -> (u32, Vec<StopReply>, String) {
+    let mut gdb = connect(addr);
+    let cycle_ms = gdb.read_u32(fixture::CYCLE_MS).unwrap();
+    let mailbox = |offset: u32| fixture::PULSE_MAILBOX + u64::from(offset);
+    let sequence = gdb.read_u32(mailbox(OFFSET_SEQUENCE)).unwrap();
+    gdb.write_u32(mailbox(OFFSET_SIGNAL), 1).unwrap();
+    gdb.write_u32(mailbox(OFFSET_SEQUENCE), sequence + 1)
+        .unwrap();
+    gdb.insert_point(BreakpointKind::Software, fixture::DETECTOR_CYCLE, 4)
+        .unwrap();
+    let stops = (0..3).map(|_| gdb.cont().unwrap()).collect();
+    gdb.remove_point(BreakpointKind::Software, fixture::DETECTOR_CYCLE, 4)
+        .unwrap();
+    let console = String::from_utf8_lossy(&gdb.take_console()).into_owned();
+    gdb.detach().unwrap();
+    (cycle_ms, stops, console)
+}
+
+fn record(name: &str, serial: Option<&'static [u8]>, script: impl FnOnce(SocketAddr)) -> Session {
+    let path = session_file(name);
+    let header = Header {
+        target: mock().to_string(),
+        serial: serial.map(|_| "/dev/ttyUSB0".to_string()),
+    };
+    let recorder = Recorder::bind(
+        "127.0.0.1:0",
+        &header,
+        std::fs::File::create(&path).unwrap(),
+    )
+    .unwrap();
+    if let Some(bytes) = serial {
+        recorder.record_serial(Cursor::new(bytes), None);
+    }
+    let (addr, done) = recorder.spawn().unwrap();
+    script(addr);
+    done.join().unwrap().unwrap();
+    let session = Session::load(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    session
+}
+
+#[test]
+fn replays_a_recorded_injection() {
+    let boot = b"rst:0x1 (POWERON_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)\n";
+    let mut live = None;
+    let session = record("inject", Some(boot), |addr| {
+        live = Some(inject_a_threat(addr))
+    });
+    let live = live.unwrap();
+    assert!(live.2.contains("THREAT DETECTED"), "{}", live.2);
+
+    assert_eq!(session.connections(), 1);
+    assert_eq!(session.serial(), boot);
+    let packets: Vec<_> = session
+        .entries
+        .iter()
+        .filter_map(|e| match &e.event {
+            Event::Packet { from, body, .. } => Some((*from, body.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(packets[0].0, Side::Gdb);
+    assert!(packets[0].1.starts_with(b"qSupported:"));
+    assert_eq!(packets.last().unwrap(), &(Side::Target, b"OK".to_vec()));
+    assert!(session.entries.windows(2).all(|w| w[0].at <= w[1].at));
+    assert!(session
+        .to_string()
+        .starts_with("1 connections to 127.0.0.1:"));
+
+    // the same script against the recording alone
+    let gdb_packets = packets.iter().filter(|p| p.0 == Side::Gdb).count();
+    let mut replayer =
+        Replayer::bind("127.0.0.1:0", session, replay::Options { time_scale: 0.0 }).unwrap();
+    let serial = Shared::default();
+    replayer.serial_to(serial.clone());
+    let (addr, done) = replayer.spawn().unwrap();
+    assert_eq!(inject_a_threat(addr), live);
+    let replayed = done.join().unwrap().unwrap();
+    assert!(replayed.faithful(), "{}", replayed);
+    assert_eq!(replayed.answered, gdb_packets);
+    assert_eq!(*serial.0.lock().unwrap(), boot);
+}
+
+#[test]
+fn replays_a_scenario_offline() {
+    let scenario = pulse_scenario::Scenario::parse(
+        r#"
+        name = "threat"
+
+        [[inject]]
+        cycle = 2
+        mailbox = "threat"
+
+        [[expect]]
+        log = "THREAT DETECTED"
+        within_ms = 3000
+        "#,
+    )
+    .unwrap();
+    let debug = DebugInfo::parse(&fixture::ghost_trigger()).unwrap();
+    let run = |addr| {
+        let mut gdb = connect(addr);
+        pulse_scenario::run(&scenario, &mut gdb, &debug, &[], None).unwrap()
+    };
+
+    let mut live = None;
+    let session = record("scenario", None, |addr| live = Some(run(addr)));
+    let live = live.unwrap();
+    assert_eq!(live.failure, None);
+
+    let replayer =
+        Replayer::bind("127.0.0.1:0", session, replay::Options { time_scale: 0.0 }).unwrap();
+    let (addr, done) = replayer.spawn().unwrap();
+    let offline = run(addr);
+    assert_eq!(offline.failure, None);
+    assert_eq!(offline.cycles, live.cycles);
+    assert_eq!(offline.injected.len(), 1);
+    assert_eq!(offline.injected[0].cycle, live.injected[0].cycle);
+    assert_eq!(offline.console, live.console);
+    let replayed = done.join().unwrap().unwrap();
+    assert!(replayed.faithful(), "{}", replayed);
+}
+
+#[test]
+fn answers_a_debugger_that_strays() {
+    let session = Session::read_from(
+        br#"{"format":"pulse-session","version":1,"target":"board:3333","serial":"/dev/ttyUSB0"}
+{"t_ms":0.0,"connect":1}
+{"t_ms":0.1,"conn":1,"gdb":"qSupported:multiprocess-;swbreak+;hwbreak+;vContSupported+"}
+{"t_ms":0.2,"conn":1,"target":"PacketSize=100"}
+{"t_ms":0.3,"conn":1,"gdb":"vCont?"}
+{"t_ms":0.4,"conn":1,"target":""}
+{"t_ms":1.0,"conn":1,"gdb":"m3ffb0020,4"}
+{"t_ms":1.1,"conn":1,"target":"e8030000"}
+{"t_ms":2.0,"conn":1,"gdb":"c"}
+{"t_ms":3000.0,"conn":1,"target":"O5448524541540a"}
+{"t_ms":3000.5,"serial":"I (3000) ghost_trigger: THREAT\n"}
+{"t_ms":9000.0,"conn":1,"interrupt":true}
+{"t_ms":9000.2,"conn":1,"target":"T02thread:1;"}
+{"t_ms":9001.0,"conn":1,"gdb":"m3ffb0020,4"}
+{"t_ms":9001.1,"conn":1,"target":"64000000"}
+{"t_ms":9002.0,"disconnect":1}
+"#
+        .as_slice(),
+    )
+    .unwrap();
+    assert_eq!(session.header.serial.as_deref(), Some("/dev/ttyUSB0"));
+    assert_eq!(session.duration(), Duration::from_micros(9_002_000));
+
+    let mut replayer = Replayer::bind("127.0.0.1:0", session, replay::Options::default()).unwrap();
+    let serial = Shared::default();
+    replayer.serial_to(serial.clone());
+    let (addr, done) = replayer.spawn().unwrap();
+    let mut gdb = connect(addr);
+    assert!(!gdb.is_no_ack());
+    assert_eq!(gdb.read_u32(fixture::CYCLE_MS).unwrap(), 1000);
+
+    // Ctrl-C well before the recorded one plays the output up to it at once
+    gdb.resume().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let started = Instant::now();
+    let stop = gdb.interrupt().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(
+        matches!(stop, StopReply::Signal { signal: 2, .. }),
+        "{:?}",
+        stop
+    );
+    assert_eq!(gdb.take_console(), b"THREAT\n");
+    assert_eq!(
+        *serial.0.lock().unwrap(),
+        b"I (3000) ghost_trigger: THREAT\n"
+    );
+
+    assert_eq!(gdb.read_u32(fixture::CYCLE_MS).unwrap(), 100);
+    assert!(matches!(
+        gdb.read_u32(fixture::PULSE_MAILBOX),
+        Err(pulse_rsp::Error::Protocol(msg)) if msg.contains("empty read")
+    ));
+    // asked again, answered with the latest reply before
+    assert_eq!(gdb.read_u32(fixture::CYCLE_MS).unwrap(), 100);
+    gdb.detach().unwrap();
+
+    let replayed = done.join().unwrap().unwrap();
+    assert_eq!(replayed.answered, 5);
+    assert_eq!(replayed.repeated, 1);
+    assert_eq!(replayed.unknown, ["m3ffb0000,4", "D"]);
+    assert!(!replayed.faithful());
+    assert_eq!(
+        replayed.to_string(),
+        "connection 1: 5 packets answered in order, 1 answered out of order, \
+         not in the recording: m3ffb0000,4, D"
+    );
+}
+
+#[test]
+fn rejects_bad_files() {
+    // binary bodies survive the round trip
+    let entry = Entry {
+        at: Duration::from_millis(5),
+        event: Event::Packet {
+            conn: 2,
+            from: Side::Gdb,
+            body: b"X3ffb1000,2:\xff\x00".to_vec(),
+        },
+    };
+    let json = entry.to_json();
+    assert!(json["gdb"].is_null());
+    assert_eq!(json["gdb_hex"], "5833666662313030302c323aff00");
+    assert_eq!(Entry::from_json(&json).unwrap(), entry);
+
+    for (text, why) in [
+        ("", "empty file"),
+        (
+            r#"{"format":"pulse-trace","version":1}"#,
+            "not a pulse session",
+        ),
+        (
+            r#"{"format":"pulse-session","version":2}"#,
+            "unsupported version 2",
+        ),
+        (
+            "{\"format\":\"pulse-session\",\"version\":1}\n{\"t_ms\":1,\"gdb\":\"?\"}",
+            "event without conn",
+        ),
+        (
+            "{\"format\":\"pulse-session\",\"version\":1}\n{\"t_ms\":1,\"bogus\":1}",
+            "unknown event",
+        ),
+        (
+            "{\"format\":\"pulse-session\",\"version\":1}\n{\"serial_hex\":\"0a\"}",
+            "without t_ms",
+        ),
+    ] {
+        match Session::read_from(text.as_bytes()) {
+            Err(Error::Format(msg)) => assert!(msg.contains(why), "{}", msg),
+            other => panic!("{:?} for {:?}", other, text),
+        }
+    }
+}
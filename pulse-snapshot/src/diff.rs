@@ -0,0 +1,155 @@
+//! Byte diff of two snapshots, grouped by the field that changed.
+
+use std::fmt;
+
+use pulse_dwarf::{format_scalar, Encoding};
+
+use crate::snapshot::{Region, Snapshot};
+use crate::symbols::SymbolMap;
+
+/// One field, symbol or run of bytes that differs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub region: String,
+    pub address: u64,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+    /// `PULSE_MAILBOX.sequence`, or `.dram0.bss+0x10` for bytes no symbol
+    /// owns.
+    pub name: String,
+    /// Owning symbol, if the address falls inside one.
+    pub symbol: Option<String>,
+    /// Access path inside the symbol, `.records[0].signal`; empty for the
+    /// symbol itself or when there is no type.
+    pub field: String,
+    pub type_name: Option<String>,
+    pub encoding: Option<Encoding>,
+}
+
+impl Change {
+    /// Before and after as numbers when the type is a scalar, else hex.
+    pub fn values(&self) -> (String, String) {
+        (
+            format_scalar(&self.before, self.encoding),
+            format_scalar(&self.after, self.encoding),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    /// Regions only one of the snapshots has.
+    pub unmatched: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.unmatched.is_empty()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            let (before, after) = change.values();
+            let ty = match &change.type_name {
+                Some(name) => name.clone(),
+                None => format!("{} bytes", change.before.len()),
+            };
+            writeln!(
+                f,
+                "{:#010x}  {}  {}  {} -> {}",
+                change.address, change.name, ty, before, after
+            )?;
+        }
+        for name in &self.unmatched {
+            writeln!(f, "region {} is only in one snapshot", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare regions of the same name over the addresses both captured.
+/// Changed bytes inside a typed static widen to the whole leaf field,
+/// inside an untyped symbol to the changed run; elsewhere they are
+/// reported as runs.
+pub fn diff(before: &Snapshot, after: &Snapshot, symbols: Option<&SymbolMap>) -> Diff {
+    let mut out = Diff::default();
+    for a in &before.regions {
+        let Some(b) = after.regions.iter().find(|b| b.name == a.name) else {
+            out.unmatched.push(a.name.clone());
+            continue;
+        };
+        diff_region(a, b, symbols, &mut out.changes);
+    }
+    for b in &after.regions {
+        if !before.regions.iter().any(|a| a.name == b.name) {
+            out.unmatched.push(b.name.clone());
+        }
+    }
+    out
+}
+
+fn diff_region(a: &Region, b: &Region, symbols: Option<&SymbolMap>, changes: &mut Vec<Change>) {
+    let start = a.address.max(b.address);
+    let end = a.end().min(b.end());
+    let byte = |r: &Region, addr: u64| r.data[(addr - r.address) as usize];
+    let bytes = |r: &Region, from: u64, to: u64| {
+        r.data[(from - r.address) as usize..(to - r.address) as usize].to_vec()
+    };
+
+    let mut addr = start;
+    while addr < end {
+        if byte(a, addr) == byte(b, addr) {
+            addr += 1;
+            continue;
+        }
+        let owner = symbols.and_then(|s| s.lookup(addr));
+        let field = owner.as_ref().and_then(|o| o.field());
+        let (from, to) = match (&owner, &field) {
+            (_, Some((_, field_start, ty))) => (
+                (*field_start).max(start),
+                (field_start + ty.size.max(1)).min(end),
+            ),
+            // run of changed bytes, not leaving the symbol or gap
+            (owner, None) => {
+                let limit = match owner {
+                    Some(o) => (o.symbol.address + o.symbol.size).min(end),
+                    None => symbols
+                        .and_then(|s| {
+                            s.symbols()
+                                .iter()
+                                .map(|s| s.address)
+                                .find(|&next| next > addr)
+                        })
+                        .map_or(end, |next| next.min(end)),
+                };
+                let mut to = addr + 1;
+                while to < limit && byte(a, to) != byte(b, to) {
+                    to += 1;
+                }
+                (addr, to)
+            }
+        };
+        let symbol = owner.as_ref().map(|o| o.symbol.name.clone());
+        let field_path = field
+            .as_ref()
+            .map_or(String::new(), |(path, _, _)| path.clone());
+        changes.push(Change {
+            region: a.name.clone(),
+            name: match &symbol {
+                Some(symbol) => format!("{}{}", symbol, field_path),
+                None => format!("{}+{:#x}", a.name, from - a.address),
+            },
+            address: from,
+            before: bytes(a, from, to),
+            after: bytes(b, from, to),
+            symbol,
+            field: field_path,
+            type_name: field.as_ref().map(|(_, _, ty)| ty.name.clone()),
+            encoding: field.as_ref().and_then(|(_, _, ty)| ty.scalar()),
+        });
+        addr = to;
+    }
+}
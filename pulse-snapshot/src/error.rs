@@ -0,0 +1,58 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Rsp(pulse_rsp::Error),
+    Dwarf(pulse_dwarf::Error),
+    Elf(String),
+    /// Not a snapshot file, or a damaged one.
+    Format(String),
+    /// A region spec that names nothing.
+    Region(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Dwarf(e) => write!(f, "debug info: {}", e),
+            Error::Elf(e) => write!(f, "bad ELF: {}", e),
+            Error::Format(what) => write!(f, "bad snapshot: {}", what),
+            Error::Region(what) => write!(f, "bad region: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_dwarf::Error> for Error {
+    fn from(e: pulse_dwarf::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
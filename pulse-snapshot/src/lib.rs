@@ -0,0 +1,38 @@
+//! Memory snapshots of ghost-trigger and symbol-mapped diffs.
+//!
+//! [`Snapshot::capture`] reads DRAM, or just the `.data`/`.bss` sections
+//! of the ELF, from a halted target over the GDB remote protocol and
+//! saves it to a small binary file. [`diff`] compares two snapshots and
+//! names every change after the static that owns it, down to the leaf
+//! field when the ELF has DWARF for it, so the question "what did that
+//! injection touch?" has an answer like
+//!
+//! ```text
+//! 0x3ffb0014  ghost_trigger::injection::PULSE_MAILBOX.ack  core::sync::atomic::AtomicU32  0 -> 1
+//! ```
+//!
+//! ```no_run
+//! # fn main() -> pulse_snapshot::Result<()> {
+//! use pulse_snapshot::{diff, select, writable_sections, Snapshot, SymbolMap};
+//!
+//! let elf = std::fs::read("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let spans = select(".data", &writable_sections(&elf)?)?;
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! let before = Snapshot::capture(&mut gdb, &spans, None, "before")?;
+//! // ... let the target run, halt it again ...
+//! let after = Snapshot::capture(&mut gdb, &spans, None, "after")?;
+//! print!("{}", diff(&before, &after, Some(&SymbolMap::from_elf(&elf)?)));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod diff;
+pub mod error;
+pub mod snapshot;
+pub mod symbols;
+
+pub use diff::{diff, Change, Diff};
+pub use error::{Error, Result};
+pub use snapshot::{select, writable_sections, Region, Snapshot, Span, DRAM};
+pub use symbols::{Owner, Symbol, SymbolMap};
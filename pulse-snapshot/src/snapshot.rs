@@ -0,0 +1,281 @@
+//! Captured memory and its file format.
+//!
+//! A snapshot file is little-endian throughout:
+//!
+//! ```text
+//! magic    b"PULSESNP"
+//! version  u32 = 1
+//! taken    u64, ms since the Unix epoch
+//! pc       u64, u64::MAX if unknown
+//! label    u16 length + UTF-8
+//! regions  u32 count, then per region:
+//!            name     u16 length + UTF-8
+//!            address  u64
+//!            length   u32
+//!            bytes
+//! ```
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use object::{Object, ObjectSection, SectionFlags};
+use pulse_rsp::Client;
+
+use crate::error::{Error, Result};
+
+pub const MAGIC: &[u8; 8] = b"PULSESNP";
+pub const VERSION: u32 = 1;
+
+/// ESP32 internal data RAM, everything the firmware can have written to.
+pub const DRAM: Span = Span {
+    name: String::new(),
+    address: 0x3ffa_e000,
+    size: 0x5_2000,
+};
+
+/// A named address range to capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+impl Span {
+    pub fn new(name: impl Into<String>, address: u64, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            address,
+            size,
+        }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.address + self.size
+    }
+}
+
+/// Writable, allocated sections of an ELF: `.dram0.data`, `.dram0.bss`
+/// and friends.
+pub fn writable_sections(elf: &[u8]) -> Result<Vec<Span>> {
+    let file = object::File::parse(elf).map_err(|e| Error::Elf(e.to_string()))?;
+    let mut spans: Vec<Span> = file
+        .sections()
+        .filter(|s| s.size() > 0 && s.address() != 0)
+        .filter(|s| match s.flags() {
+            SectionFlags::Elf { sh_flags } => {
+                let want = u64::from(object::elf::SHF_ALLOC | object::elf::SHF_WRITE);
+                sh_flags & want == want
+            }
+            _ => false,
+        })
+        .filter_map(|s| Some(Span::new(s.name().ok()?, s.address(), s.size())))
+        .collect();
+    spans.sort_by_key(|s| s.address);
+    Ok(spans)
+}
+
+/// Turn one region argument into spans:
+///
+/// - `dram`: all of [`DRAM`]
+/// - `.data`, `.bss`: every writable section with that suffix, so `.data`
+///   matches `.dram0.data`
+/// - a full section name
+/// - `ADDR:LEN`, either in decimal or `0x` hex
+pub fn select(spec: &str, sections: &[Span]) -> Result<Vec<Span>> {
+    if spec == "dram" {
+        return Ok(vec![Span::new("dram", DRAM.address, DRAM.size)]);
+    }
+    if let Some((addr, len)) = spec.split_once(':') {
+        let (Some(address), Some(size)) = (number(addr), number(len)) else {
+            return Err(Error::Region(format!("{:?} is not ADDR:LEN", spec)));
+        };
+        return Ok(vec![Span::new(format!("{:#x}", address), address, size)]);
+    }
+    let exact: Vec<Span> = sections
+        .iter()
+        .filter(|s| s.name == spec)
+        .cloned()
+        .collect();
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+    let suffixed: Vec<Span> = sections
+        .iter()
+        .filter(|s| spec.starts_with('.') && s.name.ends_with(spec))
+        .cloned()
+        .collect();
+    if suffixed.is_empty() {
+        return Err(Error::Region(format!(
+            "no section {:?}, the writable ones are {}",
+            spec,
+            sections
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    Ok(suffixed)
+}
+
+fn number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+impl Region {
+    pub fn end(&self) -> u64 {
+        self.address + self.data.len() as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Milliseconds since the Unix epoch.
+    pub taken_ms: u64,
+    /// Where the target was stopped.
+    pub pc: Option<u64>,
+    /// Free text, e.g. the target and what was about to happen.
+    pub label: String,
+    pub regions: Vec<Region>,
+}
+
+impl Snapshot {
+    /// Read `spans` from a halted target.
+    pub fn capture<S: Read + Write>(
+        gdb: &mut Client<S>,
+        spans: &[Span],
+        pc: Option<u64>,
+        label: impl Into<String>,
+    ) -> Result<Self> {
+        let mut regions = Vec::with_capacity(spans.len());
+        for span in spans {
+            regions.push(Region {
+                name: span.name.clone(),
+                address: span.address,
+                data: gdb.read_memory(span.address, span.size as usize)?,
+            });
+        }
+        Ok(Self {
+            taken_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            pc,
+            label: label.into(),
+            regions,
+        })
+    }
+
+    /// The byte at `addr`, if captured.
+    pub fn byte(&self, addr: u64) -> Option<u8> {
+        self.regions
+            .iter()
+            .find(|r| (r.address..r.end()).contains(&addr))
+            .map(|r| r.data[(addr - r.address) as usize])
+    }
+
+    pub fn write_to(&self, mut w: impl Write) -> Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&self.taken_ms.to_le_bytes())?;
+        w.write_all(&self.pc.unwrap_or(u64::MAX).to_le_bytes())?;
+        write_str(&mut w, &self.label)?;
+        w.write_all(&(self.regions.len() as u32).to_le_bytes())?;
+        for region in &self.regions {
+            write_str(&mut w, &region.name)?;
+            w.write_all(&region.address.to_le_bytes())?;
+            let len = u32::try_from(region.data.len())
+                .map_err(|_| Error::Format(format!("region {} too large", region.name)))?;
+            w.write_all(&len.to_le_bytes())?;
+            w.write_all(&region.data)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut r: impl Read) -> Result<Self> {
+        let mut magic = [0; 8];
+        read_exact(&mut r, &mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Format("not a snapshot file".into()));
+        }
+        let version = u32::from_le_bytes(read_array(&mut r)?);
+        if version != VERSION {
+            return Err(Error::Format(format!("unsupported version {}", version)));
+        }
+        let taken_ms = u64::from_le_bytes(read_array(&mut r)?);
+        let pc = Some(u64::from_le_bytes(read_array(&mut r)?)).filter(|&pc| pc != u64::MAX);
+        let label = read_str(&mut r)?;
+        let count = u32::from_le_bytes(read_array(&mut r)?);
+        let mut regions = Vec::new();
+        for _ in 0..count {
+            let name = read_str(&mut r)?;
+            let address = u64::from_le_bytes(read_array(&mut r)?);
+            let len = u32::from_le_bytes(read_array(&mut r)?) as usize;
+            let mut data = Vec::new();
+            r.by_ref().take(len as u64).read_to_end(&mut data)?;
+            if data.len() != len {
+                return Err(Error::Format(format!("region {} is truncated", name)));
+            }
+            regions.push(Region {
+                name,
+                address,
+                data,
+            });
+        }
+        Ok(Self {
+            taken_ms,
+            pc,
+            label,
+            regions,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)?;
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from(std::fs::read(path)?.as_slice())
+    }
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> Result<()> {
+    let len = u16::try_from(s.len()).map_err(|_| Error::Format("string too long".into()))?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn read_exact(r: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::Format("truncated".into()),
+        _ => Error::Io(e),
+    })
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0; N];
+    read_exact(r, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_str(r: &mut impl Read) -> Result<String> {
+    let len = u16::from_le_bytes(read_array(r)?) as usize;
+    let mut buf = vec![0; len];
+    read_exact(r, &mut buf)?;
+    String::from_utf8(buf).map_err(|_| Error::Format("string is not UTF-8".into()))
+}
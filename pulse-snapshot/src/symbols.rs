@@ -0,0 +1,102 @@
+//! Address to owning symbol, field and type.
+
+use object::{Object, ObjectSymbol, SymbolKind};
+use pulse_dwarf::{DebugInfo, Location, Type};
+
+use crate::error::{Error, Result};
+
+/// A data object at a fixed address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    /// From DWARF; symbols only found in the ELF symbol table have none.
+    pub ty: Option<Type>,
+}
+
+/// Who owns a byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner<'a> {
+    pub symbol: &'a Symbol,
+    /// Byte offset into the symbol.
+    pub offset: u64,
+}
+
+impl<'a> Owner<'a> {
+    /// The leaf field covering this byte, as `(access path, start, type)`.
+    /// The start is an address, so the whole field can be read back.
+    pub fn field(&self) -> Option<(String, u64, &'a Type)> {
+        let ty = self.symbol.ty.as_ref()?;
+        let (path, start, leaf) = ty.field_at(self.offset)?;
+        Some((path, self.symbol.address + start, leaf))
+    }
+}
+
+/// Data symbols sorted by address.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolMap {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolMap {
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by_key(|s| s.address);
+        Self { symbols }
+    }
+
+    /// Statics with their types from DWARF, plus sized data symbols from
+    /// the ELF symbol table for anything DWARF does not describe.
+    pub fn from_elf(elf: &[u8]) -> Result<Self> {
+        let mut symbols: Vec<Symbol> = DebugInfo::parse(elf)?
+            .statics()
+            .into_iter()
+            .filter_map(|v| match v.location {
+                Location::Address(address) => Some(Symbol {
+                    name: v.path,
+                    address,
+                    size: v.ty.size,
+                    ty: Some(v.ty),
+                }),
+                _ => None,
+            })
+            .collect();
+        let file = object::File::parse(elf).map_err(|e| Error::Elf(e.to_string()))?;
+        for sym in file.symbols() {
+            if sym.kind() != SymbolKind::Data || sym.size() == 0 {
+                continue;
+            }
+            let address = sym.address();
+            if symbols
+                .iter()
+                .any(|s| address < s.address + s.size.max(1) && s.address < address + sym.size())
+            {
+                continue;
+            }
+            let Ok(name) = sym.name() else { continue };
+            symbols.push(Symbol {
+                name: rustc_demangle::demangle(name).to_string(),
+                address,
+                size: sym.size(),
+                ty: None,
+            });
+        }
+        Ok(Self::new(symbols))
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn lookup(&self, addr: u64) -> Option<Owner<'_>> {
+        let i = self.symbols.partition_point(|s| s.address <= addr);
+        let symbol = &self.symbols[..i]
+            .iter()
+            .rev()
+            .find(|s| addr < s.address + s.size)?;
+        Some(Owner {
+            symbol,
+            offset: addr - symbol.address,
+        })
+    }
+}
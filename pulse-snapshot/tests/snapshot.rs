@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use pulse_core::mailbox::{Signal, OFFSET_PAYLOAD, OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_mock::{fixture, Image, Options, Server};
+use pulse_rsp::{BreakpointKind, Client};
+use pulse_snapshot::{diff, select, writable_sections, Error, Region, Snapshot, SymbolMap};
+
+fn snapshot(regions: Vec<Region>) -> Snapshot {
+    Snapshot {
+        taken_ms: 1_700_000_000_000,
+        pc: Some(fixture::DETECTOR_CYCLE),
+        label: "cycle".into(),
+        regions,
+    }
+}
+
+#[test]
+fn file_roundtrip() {
+    let snap = snapshot(vec![
+        Region {
+            name: ".dram0.data".into(),
+            address: fixture::DATA,
+            data: (0..=255).collect(),
+        },
+        Region {
+            name: ".dram0.bss".into(),
+            address: fixture::BSS,
+            data: vec![0; 16],
+        },
+    ]);
+    let mut file = Vec::new();
+    snap.write_to(&mut file).unwrap();
+    assert_eq!(Snapshot::read_from(file.as_slice()).unwrap(), snap);
+
+    let unknown_pc = Snapshot { pc: None, ..snap };
+    let mut file = Vec::new();
+    unknown_pc.write_to(&mut file).unwrap();
+    assert_eq!(Snapshot::read_from(file.as_slice()).unwrap().pc, None);
+
+    assert!(matches!(
+        Snapshot::read_from(&file[..file.len() - 1]),
+        Err(Error::Format(_))
+    ));
+    assert!(matches!(
+        Snapshot::read_from(&b"PULSESNX"[..]),
+        Err(Error::Format(_))
+    ));
+}
+
+#[test]
+fn region_specs() {
+    let sections = writable_sections(&fixture::ghost_trigger()).unwrap();
+    let names: Vec<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, [".dram0.data", ".dram0.bss"]);
+
+    assert_eq!(select(".bss", &sections).unwrap()[0].address, fixture::BSS);
+    assert_eq!(
+        select(".dram0.data", &sections).unwrap()[0].address,
+        fixture::DATA
+    );
+    let raw = &select("0x3ffb0040:64", &sections).unwrap()[0];
+    assert_eq!((raw.address, raw.size), (fixture::PULSE_AUDIT, 64));
+    assert_eq!(select("dram", &sections).unwrap()[0].address, 0x3ffa_e000);
+    assert!(matches!(select(".heap", &sections), Err(Error::Region(_))));
+    assert!(matches!(
+        select("0x10:big", &sections),
+        Err(Error::Region(_))
+    ));
+}
+
+#[test]
+fn unowned_bytes_are_runs() {
+    let before = snapshot(vec![Region {
+        name: ".dram0.bss".into(),
+        address: fixture::BSS,
+        data: vec![0; 16],
+    }]);
+    let mut after = before.clone();
+    after.regions[0].data[4..7].copy_from_slice(&[1, 2, 3]);
+    after.regions[0].data[12] = 9;
+    after.regions.push(Region {
+        name: "extra".into(),
+        address: 0,
+        data: vec![],
+    });
+
+    let symbols = SymbolMap::from_elf(&fixture::ghost_trigger()).unwrap();
+    let d = diff(&before, &after, Some(&symbols));
+    let changes: Vec<(&str, &[u8])> = d
+        .changes
+        .iter()
+        .map(|c| (c.name.as_str(), c.after.as_slice()))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (".dram0.bss+0x4", &[1, 2, 3][..]),
+            (".dram0.bss+0xc", &[9][..])
+        ]
+    );
+    assert_eq!(d.unmatched, ["extra"]);
+    assert!(diff(&before, &before, None).is_empty());
+}
+
+#[test]
+fn injection_diff_on_the_mock() {
+    let elf = fixture::ghost_trigger();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    gdb.handshake().unwrap();
+
+    let spans = select(".data", &writable_sections(&elf).unwrap()).unwrap();
+    gdb.insert_point(BreakpointKind::Hardware, fixture::DETECTOR_CYCLE, 2)
+        .unwrap();
+    gdb.cont().unwrap();
+    let before = Snapshot::capture(&mut gdb, &spans, None, "before").unwrap();
+
+    let mailbox = fixture::PULSE_MAILBOX;
+    gdb.write_u32(mailbox + u64::from(OFFSET_SIGNAL), Signal::Threat.id())
+        .unwrap();
+    gdb.write_u32(mailbox + u64::from(OFFSET_PAYLOAD), 3)
+        .unwrap();
+    gdb.write_u32(mailbox + u64::from(OFFSET_SEQUENCE), 1)
+        .unwrap();
+    gdb.cont().unwrap();
+    gdb.cont().unwrap();
+    let after = Snapshot::capture(&mut gdb, &spans, None, "after").unwrap();
+    gdb.detach().unwrap();
+
+    let symbols = SymbolMap::from_elf(&elf).unwrap();
+    let d = diff(&before, &after, Some(&symbols));
+    let text = d.to_string();
+    let line = |name: &str| {
+        text.lines()
+            .find(|l| l.split("  ").nth(1) == Some(name))
+            .unwrap_or_else(|| panic!("no {} in\n{}", name, text))
+    };
+    assert_eq!(
+        line("ghost_trigger::injection::PULSE_MAILBOX.ack"),
+        "0x3ffb0014  ghost_trigger::injection::PULSE_MAILBOX.ack  \
+         core::sync::atomic::AtomicU32  0 -> 1"
+    );
+    assert!(line("ghost_trigger::injection::PULSE_MAILBOX.sequence").ends_with("0 -> 1"));
+    assert!(line("ghost_trigger::injection::PULSE_AUDIT.head").ends_with("0 -> 1"));
+    assert!(line("ghost_trigger::injection::PULSE_AUDIT.records[0].signal").ends_with("0 -> 1"));
+    assert!(d.changes.iter().all(|c| c.symbol.is_some()), "{}", text);
+}
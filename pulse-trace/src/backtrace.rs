@@ -0,0 +1,240 @@
+//! Xtensa windowed-ABI backtraces, walked the way ESP-IDF's panic handler
+//! does.
+//!
+//! `a0` holds the return address with the caller's window increment in
+//! its top two bits, `a1` the stack pointer. On a halted target the most
+//! recent callers are usually still in the register file: the caller of
+//! a `callN` sits `N / 4` register groups below its callee, and is live as
+//! long as its `WINDOWSTART` bit is set. Once a window overflow spilled a
+//! frame, its `a0` and `a1` are in the base save area, the 16 bytes below
+//! its callee's stack pointer, and so is everything older. One word pair
+//! per frame leads all the way up to the task entry, whose return address
+//! is 0.
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use pulse_dwarf::DebugInfo;
+use pulse_rsp::Client;
+use pulse_xtensa::{RegisterFile, TargetDescription};
+
+use crate::error::Result;
+
+/// GDB's ESP32 register numbering: `pc`, the physical `ar0`..`ar63`, and
+/// further on `windowbase`, which `ar` register is `a0`, in fours, and
+/// `windowstart`.
+pub const REG_PC: usize = 0;
+const REG_AR0: usize = 1;
+const AR_COUNT: usize = 64;
+const REG_WINDOWBASE: usize = 69;
+const REG_WINDOWSTART: usize = 70;
+/// Groups of four `ar` registers, what `windowbase` counts in.
+const WINDOWS: u32 = AR_COUNT as u32 / 4;
+
+/// Frames past this are almost certainly a corrupt stack.
+pub const MAX_DEPTH: usize = 32;
+
+/// ESP32 instruction memory: IRAM, and flash mapped for execution.
+pub fn is_code(addr: u64) -> bool {
+    (0x4000_0000..0x4040_0000).contains(&addr)
+}
+
+/// Where the call that left `ra` in `a0` sits: the window increment bits
+/// replaced by the code region's, minus the 3 byte `callN`.
+pub fn call_site(ra: u32) -> u64 {
+    (u64::from(ra & 0x3fff_ffff) | 0x4000_0000).saturating_sub(3)
+}
+
+/// The physical register file, where callers not yet spilled to the
+/// stack keep their `a0` and `a1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Windows {
+    /// `a0` is `ar[windowbase * 4]`.
+    pub windowbase: u32,
+    /// One bit per group of four `ar` registers that starts a live frame.
+    pub windowstart: u32,
+    pub ar: [u32; AR_COUNT],
+}
+
+impl Windows {
+    /// `a0` and `a1` of the live frame whose window starts at group `base`.
+    fn frame(&self, base: u32) -> (u32, u32) {
+        let ar0 = (base % WINDOWS) as usize * 4;
+        (self.ar[ar0], self.ar[ar0 + 1])
+    }
+}
+
+/// What the unwinder starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u32,
+    pub a0: u32,
+    pub a1: u32,
+    /// Without them every caller is read from its save area, which is only
+    /// right once the windows were spilled, as in a core dump.
+    pub windows: Option<Windows>,
+}
+
+impl Registers {
+    /// `pc`, the current window's `a0` and `a1` and the register file of a
+    /// halted target, reading what the stub's `g` packet leaves out.
+    pub fn read<S: Read + Write>(gdb: &mut Client<S>, desc: &TargetDescription) -> Result<Self> {
+        let ar: Vec<String> = (0..AR_COUNT).map(|n| format!("ar{}", n)).collect();
+        let mut names = vec!["pc", "a0", "a1", "windowbase", "windowstart"];
+        names.extend(ar.iter().map(String::as_str));
+        let regs = RegisterFile::read_some(gdb, desc, &names)?;
+        let get = |name: &str| {
+            regs.get(name)
+                .map(|v| v as u32)
+                .ok_or_else(|| pulse_xtensa::Error::NoRegister(name.into()))
+        };
+        let windows = (|| {
+            let mut file = [0; AR_COUNT];
+            for (value, name) in file.iter_mut().zip(&ar) {
+                *value = regs.get(name)? as u32;
+            }
+            Some(Windows {
+                windowbase: regs.get("windowbase")? as u32,
+                windowstart: regs.get("windowstart")? as u32,
+                ar: file,
+            })
+        })();
+        Ok(Self {
+            pc: get("pc")?,
+            a0: get("a0")?,
+            a1: get("a1")?,
+            windows,
+        })
+    }
+
+    /// From a `g` packet. Without `windowbase` in it, `a0` is `ar0`;
+    /// without `windowstart` there are no live callers.
+    pub fn from_g(bytes: &[u8]) -> Option<Self> {
+        let word = |n: usize| {
+            let b = bytes.get(n * 4..n * 4 + 4)?;
+            Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let windowbase = word(REG_WINDOWBASE).unwrap_or(0);
+        let base = windowbase as usize * 4;
+        let windows = (|| {
+            let mut ar = [0; AR_COUNT];
+            for (n, value) in ar.iter_mut().enumerate() {
+                *value = word(REG_AR0 + n)?;
+            }
+            Some(Windows {
+                windowbase,
+                windowstart: word(REG_WINDOWSTART)?,
+                ar,
+            })
+        })();
+        Some(Self {
+            pc: word(REG_PC)?,
+            a0: word(REG_AR0 + base % AR_COUNT)?,
+            a1: word(REG_AR0 + (base + 1) % AR_COUNT)?,
+            windows,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u64,
+    pub sp: u64,
+    /// The function `pc` is in, from the ELF symbol table.
+    pub function: Option<String>,
+    pub offset: u64,
+}
+
+impl Frame {
+    pub fn new(pc: u64, sp: u64) -> Self {
+        Self {
+            pc,
+            sp,
+            function: None,
+            offset: 0,
+        }
+    }
+
+    pub fn symbolize(&mut self, debug: &DebugInfo) {
+        if let Some((function, offset)) = debug.function_at(self.pc) {
+            self.function = Some(function.to_string());
+            self.offset = offset;
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.pc)?;
+        match &self.function {
+            Some(function) if self.offset == 0 => write!(f, ": {}", function),
+            Some(function) => write!(f, ": {}+{:#x}", function, self.offset),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Walk the stack from `regs`: through the live windows first, then base
+/// save areas read with `read_u32`. Stops at a zero return address, at
+/// anything that is not code, when the stack pointer stops growing
+/// towards the caller or after `depth` frames.
+pub fn unwind(
+    regs: Registers,
+    depth: usize,
+    mut read_u32: impl FnMut(u64) -> Option<u32>,
+) -> Vec<Frame> {
+    let mut frames = vec![Frame::new(u64::from(regs.pc), u64::from(regs.a1))];
+    let (mut ra, mut sp) = (regs.a0, u64::from(regs.a1));
+    // the window of the frame being unwound and how far back it is from
+    // the current one, while its callers can still be live
+    let mut live = regs.windows.map(|w| (w, w.windowbase % WINDOWS, 0));
+    while frames.len() < depth && ra != 0 {
+        let pc = call_site(ra);
+        if !is_code(pc) || sp < 16 {
+            break;
+        }
+        // callN moved the callee's window up by the increment in `ra`
+        let increment = ra >> 30;
+        let caller = live.and_then(|(windows, base, back)| {
+            let caller = (base + WINDOWS - increment) % WINDOWS;
+            let back = back + increment;
+            let started = windows.windowstart & (1 << caller) != 0;
+            (increment != 0 && back < WINDOWS && started).then_some((windows, caller, back))
+        });
+        let (caller_ra, caller_sp) = match caller {
+            Some((windows, base, _)) => windows.frame(base),
+            // spilled, and so is every frame older than this one
+            None => match (read_u32(sp - 16), read_u32(sp - 12)) {
+                (Some(ra), Some(sp)) => (ra, sp),
+                _ => break,
+            },
+        };
+        live = caller;
+        let caller_sp = u64::from(caller_sp);
+        if caller_sp <= sp {
+            break;
+        }
+        frames.push(Frame::new(pc, caller_sp));
+        (ra, sp) = (caller_ra, caller_sp);
+    }
+    frames
+}
+
+/// The backtrace of a halted target, symbolized when `debug` is given.
+pub fn capture<S: Read + Write>(
+    gdb: &mut Client<S>,
+    desc: &TargetDescription,
+    depth: usize,
+    debug: Option<&DebugInfo>,
+) -> Result<Vec<Frame>> {
+    let regs = Registers::read(gdb, desc)?;
+    // an unreadable save area ends the walk, like a zero return address
+    let mut frames = unwind(regs, depth, |addr| {
+        let b = gdb.read_memory(addr, 4).ok()?;
+        Some(u32::from_le_bytes(b.get(..4)?.try_into().ok()?))
+    });
+    if let Some(debug) = debug {
+        frames.iter_mut().for_each(|f| f.symbolize(debug));
+    }
+    Ok(frames)
+}
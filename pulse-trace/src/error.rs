@@ -0,0 +1,59 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Rsp(pulse_rsp::Error),
+    Xtensa(pulse_xtensa::Error),
+    /// The target stopped for something other than the watchpoint.
+    Stopped(String),
+    /// A trace file that does not parse.
+    Format(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Xtensa(e) => write!(f, "registers: {}", e),
+            Error::Stopped(why) => write!(f, "stopped without a watchpoint hit: {}", why),
+            Error::Format(what) => write!(f, "bad trace: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Xtensa(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_xtensa::Error> for Error {
+    fn from(e: pulse_xtensa::Error) -> Self {
+        match e {
+            pulse_xtensa::Error::Rsp(e) => Error::Rsp(e),
+            e => Error::Xtensa(e),
+        }
+    }
+}
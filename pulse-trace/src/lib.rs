@@ -0,0 +1,42 @@
+//! Watchpoint write traces for ghost-trigger.
+//!
+//! `watch threat_detected` in GDB stops on every write and leaves the
+//! rest to whoever sits at the prompt. [`record`] sets the hardware
+//! watchpoint, continues through every hit and notes the PC, a
+//! symbolized Xtensa backtrace, the old and new value and `CCOUNT`, so a
+//! long unattended run ends with a [`Trace`] of every writer of a
+//! variable.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use pulse_trace::{record, Options, TraceWriter, Watched};
+//!
+//! let debug = pulse_dwarf::DebugInfo::from_file("target/xtensa-esp32-espidf/debug/ghost-trigger")?;
+//! let var = debug.resolve("PULSE_MAILBOX.ack", None)?;
+//! let watched = Watched {
+//!     name: var.path.clone(),
+//!     address: var.location.address(|_| None).unwrap(),
+//!     size: var.ty.size,
+//!     type_name: Some(var.ty.name.clone()),
+//! };
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! let options = Options { hits: Some(100), ..Options::default() };
+//! let mut trace = TraceWriter::new(std::fs::File::create("ack.trace")?, &watched, options.kind)?;
+//! record(&mut gdb, &watched, Some(&debug), &options, |hit| {
+//!     println!("{}", hit);
+//!     trace.hit(hit)
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod backtrace;
+pub mod error;
+pub mod record;
+pub mod trace;
+
+pub use backtrace::{capture, unwind, Frame, Registers, Windows, MAX_DEPTH};
+pub use error::{Error, Result};
+pub use record::{parse_ccount, record, Options};
+pub use trace::{Hit, Trace, TraceWriter, Watched, Writer};
@@ -0,0 +1,164 @@
+//! The recorder loop: watch, continue, note who hit it, continue.
+
+use std::time::{Duration, Instant};
+
+use pulse_dwarf::DebugInfo;
+use pulse_rsp::{BreakpointKind, Client, StopReason, StopReply, WatchKind};
+use pulse_xtensa::TargetDescription;
+
+use crate::backtrace::{self, MAX_DEPTH};
+use crate::error::{Error, Result};
+use crate::trace::{Hit, Watched};
+
+/// Replies to everything but `continue`.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often a time-limited run checks the clock while the target runs.
+const POLL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub kind: WatchKind,
+    /// Stop after this many hits.
+    pub hits: Option<usize>,
+    /// Stop after this long, halting the target if it is running.
+    pub duration: Option<Duration>,
+    /// Backtrace frames per hit.
+    pub depth: usize,
+    /// Ask the debug server for `CCOUNT` at every hit, one extra
+    /// `monitor reg ccount` round trip.
+    pub ccount: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            kind: WatchKind::Write,
+            hits: None,
+            duration: None,
+            depth: MAX_DEPTH,
+            ccount: true,
+        }
+    }
+}
+
+/// Record hits on `watched` until `options` says stop, handing each to
+/// `on_hit` as it happens. With neither a hit count nor a duration this
+/// runs until the connection fails. The target is left halted, with the
+/// watchpoint removed. Returns the number of hits.
+pub fn record(
+    gdb: &mut Client,
+    watched: &Watched,
+    debug: Option<&DebugInfo>,
+    options: &Options,
+    mut on_hit: impl FnMut(&Hit) -> Result<()>,
+) -> Result<usize> {
+    let kind = match options.kind {
+        WatchKind::Write => BreakpointKind::WriteWatch,
+        WatchKind::Read => BreakpointKind::ReadWatch,
+        WatchKind::Access => BreakpointKind::AccessWatch,
+    };
+    let len = watched.size.max(1) as usize;
+    gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+    let desc = TargetDescription::fetch_or_esp32(gdb)?;
+    let mut last = gdb.read_memory(watched.address, len)?;
+    gdb.insert_point(kind, watched.address, len)?;
+
+    let result = run(gdb, &desc, watched, debug, options, &mut last, &mut on_hit);
+    gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+    gdb.remove_point(kind, watched.address, len)?;
+    result
+}
+
+fn run(
+    gdb: &mut Client,
+    desc: &TargetDescription,
+    watched: &Watched,
+    debug: Option<&DebugInfo>,
+    options: &Options,
+    last: &mut Vec<u8>,
+    on_hit: &mut impl FnMut(&Hit) -> Result<()>,
+) -> Result<usize> {
+    let start = Instant::now();
+    let deadline = options.duration.map(|d| start + d);
+    let len = last.len();
+    let mut count = 0;
+    while options.hits.is_none_or(|max| count < max) {
+        let Some(stop) = cont(gdb, deadline)? else {
+            break;
+        };
+        // serial output relayed by the debug server, not ours to show
+        drop(gdb.take_console());
+        let StopReply::Signal {
+            reason: Some(StopReason::Watch { kind, .. }),
+            ..
+        } = stop
+        else {
+            return Err(Error::Stopped(format!("{:?}", stop)));
+        };
+        let elapsed = start.elapsed();
+        gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+        let new = gdb.read_memory(watched.address, len)?;
+        let ccount = match options.ccount {
+            true => parse_ccount(&gdb.monitor("reg ccount")?),
+            false => None,
+        };
+        count += 1;
+        let hit = Hit {
+            index: count,
+            kind,
+            elapsed,
+            ccount,
+            old: std::mem::replace(last, new.clone()),
+            new,
+            backtrace: backtrace::capture(gdb, desc, options.depth.max(1), debug)?,
+        };
+        on_hit(&hit)?;
+    }
+    Ok(count)
+}
+
+/// Continue until the target stops, or until `deadline`, when it is
+/// halted and `None` returned.
+fn cont(gdb: &mut Client, deadline: Option<Instant>) -> Result<Option<StopReply>> {
+    let Some(deadline) = deadline else {
+        // hits can be a whole detection cycle apart, or never come
+        gdb.set_timeout(None)?;
+        return Ok(Some(gdb.cont()?));
+    };
+    if Instant::now() >= deadline {
+        return Ok(None);
+    }
+    gdb.set_timeout(Some(POLL))?;
+    gdb.resume()?;
+    loop {
+        match gdb.poll_stop() {
+            Ok(Some(stop)) => return Ok(Some(stop)),
+            // console output, nobody is reading it here
+            Ok(None) => drop(gdb.take_console()),
+            Err(e) if e.is_timeout() => {}
+            Err(e) => return Err(e.into()),
+        }
+        if Instant::now() >= deadline {
+            gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+            let stop = gdb.interrupt()?;
+            // the hit may have raced the Ctrl-C
+            return Ok(match stop {
+                StopReply::Signal {
+                    reason: Some(StopReason::Watch { .. }),
+                    ..
+                } => Some(stop),
+                _ => None,
+            });
+        }
+    }
+}
+
+/// `ccount (/32): 0x0001e240` from OpenOCD's `reg ccount`.
+pub fn parse_ccount(output: &str) -> Option<u32> {
+    let (_, value) = output.trim().split_once(": ")?;
+    let value = value.trim();
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
@@ -0,0 +1,288 @@
+//! Hits and the trace file: JSON lines, a header naming the watched
+//! variable and then one object per hit, flushed as they happen so a long
+//! run that gets killed still leaves everything up to that point.
+
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use pulse_rsp::WatchKind;
+use serde_json::{json, Value};
+
+use crate::backtrace::Frame;
+use crate::error::{Error, Result};
+
+pub const FORMAT: &str = "pulse-trace";
+pub const VERSION: u64 = 1;
+
+/// What is being watched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watched {
+    /// Variable path, or the address for raw watches.
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub type_name: Option<String>,
+}
+
+/// One watchpoint stop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    /// Counting from 1.
+    pub index: usize,
+    pub kind: WatchKind,
+    /// Since recording started.
+    pub elapsed: Duration,
+    /// `CCOUNT` at the stop, if the debug server would tell.
+    pub ccount: Option<u32>,
+    /// The value at the previous hit, or when recording started.
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+    /// Innermost first, `backtrace[0].pc` is the access.
+    pub backtrace: Vec<Frame>,
+}
+
+impl Hit {
+    pub fn pc(&self) -> u64 {
+        self.backtrace.first().map_or(0, |f| f.pc)
+    }
+
+    /// Little-endian value of up to 8 bytes.
+    pub fn old_value(&self) -> Option<u64> {
+        little_endian(&self.old)
+    }
+
+    pub fn new_value(&self) -> Option<u64> {
+        little_endian(&self.new)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "hit": self.index,
+            "kind": kind_name(self.kind),
+            "pc": self.pc(),
+            "elapsed_ms": self.elapsed.as_secs_f64() * 1000.0,
+            "ccount": self.ccount,
+            "old": hex_bytes(&self.old),
+            "new": hex_bytes(&self.new),
+            "old_value": self.old_value(),
+            "new_value": self.new_value(),
+            "backtrace": self.backtrace.iter().map(|f| json!({
+                "pc": f.pc,
+                "sp": f.sp,
+                "function": f.function,
+                "offset": f.offset,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn from_json(v: &Value) -> Result<Self> {
+        let bad = |what: &str| Error::Format(format!("hit without {}", what));
+        let u64_of = |v: &Value, key: &str| v[key].as_u64().ok_or_else(|| bad(key));
+        let backtrace = v["backtrace"]
+            .as_array()
+            .ok_or_else(|| bad("backtrace"))?
+            .iter()
+            .map(|f| {
+                Ok(Frame {
+                    pc: u64_of(f, "pc")?,
+                    sp: u64_of(f, "sp")?,
+                    function: f["function"].as_str().map(str::to_string),
+                    offset: f["offset"].as_u64().unwrap_or(0),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            index: u64_of(v, "hit")? as usize,
+            kind: v["kind"]
+                .as_str()
+                .and_then(parse_kind)
+                .ok_or_else(|| bad("kind"))?,
+            elapsed: Duration::from_secs_f64(
+                v["elapsed_ms"].as_f64().ok_or_else(|| bad("elapsed_ms"))? / 1000.0,
+            ),
+            ccount: v["ccount"].as_u64().map(|c| c as u32),
+            old: parse_hex(&v["old"]).ok_or_else(|| bad("old"))?,
+            new: parse_hex(&v["new"]).ok_or_else(|| bad("new"))?,
+            backtrace,
+        })
+    }
+}
+
+impl fmt::Display for Hit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value =
+            |bytes: &[u8]| little_endian(bytes).map_or_else(|| hex_bytes(bytes), |v| v.to_string());
+        write!(
+            f,
+            "#{} {} at {:#010x}, +{} ms",
+            self.index,
+            kind_name(self.kind),
+            self.pc(),
+            self.elapsed.as_millis()
+        )?;
+        if let Some(ccount) = self.ccount {
+            write!(f, ", ccount {}", ccount)?;
+        }
+        write!(f, ": {} -> {}", value(&self.old), value(&self.new))?;
+        for frame in &self.backtrace {
+            write!(f, "\n    {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn kind_name(kind: WatchKind) -> &'static str {
+    match kind {
+        WatchKind::Write => "write",
+        WatchKind::Read => "read",
+        WatchKind::Access => "access",
+    }
+}
+
+fn parse_kind(name: &str) -> Option<WatchKind> {
+    match name {
+        "write" => Some(WatchKind::Write),
+        "read" => Some(WatchKind::Read),
+        "access" => Some(WatchKind::Access),
+        _ => None,
+    }
+}
+
+fn little_endian(bytes: &[u8]) -> Option<u64> {
+    (!bytes.is_empty() && bytes.len() <= 8).then(|| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| acc << 8 | u64::from(b))
+    })
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(v: &Value) -> Option<Vec<u8>> {
+    let s = v.as_str()?;
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Writes a trace file as the hits come in.
+pub struct TraceWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(mut out: W, watched: &Watched, kind: WatchKind) -> Result<Self> {
+        let header = json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "variable": watched.name,
+            "address": watched.address,
+            "size": watched.size,
+            "type": watched.type_name,
+            "kind": kind_name(kind),
+        });
+        writeln!(out, "{}", header)?;
+        out.flush()?;
+        Ok(Self { out })
+    }
+
+    pub fn hit(&mut self, hit: &Hit) -> Result<()> {
+        writeln!(self.out, "{}", hit.to_json())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// A trace file read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub watched: Watched,
+    pub kind: WatchKind,
+    pub hits: Vec<Hit>,
+}
+
+/// Every distinct accessing PC in a trace, with how often it hit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Writer {
+    pub pc: u64,
+    pub function: Option<String>,
+    pub offset: u64,
+    pub hits: usize,
+}
+
+impl Trace {
+    pub fn read_from(input: impl BufRead) -> Result<Self> {
+        let mut lines = input.lines();
+        let header: Value = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| Error::Format(e.to_string()))?,
+            None => return Err(Error::Format("empty file".into())),
+        };
+        if header["format"] != FORMAT {
+            return Err(Error::Format("not a pulse trace".into()));
+        }
+        if header["version"] != VERSION {
+            return Err(Error::Format(format!(
+                "unsupported version {}",
+                header["version"]
+            )));
+        }
+        let watched = Watched {
+            name: header["variable"].as_str().unwrap_or_default().to_string(),
+            address: header["address"].as_u64().unwrap_or(0),
+            size: header["size"].as_u64().unwrap_or(0),
+            type_name: header["type"].as_str().map(str::to_string),
+        };
+        let kind = header["kind"]
+            .as_str()
+            .and_then(parse_kind)
+            .ok_or_else(|| Error::Format("header without kind".into()))?;
+        let mut hits = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let v: Value = serde_json::from_str(&line).map_err(|e| Error::Format(e.to_string()))?;
+            hits.push(Hit::from_json(&v)?);
+        }
+        Ok(Self {
+            watched,
+            kind,
+            hits,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// Who touched the variable, most frequent first.
+    pub fn writers(&self) -> Vec<Writer> {
+        let mut writers: Vec<Writer> = Vec::new();
+        for hit in &self.hits {
+            let Some(frame) = hit.backtrace.first() else {
+                continue;
+            };
+            match writers.iter_mut().find(|w| w.pc == frame.pc) {
+                Some(writer) => writer.hits += 1,
+                None => writers.push(Writer {
+                    pc: frame.pc,
+                    function: frame.function.clone(),
+                    offset: frame.offset,
+                    hits: 1,
+                }),
+            }
+        }
+        writers.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.pc.cmp(&b.pc)));
+        writers
+    }
+}
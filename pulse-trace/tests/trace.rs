@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use pulse_core::mailbox::{Signal, OFFSET_ACK, OFFSET_SEQUENCE, OFFSET_SIGNAL};
+use pulse_dwarf::DebugInfo;
+use pulse_mock::{fixture, Image, Options as MockOptions, Server};
+use pulse_rsp::{Client, WatchKind};
+use pulse_trace::{
+    parse_ccount, record, unwind, Options, Registers, Trace, TraceWriter, Watched, Windows,
+};
+
+#[test]
+fn windowed_unwind() {
+    // task entry <- main <- cycle, stacks growing down
+    let memory = [
+        (0x3ffe_1000 - 16, 0x8000_0000 | 0x000d_0123), // main's ra, call8 bits
+        (0x3ffe_1000 - 12, 0x3ffe_1040),
+        (0x3ffe_1040 - 16, 0),
+        (0x3ffe_1040 - 12, 0x3ffe_1100),
+    ];
+    let read = |addr: u64| memory.iter().find(|(a, _)| *a == addr).map(|&(_, v)| v);
+    let regs = Registers {
+        pc: 0x400d_0400,
+        a0: 0x8000_0000 | 0x000d_01c3,
+        a1: 0x3ffe_1000,
+        windows: None,
+    };
+    let pcs: Vec<(u64, u64)> = unwind(regs, 32, read)
+        .iter()
+        .map(|f| (f.pc, f.sp))
+        .collect();
+    assert_eq!(
+        pcs,
+        [
+            (0x400d_0400, 0x3ffe_1000),
+            (0x400d_01c0, 0x3ffe_1040),
+            (0x400d_0120, 0x3ffe_1100),
+        ]
+    );
+    assert_eq!(unwind(regs, 2, read).len(), 2);
+
+    // a save area pointing back down the stack is garbage, stop there
+    let looping = |addr: u64| {
+        Some(if addr.is_multiple_of(8) {
+            regs.a0
+        } else {
+            0x3ffe_0f00
+        })
+    };
+    assert_eq!(unwind(regs, 32, looping).len(), 1);
+
+    // windowbase 1 rotates a0/a1 to ar4/ar5
+    let mut g = vec![0u8; 70 * 4];
+    g[..4].copy_from_slice(&0x400d_0400u32.to_le_bytes());
+    g[5 * 4..6 * 4].copy_from_slice(&0x8000_1234u32.to_le_bytes());
+    g[6 * 4..7 * 4].copy_from_slice(&0x3ffe_1000u32.to_le_bytes());
+    g[69 * 4..70 * 4].copy_from_slice(&1u32.to_le_bytes());
+    assert_eq!(
+        Registers::from_g(&g),
+        Some(Registers {
+            pc: 0x400d_0400,
+            a0: 0x8000_1234,
+            a1: 0x3ffe_1000,
+            windows: None,
+        })
+    );
+    assert_eq!(Registers::from_g(&g[..8]), None);
+}
+
+#[test]
+fn live_windows_before_save_areas() {
+    // cycle at windowbase 4, main at 2 still live, app_main at 0 spilled
+    let mut ar = [0; 64];
+    ar[16] = 0x8000_0000 | 0x000d_01c3; // cycle's ra into main
+    ar[17] = 0x3ffe_1000;
+    ar[8] = 0x8000_0000 | 0x000d_0123; // main's ra into app_main
+    ar[9] = 0x3ffe_1040;
+    // stale words an unwinder reading main's save area would trust
+    ar[0] = 0xdead_beef;
+    let memory = [
+        (0x3ffe_1000 - 16, 0x8000_0000 | 0x000d_0777),
+        (0x3ffe_1000 - 12, 0x3ffe_1f00),
+        (0x3ffe_1040 - 16, 0),
+        (0x3ffe_1040 - 12, 0x3ffe_1100),
+    ];
+    let read = |addr: u64| memory.iter().find(|(a, _)| *a == addr).map(|&(_, v)| v);
+    let mut regs = Registers {
+        pc: 0x400d_0400,
+        a0: ar[16],
+        a1: ar[17],
+        windows: Some(Windows {
+            windowbase: 4,
+            windowstart: 0b1_0100,
+            ar,
+        }),
+    };
+    let walk = |regs: Registers| -> Vec<(u64, u64)> {
+        unwind(regs, 32, read)
+            .iter()
+            .map(|f| (f.pc, f.sp))
+            .collect()
+    };
+    assert_eq!(
+        walk(regs),
+        [
+            (0x400d_0400, 0x3ffe_1000),
+            (0x400d_01c0, 0x3ffe_1040),
+            (0x400d_0120, 0x3ffe_1100),
+        ]
+    );
+
+    // once main is spilled too, its save area is the truth
+    regs.windows.as_mut().unwrap().windowstart = 0b1_0000;
+    assert_eq!(
+        walk(regs),
+        [(0x400d_0400, 0x3ffe_1000), (0x400d_01c0, 0x3ffe_1f00)]
+    );
+
+    // `g` with windowstart: the register file comes along
+    let mut g = vec![0u8; 71 * 4];
+    g[69 * 4..70 * 4].copy_from_slice(&4u32.to_le_bytes());
+    g[70 * 4..71 * 4].copy_from_slice(&0b1_0100u32.to_le_bytes());
+    for (n, value) in ar.iter().enumerate() {
+        g[(n + 1) * 4..(n + 2) * 4].copy_from_slice(&value.to_le_bytes());
+    }
+    let from_g = Registers::from_g(&g).unwrap();
+    assert_eq!((from_g.a0, from_g.a1), (ar[16], ar[17]));
+    assert_eq!(from_g.windows.map(|w| w.windowstart), Some(0b1_0100));
+}
+
+#[test]
+fn ccount_output() {
+    assert_eq!(parse_ccount("ccount (/32): 0x0001e240\n"), Some(0x1e240));
+    assert_eq!(parse_ccount("ccount (/32): 123"), Some(123));
+    assert_eq!(parse_ccount("mock target: ignoring `reg ccount`"), None);
+}
+
+#[test]
+fn mailbox_ack_writers_on_the_mock() {
+    let elf = fixture::ghost_trigger();
+    let debug = DebugInfo::parse(&elf).unwrap();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&elf).unwrap(),
+        MockOptions { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+
+    let ack = debug.resolve("PULSE_MAILBOX.ack", None).unwrap();
+    let watched = Watched {
+        name: ack.path.clone(),
+        address: fixture::PULSE_MAILBOX + u64::from(OFFSET_ACK),
+        size: ack.ty.size,
+        type_name: Some(ack.ty.name.clone()),
+    };
+    let post = |gdb: &mut Client, sequence: u32| {
+        let at = |offset: u32| fixture::PULSE_MAILBOX + u64::from(offset);
+        gdb.write_u32(at(OFFSET_SIGNAL), Signal::Threat.id())
+            .unwrap();
+        gdb.write_u32(at(OFFSET_SEQUENCE), sequence).unwrap();
+    };
+
+    let mut file = Vec::new();
+    let mut writer = TraceWriter::new(&mut file, &watched, WatchKind::Write).unwrap();
+    let options = Options {
+        hits: Some(1),
+        ..Options::default()
+    };
+    for sequence in 1..=2 {
+        post(&mut gdb, sequence);
+        let hits = record(&mut gdb, &watched, Some(&debug), &options, |hit| {
+            writer.hit(hit)
+        })
+        .unwrap();
+        assert_eq!(hits, 1);
+    }
+
+    // nothing posted, nothing written, the deadline ends the run
+    let quiet = Options {
+        duration: Some(Duration::from_millis(100)),
+        ..Options::default()
+    };
+    assert_eq!(
+        record(&mut gdb, &watched, None, &quiet, |_| Ok(())).unwrap(),
+        0
+    );
+    gdb.detach().unwrap();
+
+    let trace = Trace::read_from(file.as_slice()).unwrap();
+    assert_eq!(trace.watched, watched);
+    let values: Vec<(Option<u64>, Option<u64>)> = trace
+        .hits
+        .iter()
+        .map(|h| (h.old_value(), h.new_value()))
+        .collect();
+    assert_eq!(values, [(Some(0), Some(1)), (Some(1), Some(2))]);
+    let first = &trace.hits[0];
+    let frames: Vec<String> = first.backtrace.iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        frames,
+        [
+            "0x400d0400: pulse_core::detector::Detector::cycle",
+            "0x400d01c0: ghost_trigger::main+0x40",
+            "0x400d0120: app_main+0x20",
+        ]
+    );
+    assert!(first.ccount.unwrap() > 0);
+    assert!(trace.hits[1].ccount > first.ccount);
+
+    let writers = trace.writers();
+    assert_eq!(writers.len(), 1);
+    assert_eq!(writers[0].pc, fixture::DETECTOR_CYCLE);
+    assert_eq!(writers[0].hits, 2);
+}
+
+#[test]
+fn bad_trace_files() {
+    assert!(matches!(
+        Trace::read_from(&b""[..]),
+        Err(pulse_trace::Error::Format(_))
+    ));
+    assert!(matches!(
+        Trace::read_from(&b"{\"format\":\"other\"}\n"[..]),
+        Err(pulse_trace::Error::Format(_))
+    ));
+}
@@ -0,0 +1,109 @@
+//! What the bits of the special registers mean.
+
+use std::fmt;
+
+/// The processor state register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ps(pub u32);
+
+impl Ps {
+    /// Interrupts at this level and below are masked.
+    pub fn intlevel(self) -> u32 {
+        self.0 & 0xf
+    }
+
+    /// Exception mode: set while an exception handler runs.
+    pub fn excm(self) -> bool {
+        self.0 & (1 << 4) != 0
+    }
+
+    /// User vector mode, how exceptions are dispatched. Set in app code.
+    pub fn um(self) -> bool {
+        self.0 & (1 << 5) != 0
+    }
+
+    pub fn ring(self) -> u32 {
+        (self.0 >> 6) & 0x3
+    }
+
+    /// Old window base, saved on window overflow and underflow.
+    pub fn owb(self) -> u32 {
+        (self.0 >> 8) & 0xf
+    }
+
+    /// Window increment of the last `callN`, what `entry` rotates by.
+    pub fn callinc(self) -> u32 {
+        (self.0 >> 16) & 0x3
+    }
+
+    /// Window overflow detection enabled, the windowed ABI is in use.
+    pub fn woe(self) -> bool {
+        self.0 & (1 << 18) != 0
+    }
+}
+
+impl fmt::Display for Ps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "INTLEVEL={} EXCM={} UM={} RING={} OWB={} CALLINC={} WOE={}",
+            self.intlevel(),
+            u8::from(self.excm()),
+            u8::from(self.um()),
+            self.ring(),
+            self.owb(),
+            self.callinc(),
+            u8::from(self.woe()),
+        )
+    }
+}
+
+/// `EXCCAUSE` codes by the names ESP-IDF's panic handler prints.
+pub fn exccause_name(cause: u32) -> Option<&'static str> {
+    Some(match cause {
+        0 => "IllegalInstruction",
+        1 => "Syscall",
+        2 => "InstructionFetchError",
+        3 => "LoadStoreError",
+        4 => "Level1Interrupt",
+        5 => "Alloca",
+        6 => "IntegerDivideByZero",
+        7 => "PCValue",
+        8 => "Privileged",
+        9 => "LoadStoreAlignment",
+        12 => "InstrPIFDataError",
+        13 => "LoadStorePIFDataError",
+        14 => "InstrPIFAddrError",
+        15 => "LoadStorePIFAddrError",
+        16 => "InstTLBMiss",
+        17 => "InstTLBMultiHit",
+        18 => "InstFetchPrivilege",
+        20 => "InstFetchProhibited",
+        24 => "LoadStoreTLBMiss",
+        25 => "LoadStoreTLBMultiHit",
+        26 => "LoadStorePrivilege",
+        28 => "LoadProhibited",
+        29 => "StoreProhibited",
+        32..=39 => [
+            "Cp0Disabled",
+            "Cp1Disabled",
+            "Cp2Disabled",
+            "Cp3Disabled",
+            "Cp4Disabled",
+            "Cp5Disabled",
+            "Cp6Disabled",
+            "Cp7Disabled",
+        ][cause as usize - 32],
+        _ => return None,
+    })
+}
+
+/// `WINDOWSTART` bits, rotated so the current window comes first: which
+/// of the 16 groups of four `ar` registers hold live frames, counting
+/// back from the current frame to the oldest one not yet spilled.
+pub fn live_windows(windowstart: u32, windowbase: u32) -> Vec<u32> {
+    (0..16)
+        .map(|back| (windowbase + 16 - back) % 16)
+        .filter(|&w| windowstart & (1 << w) != 0)
+        .collect()
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Rsp(pulse_rsp::Error),
+    /// A target description that does not parse.
+    Xml(String),
+    /// A register the description does not have.
+    NoRegister(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Xml(what) => write!(f, "bad target description: {}", what),
+            Error::NoRegister(name) => write!(f, "no register {:?} on this target", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Rsp(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
@@ -0,0 +1,36 @@
+//! Xtensa LX6 registers for ghost-trigger's ESP32, by name.
+//!
+//! GDB's `info registers` lists the 64 physical `ar` registers and leaves
+//! working out which of them are the current frame's `a0`..`a15` to the
+//! reader. [`TargetDescription::fetch`] reads the register list the debug
+//! stub serves (`qXfer:features:read`), [`RegisterFile::read`] reads
+//! every register in it, and [`RegisterFile::get`] answers by name:
+//! special registers like `ps`, `exccause` and `epc1` as they are, `a{n}`
+//! through `WINDOWBASE` so it is the halted frame's. [`Ps`] and
+//! [`exccause_name`] decode the bits.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use pulse_xtensa::{RegisterFile, TargetDescription};
+//!
+//! let mut gdb = pulse_rsp::Client::connect("127.0.0.1:3333")?;
+//! gdb.handshake()?;
+//! let desc = TargetDescription::fetch_or_esp32(&mut gdb)?;
+//! let regs = RegisterFile::read(&mut gdb, &desc)?;
+//! println!("stack pointer {:#x?}", regs.get("a1"));
+//! for entry in regs.entries() {
+//!     println!("{}", entry);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod decode;
+pub mod error;
+pub mod regs;
+pub mod tdesc;
+
+pub use decode::{exccause_name, live_windows, Ps};
+pub use error::{Error, Result};
+pub use regs::{Entry, RegisterFile, Window};
+pub use tdesc::{Register, TargetDescription};
@@ -0,0 +1,270 @@
+//! Register values by name, with `a0`..`a15` resolved through the window.
+//!
+//! The LX6 has 64 physical `ar` registers; code only ever sees the 16 of
+//! the current window, `a0`..`a15`, which start at `ar[WINDOWBASE * 4]`.
+//! Every `call8` moves the window up by eight, so `ar1` is the stack
+//! pointer only in the outermost frame. GDB's `info registers` shows the
+//! `ar` view; here `a{n}` always means the current frame's register.
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use pulse_rsp::Client;
+
+use crate::decode::{exccause_name, live_windows, Ps};
+use crate::error::{Error, Result};
+use crate::tdesc::{Register, TargetDescription};
+
+pub const AR_COUNT: u32 = 64;
+pub const A_COUNT: u32 = 16;
+
+/// Where `a0`..`a15` are in the physical register file right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    /// `WINDOWBASE`, in groups of four `ar` registers.
+    pub base: u32,
+    /// Register number of `ar0`.
+    ar0: u32,
+}
+
+impl Window {
+    /// Without a `windowbase` register the target uses the call0 ABI and
+    /// `a{n}` is `ar{n}`.
+    pub fn new(desc: &TargetDescription, base: u32) -> Result<Self> {
+        let ar0 = desc
+            .regnum("ar0")
+            .ok_or_else(|| Error::NoRegister("ar0".into()))?;
+        Ok(Self { base, ar0 })
+    }
+
+    /// Read `windowbase` with `p`.
+    pub fn read<S: Read + Write>(gdb: &mut Client<S>, desc: &TargetDescription) -> Result<Self> {
+        let base = match desc.regnum("windowbase") {
+            Some(regnum) => word(&gdb.read_register(regnum)?) as u32,
+            None => 0,
+        };
+        Self::new(desc, base)
+    }
+
+    /// Physical `ar` index of `a{n}`.
+    pub fn ar(&self, n: u32) -> u32 {
+        (self.base * 4 + n) % AR_COUNT
+    }
+
+    /// Register number to read or write `a{n}` of the current frame with.
+    pub fn regnum(&self, n: u32) -> u32 {
+        self.ar0 + self.ar(n)
+    }
+}
+
+/// One line of [`RegisterFile::entries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    /// `None` when the stub could not read it.
+    pub value: Option<u64>,
+    /// Decoded fields, or which `ar` an `a` register is.
+    pub note: Option<String>,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{:<12}{:#010x}", self.name, value)?,
+            None => write!(f, "{:<12}{:<10}", self.name, "<unavailable>")?,
+        }
+        if let Some(note) = &self.note {
+            write!(f, "  {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot of every register the target description lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterFile {
+    desc: TargetDescription,
+    /// Raw target-endian bytes, parallel to `desc.registers`.
+    values: Vec<Option<Vec<u8>>>,
+}
+
+impl RegisterFile {
+    /// From values already read, `values[i]` belonging to `desc.registers[i]`.
+    pub fn new(desc: TargetDescription, mut values: Vec<Option<Vec<u8>>>) -> Self {
+        values.resize(desc.registers.len(), None);
+        Self { desc, values }
+    }
+
+    /// Every register: what `g` covers, the rest one `p` at a time.
+    /// Registers the stub refuses to read are left out.
+    pub fn read<S: Read + Write>(gdb: &mut Client<S>, desc: &TargetDescription) -> Result<Self> {
+        let names: Vec<&str> = desc.registers.iter().map(|r| r.name.as_str()).collect();
+        Self::read_some(gdb, desc, &names)
+    }
+
+    /// `g`, plus `p` for the `names` it did not cover. `a{n}` names read
+    /// `windowbase` and the `ar` register behind them.
+    pub fn read_some<S: Read + Write>(
+        gdb: &mut Client<S>,
+        desc: &TargetDescription,
+        names: &[&str],
+    ) -> Result<Self> {
+        let g = gdb.request(b"g")?;
+        let mut file = Self::new(desc.clone(), Vec::new());
+        let mut at = 0;
+        for (i, reg) in desc.registers.iter().enumerate() {
+            let len = reg.bitsize as usize / 4;
+            let Some(hex) = g.get(at..at + len) else {
+                break;
+            };
+            at += len;
+            // OpenOCD sends `xx` for registers it cannot read
+            file.values[i] = pulse_rsp::packet::from_hex(hex).ok();
+        }
+
+        let mut wanted: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        if names.iter().any(|n| windowed(n).is_some()) {
+            wanted.push("windowbase".into());
+        }
+        wanted.sort_by_key(|n| n.as_str() != "windowbase");
+        for name in wanted {
+            let name = match windowed(&name) {
+                Some(n) => match file.window() {
+                    Some(window) => format!("ar{}", window.ar(n)),
+                    None => name,
+                },
+                None => name,
+            };
+            let Some(i) = file.index(&name) else {
+                continue;
+            };
+            if file.values[i].is_some() {
+                continue;
+            }
+            match gdb.read_register(file.desc.registers[i].regnum) {
+                Ok(bytes) => file.values[i] = Some(bytes),
+                Err(pulse_rsp::Error::Remote(_) | pulse_rsp::Error::Unsupported(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(file)
+    }
+
+    pub fn description(&self) -> &TargetDescription {
+        &self.desc
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.desc
+            .registers
+            .iter()
+            .position(|r| r.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Raw bytes of a register under its own name, no window mapping.
+    pub fn raw(&self, name: &str) -> Option<&[u8]> {
+        self.values[self.index(name)?].as_deref()
+    }
+
+    /// The current window, if `windowbase` was read.
+    pub fn window(&self) -> Option<Window> {
+        let base = match self.desc.register("windowbase") {
+            Some(_) => word(self.raw("windowbase")?) as u32,
+            None => 0,
+        };
+        Window::new(&self.desc, base).ok()
+    }
+
+    /// `a{n}` of the current frame.
+    pub fn a(&self, n: u32) -> Option<u64> {
+        let window = self.window()?;
+        self.raw(&format!("ar{}", window.ar(n))).map(word)
+    }
+
+    /// A register by name, `a0`..`a15` from the current window.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        match windowed(name) {
+            Some(n) if self.desc.register("ar0").is_some() => self.a(n),
+            _ => self.raw(name).map(word),
+        }
+    }
+
+    pub fn pc(&self) -> Option<u64> {
+        self.get("pc")
+    }
+
+    /// `pc`, `a0`..`a15`, then every special register with what its
+    /// bits mean. The `ar` registers and the stub's own `a` views are
+    /// left out; `a{n}` says which `ar` it is.
+    pub fn entries(&self) -> Vec<Entry> {
+        let window = self.window();
+        let mut out = vec![Entry {
+            name: "pc".into(),
+            value: self.pc(),
+            note: None,
+        }];
+        for n in 0..A_COUNT {
+            out.push(Entry {
+                name: format!("a{}", n),
+                value: self.a(n),
+                note: window.map(|w| format!("ar{}", w.ar(n))),
+            });
+        }
+        for (reg, value) in self.desc.registers.iter().zip(&self.values) {
+            if reg.name == "pc" || is_numbered(&reg.name, "ar") || is_numbered(&reg.name, "a") {
+                continue;
+            }
+            let value = value.as_deref().map(word);
+            out.push(Entry {
+                name: reg.name.clone(),
+                value,
+                note: value.and_then(|v| self.note(reg, v)),
+            });
+        }
+        out
+    }
+
+    fn note(&self, reg: &Register, value: u64) -> Option<String> {
+        let value32 = value as u32;
+        match reg.name.to_ascii_lowercase().as_str() {
+            "ps" => Some(Ps(value32).to_string()),
+            "exccause" => exccause_name(value32).map(str::to_string),
+            "windowbase" => Some(format!("a0 is ar{}", (value32 * 4) % AR_COUNT)),
+            "windowstart" => {
+                let base = self.window().map_or(0, |w| w.base);
+                let live: Vec<String> = live_windows(value32, base)
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect();
+                Some(format!(
+                    "{:016b} live windows {}",
+                    value32 & 0xffff,
+                    live.join(" ")
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `n` for `a{n}` with `n` below 16.
+fn windowed(name: &str) -> Option<u32> {
+    let digits = name.strip_prefix(['a', 'A'])?;
+    if !is_numbered(name, &name[..1]) || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    digits.parse().ok().filter(|&n| n < A_COUNT)
+}
+
+fn is_numbered(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Little-endian, as much of it as fits in a `u64`.
+fn word(bytes: &[u8]) -> u64 {
+    let mut out = [0u8; 8];
+    let n = bytes.len().min(8);
+    out[..n].copy_from_slice(&bytes[..n]);
+    u64::from_le_bytes(out)
+}
@@ -0,0 +1,224 @@
+//! GDB target descriptions: which registers a target has, and their numbers.
+//!
+//! Only the parts of the format register decoding needs are read:
+//! `<architecture>`, `<reg>` and `<xi:include>`. Types, flags fields and
+//! everything else are skipped.
+
+use std::io::{Read, Write};
+
+use pulse_rsp::Client;
+
+use crate::error::{Error, Result};
+
+/// Includes nested deeper than this are a loop.
+const MAX_INCLUDE_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub name: String,
+    /// What `p`/`P` and the `g` order go by.
+    pub regnum: u32,
+    pub bitsize: u32,
+    /// `general`, `system`, `float`..., as the stub groups them.
+    pub group: Option<String>,
+    /// `uint32`, `code_ptr`, `data_ptr` or a type the description defines.
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetDescription {
+    pub architecture: Option<String>,
+    /// In register number order.
+    pub registers: Vec<Register>,
+}
+
+/// The core registers every ESP32 debug stub numbers the same way, for
+/// stubs that do not serve a description: `pc`, `ar0`..`ar63`, then the
+/// loop, shift and window registers and `ps`.
+const ESP32_SPECIALS: [&str; 10] = [
+    "lbeg",
+    "lend",
+    "lcount",
+    "sar",
+    "windowbase",
+    "windowstart",
+    "configid0",
+    "configid1",
+    "ps",
+    "threadptr",
+];
+
+impl TargetDescription {
+    /// Parse `target.xml`, fetching included annexes through `include`.
+    pub fn parse(xml: &str, mut include: impl FnMut(&str) -> Result<String>) -> Result<Self> {
+        let mut desc = Self::default();
+        let mut next = 0;
+        desc.parse_into(xml, &mut include, &mut next, 0)?;
+        desc.registers.sort_by_key(|r| r.regnum);
+        Ok(desc)
+    }
+
+    /// `qXfer:features:read:target.xml` and everything it includes.
+    pub fn fetch<S: Read + Write>(gdb: &mut Client<S>) -> Result<Self> {
+        let mut read = |annex: &str| -> Result<String> {
+            let bytes = gdb.read_xfer("features", annex)?;
+            String::from_utf8(bytes).map_err(|_| Error::Xml(format!("{} is not UTF-8", annex)))
+        };
+        let xml = read("target.xml")?;
+        Self::parse(&xml, read)
+    }
+
+    /// [`TargetDescription::fetch`], falling back to [`TargetDescription::esp32`]
+    /// for stubs without `qXfer:features:read`.
+    pub fn fetch_or_esp32<S: Read + Write>(gdb: &mut Client<S>) -> Result<Self> {
+        match Self::fetch(gdb) {
+            Ok(desc) => Ok(desc),
+            Err(Error::Rsp(pulse_rsp::Error::Unsupported(_) | pulse_rsp::Error::Remote(_))) => {
+                Ok(Self::esp32())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The numbering of GDB's built-in ESP32 register file, as far as it
+    /// is the same in every version.
+    pub fn esp32() -> Self {
+        let reg = |name: String, regnum: u32| Register {
+            name,
+            regnum,
+            bitsize: 32,
+            group: None,
+            kind: None,
+        };
+        let mut registers = vec![reg("pc".into(), 0)];
+        registers.extend((0..64).map(|n| reg(format!("ar{}", n), n + 1)));
+        registers.extend(
+            ESP32_SPECIALS
+                .iter()
+                .zip(65..)
+                .map(|(name, regnum)| reg(name.to_string(), regnum)),
+        );
+        Self {
+            architecture: Some("xtensa".into()),
+            registers,
+        }
+    }
+
+    /// By name, ignoring case: OpenOCD says `PS` in some versions.
+    pub fn register(&self, name: &str) -> Option<&Register> {
+        self.registers
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn regnum(&self, name: &str) -> Option<u32> {
+        self.register(name).map(|r| r.regnum)
+    }
+
+    fn parse_into(
+        &mut self,
+        xml: &str,
+        include: &mut impl FnMut(&str) -> Result<String>,
+        next: &mut u32,
+        depth: usize,
+    ) -> Result<()> {
+        let mut rest = xml;
+        while let Some(open) = rest.find('<') {
+            rest = &rest[open..];
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->").map(|at| at + 3)
+            } else {
+                rest.find('>').map(|at| at + 1)
+            }
+            .ok_or_else(|| Error::Xml("unterminated tag".into()))?;
+            let tag = &rest[1..end - 1];
+            rest = &rest[end..];
+            if tag.starts_with(['?', '!', '/']) {
+                continue;
+            }
+
+            let tag = tag.trim_end_matches('/');
+            let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            let attrs = attributes(attrs)?;
+            let attr = |key: &str| {
+                attrs
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v.as_str())
+            };
+            match name {
+                "architecture" => {
+                    let text = rest.split('<').next().unwrap_or_default().trim();
+                    self.architecture = Some(unescape(text));
+                }
+                "reg" => {
+                    let name =
+                        attr("name").ok_or_else(|| Error::Xml("reg without a name".into()))?;
+                    let number = |key: &str| -> Result<Option<u32>> {
+                        attr(key)
+                            .map(|v| {
+                                v.parse()
+                                    .map_err(|_| Error::Xml(format!("{}={:?} of {}", key, v, name)))
+                            })
+                            .transpose()
+                    };
+                    let regnum = number("regnum")?.unwrap_or(*next);
+                    let bitsize = number("bitsize")?
+                        .ok_or_else(|| Error::Xml(format!("{} has no bitsize", name)))?;
+                    *next = regnum + 1;
+                    self.registers.push(Register {
+                        name: name.to_string(),
+                        regnum,
+                        bitsize,
+                        group: attr("group").map(str::to_string),
+                        kind: attr("type").map(str::to_string),
+                    });
+                }
+                "xi:include" => {
+                    let href = attr("href")
+                        .ok_or_else(|| Error::Xml("xi:include without an href".into()))?;
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(Error::Xml(format!("includes nested too deep at {}", href)));
+                    }
+                    let included = include(href)?;
+                    self.parse_into(&included, include, next, depth + 1)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `key="value"` pairs, either quote.
+fn attributes(mut text: &str) -> Result<Vec<(String, String)>> {
+    let mut out = Vec::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return Ok(out);
+        }
+        let (key, rest) = text
+            .split_once('=')
+            .ok_or_else(|| Error::Xml(format!("attribute without a value: {:?}", text)))?;
+        let rest = rest.trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))
+            .ok_or_else(|| Error::Xml(format!("unquoted attribute {}", key.trim())))?;
+        let value_end = rest[1..]
+            .find(quote)
+            .ok_or_else(|| Error::Xml(format!("unterminated attribute {}", key.trim())))?;
+        out.push((key.trim().to_string(), unescape(&rest[1..1 + value_end])));
+        text = &rest[value_end + 2..];
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
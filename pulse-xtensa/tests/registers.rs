@@ -0,0 +1,153 @@
+use pulse_mock::{cpu, fixture, Image, Options, Server};
+use pulse_rsp::{BreakpointKind, Client};
+use pulse_xtensa::{
+    exccause_name, live_windows, Error, Ps, RegisterFile, TargetDescription, Window,
+};
+
+fn connect() -> Client {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let mut gdb = Client::connect(addr).unwrap();
+    gdb.handshake().unwrap();
+    gdb
+}
+
+#[test]
+fn descriptions_with_includes() {
+    let target = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>xtensa</architecture>
+  <!-- <reg name="commented" bitsize="32"/> -->
+  <xi:include href="core.xml"/>
+</target>"#;
+    let core = r#"<feature name="org.gnu.gdb.xtensa.core">
+  <reg name="pc" bitsize="32" regnum="0" type="code_ptr"/>
+  <reg name='ar0' bitsize='32' group="general"/>
+  <reg name="ar1" bitsize="32"/>
+  <reg name="PS" bitsize="32" regnum="73" group="system"/>
+  <reg name="sar" bitsize="32" regnum="68"/>
+</feature>"#;
+    let desc = TargetDescription::parse(target, |href| {
+        assert_eq!(href, "core.xml");
+        Ok(core.to_string())
+    })
+    .unwrap();
+    assert_eq!(desc.architecture.as_deref(), Some("xtensa"));
+    let numbers: Vec<(&str, u32)> = desc
+        .registers
+        .iter()
+        .map(|r| (r.name.as_str(), r.regnum))
+        .collect();
+    assert_eq!(
+        numbers,
+        [("pc", 0), ("ar0", 1), ("ar1", 2), ("sar", 68), ("PS", 73)]
+    );
+    assert_eq!(desc.regnum("ps"), Some(73));
+    assert_eq!(
+        desc.register("ar0").unwrap().group.as_deref(),
+        Some("general")
+    );
+
+    let looping = r#"<target><xi:include href="target.xml"/></target>"#;
+    assert!(matches!(
+        TargetDescription::parse(looping, |_| Ok(looping.to_string())),
+        Err(Error::Xml(_))
+    ));
+    assert!(matches!(
+        TargetDescription::parse(r#"<reg name="pc"/>"#, |_| unreachable!()),
+        Err(Error::Xml(_))
+    ));
+
+    // without a description: GDB's numbering, windowbase where backtraces expect it
+    let esp32 = TargetDescription::esp32();
+    assert_eq!(esp32.regnum("ar63"), Some(64));
+    assert_eq!(esp32.regnum("windowbase"), Some(69));
+    assert_eq!(esp32.regnum("ps"), Some(73));
+}
+
+#[test]
+fn windowed_registers_on_the_mock() {
+    let mut gdb = connect();
+    let desc = TargetDescription::fetch_or_esp32(&mut gdb).unwrap();
+    assert_eq!(desc.regnum("exccause"), Some(cpu::REG_EXCCAUSE as u32));
+    assert_eq!(desc.regnum("a0"), Some(cpu::REG_A0 as u32));
+
+    // two call8s deep: app_main -> main -> Detector::cycle
+    gdb.insert_point(BreakpointKind::Hardware, fixture::DETECTOR_CYCLE, 2)
+        .unwrap();
+    gdb.cont().unwrap();
+    let regs = RegisterFile::read(&mut gdb, &desc).unwrap();
+    assert_eq!(regs.pc(), Some(fixture::DETECTOR_CYCLE));
+    assert_eq!(regs.get("windowbase"), Some(4));
+    let window = regs.window().unwrap();
+    assert_eq!((window.ar(0), window.ar(1)), (16, 17));
+
+    let reset_sp = cpu::STACK_BASE + cpu::STACK_SIZE as u64 - 0x100;
+    assert_eq!(regs.get("a1"), Some(reset_sp - 0x40 - 0x60));
+    assert_eq!(
+        regs.get("a0"),
+        Some(0x8000_0000 | (fixture::MAIN + 0x43) & 0x3fff_ffff)
+    );
+    // the callers' frames are still in the register file
+    assert_eq!(regs.get("ar9"), Some(reset_sp - 0x40));
+    assert_eq!(regs.get("ar1"), Some(reset_sp));
+    // and agree with the stub's own view of the window
+    let stub_a1 = gdb.read_register(cpu::REG_A0 as u32 + 1).unwrap();
+    assert_eq!(stub_a1, ((reset_sp - 0xa0) as u32).to_le_bytes());
+
+    let ps = Ps(regs.get("ps").unwrap() as u32);
+    assert_eq!(
+        (ps.callinc(), ps.woe(), ps.um(), ps.excm()),
+        (2, true, true, false)
+    );
+    assert_eq!(
+        live_windows(regs.get("windowstart").unwrap() as u32, 4),
+        [4, 2, 0]
+    );
+
+    let entries = regs.entries();
+    let a1 = entries.iter().find(|e| e.name == "a1").unwrap();
+    assert_eq!(a1.to_string(), "a1          0x3ffe1e60  ar17");
+    assert!(!entries.iter().any(|e| e.name == "ar17"));
+
+    // a1 of the current frame through the window, what DWARF's reg 1 means
+    let live = Window::read(&mut gdb, &desc).unwrap();
+    assert_eq!(live.regnum(1), cpu::REG_AR0 as u32 + 17);
+
+    gdb.write_register(cpu::REG_EXCCAUSE as u32, &28u32.to_le_bytes())
+        .unwrap();
+    let regs = RegisterFile::read_some(&mut gdb, &desc, &["exccause", "a2"]).unwrap();
+    let cause = regs
+        .entries()
+        .into_iter()
+        .find(|e| e.name == "exccause")
+        .unwrap();
+    assert_eq!(cause.note.as_deref(), Some("LoadProhibited"));
+    assert_eq!(regs.get("epc1"), None);
+}
+
+#[test]
+fn special_register_fields() {
+    let ps = Ps(0x0006_0a25);
+    assert_eq!(ps.intlevel(), 5);
+    assert!(!ps.excm());
+    assert!(ps.um());
+    assert_eq!(ps.owb(), 0xa);
+    assert_eq!(
+        ps.to_string(),
+        "INTLEVEL=5 EXCM=0 UM=1 RING=0 OWB=10 CALLINC=2 WOE=1"
+    );
+
+    assert_eq!(exccause_name(29), Some("StoreProhibited"));
+    assert_eq!(exccause_name(33), Some("Cp1Disabled"));
+    assert_eq!(exccause_name(10), None);
+
+    // window 1 is current, 15 wrapped around below it
+    assert_eq!(live_windows(0b1000_0000_0000_0011, 1), [1, 0, 15]);
+}
@@ -0,0 +1,74 @@
+//! Hand-rolled option parsing, shared by every subcommand.
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// What is left of the command line. Options are taken out by name, in
+/// any position, and whatever remains must be positional.
+pub struct Args {
+    items: Vec<String>,
+}
+
+impl Args {
+    pub fn new(items: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            items: items.into_iter().collect(),
+        }
+    }
+
+    /// A switch like `--json`.
+    pub fn flag(&mut self, name: &str) -> bool {
+        match self.items.iter().position(|a| a == name) {
+            Some(at) => {
+                self.items.remove(at);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// An option with a value, `--port /dev/ttyUSB0`.
+    pub fn value(&mut self, name: &str) -> Result<Option<String>> {
+        let Some(at) = self.items.iter().position(|a| a == name) else {
+            return Ok(None);
+        };
+        if at + 1 >= self.items.len() {
+            return Err(Error::usage(format!("{} needs a value", name)));
+        }
+        self.items.remove(at);
+        Ok(Some(self.items.remove(at)))
+    }
+
+    pub fn parsed<T: FromStr>(&mut self, name: &str) -> Result<Option<T>> {
+        match self.value(name)? {
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::usage(format!("bad value {:?} for {}", v, name))),
+            None => Ok(None),
+        }
+    }
+
+    /// The first positional argument, the subcommand.
+    pub fn take_first(&mut self) -> Option<String> {
+        let at = self.items.iter().position(|a| !a.starts_with('-'))?;
+        Some(self.items.remove(at))
+    }
+
+    /// Positional arguments, rejecting any option nobody took.
+    pub fn finish(self) -> Result<Vec<String>> {
+        match self.items.iter().find(|a| a.starts_with("--")) {
+            Some(unknown) => Err(Error::usage(format!("unknown option {}", unknown))),
+            None => Ok(self.items),
+        }
+    }
+}
+
+/// Decimal or `0x` hex.
+pub fn number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
@@ -0,0 +1,193 @@
+//! `crash`: panic dumps from a serial log, symbolized against the ELF.
+//! `coredump`: an ESP-IDF core dump, every task unwound and the last
+//! values of the detector's state recovered. `monitor` reports the ones
+//! it sees live the same way.
+
+use std::fs::File;
+use std::io::Read;
+
+use pulse_coredump::{CoreDump, CoreReport, LastValue, TaskReport, DEFAULT_VALUES};
+use pulse_dwarf::DebugInfo;
+use pulse_panic::{parse_dumps, Cause, CrashReport, Frame, PanicDump};
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::error::{Error, Result};
+use crate::output::hex;
+use crate::Global;
+
+/// `pulse crash [FILE]`, the log on stdin without a file.
+pub fn crash(global: &Global, args: Args) -> Result<()> {
+    let log = file_or_stdin(args)?;
+    let dumps = parse_dumps(&String::from_utf8_lossy(&log));
+    if dumps.is_empty() {
+        return Err(Error::Failed("no panic dump in the log".into()));
+    }
+    let mut symbolizer = Symbolizer::new(global);
+    for dump in dumps {
+        symbolizer.report(dump);
+    }
+    Ok(())
+}
+
+/// `pulse coredump [FILE] [--values LIST]`: the `coredump` partition as
+/// `parttool.py` reads it, a bare ELF core, or a log with a UART dump,
+/// from stdin without a file.
+pub fn coredump(global: &Global, mut args: Args) -> Result<()> {
+    let values = args.value("--values")?;
+    let data = file_or_stdin(args)?;
+    let values: Vec<&str> = match &values {
+        Some(list) => list.split(',').map(str::trim).collect(),
+        None => DEFAULT_VALUES.to_vec(),
+    };
+    Symbolizer::new(global).core_dump(CoreDump::parse(&data)?, &values);
+    Ok(())
+}
+
+fn file_or_stdin(args: Args) -> Result<Vec<u8>> {
+    let mut files = args.finish()?;
+    if files.len() > 1 {
+        return Err(Error::usage(format!("unexpected argument {:?}", files[1])));
+    }
+    let mut data = Vec::new();
+    match files.pop() {
+        Some(file) => File::open(file)?.read_to_end(&mut data)?,
+        None => std::io::stdin().read_to_end(&mut data)?,
+    };
+    Ok(data)
+}
+
+/// Loads the ELF the first time a dump needs it. Without one the
+/// reports carry addresses only.
+pub struct Symbolizer<'a> {
+    global: &'a Global,
+    elf: Option<Option<(Vec<u8>, DebugInfo)>>,
+}
+
+impl<'a> Symbolizer<'a> {
+    pub fn new(global: &'a Global) -> Self {
+        Self { global, elf: None }
+    }
+
+    fn elf(&mut self) -> Option<&(Vec<u8>, DebugInfo)> {
+        let global = self.global;
+        self.elf
+            .get_or_insert_with(|| {
+                let path = global.elf();
+                let loaded = std::fs::read(&path)
+                    .ok()
+                    .and_then(|bytes| Some((DebugInfo::parse(&bytes).ok()?, bytes)));
+                if loaded.is_none() && !global.out.json {
+                    eprintln!("pulse: no debug info in {}, addresses only", path.display());
+                }
+                loaded.map(|(debug, bytes)| (bytes, debug))
+            })
+            .as_ref()
+    }
+
+    /// Symbolize `dump` and print the report.
+    pub fn report(&mut self, dump: PanicDump) {
+        let elf = self.elf();
+        let mut report = CrashReport::new(dump, elf.map(|(_, debug)| debug));
+        if let Some((bytes, _)) = elf {
+            report.check_elf(bytes);
+        }
+        self.global.out.emit(
+            report.to_string().trim_end(),
+            json!({ "crash": report_json(&report) }),
+        );
+    }
+
+    /// Unwind every task of `dump`, look up `values` and print the report.
+    pub fn core_dump(&mut self, dump: CoreDump, values: &[&str]) {
+        let elf = self.elf();
+        let debug = elf.map(|(_, debug)| debug);
+        let mut report = CoreReport::new(dump, debug, &Default::default(), values);
+        if let Some((bytes, _)) = elf {
+            report.check_elf(bytes);
+        }
+        self.global.out.emit(
+            report.to_string().trim_end(),
+            json!({ "coredump": core_report_json(&report) }),
+        );
+    }
+}
+
+fn frame_json(f: &Frame) -> Value {
+    json!({
+        "index": f.index,
+        "pc": hex(f.pc),
+        "sp": hex(f.sp),
+        "function": f.function,
+        "offset": f.offset,
+        "file": f.file,
+        "line": f.line,
+        "column": f.column,
+        "inlined": f.inlined,
+    })
+}
+
+pub fn report_json(report: &CrashReport) -> Value {
+    let dump = &report.dump;
+    let cause = match &dump.cause {
+        Cause::Exception(reason) => json!({ "kind": "exception", "reason": reason }),
+        Cause::Abort { pc } => json!({ "kind": "abort", "pc": hex(*pc) }),
+        Cause::StackOverflow { task } => json!({ "kind": "stack_overflow", "task": task }),
+        Cause::Panic => json!({ "kind": "panic" }),
+    };
+    let registers: serde_json::Map<String, Value> = dump
+        .registers
+        .iter()
+        .map(|(name, value)| (name.clone(), json!(hex(u64::from(*value)))))
+        .collect();
+    let frames: Vec<Value> = report.frames.iter().map(frame_json).collect();
+    json!({
+        "summary": report.summary(),
+        "cause": cause,
+        "core": dump.core,
+        "panic": dump.panic.as_ref().map(|p| json!({
+            "thread": p.thread,
+            "location": p.location,
+            "message": p.message,
+        })),
+        "exccause": report.exccause().map(|(code, name)| json!({ "code": code, "name": name })),
+        "registers": registers,
+        "frames": frames,
+        "corrupted": dump.corrupted,
+        "elf_sha256": dump.elf_sha256,
+        "elf_matches": report.elf_matches,
+    })
+}
+
+pub fn core_report_json(report: &CoreReport) -> Value {
+    let value = |v: &LastValue| {
+        json!({
+            "path": v.path,
+            "value": v.value.as_ref().ok(),
+            "unavailable": v.value.as_ref().err(),
+            "task": v.frame.as_ref().map(|(task, _)| task),
+            "frame": v.frame.as_ref().map(|(_, index)| index),
+            "location": v.location,
+        })
+    };
+    let task = |t: &TaskReport| {
+        json!({
+            "name": t.name,
+            "tcb": hex(t.tcb),
+            "crashed": t.crashed,
+            "pc": hex(t.pc),
+            "sp": hex(t.sp),
+            "frames": t.frames.iter().map(frame_json).collect::<Vec<_>>(),
+        })
+    };
+    json!({
+        "summary": report.summary(),
+        "exccause": report.exccause().map(|(code, name)| json!({ "code": code, "name": name })),
+        "excvaddr": report.dump.excvaddr().map(|a| hex(u64::from(a))),
+        "panic_details": report.dump.panic_details,
+        "values": report.values.iter().map(value).collect::<Vec<_>>(),
+        "tasks": report.tasks.iter().map(task).collect::<Vec<_>>(),
+        "elf_sha256": report.dump.app_sha256,
+        "elf_matches": report.elf_matches,
+    })
+}
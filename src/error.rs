@@ -0,0 +1,125 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Bad command line, exits 2.
+    Usage(String),
+    Io(io::Error),
+    Rsp(pulse_rsp::Error),
+    Dwarf(pulse_dwarf::Error),
+    Scenario(pulse_scenario::Error),
+    Snapshot(pulse_snapshot::Error),
+    Trace(pulse_trace::Error),
+    FreeRtos(pulse_freertos::Error),
+    Xtensa(pulse_xtensa::Error),
+    Coredump(pulse_coredump::Error),
+    Session(pulse_session::Error),
+    /// A step that ran but did not succeed, e.g. `cargo build`.
+    Failed(String),
+}
+
+impl Error {
+    pub fn usage(what: impl Into<String>) -> Self {
+        Error::Usage(what.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(what) => f.write_str(what),
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Rsp(e) => write!(f, "debug server: {}", e),
+            Error::Dwarf(e) => write!(f, "debug info: {}", e),
+            Error::Scenario(e) => write!(f, "{}", e),
+            Error::Snapshot(e) => write!(f, "{}", e),
+            Error::Trace(e) => write!(f, "{}", e),
+            Error::FreeRtos(e) => write!(f, "{}", e),
+            Error::Xtensa(e) => write!(f, "{}", e),
+            Error::Coredump(e) => write!(f, "{}", e),
+            Error::Session(e) => write!(f, "{}", e),
+            Error::Failed(what) => f.write_str(what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Rsp(e) => Some(e),
+            Error::Dwarf(e) => Some(e),
+            Error::Scenario(e) => Some(e),
+            Error::Snapshot(e) => Some(e),
+            Error::Trace(e) => Some(e),
+            Error::FreeRtos(e) => Some(e),
+            Error::Xtensa(e) => Some(e),
+            Error::Coredump(e) => Some(e),
+            Error::Session(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<pulse_rsp::Error> for Error {
+    fn from(e: pulse_rsp::Error) -> Self {
+        Error::Rsp(e)
+    }
+}
+
+impl From<pulse_dwarf::Error> for Error {
+    fn from(e: pulse_dwarf::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
+
+impl From<pulse_scenario::Error> for Error {
+    fn from(e: pulse_scenario::Error) -> Self {
+        Error::Scenario(e)
+    }
+}
+
+impl From<pulse_snapshot::Error> for Error {
+    fn from(e: pulse_snapshot::Error) -> Self {
+        Error::Snapshot(e)
+    }
+}
+
+impl From<pulse_trace::Error> for Error {
+    fn from(e: pulse_trace::Error) -> Self {
+        Error::Trace(e)
+    }
+}
+
+impl From<pulse_freertos::Error> for Error {
+    fn from(e: pulse_freertos::Error) -> Self {
+        Error::FreeRtos(e)
+    }
+}
+
+impl From<pulse_xtensa::Error> for Error {
+    fn from(e: pulse_xtensa::Error) -> Self {
+        Error::Xtensa(e)
+    }
+}
+
+impl From<pulse_coredump::Error> for Error {
+    fn from(e: pulse_coredump::Error) -> Self {
+        Error::Coredump(e)
+    }
+}
+
+impl From<pulse_session::Error> for Error {
+    fn from(e: pulse_session::Error) -> Self {
+        Error::Session(e)
+    }
+}
@@ -0,0 +1,186 @@
+//! `build`, `flash` and `monitor`: the firmware side of the loop.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::Command;
+
+use pulse_core::Event as DetectorEvent;
+use pulse_log::{Entry, Event, Lines, Parser};
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::crash::Symbolizer;
+use crate::error::{Error, Result};
+use crate::Global;
+
+/// `pulse build [--features LIST] [--no-default-features]`
+pub fn build(global: &Global, mut args: Args) -> Result<()> {
+    let features = args.value("--features")?;
+    let no_default = args.flag("--no-default-features");
+    no_positionals(args)?;
+
+    cargo_build(global, features.as_deref(), no_default)?;
+    let elf = global.elf();
+    global.out.emit(
+        format!("built {}", elf.display()),
+        json!({ "elf": elf, "release": global.release }),
+    );
+    Ok(())
+}
+
+/// `pulse flash [--port DEV] [--monitor] [--no-build] [--features LIST]`
+pub fn flash(global: &Global, mut args: Args) -> Result<()> {
+    let port = args.value("--port")?;
+    let monitor = args.flag("--monitor");
+    let no_build = args.flag("--no-build");
+    let features = args.value("--features")?;
+    let no_default = args.flag("--no-default-features");
+    no_positionals(args)?;
+
+    if !no_build {
+        cargo_build(global, features.as_deref(), no_default)?;
+    }
+    let elf = global.elf();
+    let mut espflash = Command::new("espflash");
+    espflash.arg("flash");
+    if let Some(port) = &port {
+        espflash.args(["--port", port]);
+    }
+    if monitor {
+        espflash.arg("--monitor");
+    }
+    // the layout with the `coredump` partition, espflash's default has none
+    let partitions = global.project.join("partitions.csv");
+    if partitions.is_file() {
+        espflash.arg("--partition-table").arg(&partitions);
+    }
+    let status = espflash
+        .arg(&elf)
+        .status()
+        .map_err(|e| Error::Failed(format!("cannot run espflash ({}), see `pulse doctor`", e)))?;
+    if !status.success() {
+        return Err(Error::Failed(format!("espflash {}", status)));
+    }
+    global.out.emit(
+        format!("flashed {}", elf.display()),
+        json!({ "elf": elf, "port": port }),
+    );
+    Ok(())
+}
+
+fn cargo_build(global: &Global, features: Option<&str>, no_default: bool) -> Result<()> {
+    let mut cargo = Command::new("cargo");
+    cargo.current_dir(&global.project).arg("build");
+    // set when we run under `cargo run`, and would override the firmware's
+    // rust-toolchain.toml with the host toolchain
+    cargo.env_remove("RUSTUP_TOOLCHAIN");
+    if global.release {
+        cargo.arg("--release");
+    }
+    if no_default {
+        cargo.arg("--no-default-features");
+    }
+    if let Some(features) = features {
+        cargo.args(["--features", features]);
+    }
+    // cargo reports progress on stderr, stdout stays clean for --json
+    let status = cargo.status()?;
+    if !status.success() {
+        return Err(Error::Failed(format!("cargo build {}", status)));
+    }
+    Ok(())
+}
+
+/// `pulse monitor [--port DEV] [--lines N] [--until TEXT]`
+///
+/// The port has to be set to 115200 baud already (`stty -F DEV 115200 raw`).
+/// Panic dumps in the log come out as symbolized crash reports, core
+/// dumps printed to the UART as core dump reports.
+pub fn monitor(global: &Global, mut args: Args) -> Result<()> {
+    let port = args.value("--port")?.map(PathBuf::from);
+    let lines: Option<usize> = args.parsed("--lines")?;
+    let until = args.value("--until")?;
+    no_positionals(args)?;
+
+    let port = match port {
+        Some(port) => port,
+        None => {
+            let env = pulse_doctor::Env::current(&global.project);
+            pulse_doctor::usb::serial_ports(&env)
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::Failed("no serial port found, pass --port".into()))?
+        }
+    };
+
+    let mut parser = Parser::new();
+    let mut panics = pulse_panic::Parser::new();
+    let mut cores = pulse_coredump::Uart::new();
+    let mut symbolizer = Symbolizer::new(global);
+    let mut seen = 0;
+    for line in Lines::new(File::open(&port)?) {
+        let line = line?;
+        // a log line ends the dump before it, so the report comes first
+        if let Some(dump) = panics.line(&line) {
+            symbolizer.report(dump);
+        }
+        match cores.line(&line) {
+            Some(Ok(dump)) => symbolizer.core_dump(dump, &pulse_coredump::DEFAULT_VALUES),
+            Some(Err(e)) => global
+                .out
+                .emit(format!("pulse: {}", e), json!({ "error": e.to_string() })),
+            None => {}
+        }
+        let Some(entry) = parser.line(&line) else {
+            continue;
+        };
+        global.out.emit(entry.line.to_string(), entry_json(&entry));
+        seen += 1;
+        let matched = until
+            .as_deref()
+            .is_some_and(|text| entry.line.to_string().contains(text));
+        if matched || lines == Some(seen) {
+            break;
+        }
+    }
+    if let Some(dump) = panics.finish() {
+        symbolizer.report(dump);
+    }
+    Ok(())
+}
+
+pub fn entry_json(entry: &Entry) -> Value {
+    let line = &entry.line;
+    json!({
+        "level": line.level.as_str(),
+        "timestamp_ms": line.timestamp_ms,
+        "tag": line.tag,
+        "message": line.message,
+        "event": entry.event.map(event_json),
+    })
+}
+
+fn event_json(event: Event) -> Value {
+    let kind = match event {
+        Event::Boot => return json!({ "kind": "boot" }),
+        Event::Detector(DetectorEvent::Secure { .. }) => "secure",
+        Event::Detector(DetectorEvent::ThreatDetected { .. }) => "threat_detected",
+        Event::Detector(DetectorEvent::EngagingBackup { .. }) => "engaging_backup",
+        Event::Detector(DetectorEvent::Transition(t)) => {
+            return json!({
+                "kind": "transition",
+                "cycle": t.cycle,
+                "from": t.from.name(),
+                "to": t.to.name(),
+            })
+        }
+    };
+    json!({ "kind": kind, "cycle": event.cycle() })
+}
+
+pub fn no_positionals(args: Args) -> Result<()> {
+    match args.finish()?.first() {
+        Some(extra) => Err(Error::usage(format!("unexpected argument {:?}", extra))),
+        None => Ok(()),
+    }
+}
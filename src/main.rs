@@ -1,15 +1,184 @@
-fn main() {
-    
-    println!("Hello, world!");
-    
-    let mut x: i16 = 3;
-    
-    let y: i16 = 123;
+//! `pulse`: one command line for the whole ghost-trigger loop.
+//!
+//! Builds and flashes the firmware, follows its serial log, symbolizes
+//! its panics and decodes its core dumps, and talks to the debug server (OpenOCD or `pulse-mock`) to
+//! inject named variables, watch them, dump memory, diff snapshots of it, list the FreeRTOS
+//! tasks and decode the registers, and records debug sessions to replay them without the board.
+//! `--json` turns every result into one JSON object per line.
 
-    println!("{}", x+y);
+mod args;
+mod crash;
+mod error;
+mod firmware;
+mod output;
+mod regs;
+mod session;
+mod snapshot;
+mod target;
+mod tasks;
 
-    x = 37;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-    println!("{}", x+y)
+use serde_json::json;
 
+use crate::args::Args;
+use crate::error::{Error, Result};
+use crate::output::Output;
+
+const USAGE: &str = "\
+usage: pulse [--json] [--target HOST:PORT] [--elf PATH] [--project DIR] [--release] <command>
+
+commands:
+  doctor                          check tools, versions and adapter permissions
+  build                           cargo build the firmware [--features LIST] [--no-default-features]
+  flash                           build, then espflash it [--port DEV] [--monitor] [--no-build]
+  monitor                         print the serial log, symbolized panics and core dumps [--port DEV]
+                                  [--lines N] [--until TEXT]
+  crash [FILE]                    symbolize the panic dumps of a saved log, or of stdin
+  coredump [FILE]                 backtraces and last values from a core dump, flash, UART or ELF
+                                  [--values LIST, default threat_detected,detector.counter]
+  attach                          check the debug server and resume the target [--gdb for a shell]
+  inject PATH=VALUE ...           write variables, resolved through DWARF at the stop PC; values must
+                                  fit the type and any declared injection_point! range
+         --mailbox SIGNAL         post threat, dump_audit or a signal id [--payload N]
+  watch PATH                      report writes with backtraces [--read | --access] [--count N]
+                                  [--for SECS] [--depth N] [--record FILE for a trace of every hit]
+  dump ADDR|PATH [LEN]            hex dump memory or a variable
+  snapshot [REGION ...] -o FILE   save .data and .bss, or dram, a section or ADDR:LEN [--label TEXT]
+  diff BEFORE AFTER               changed fields between two snapshots
+  tasks                           FreeRTOS tasks with state, priority, core and stack headroom
+  regs [NAME ...]                 registers by name, a0-a15 of the current window, PS and EXCCAUSE decoded
+  record -o FILE                  proxy the debug server and record RSP traffic and serial output
+                                  [--listen PORT, default 3334] [--port DEV] [--serial-out PATH] [--once]
+  replay FILE                     serve a recorded session as a fake debug server [--listen PORT]
+                                  [--time-scale X] [--serial-out PATH]";
+
+/// Options every command understands.
+pub struct Global {
+    pub out: Output,
+    /// Debug server, OpenOCD's GDB port by default.
+    pub target: String,
+    pub elf: Option<PathBuf>,
+    /// The firmware crate.
+    pub project: PathBuf,
+    pub release: bool,
+}
+
+impl Global {
+    /// `--elf`, else what `cargo build` leaves in the project.
+    pub fn elf(&self) -> PathBuf {
+        self.elf.clone().unwrap_or_else(|| {
+            let profile = if self.release { "release" } else { "debug" };
+            self.project
+                .join("target/xtensa-esp32-espidf")
+                .join(profile)
+                .join("ghost-trigger")
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::new(std::env::args().skip(1));
+    let json = args.flag("--json");
+    let out = Output { json };
+    match run(&mut args, out).and_then(|(command, global)| dispatch(&command, &global, args)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                println!("{}", json!({ "error": e.to_string() }));
+            } else {
+                eprintln!("pulse: {}", e);
+            }
+            if let Error::Usage(_) = e {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &mut Args, out: Output) -> Result<(String, Global)> {
+    if args.flag("-h") || args.flag("--help") {
+        println!("{}", USAGE);
+        std::process::exit(0);
+    }
+    let global = Global {
+        out,
+        target: args
+            .value("--target")?
+            .unwrap_or_else(|| format!("127.0.0.1:{}", pulse_rsp::DEFAULT_PORT)),
+        elf: args.value("--elf")?.map(PathBuf::from),
+        project: args
+            .value("--project")?
+            .map_or_else(default_project, PathBuf::from),
+        release: args.flag("--release"),
+    };
+    let command = args
+        .take_first()
+        .ok_or_else(|| Error::usage("missing command"))?;
+    Ok((command, global))
+}
+
+fn dispatch(command: &str, global: &Global, args: Args) -> Result<()> {
+    match command {
+        "doctor" => doctor(global, args),
+        "build" => firmware::build(global, args),
+        "flash" => firmware::flash(global, args),
+        "monitor" => firmware::monitor(global, args),
+        "crash" => crash::crash(global, args),
+        "coredump" => crash::coredump(global, args),
+        "attach" => target::attach(global, args),
+        "inject" => target::inject(global, args),
+        "watch" => target::watch(global, args),
+        "dump" => target::dump(global, args),
+        "snapshot" => snapshot::snapshot(global, args),
+        "diff" => snapshot::diff(global, args),
+        "tasks" => tasks::tasks(global, args),
+        "regs" => regs::regs(global, args),
+        "record" => session::record(global, args),
+        "replay" => session::replay(global, args),
+        other => Err(Error::usage(format!("unknown command {:?}", other))),
+    }
+}
+
+/// `ghost-trigger` from the workspace root, else the current directory.
+fn default_project() -> PathBuf {
+    let nested = PathBuf::from("ghost-trigger");
+    if nested.is_dir() {
+        nested
+    } else {
+        PathBuf::from(".")
+    }
+}
+
+fn doctor(global: &Global, args: Args) -> Result<()> {
+    firmware::no_positionals(args)?;
+    let checks = pulse_doctor::run(&pulse_doctor::Env::current(&global.project));
+    for check in &checks {
+        let status = match check.status {
+            pulse_doctor::Status::Ok => "ok",
+            pulse_doctor::Status::Warn => "warn",
+            pulse_doctor::Status::Fail => "fail",
+            pulse_doctor::Status::Skip => "skip",
+        };
+        global.out.emit(
+            check.to_string(),
+            json!({
+                "check": check.name,
+                "status": status,
+                "detail": check.detail,
+                "fix": check.fix,
+            }),
+        );
+    }
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == pulse_doctor::Status::Fail)
+        .count();
+    match failed {
+        0 => Ok(()),
+        n => Err(Error::Failed(format!("{} checks failed", n))),
+    }
 }
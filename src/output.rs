@@ -0,0 +1,21 @@
+use serde_json::Value;
+
+/// Where results go: human text, or one JSON object per line for scripts.
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    pub json: bool,
+}
+
+impl Output {
+    pub fn emit(&self, text: impl AsRef<str>, json: Value) {
+        if self.json {
+            println!("{}", json);
+        } else {
+            println!("{}", text.as_ref());
+        }
+    }
+}
+
+pub fn hex(addr: u64) -> String {
+    format!("{:#010x}", addr)
+}
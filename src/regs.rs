@@ -0,0 +1,58 @@
+//! `regs`: the Xtensa register file by name, `a0`..`a15` of the current
+//! window, special registers decoded.
+
+use pulse_xtensa::{Entry, RegisterFile, TargetDescription};
+use serde_json::json;
+
+use crate::args::Args;
+use crate::error::Result;
+use crate::target::Session;
+use crate::Global;
+
+/// `pulse regs [NAME ...]`
+pub fn regs(global: &Global, args: Args) -> Result<()> {
+    let names = args.finish()?;
+    let mut session = Session::connect(global)?;
+    let desc = TargetDescription::fetch_or_esp32(&mut session.gdb)?;
+    let regs = match names.is_empty() {
+        true => RegisterFile::read(&mut session.gdb, &desc)?,
+        false => {
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            RegisterFile::read_some(&mut session.gdb, &desc, &names)?
+        }
+    };
+    session.gdb.detach()?;
+
+    let mut entries = regs.entries();
+    if !names.is_empty() {
+        entries = names
+            .iter()
+            .map(|name| {
+                entries
+                    .iter()
+                    .find(|e| e.name.eq_ignore_ascii_case(name))
+                    .cloned()
+                    .or_else(|| {
+                        // an `ar` register or anything else entries() leaves out
+                        desc.register(name).map(|r| Entry {
+                            name: r.name.clone(),
+                            value: regs.get(&r.name),
+                            note: None,
+                        })
+                    })
+                    .ok_or_else(|| pulse_xtensa::Error::NoRegister(name.clone()).into())
+            })
+            .collect::<Result<_>>()?;
+    }
+    for entry in &entries {
+        global.out.emit(
+            entry.to_string(),
+            json!({
+                "name": entry.name,
+                "value": entry.value,
+                "note": entry.note,
+            }),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! `record` and `replay`: debug sessions captured on the board and served
+//! back without it.
+
+use std::fs::File;
+use std::io::Write;
+
+use pulse_session::{replay, Header, Recorder, Replayer, Session};
+use serde_json::json;
+
+use crate::args::Args;
+use crate::error::{Error, Result};
+use crate::firmware::no_positionals;
+use crate::Global;
+
+/// `pulse record -o FILE [--listen PORT] [--port DEV] [--serial-out PATH] [--once]`
+///
+/// Debuggers connect to the listen port instead of the debug server and
+/// are passed through to it. Runs until killed, the file is complete up to
+/// then, or until the first debugger is done with `--once`. `--serial-out`
+/// hands the UART on, e.g. to a FIFO for `pulse-scenario --serial`.
+pub fn record(global: &Global, mut args: Args) -> Result<()> {
+    let file = args
+        .value("-o")?
+        .ok_or_else(|| Error::usage("record needs -o FILE"))?;
+    let listen: u16 = args
+        .parsed("--listen")?
+        .unwrap_or(pulse_rsp::DEFAULT_PORT + 1);
+    let port = args.value("--port")?;
+    let serial_out = args.value("--serial-out")?;
+    let once = args.flag("--once");
+    no_positionals(args)?;
+
+    let header = Header {
+        target: global.target.clone(),
+        serial: port.clone(),
+    };
+    let recorder = Recorder::bind(("127.0.0.1", listen), &header, File::create(&file)?)?;
+    if let Some(port) = &port {
+        let tee = match &serial_out {
+            Some(path) => Some(Box::new(File::create(path)?) as Box<dyn Write + Send>),
+            None => None,
+        };
+        recorder.record_serial(File::open(port)?, tee);
+    }
+    eprintln!(
+        "recording {} on 127.0.0.1:{} to {}",
+        global.target,
+        recorder.local_addr()?.port(),
+        file
+    );
+    if once {
+        recorder.serve_one()?;
+    } else {
+        recorder.serve()?;
+    }
+
+    let session = Session::load(&file)?;
+    global.out.emit(
+        format!("{}: {}", file, session),
+        json!({
+            "file": file,
+            "target": session.header.target,
+            "connections": session.connections(),
+            "entries": session.entries.len(),
+            "serial_bytes": session.serial().len(),
+            "duration_ms": session.duration().as_millis() as u64,
+        }),
+    );
+    Ok(())
+}
+
+/// `pulse replay FILE [--listen PORT] [--time-scale X] [--serial-out PATH]`
+///
+/// Stands in for the debug server on the listen port, OpenOCD's by
+/// default, and plays each recorded connection to the next debugger.
+pub fn replay(global: &Global, mut args: Args) -> Result<()> {
+    let listen: u16 = args.parsed("--listen")?.unwrap_or(pulse_rsp::DEFAULT_PORT);
+    let time_scale: f64 = args.parsed("--time-scale")?.unwrap_or(1.0);
+    let serial_out = args.value("--serial-out")?;
+    let [file] = <[String; 1]>::try_from(args.finish()?)
+        .map_err(|_| Error::usage("replay takes one session file"))?;
+
+    let session = Session::load(&file)?;
+    eprintln!("{}: {}", file, session);
+    let mut replayer = Replayer::bind(
+        ("127.0.0.1", listen),
+        session,
+        replay::Options { time_scale },
+    )?;
+    if let Some(path) = &serial_out {
+        replayer.serial_to(File::create(path)?);
+    }
+    eprintln!("replaying on 127.0.0.1:{}", replayer.local_addr()?.port());
+    replayer.serve(|played| {
+        global.out.emit(
+            played.to_string(),
+            json!({
+                "connection": played.connection,
+                "answered": played.answered,
+                "skipped": played.skipped,
+                "repeated": played.repeated,
+                "unknown": played.unknown,
+                "faithful": played.faithful(),
+            }),
+        )
+    })?;
+    Ok(())
+}
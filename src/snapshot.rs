@@ -0,0 +1,119 @@
+//! `snapshot` and `diff`: DRAM captures and what changed between them.
+
+use std::path::Path;
+
+use pulse_snapshot::{select, writable_sections, Snapshot, Span, SymbolMap};
+use serde_json::json;
+
+use crate::args::Args;
+use crate::error::{Error, Result};
+use crate::output::hex;
+use crate::target::Session;
+use crate::Global;
+
+/// `pulse snapshot [REGION ...] -o FILE [--label TEXT]`
+pub fn snapshot(global: &Global, mut args: Args) -> Result<()> {
+    let file = args
+        .value("-o")?
+        .ok_or_else(|| Error::usage("snapshot needs -o FILE"))?;
+    let label = args.value("--label")?;
+    let mut specs = args.finish()?;
+    if specs.is_empty() {
+        specs = vec![".data".into(), ".bss".into()];
+    }
+
+    // sections only matter for names; `dram` and ADDR:LEN work without an ELF
+    let sections = match std::fs::read(global.elf()) {
+        Ok(elf) => writable_sections(&elf)?,
+        Err(_) => Vec::new(),
+    };
+    let mut spans: Vec<Span> = Vec::new();
+    for spec in &specs {
+        spans.extend(select(spec, &sections)?);
+    }
+
+    let mut session = Session::connect(global)?;
+    let label = label.unwrap_or_else(|| global.target.clone());
+    let snap = Snapshot::capture(&mut session.gdb, &spans, Some(session.pc), label)?;
+    session.gdb.detach()?;
+    snap.save(&file)?;
+
+    let bytes: usize = snap.regions.iter().map(|r| r.data.len()).sum();
+    global.out.emit(
+        format!(
+            "{}: {} bytes from {} at {}",
+            file,
+            bytes,
+            snap.regions
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            hex(session.pc)
+        ),
+        json!({
+            "file": file,
+            "pc": session.pc,
+            "bytes": bytes,
+            "regions": snap.regions.iter().map(|r| json!({
+                "name": r.name,
+                "address": r.address,
+                "length": r.data.len(),
+            })).collect::<Vec<_>>(),
+        }),
+    );
+    Ok(())
+}
+
+/// `pulse diff BEFORE AFTER`, annotated from the ELF when there is one.
+pub fn diff(global: &Global, args: Args) -> Result<()> {
+    let [before, after] = <[String; 2]>::try_from(args.finish()?)
+        .map_err(|_| Error::usage("diff takes two snapshot files"))?;
+    let before = Snapshot::load(&before)?;
+    let after = Snapshot::load(&after)?;
+
+    let elf = global.elf();
+    let symbols = match (&global.elf, Path::new(&elf).exists()) {
+        (None, false) => None,
+        _ => Some(SymbolMap::from_elf(&std::fs::read(&elf)?)?),
+    };
+    let diff = pulse_snapshot::diff(&before, &after, symbols.as_ref());
+
+    for change in &diff.changes {
+        let (old, new) = change.values();
+        let ty = change
+            .type_name
+            .clone()
+            .unwrap_or_else(|| format!("{} bytes", change.before.len()));
+        global.out.emit(
+            format!(
+                "{}  {}  {}  {} -> {}",
+                hex(change.address),
+                change.name,
+                ty,
+                old,
+                new
+            ),
+            json!({
+                "address": change.address,
+                "name": change.name,
+                "region": change.region,
+                "symbol": change.symbol,
+                "field": change.field,
+                "type": change.type_name,
+                "before": old,
+                "after": new,
+            }),
+        );
+    }
+    for region in &diff.unmatched {
+        global.out.emit(
+            format!("region {} is only in one snapshot", region),
+            json!({ "unmatched": region }),
+        );
+    }
+    if diff.is_empty() {
+        global.out.emit("no changes", json!({ "changes": 0 }));
+    }
+    Ok(())
+}
@@ -0,0 +1,363 @@
+//! `attach`, `inject`, `watch` and `dump`: talking to the debug server.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::Command;
+use std::time::Duration;
+
+use pulse_core::mailbox::Signal;
+use pulse_dwarf::{DebugInfo, Variable};
+use pulse_rsp::{Client, Features, StopReply, WatchKind};
+use pulse_scenario::{stop_pc, variable_address, Action};
+use pulse_trace::{Trace, TraceWriter, Watched};
+use serde_json::json;
+
+use crate::args::{number, Args};
+use crate::error::{Error, Result};
+use crate::firmware::no_positionals;
+use crate::output::hex;
+use crate::Global;
+
+/// Replies to everything but `continue`.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A stopped target. OpenOCD halts it when GDB attaches, the mock starts
+/// halted; [`Client::detach`] lets it run again.
+pub struct Session {
+    pub gdb: Client,
+    features: Features,
+    stop: StopReply,
+    pub pc: u64,
+}
+
+impl Session {
+    pub fn connect(global: &Global) -> Result<Self> {
+        let mut gdb = Client::connect(global.target.as_str())?;
+        gdb.set_timeout(Some(REPLY_TIMEOUT))?;
+        let features = gdb.handshake()?;
+        let stop = gdb.halt_reason()?;
+        if !matches!(stop, StopReply::Signal { .. }) {
+            return Err(Error::Failed(format!("target is not running: {:?}", stop)));
+        }
+        let pc = stop_pc(&mut gdb, &stop)?;
+        Ok(Self {
+            gdb,
+            features,
+            stop,
+            pc,
+        })
+    }
+
+    fn resolve(&mut self, debug: &DebugInfo, path: &str) -> Result<Variable> {
+        Ok(debug.resolve(path, Some(self.pc))?)
+    }
+
+    /// Address of a variable that lives in memory.
+    fn address(&mut self, variable: &Variable) -> Result<u64> {
+        variable_address(&mut self.gdb, variable)?.ok_or_else(|| {
+            Error::Failed(format!(
+                "{} is in {} at {}, not in memory",
+                variable.path,
+                variable.location,
+                hex(self.pc)
+            ))
+        })
+    }
+}
+
+/// `pulse attach [--gdb]`
+pub fn attach(global: &Global, mut args: Args) -> Result<()> {
+    let gdb = args.flag("--gdb");
+    no_positionals(args)?;
+
+    if gdb {
+        let status = Command::new("xtensa-esp32-elf-gdb")
+            .arg("-q")
+            .arg(global.elf())
+            .args(["-ex", &format!("target remote {}", global.target)])
+            .status()
+            .map_err(|e| {
+                Error::Failed(format!(
+                    "cannot run xtensa-esp32-elf-gdb ({}), see `pulse doctor`",
+                    e
+                ))
+            })?;
+        return match status.success() {
+            true => Ok(()),
+            false => Err(Error::Failed(format!("gdb {}", status))),
+        };
+    }
+
+    let mut session = Session::connect(global)?;
+    let features = &session.features;
+    let (signal, thread) = match &session.stop {
+        StopReply::Signal { signal, thread, .. } => (*signal, *thread),
+        _ => unreachable!("Session::connect checks for a stop"),
+    };
+    session.gdb.detach()?;
+    global.out.emit(
+        format!(
+            "{}: stopped at {} (signal {}{}), packet size {}, resumed",
+            global.target,
+            hex(session.pc),
+            signal,
+            thread.map_or(String::new(), |t| format!(", thread {}", t)),
+            features.packet_size.unwrap_or(0)
+        ),
+        json!({
+            "target": global.target,
+            "pc": session.pc,
+            "signal": signal,
+            "thread": thread,
+            "packet_size": features.packet_size,
+            "no_ack": features.no_ack_mode,
+            "vcont": features.vcont,
+        }),
+    );
+    Ok(())
+}
+
+/// `pulse inject [PATH=VALUE ...] [--mailbox SIGNAL [--payload N]]`
+pub fn inject(global: &Global, mut args: Args) -> Result<()> {
+    let mailbox = args.value("--mailbox")?;
+    let payload = args.value("--payload")?;
+    let mut actions = Vec::new();
+    if let Some(signal) = mailbox {
+        let signal = match signal.as_str() {
+            "threat" => Signal::Threat,
+            "dump_audit" => Signal::DumpAudit,
+            id => Signal::from_id(
+                number(id)
+                    .and_then(|id| u32::try_from(id).ok())
+                    .ok_or_else(|| {
+                        Error::usage("--mailbox is threat, dump_audit or a signal id")
+                    })?,
+            ),
+        };
+        let payload = match payload {
+            Some(p) => number(&p)
+                .and_then(|p| u32::try_from(p).ok())
+                .ok_or_else(|| Error::usage(format!("bad --payload {:?}", p)))?,
+            None => 0,
+        };
+        actions.push(Action::Mailbox { signal, payload });
+    } else if payload.is_some() {
+        return Err(Error::usage("--payload goes with --mailbox"));
+    }
+    for write in args.finish()? {
+        let (var, value) = write
+            .split_once('=')
+            .ok_or_else(|| Error::usage(format!("expected PATH=VALUE, got {:?}", write)))?;
+        // negative values travel two's complement, like the TOML ones
+        let value = match value {
+            "true" => Some(1),
+            "false" => Some(0),
+            v => match v.strip_prefix('-') {
+                Some(magnitude) => number(magnitude)
+                    .and_then(|n| i64::try_from(n).ok())
+                    .map(|n| n.wrapping_neg() as u64),
+                None => number(v),
+            },
+        }
+        .ok_or_else(|| Error::usage(format!("bad value {:?}", value)))?;
+        actions.push(Action::Write {
+            var: var.to_string(),
+            value,
+        });
+    }
+    if actions.is_empty() {
+        return Err(Error::usage("nothing to inject"));
+    }
+
+    let elf = std::fs::read(global.elf())?;
+    let debug = DebugInfo::parse(&elf)?;
+    let points = pulse_scenario::injection_points(&elf)?;
+    let mut session = Session::connect(global)?;
+    for action in &actions {
+        let done = pulse_scenario::apply(&mut session.gdb, &debug, &points, action, session.pc)?;
+        global.out.emit(
+            format!("{}: {}", hex(session.pc), done),
+            json!({ "pc": session.pc, "injected": done }),
+        );
+    }
+    session.gdb.detach()?;
+    Ok(())
+}
+
+/// `pulse watch PATH [--read | --access] [--count N] [--for SECS] [--record FILE] [--depth N]`
+pub fn watch(global: &Global, mut args: Args) -> Result<()> {
+    let kind = match (args.flag("--read"), args.flag("--access")) {
+        (false, false) => WatchKind::Write,
+        (true, false) => WatchKind::Read,
+        (false, true) => WatchKind::Access,
+        (true, true) => return Err(Error::usage("--read or --access, not both")),
+    };
+    let count: Option<usize> = args.parsed("--count")?;
+    let duration: Option<f64> = args.parsed("--for")?;
+    let record_to = args.value("--record")?;
+    let depth: usize = args.parsed("--depth")?.unwrap_or(pulse_trace::MAX_DEPTH);
+    let [path] = <[String; 1]>::try_from(args.finish()?)
+        .map_err(|_| Error::usage("watch takes one variable path"))?;
+    let duration = match duration {
+        Some(secs) if secs > 0.0 && secs.is_finite() => Some(Duration::from_secs_f64(secs)),
+        Some(_) => return Err(Error::usage("--for takes a positive number of seconds")),
+        None => None,
+    };
+    // one hit unless this is meant to be a long recording
+    let hits = match (count, duration, &record_to) {
+        (Some(n), _, _) => Some(n),
+        (None, None, None) => Some(1),
+        _ => None,
+    };
+
+    let debug = DebugInfo::from_file(global.elf())?;
+    let mut session = Session::connect(global)?;
+    let variable = session.resolve(&debug, &path)?;
+    let watched = Watched {
+        name: variable.path.clone(),
+        address: session.address(&variable)?,
+        size: variable.ty.size,
+        type_name: Some(variable.ty.name.clone()),
+    };
+    let mut trace = match &record_to {
+        Some(file) => Some(TraceWriter::new(
+            BufWriter::new(File::create(file)?),
+            &watched,
+            kind,
+        )?),
+        None => None,
+    };
+    let options = pulse_trace::Options {
+        kind,
+        hits,
+        duration,
+        depth,
+        ccount: true,
+    };
+    let mut recorded = Vec::new();
+    pulse_trace::record(&mut session.gdb, &watched, Some(&debug), &options, |hit| {
+        let mut json = hit.to_json();
+        json["variable"] = json!(watched.name);
+        json["address"] = json!(watched.address);
+        // what `watch` has always reported: the value after the access
+        json["value"] = json!(hit.new_value());
+        json["bytes"] = json["new"].clone();
+        global.out.emit(format!("{}: {}", watched.name, hit), json);
+        if let Some(trace) = &mut trace {
+            trace.hit(hit)?;
+            recorded.push(hit.clone());
+        }
+        Ok(())
+    })?;
+    session.gdb.detach()?;
+
+    if let Some(file) = record_to {
+        let trace = Trace {
+            watched,
+            kind,
+            hits: recorded,
+        };
+        for writer in trace.writers() {
+            let function = writer
+                .function
+                .as_ref()
+                .map_or(String::new(), |f| format!(" {}+{:#x}", f, writer.offset));
+            global.out.emit(
+                format!("{}{}: {} hits", hex(writer.pc), function, writer.hits),
+                json!({
+                    "writer": writer.pc,
+                    "function": writer.function,
+                    "offset": writer.offset,
+                    "hits": writer.hits,
+                }),
+            );
+        }
+        global.out.emit(
+            format!("{} hits recorded to {}", trace.hits.len(), file),
+            json!({ "trace": file, "hits": trace.hits.len() }),
+        );
+    }
+    Ok(())
+}
+
+/// `pulse dump (ADDR [LEN] | PATH [LEN])`
+pub fn dump(global: &Global, args: Args) -> Result<()> {
+    let positionals = args.finish()?;
+    let (what, len) = match positionals.as_slice() {
+        [what] => (what, None),
+        [what, len] => (
+            what,
+            Some(number(len).ok_or_else(|| Error::usage(format!("bad length {:?}", len)))?),
+        ),
+        _ => {
+            return Err(Error::usage(
+                "dump takes an address or a variable path, and a length",
+            ))
+        }
+    };
+
+    let mut session = Session::connect(global)?;
+    let (addr, len, variable) = match number(what) {
+        Some(addr) => (addr, len.unwrap_or(64), None),
+        None => {
+            let debug = DebugInfo::from_file(global.elf())?;
+            let variable = session.resolve(&debug, what)?;
+            let addr = session.address(&variable)?;
+            (addr, len.unwrap_or(variable.ty.size), Some(variable))
+        }
+    };
+    let bytes = session.gdb.read_memory(addr, len as usize)?;
+    session.gdb.detach()?;
+
+    let mut text = String::new();
+    if let Some(variable) = &variable {
+        let _ = writeln!(
+            text,
+            "{}: {} at {}",
+            variable.path,
+            variable.ty.name,
+            hex(addr)
+        );
+    }
+    text.push_str(&hexdump(addr, &bytes));
+    global.out.emit(
+        text.trim_end(),
+        json!({
+            "address": addr,
+            "length": bytes.len(),
+            "variable": variable.as_ref().map(|v| &v.path),
+            "type": variable.as_ref().map(|v| &v.ty.name),
+            "bytes": hex_bytes(&bytes),
+        }),
+    );
+    Ok(())
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 16 bytes a line, `xxd` style.
+fn hexdump(addr: u64, bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "{}:", hex(addr + i as u64 * 16));
+        for b in line {
+            let _ = write!(out, " {:02x}", b);
+        }
+        let pad = (16 - line.len()) * 3;
+        let ascii: String = line
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let _ = writeln!(out, "{:pad$}  {}", "", ascii, pad = pad);
+    }
+    out
+}
@@ -0,0 +1,47 @@
+//! `tasks`: the FreeRTOS task list, decoded from target memory.
+
+use pulse_freertos::{Kernel, Layout, HEADER};
+use serde_json::json;
+
+use crate::args::Args;
+use crate::error::Result;
+use crate::target::Session;
+use crate::Global;
+
+/// `pulse tasks`
+pub fn tasks(global: &Global, args: Args) -> Result<()> {
+    crate::firmware::no_positionals(args)?;
+    let elf = std::fs::read(global.elf())?;
+    let layout = Layout::default();
+    let kernel = Kernel::from_elf(&elf, &layout)?;
+
+    let mut session = Session::connect(global)?;
+    let tasks = pulse_freertos::tasks(&mut session.gdb, &kernel, &layout)?;
+    session.gdb.detach()?;
+
+    if !global.out.json {
+        println!("{}", HEADER);
+    }
+    for task in &tasks {
+        let running_on = match task.state {
+            pulse_freertos::State::Running(core) => Some(core),
+            _ => None,
+        };
+        global.out.emit(
+            task.to_string(),
+            json!({
+                "tcb": task.tcb,
+                "name": task.name,
+                "state": task.state.name(),
+                "running_on": running_on,
+                "priority": task.priority,
+                "affinity": task.affinity,
+                "top_of_stack": task.top_of_stack,
+                "stack": task.stack,
+                "stack_size": task.stack_size,
+                "high_water": task.high_water,
+            }),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::OnceLock;
+
+use pulse_mock::{coredump, fixture, Image, Options, Server};
+use serde_json::Value;
+
+/// The fixture ELF on disk, for `--elf`.
+///
+/// Written once per test binary and renamed into place, so a test running
+/// in parallel never sees it half written. It lives in cargo's per-target
+/// scratch directory under a fixed name: the next run replaces it and
+/// `cargo clean` removes it.
+fn elf() -> &'static Path {
+    static ELF: OnceLock<PathBuf> = OnceLock::new();
+    ELF.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+        let path = dir.join("pulse-cli.elf");
+        let partial = dir.join(format!("pulse-cli.elf.{}", std::process::id()));
+        std::fs::write(&partial, fixture::ghost_trigger()).unwrap();
+        std::fs::rename(&partial, &path).unwrap();
+        path
+    })
+}
+
+/// Run `pulse --json` against a fresh mock target.
+fn pulse(args: &[&str]) -> Output {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    pulse_at(&addr.to_string(), args)
+}
+
+fn command(target: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pulse"));
+    command
+        .arg("--json")
+        .args(["--target", target])
+        .arg("--elf")
+        .arg(elf())
+        .args(args);
+    command
+}
+
+fn pulse_at(target: &str, args: &[&str]) -> Output {
+    command(target, args).output().unwrap()
+}
+
+/// Start a `pulse` that serves a port and wait until it listens.
+fn serving(target: &str, args: &[&str]) -> (Child, String) {
+    let mut child = command(target, args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = BufReader::new(child.stderr.take().unwrap());
+    for line in stderr.lines() {
+        let line = line.unwrap();
+        if let Some((_, rest)) = line.split_once(" on 127.0.0.1:") {
+            let port = rest.split(' ').next().unwrap();
+            return (child, format!("127.0.0.1:{}", port));
+        }
+    }
+    panic!("{:?} never listened: {}", args, child.wait().unwrap());
+}
+
+fn lines(output: &Output) -> Vec<Value> {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn attach_reports_the_stop() {
+    let out = lines(&pulse(&["attach"]));
+    assert_eq!(out.len(), 1);
+    // halted at boot, before app_main
+    assert_eq!(out[0]["pc"], 0);
+    assert_eq!(out[0]["signal"], 5);
+    assert_eq!(out[0]["no_ack"], true);
+}
+
+#[test]
+fn dump_a_variable_and_an_address() {
+    let out = lines(&pulse(&["dump", "ghost_trigger::injection::CYCLE_MS"]));
+    assert_eq!(out[0]["address"], fixture::CYCLE_MS);
+    assert_eq!(out[0]["type"], "core::sync::atomic::AtomicU32");
+    assert_eq!(out[0]["bytes"], "e8030000");
+
+    let out = lines(&pulse(&[
+        "dump",
+        &format!("{:#x}", fixture::PULSE_MAILBOX),
+        "8",
+    ]));
+    // magic and version
+    assert_eq!(out[0]["length"], 8);
+    assert_eq!(out[0]["variable"], Value::Null);
+}
+
+#[test]
+fn inject_variables_and_mailbox() {
+    let out = lines(&pulse(&[
+        "inject",
+        "CYCLE_MS=0x10",
+        "--mailbox",
+        "threat",
+        "--payload",
+        "2",
+    ]));
+    assert_eq!(out.len(), 2);
+    let done: Vec<&str> = out
+        .iter()
+        .map(|v| v["injected"].as_str().unwrap())
+        .collect();
+    assert!(
+        done[0].starts_with("mailbox Threat payload 2"),
+        "{:?}",
+        done
+    );
+    assert!(
+        done[1].starts_with("ghost_trigger::injection::CYCLE_MS = 16"),
+        "{:?}",
+        done
+    );
+
+    // outside range(10, 60_000), and not a u32 at all
+    for (write, why) in [
+        ("CYCLE_MS=0", "declared range(10, 60000)"),
+        ("CYCLE_MS=-3", "-3 does not fit"),
+    ] {
+        let out = pulse(&["inject", write]);
+        assert_eq!(out.status.code(), Some(1), "{}", write);
+        let error: Value = serde_json::from_slice(&out.stdout).unwrap();
+        assert!(error["error"].as_str().unwrap().contains(why), "{}", error);
+    }
+}
+
+#[test]
+fn watch_reads() {
+    let out = lines(&pulse(&["watch", "CYCLE_MS", "--read", "--count", "2"]));
+    assert_eq!(out.len(), 2);
+    for (i, hit) in out.iter().enumerate() {
+        assert_eq!(hit["hit"], i + 1);
+        assert_eq!(hit["kind"], "read");
+        assert_eq!(hit["value"], 1000);
+    }
+}
+
+#[test]
+fn watch_records_a_trace() {
+    let file = std::env::temp_dir().join(format!("pulse-cli-{}.trace", std::process::id()));
+    let out = lines(&pulse(&[
+        "watch",
+        "CYCLE_MS",
+        "--read",
+        "--count",
+        "3",
+        "--record",
+        file.to_str().unwrap(),
+    ]));
+    assert_eq!(out.len(), 3 + 2, "{:?}", out);
+    let backtrace = &out[0]["backtrace"];
+    assert_eq!(backtrace[0]["function"], "ghost_trigger::main");
+    assert_eq!(backtrace[1]["function"], "app_main");
+    assert!(out[0]["ccount"].as_u64().unwrap() < out[2]["ccount"].as_u64().unwrap());
+    assert_eq!(out[3]["writer"], fixture::MAIN);
+    assert_eq!(out[3]["hits"], 3);
+    assert_eq!(out[4]["hits"], 3);
+
+    let trace = pulse_trace::Trace::load(&file).unwrap();
+    assert_eq!(trace.watched.address, fixture::CYCLE_MS);
+    assert_eq!(trace.hits.len(), 3);
+    assert_eq!(trace.hits[2].new_value(), Some(1000));
+}
+
+#[test]
+fn snapshot_and_diff() {
+    let dir = std::env::temp_dir();
+    let before = dir.join(format!("pulse-cli-{}-before.snap", std::process::id()));
+    let after = dir.join(format!("pulse-cli-{}-after.snap", std::process::id()));
+    let out = lines(&pulse(&["snapshot", "-o", before.to_str().unwrap()]));
+    assert_eq!(out[0]["pc"], 0);
+    assert_eq!(out[0]["regions"][0]["name"], ".dram0.data");
+    assert_eq!(out[0]["regions"][1]["name"], ".dram0.bss");
+
+    let mut snap = pulse_snapshot::Snapshot::load(&before).unwrap();
+    let data = &mut snap.regions[0].data;
+    let at = (fixture::CYCLE_MS - fixture::DATA) as usize;
+    data[at..at + 4].copy_from_slice(&16u32.to_le_bytes());
+    snap.save(&after).unwrap();
+
+    let out = lines(&pulse(&[
+        "diff",
+        before.to_str().unwrap(),
+        after.to_str().unwrap(),
+    ]));
+    assert_eq!(out.len(), 1);
+    assert_eq!(out[0]["address"], fixture::CYCLE_MS);
+    assert_eq!(out[0]["name"], "ghost_trigger::injection::CYCLE_MS");
+    assert_eq!(out[0]["type"], "core::sync::atomic::AtomicU32");
+    assert_eq!(
+        (&out[0]["before"], &out[0]["after"]),
+        (&"1000".into(), &"16".into())
+    );
+}
+
+#[test]
+fn monitor_a_recorded_log() {
+    let log = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/pulse-log/tests/data/threat.log"
+    );
+    let out = lines(&pulse(&["monitor", "--port", log, "--until", "THREAT"]));
+    let last = out.last().unwrap();
+    assert_eq!(last["level"], "ERROR");
+    assert_eq!(last["event"]["kind"], "threat_detected");
+    assert_eq!(last["event"]["cycle"], 4);
+}
+
+#[test]
+fn monitor_symbolizes_a_panic() {
+    let log = std::env::temp_dir().join(format!("pulse-cli-{}-panic.log", std::process::id()));
+    std::fs::write(
+        &log,
+        format!(
+            "I (3338) pulse_core::sink: System secure. [Cycle: 3]\r
+Guru Meditation Error: Core  0 panic'ed (LoadProhibited). Exception was unhandled.\r
+\r
+Core  0 register dump:\r
+PC      : 0x400d0400  PS      : 0x00060530  A0      : 0x800d01e7  A1      : 0x3ffe1e60  \r
+EXCCAUSE: 0x0000001c  EXCVADDR: 0x00000000  \r
+\r
+Backtrace: 0x400d0400:0x3ffe1e60 {:#010x}:0x3ffe1ec0 0x400d0120:0x3ffe1f00\r
+\r
+ELF file SHA256: 0123456789abcdef\r
+\r
+Rebooting...\r
+I (120) ghost_trigger: System altered!\r
+",
+            fixture::MAIN_TAKE + 4
+        ),
+    )
+    .unwrap();
+    let out = lines(&pulse(&["monitor", "--port", log.to_str().unwrap()]));
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[0]["event"]["kind"], "secure");
+    assert_eq!(out[2]["event"]["kind"], "boot");
+
+    let crash = &out[1]["crash"];
+    assert_eq!(
+        crash["summary"],
+        "Guru Meditation on core 0: LoadProhibited in pulse_core::detector::Detector::cycle"
+    );
+    assert_eq!(crash["exccause"]["name"], "LoadProhibited");
+    assert_eq!(crash["registers"]["PC"], "0x400d0400");
+    assert_eq!(crash["elf_matches"], false);
+    let frames = crash["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[1]["function"], "pulse_core::mailbox::Mailbox::take");
+    assert_eq!(frames[1]["inlined"], true);
+    assert_eq!(frames[2]["function"], "ghost_trigger::main");
+    assert_eq!(frames[2]["line"], 49);
+    assert_eq!(frames[3]["function"], "app_main");
+
+    // the same dump from a saved log
+    let out = lines(&pulse(&["crash", log.to_str().unwrap()]));
+    assert_eq!(out.len(), 1);
+    assert_eq!(out[0]["crash"]["frames"], crash["frames"]);
+}
+
+#[test]
+fn coredump_from_flash_and_the_uart() {
+    let partition = coredump::partition(&coredump::ghost_trigger());
+    let dir = std::env::temp_dir();
+    let core = dir.join(format!("pulse-cli-{}-core.bin", std::process::id()));
+    std::fs::write(&core, &partition).unwrap();
+    let out = lines(&pulse(&["coredump", core.to_str().unwrap()]));
+    assert_eq!(out.len(), 1);
+    let report = &out[0]["coredump"];
+    assert_eq!(
+        report["summary"],
+        "core dump: LoadProhibited in task main, ghost_trigger::main"
+    );
+    assert_eq!(report["elf_matches"], true);
+    assert_eq!(report["values"][0]["path"], "threat_detected");
+    assert_eq!(report["values"][0]["value"], "true");
+    assert_eq!(report["values"][1]["value"], "7");
+    assert_eq!(report["values"][1]["task"], "main");
+    let tasks = report["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 6);
+    assert_eq!(tasks[0]["crashed"], true);
+    assert_eq!(tasks[0]["frames"][1]["function"], "app_main");
+
+    let out = lines(&pulse(&[
+        "coredump",
+        core.to_str().unwrap(),
+        "--values",
+        "detector.timing.cycle_ms",
+    ]));
+    assert_eq!(out[0]["coredump"]["values"][0]["value"], "1000");
+
+    // printed to the UART instead, between two log lines
+    let log = dir.join(format!("pulse-cli-{}-core.log", std::process::id()));
+    std::fs::write(
+        &log,
+        format!(
+            "I (3338) pulse_core::sink: System secure. [Cycle: 3]\r\n{}I (120) ghost_trigger: System altered!\r\n",
+            coredump::uart(&partition)
+        ),
+    )
+    .unwrap();
+    let out = lines(&pulse(&["monitor", "--port", log.to_str().unwrap()]));
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[1]["coredump"], *report);
+    assert_eq!(out[2]["event"]["kind"], "boot");
+}
+
+#[test]
+fn record_and_replay_a_dump() {
+    let file = std::env::temp_dir().join(format!("pulse-cli-{}.session", std::process::id()));
+    let file = file.to_str().unwrap();
+    let server = Server::bind(
+        "127.0.0.1:0",
+        Image::parse(&fixture::ghost_trigger()).unwrap(),
+        Options { time_scale: 0.0 },
+    )
+    .unwrap();
+    let (addr, _) = server.spawn().unwrap();
+    let dump = ["dump", "ghost_trigger::injection::CYCLE_MS"];
+
+    let (recorder, proxy) = serving(
+        &addr.to_string(),
+        &["record", "-o", file, "--listen", "0", "--once"],
+    );
+    let live = lines(&pulse_at(&proxy, &dump));
+    assert_eq!(live[0]["bytes"], "e8030000");
+    let recorded = lines(&recorder.wait_with_output().unwrap());
+    assert_eq!(recorded[0]["connections"], 1);
+    assert_eq!(recorded[0]["target"], addr.to_string());
+
+    // no board, no mock: the recording answers
+    let (replayer, fake) = serving(
+        "unused:1",
+        &["replay", file, "--listen", "0", "--time-scale", "0"],
+    );
+    assert_eq!(lines(&pulse_at(&fake, &dump)), live);
+    let replayed = lines(&replayer.wait_with_output().unwrap());
+    assert_eq!(replayed[0]["connection"], 1);
+    assert_eq!(replayed[0]["faithful"], true);
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn usage_errors() {
+    let out = pulse(&["frobnicate"]);
+    assert_eq!(out.status.code(), Some(2));
+    let error: Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(error["error"], "unknown command \"frobnicate\"");
+
+    assert_eq!(pulse(&["dump"]).status.code(), Some(2));
+    assert_eq!(pulse(&["inject", "--payload", "1"]).status.code(), Some(2));
+    assert_eq!(pulse(&["record"]).status.code(), Some(2));
+}
+
+#[test]
+fn tasks_lists_the_kernel() {
+    let out = lines(&pulse(&["tasks"]));
+    let names: Vec<&str> = out.iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        ["main", "IDLE1", "ipc0", "ipc1", "esp_timer", "IDLE0"]
+    );
+    assert_eq!(out[0]["state"], "running");
+    assert_eq!(out[0]["running_on"], 0);
+    assert_eq!(out[0]["stack_size"], 0x2000);
+    assert_eq!(out[0]["high_water"], 0x1d00);
+    assert_eq!(out[3]["state"], "suspended");
+    assert_eq!(out[4]["state"], "blocked");
+    assert_eq!(out[4]["affinity"], 0);
+}
+
+#[test]
+fn regs_decodes_the_window_and_specials() {
+    let out = lines(&pulse(&["regs"]));
+    assert_eq!(out[0]["name"], "pc");
+    let reg = |name: &str| out.iter().find(|r| r["name"] == name).unwrap();
+    // halted at reset: window 0, so a1 is ar1
+    assert_eq!(reg("a1")["value"], 0x3ffe_1f00u64);
+    assert_eq!(reg("a1")["note"], "ar1");
+    assert_eq!(
+        reg("windowstart")["note"],
+        "0000000000000001 live windows 0"
+    );
+    assert_eq!(
+        reg("ps")["note"],
+        "INTLEVEL=15 EXCM=1 UM=0 RING=0 OWB=0 CALLINC=0 WOE=0"
+    );
+    assert!(out.iter().all(|r| r["name"] != "ar1"));
+
+    let out = lines(&pulse(&["regs", "PS", "a1", "ar63"]));
+    let names: Vec<&str> = out.iter().map(|r| r["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["ps", "a1", "ar63"]);
+
+    let missing = pulse(&["regs", "f64r0"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stdout).contains("no register \\\"f64r0\\\""));
+}